@@ -0,0 +1,189 @@
+//! # Audit Module
+//!
+//! Finds commits made with the wrong identity. A commit is suspicious when
+//! one of the configured groups authored it, but the repository resolves to
+//! another group through its remotes (see the `resolve` module). Commits by
+//! people without a group are not yours and are never reported.
+
+use crate::config::{Config, UserConfig};
+use crate::resolve;
+use crate::utils;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Separator between the fields of a `git log` line
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// A commit authored with the wrong group
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct WrongCommit {
+    pub commit: String,
+    pub name: String,
+    pub email: String,
+    pub subject: String,
+    /// Groups holding the identity the commit was authored with
+    pub groups: Vec<String>,
+}
+
+/// Audit result of one repository
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RepositoryReport {
+    pub path: PathBuf,
+    /// Group the repository resolves to, `None` when no rule matches
+    pub group: Option<String>,
+    pub reason: Option<String>,
+    /// Number of commits looked at
+    pub checked: usize,
+    pub wrong: Vec<WrongCommit>,
+}
+
+/// Audit of a set of repositories
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub repositories: Vec<RepositoryReport>,
+    /// Wrong commits across all repositories
+    pub wrong_commits: usize,
+}
+
+impl Report {
+    /// Add a repository to the report
+    pub fn push(&mut self, repository: RepositoryReport) {
+        self.wrong_commits += repository.wrong.len();
+        self.repositories.push(repository);
+    }
+}
+
+/// Audit the latest `max_count` commits of the repository at `dir`
+///
+/// Repositories that resolve to no group are reported without checking
+/// their commits, since there is nothing to compare with.
+pub fn audit_repository(
+    config: &Config,
+    dir: &Path,
+    max_count: usize,
+) -> anyhow::Result<RepositoryReport> {
+    let mut report = RepositoryReport {
+        path: dir.to_path_buf(),
+        group: None,
+        reason: None,
+        checked: 0,
+        wrong: Vec::new(),
+    };
+    let Some(resolution) = resolve::resolve(config, Some(dir)) else {
+        log::debug!("{} resolves to no group", dir.display());
+        return Ok(report);
+    };
+    let commits = recent_commits(dir, max_count)?;
+    report.checked = commits.len();
+    report.wrong = wrong_commits(config, &resolution.group, commits);
+    report.group = Some(resolution.group);
+    report.reason = Some(resolution.reason);
+    Ok(report)
+}
+
+/// Commits authored by a group other than `expected`
+pub fn wrong_commits(
+    config: &Config,
+    expected: &str,
+    commits: Vec<(String, UserConfig, String)>,
+) -> Vec<WrongCommit> {
+    commits
+        .into_iter()
+        .filter_map(|(commit, author, subject)| {
+            let groups = config.match_groups(&author);
+            if groups.is_empty() || groups.iter().any(|group| group == expected) {
+                return None;
+            }
+            Some(WrongCommit {
+                commit,
+                name: author.name,
+                email: author.email,
+                subject,
+                groups,
+            })
+        })
+        .collect()
+}
+
+/// Commit id, author and subject of the latest `max_count` commits on local
+/// branches of the repository at `dir`
+pub fn recent_commits(
+    dir: &Path,
+    max_count: usize,
+) -> anyhow::Result<Vec<(String, UserConfig, String)>> {
+    Ok(parse_log(&git_log(dir, max_count)?))
+}
+
+/// `git log` output of the latest `max_count` commits on local branches
+fn git_log(dir: &Path, max_count: usize) -> anyhow::Result<String> {
+    let format = format!("--format=%H{0}%an{0}%ae{0}%s", FIELD_SEPARATOR);
+    let output = utils::output(
+        utils::git()
+            .arg("-C")
+            .arg(dir)
+            .args(["log", "--branches", "--no-color", &format])
+            .arg(format!("--max-count={}", max_count)),
+    )?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git log failed in {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `git log` lines into commit id, author and subject
+fn parse_log(log: &str) -> Vec<(String, UserConfig, String)> {
+    log.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, FIELD_SEPARATOR);
+            let commit = fields.next()?.to_string();
+            let author = UserConfig {
+                name: fields.next()?.to_string(),
+                email: fields.next()?.to_string(),
+                ..UserConfig::default()
+            };
+            Some((
+                commit,
+                author,
+                fields.next().unwrap_or_default().to_string(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrong_commits() {
+        let mut config = Config::new();
+        for (group, email) in [("work", "li@acme.com"), ("oss", "li@example.org")] {
+            config.groups.insert(
+                group.to_string(),
+                UserConfig {
+                    name: "Li".to_string(),
+                    email: email.to_string(),
+                    ..UserConfig::default()
+                },
+            );
+        }
+        let log = "a1\x1fLi\x1fli@acme.com\x1fFix build\n\
+                   b2\x1fLi\x1fli@example.org\x1fAdd feature\n\
+                   c3\x1fSomeone\x1fsomeone@acme.com\x1fReview fixes\n";
+        let wrong = wrong_commits(&config, "work", parse_log(log));
+        assert_eq!(
+            wrong,
+            [WrongCommit {
+                commit: "b2".to_string(),
+                name: "Li".to_string(),
+                email: "li@example.org".to_string(),
+                subject: "Add feature".to_string(),
+                groups: vec!["oss".to_string()],
+            }]
+        );
+    }
+}
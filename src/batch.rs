@@ -0,0 +1,345 @@
+//! # Batch Module
+//!
+//! Applies a list of configuration changes given as JSON, e.g. by a
+//! configuration management tool:
+//!
+//! ```json
+//! [
+//!   { "op": "set", "group": "work", "name": "Li Si", "email": "li@acme.com" },
+//!   { "op": "rule-add", "host": "*.acme.com", "group": "work" },
+//!   { "op": "delete", "group": "old" }
+//! ]
+//! ```
+//!
+//! The operations are all-or-nothing: when one of them fails, the groups,
+//! aliases and host rules are put back as they were, so nothing is saved.
+
+use crate::config::{self, Config, UserConfig};
+use crate::utils;
+use crate::validate;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// One change to the configuration file
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Operation {
+    /// Create a group or update the fields given
+    Set(Box<SetFields>),
+    /// Delete a group, with its aliases and host rules
+    Delete { group: String },
+    /// Map a git host (or `*.domain`) to a default group
+    #[serde(alias = "rule add")]
+    RuleAdd { host: String, group: String },
+}
+
+/// Fields of a `set` operation; empty strings remove optional fields
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SetFields {
+    pub group: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub committer_name: Option<String>,
+    pub committer_email: Option<String>,
+    pub commit_template: Option<String>,
+    pub inherit: Option<String>,
+    pub signing_key: Option<String>,
+    /// `openpgp`, `ssh` or `x509`; kept when only the key changes
+    pub signing_format: Option<String>,
+    pub http_proxy: Option<String>,
+    pub ssl_ca_info: Option<String>,
+    pub credential_helper: Option<String>,
+    pub sendemail_from: Option<String>,
+    pub smtp_server: Option<String>,
+    pub smtp_server_port: Option<String>,
+    pub smtp_user: Option<String>,
+    pub smtp_encryption: Option<String>,
+    /// Alternate emails to add (empty email to remove)
+    #[serde(default)]
+    pub email_variants: BTreeMap<String, String>,
+    /// Workstyle settings to add (empty value to remove)
+    #[serde(default)]
+    pub workstyle: BTreeMap<String, String>,
+    /// Commit trailer templates replacing the group's (empty list to remove)
+    pub trailers: Option<Vec<String>>,
+    /// Sign off commits and patches
+    pub signoff: Option<bool>,
+    /// Last day the group may be used, `YYYY-MM-DD`
+    pub expires: Option<String>,
+    /// Note on what the group is for (empty to remove)
+    pub description: Option<String>,
+    /// Skip email validation
+    #[serde(default)]
+    pub force: bool,
+}
+
+impl Operation {
+    /// Short description, e.g. `set work`
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Set(fields) => format!("set {}", fields.group),
+            Operation::Delete { group } => format!("delete {}", group),
+            Operation::RuleAdd { host, group } => format!("rule-add {} -> {}", host, group),
+        }
+    }
+}
+
+/// Parse a JSON array of operations
+pub fn parse(input: &str) -> anyhow::Result<Vec<Operation>> {
+    serde_json::from_str(input).map_err(|e| anyhow::anyhow!("Invalid batch: {}", e))
+}
+
+/// Apply operations to the configuration, all or none
+///
+/// On failure the error names the operation (counted from 1) and the
+/// configuration is left as it was. Saving is up to the caller.
+pub fn apply(config: &mut Config, operations: &[Operation]) -> anyhow::Result<()> {
+    let saved = (
+        config.groups.clone(),
+        config.aliases.clone(),
+        config.hosts.clone(),
+        config.branches.clone(),
+    );
+    for (i, operation) in operations.iter().enumerate() {
+        if let Err(e) = apply_one(config, operation) {
+            (config.groups, config.aliases, config.hosts, config.branches) = saved;
+            anyhow::bail!(
+                "Operation {} ({}) failed: {}",
+                i + 1,
+                operation.describe(),
+                e
+            );
+        }
+        log::debug!("Applied batch operation {}", operation.describe());
+    }
+    Ok(())
+}
+
+/// Apply a single operation
+fn apply_one(config: &mut Config, operation: &Operation) -> anyhow::Result<()> {
+    match operation {
+        Operation::Set(fields) => {
+            let group = config.normalize_group_name(&fields.group);
+            if validate::is_reserved_group_name(&group) {
+                anyhow::bail!("\"{}\" is a reserved group name", group);
+            }
+            config.check_unlocked(&group, false)?;
+            if config.get_group(&group).is_none() {
+                config
+                    .check_new_group_name(&group)
+                    .map_err(anyhow::Error::msg)?;
+            }
+            let mut user = config.groups.get(&group).cloned().unwrap_or_default();
+            set_fields(config, &mut user, fields)?;
+            config.groups.insert(group.clone(), user);
+            let resolved = config.resolve_group(&group)?;
+            validate::check_identity_complete(&resolved.name, &resolved.email)
+                .map_err(anyhow::Error::msg)?;
+        }
+        Operation::Delete { group } => {
+            let group = config.normalize_group_name(group);
+            if config.is_system_group(&group) {
+                anyhow::bail!("group {} comes from the system configuration", group);
+            }
+            config.check_unlocked(&group, false)?;
+            let children = config.children_of(&group);
+            if !children.is_empty() {
+                anyhow::bail!("{} inherit from group {}", children.join(", "), group);
+            }
+            config
+                .remove_group(&group)
+                .ok_or_else(|| anyhow::anyhow!("group {} does not exist", group))?;
+        }
+        Operation::RuleAdd { host, group } => {
+            let group = config.normalize_group_name(group);
+            if config.get_group(&group).is_none() {
+                anyhow::bail!("group {} does not exist", group);
+            }
+            let host = host.trim().to_lowercase();
+            if host.is_empty() {
+                anyhow::bail!("host is empty");
+            }
+            config.hosts.insert(host, group);
+        }
+    }
+    Ok(())
+}
+
+/// Copy the fields of a `set` operation onto a group
+fn set_fields(config: &Config, user: &mut UserConfig, fields: &SetFields) -> anyhow::Result<()> {
+    let new_emails = fields
+        .email
+        .iter()
+        .chain(&fields.committer_email)
+        .chain(fields.email_variants.values());
+    for email in new_emails.filter(|email| !email.is_empty()) {
+        if let Err(reason) = validate::check_email(email, &config.settings.allowed_email_domains)
+            && !fields.force
+        {
+            anyhow::bail!(reason);
+        }
+    }
+    for key in fields.workstyle.keys() {
+        if !config::is_workstyle_key(key) {
+            anyhow::bail!("\"{}\" is not a workstyle setting", key);
+        }
+    }
+    for template in fields.trailers.iter().flatten() {
+        validate::check_trailer(template).map_err(anyhow::Error::msg)?;
+    }
+    if let Some(format) = fields.signing_format.as_deref().filter(|f| !f.is_empty())
+        && !matches!(format, "openpgp" | "ssh" | "x509")
+    {
+        anyhow::bail!(
+            "\"{}\" is not a signing format (openpgp, ssh or x509)",
+            format
+        );
+    }
+    if let Some(expires) = fields.expires.as_deref().filter(|e| !e.is_empty())
+        && utils::parse_date(expires).is_none()
+    {
+        anyhow::bail!("\"{}\" is not a date like 2025-12-31", expires);
+    }
+
+    if let Some(ref name) = fields.name {
+        user.name = name.clone();
+    }
+    if let Some(ref email) = fields.email {
+        user.email = email.clone();
+    }
+    let optional = [
+        (&fields.committer_name, &mut user.committer_name),
+        (&fields.committer_email, &mut user.committer_email),
+        (&fields.commit_template, &mut user.commit_template),
+        (&fields.signing_key, &mut user.signing_key),
+        (&fields.signing_format, &mut user.signing_format),
+        (&fields.http_proxy, &mut user.http_proxy),
+        (&fields.ssl_ca_info, &mut user.ssl_ca_info),
+        (&fields.credential_helper, &mut user.credential_helper),
+        (&fields.sendemail_from, &mut user.sendemail_from),
+        (&fields.smtp_server, &mut user.smtp_server),
+        (&fields.smtp_server_port, &mut user.smtp_server_port),
+        (&fields.smtp_user, &mut user.smtp_user),
+        (&fields.smtp_encryption, &mut user.smtp_encryption),
+        (&fields.expires, &mut user.expires),
+        (&fields.description, &mut user.description),
+    ];
+    for (value, field) in optional {
+        if let Some(value) = value {
+            *field = Some(value.clone()).filter(|v| !v.is_empty());
+        }
+    }
+    if let Some(ref trailers) = fields.trailers {
+        user.trailers = trailers.clone();
+    }
+    if fields.signoff.is_some() {
+        user.signoff = fields.signoff;
+    }
+    if let Some(ref parent) = fields.inherit {
+        user.inherit = Some(config.normalize_group_name(parent)).filter(|p| !p.is_empty());
+    }
+    for (maps, target) in [
+        (&fields.email_variants, &mut user.email_variants),
+        (&fields.workstyle, &mut user.workstyle),
+    ] {
+        for (key, value) in maps {
+            if value.is_empty() {
+                target.remove(key);
+            } else {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let operations = parse(
+            r#"[
+                {"op": "set", "group": "work", "name": "Li", "email": "li@acme.com"},
+                {"op": "rule add", "host": "*.acme.com", "group": "work"},
+                {"op": "delete", "group": "old"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(operations.len(), 3);
+        assert_eq!(operations[1].describe(), "rule-add *.acme.com -> work");
+        assert!(parse(r#"[{"op": "rename", "group": "x"}]"#).is_err());
+        assert!(parse(r#"[{"op": "delete", "group": "x", "typo": 1}]"#).is_err());
+    }
+
+    #[test]
+    fn test_apply_all_or_nothing() {
+        let mut config = Config::new();
+        let operations = parse(
+            r#"[
+                {"op": "set", "group": "work", "name": "Li", "email": "li@acme.com",
+                 "workstyle": {"pull.rebase": "true"}},
+                {"op": "set", "group": "oss", "inherit": "work", "email": "li@example.com"},
+                {"op": "rule-add", "host": "GitHub.com", "group": "oss"}
+            ]"#,
+        )
+        .unwrap();
+        apply(&mut config, &operations).unwrap();
+        assert_eq!(config.resolve_group("oss").unwrap().name, "Li");
+        assert_eq!(config.hosts["github.com"], "oss");
+
+        // The inherited group cannot go, and the set before it is rolled back
+        let operations = parse(
+            r#"[
+                {"op": "set", "group": "work", "name": "Li Si"},
+                {"op": "delete", "group": "work"}
+            ]"#,
+        )
+        .unwrap();
+        let err = apply(&mut config, &operations).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Operation 2 (delete work) failed: oss inherit from group work"
+        );
+        assert_eq!(config.groups["work"].name, "Li");
+
+        let operations = parse(r#"[{"op": "set", "group": "x", "email": "bad"}]"#).unwrap();
+        assert!(apply(&mut config, &operations).is_err());
+        assert!(!config.groups.contains_key("x"));
+        let operations = parse(r#"[{"op": "set", "group": "x", "email": "x@ex.org"}]"#).unwrap();
+        let err = apply(&mut config, &operations).unwrap_err().to_string();
+        assert!(err.ends_with("name is empty"), "{}", err);
+        assert!(!config.groups.contains_key("x"));
+
+        let operations =
+            parse(r#"[{"op": "set", "group": "oss", "expires": "2025-02-30"}]"#).unwrap();
+        assert!(apply(&mut config, &operations).is_err());
+        let operations =
+            parse(r#"[{"op": "set", "group": "oss", "expires": "2025-12-31"}]"#).unwrap();
+        apply(&mut config, &operations).unwrap();
+        assert_eq!(config.groups["oss"].expires.as_deref(), Some("2025-12-31"));
+
+        // The format is kept when only the key changes
+        let operations = parse(
+            r#"[{"op": "set", "group": "oss", "signing_key": "~/.ssh/a.pub", "signing_format": "ssh"},
+                {"op": "set", "group": "oss", "signing_key": "~/.ssh/b.pub"}]"#,
+        )
+        .unwrap();
+        apply(&mut config, &operations).unwrap();
+        assert_eq!(config.groups["oss"].signing_format.as_deref(), Some("ssh"));
+        let operations =
+            parse(r#"[{"op": "set", "group": "oss", "signing_format": "pgp"}]"#).unwrap();
+        assert!(apply(&mut config, &operations).is_err());
+
+        config.groups.get_mut("oss").unwrap().locked = true;
+        for operation in [
+            r#"[{"op": "set", "group": "oss", "name": "Lee"}]"#,
+            r#"[{"op": "delete", "group": "oss"}]"#,
+        ] {
+            let err = apply(&mut config, &parse(operation).unwrap()).unwrap_err();
+            assert!(err.to_string().ends_with("oss is locked"));
+        }
+    }
+}
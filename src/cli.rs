@@ -8,8 +8,20 @@
 //! ## Main Components
 //! - `Cli`: Main CLI struct, contains subcommands.
 //! - `Commands`: Subcommand enum, defines all available commands.
+//! - `ConfigCommands`: Subcommands operating on the configuration file itself.
+//! - `AliasCommands`: Subcommands managing group name aliases.
+//! - `TemplateCommands`: Subcommands managing the git template directory.
+//! - `RuleCommands`: Subcommands managing host and branch rules.
+//! - `PolicyCommands`: Subcommands managing push policies.
+//! - `HookCommands`: Subcommands installing git hooks.
+//! - `PairCommands`: Subcommands of pair programming.
 
-use clap::{Parser, Subcommand};
+use crate::completions::Shell;
+use crate::hooks::HookKind;
+use crate::logging::LogFormat;
+use clap::{ArgAction, ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use std::net::SocketAddr;
+use std::path::PathBuf;
 
 /// Main command line interface struct
 ///
@@ -20,10 +32,124 @@ use clap::{Parser, Subcommand};
 #[command(name = "gum")]
 #[command(about = "Git multiple user config manager")]
 #[command(version)]
+#[command(infer_subcommands = true)]
 pub struct Cli {
+    /// Path to the configuration file (overrides GUM_CONFIG and the platform default)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Workspace whose groups, rules and history to use (overrides
+    /// GUM_WORKSPACE and `gum workspace use`)
+    #[arg(long, global = true, value_name = "NAME")]
+    pub workspace: Option<String>,
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+    /// Disable log output
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Log record format
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+    /// Append log records to this file instead of stderr
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    /// Print the git commands and file edits instead of running them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// Report on stderr how long reading config files, each git command and
+    /// rendering took
+    #[arg(long, global = true)]
+    pub timings: bool,
+    /// Do not pipe long output into a pager
+    #[arg(long, global = true)]
+    pub no_pager: bool,
+    /// Never prompt; commands that need an answer fail with exit status 3
+    ///
+    /// Enabled automatically when stdin is not a terminal.
+    #[arg(long, global = true)]
+    pub non_interactive: bool,
+    /// Mask emails (`l***@acme.com`) in output, logs and errors, e.g. while
+    /// sharing the screen
+    #[arg(long, global = true)]
+    pub redact: bool,
+    /// Print without colors or box-drawing characters: aligned columns and
+    /// `key: value` lines for screen readers, CI logs and dumb terminals
+    #[arg(long, global = true)]
+    pub plain: bool,
+    /// Serve JSON-RPC on stdin/stdout for editor integrations
+    #[arg(long)]
+    pub rpc: bool,
     /// Subcommand enum, specifies the operation to execute
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Command>,
+}
+
+/// Names and visible aliases of the subcommands starting with `prefix`
+///
+/// Subcommands can be abbreviated to any unambiguous prefix; this lists
+/// the candidates of an ambiguous one.
+pub fn commands_starting_with(prefix: &str) -> Vec<String> {
+    let mut names: Vec<String> = Cli::command()
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set())
+        .flat_map(|sub| std::iter::once(sub.get_name()).chain(sub.get_visible_aliases()))
+        .filter(|name| name.starts_with(prefix))
+        .map(str::to_string)
+        .collect();
+    names.sort();
+    names
+}
+
+/// A subcommand: one of the [`StandaloneCommands`] run before the
+/// configuration is loaded, or one of the [`Commands`] run with it
+///
+/// The standalone ones come first, since [`Commands::External`] takes any
+/// name it is offered.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Standalone(StandaloneCommands),
+    #[command(flatten)]
+    Configured(Commands),
+}
+
+/// Subcommands run without loading the configuration, since shells run them
+/// on every prompt or start
+#[derive(Subcommand, Debug)]
+pub enum StandaloneCommands {
+    /// Print a one-line identity segment for shell prompts
+    ///
+    /// Shows the group holding the effective git identity (green), in red
+    /// when a `hosts` rule of the repository's remotes asks for another
+    /// group, or the identity itself in red when no group holds it. Prints
+    /// nothing when no identity is configured. Skips full config loading to
+    /// stay fast.
+    Prompt {
+        /// Symbol printed before the group name
+        #[arg(long)]
+        symbol: Option<String>,
+        /// Disable ANSI colors
+        #[arg(long)]
+        no_color: bool,
+        /// Emit tmux format strings instead of ANSI colors (caches for 5 seconds by default)
+        #[arg(long, conflicts_with = "no_color")]
+        tmux: bool,
+        /// Resolve the identity for this directory instead of the working directory
+        #[arg(long, value_name = "DIR")]
+        path: Option<PathBuf>,
+        /// Reuse the segment rendered for the same directory within this many seconds
+        #[arg(long, value_name = "SECONDS")]
+        cache_ttl: Option<u64>,
+    },
+    /// Print the shell completion script
+    ///
+    /// Completes subcommands, short and long options, e.g.
+    /// `eval "$(gum completions zsh)"` in `~/.zshrc`.
+    Completions {
+        /// Shell to complete in
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
 /// Subcommand enum
@@ -36,39 +162,930 @@ pub enum Commands {
     ///
     /// This command displays a list of all currently stored Git user configuration groups.
     /// Each configuration group contains username and email information.
-    List,
+    #[command(visible_alias = "ls")]
+    List {
+        /// Also show the aliases, signing key, tags, description, when each
+        /// group was last used, how many host rules pick it and its problems
+        /// (`--verbose` sets the log level)
+        #[arg(short, long)]
+        long: bool,
+        /// Keep the table layout even when it is wider than the terminal
+        #[arg(short, long)]
+        wide: bool,
+        /// Print each group with a template instead of the table, e.g.
+        /// '{group}\t{email}'
+        #[arg(long, conflicts_with_all = ["long", "wide"])]
+        template: Option<String>,
+        /// Also show the remotes of the current repository, the rule each
+        /// matches and the group it would choose
+        #[arg(long, conflicts_with = "template")]
+        remotes: bool,
+        /// Show the groups under a heading per tag, with the number of groups
+        /// in each; untagged groups come last
+        #[arg(long, conflicts_with = "template")]
+        tree: bool,
+    },
     /// Set a user configuration group
     ///
     /// Creates or updates a specified user configuration group. Can specify group name,
     /// username, and email. If the group exists, its configuration will be updated;
-    /// otherwise a new group will be created.
-    Set {
-        /// Name of the configuration group, used to identify different user configurations
-        group_name: String,
-        /// Optional username, if provided will set the username for this group
-        #[arg(long)]
-        name: Option<String>,
-        /// Optional email, if provided will set the email for this group
-        #[arg(long)]
-        email: Option<String>,
-    },
+    /// otherwise a new group will be created. When neither username nor email is
+    /// given, both are asked for interactively.
+    #[command(visible_alias = "add")]
+    Set(Box<SetArgs>),
     /// Use specified configuration group
     ///
     /// Applies the specified user configuration group to Git configuration.
     /// Can choose to set it as global or local configuration.
+    #[command(visible_aliases = ["sw", "u"])]
     Use {
         /// Name of the configuration group to use
-        group_name: String,
+        ///
+        /// Defaults to `GUM_GROUP`, then to the group mapped to the host of
+        /// the repository's remote in the `hosts` section of the config file.
+        /// `GUM_NAME` and `GUM_EMAIL` override the applied name and email,
+        /// and together apply an identity without any group.
+        group_name: Option<String>,
         /// Whether to set as global Git configuration (default is local)
         #[arg(long)]
         global: bool,
+        /// Set the local Git configuration; with `--global`, both
+        #[arg(long)]
+        local: bool,
+        /// Set the global Git configuration and, inside a repository, the
+        /// local one too, e.g. when re-provisioning a machine
+        #[arg(long, conflicts_with_all = ["global", "local"])]
+        all_scopes: bool,
+        /// Use one of the group's alternate emails instead of its primary one
+        #[arg(long, value_name = "VARIANT")]
+        email_variant: Option<String>,
+        /// Also apply the group's workstyle (aliases, editor, pull and push settings)
+        #[arg(long)]
+        with_workstyle: bool,
+        /// Revert to the previous identity after this long, e.g. `30m`,
+        /// `2h` or `1d`
+        #[arg(long = "for", value_name = "DURATION")]
+        for_duration: Option<String>,
+        /// Revert to the previous identity once the current shell exits
+        #[arg(long, conflicts_with = "for_duration")]
+        until_shell_exit: bool,
+    },
+    /// Run a command as a group, without changing any git configuration
+    ///
+    /// Sets `GIT_AUTHOR_NAME`/`GIT_AUTHOR_EMAIL` from the group's identity and
+    /// `GIT_COMMITTER_NAME`/`GIT_COMMITTER_EMAIL` from its committer fields
+    /// (the identity when unset), and passes the group's git settings through
+    /// `GIT_CONFIG_COUNT`, e.g. `gum exec bot -- git commit`. Exits with the
+    /// command's exit status.
+    Exec {
+        /// Name of the configuration group to run as
+        ///
+        /// Defaults to `GUM_GROUP`. `GUM_NAME` and `GUM_EMAIL` override the
+        /// name and email, and together run as an identity without any group.
+        group_name: Option<String>,
+        /// Command and its arguments, after `--`
+        #[arg(
+            required = true,
+            last = true,
+            allow_hyphen_values = true,
+            value_name = "COMMAND"
+        )]
+        command: Vec<String>,
+    },
+    /// Start a shell committing as a group, without changing any git
+    /// configuration
+    ///
+    /// Runs `$SHELL` with the environment of `gum exec`, so every git
+    /// command of the session uses the group until the shell exits. With
+    /// `--print`, prints `export` statements to evaluate in the current
+    /// shell instead: `eval "$(gum shell work --print)"`.
+    Shell {
+        /// Name of the configuration group to use in the session
+        group_name: String,
+        /// Print `export` statements instead of starting a shell
+        #[arg(long)]
+        print: bool,
     },
-    /// Delete specified configuration group
+    /// Remove the identity and group settings from a git scope
     ///
-    /// Deletes the specified user configuration group from storage.
-    /// After deletion, the configuration group will no longer be available.
+    /// Unsets `user.name` and `user.email`, plus the settings `use` applies
+    /// (template, SSH key, signing, proxy, CA bundle, credential helper and
+    /// workstyle) when a group set them. Values configured by hand are kept.
+    /// `gum undo` puts everything back.
+    Unset {
+        /// Unset the global git configuration (default is local)
+        #[arg(long)]
+        global: bool,
+    },
+    /// Delete specified configuration groups
+    ///
+    /// Deletes the specified user configuration groups from storage and
+    /// reports each one; groups that cannot be deleted are skipped and the
+    /// config file is saved once. After deletion, the configuration groups
+    /// will no longer be available.
+    #[command(visible_alias = "rm")]
     Delete {
-        /// Name of the configuration group to delete
+        /// Names of the configuration groups to delete
+        #[arg(required_unless_present = "interactive", value_name = "GROUP_NAME")]
+        group_names: Vec<String>,
+        /// Choose the groups to delete from a list, confirming once
+        #[arg(short, long, conflicts_with = "group_names")]
+        interactive: bool,
+        /// Delete the group even if it is locked
+        #[arg(long)]
+        force: bool,
+    },
+    /// Open the configuration file in your editor
+    ///
+    /// The file is edited as a copy and validated when the editor exits;
+    /// a copy that does not parse can be reopened or discarded, so the real
+    /// file is never left broken.
+    Edit,
+    /// Merge configuration groups that hold the same identity
+    ///
+    /// Lists every set of groups sharing a name and email, asks which group
+    /// to keep, and deletes the others.
+    Dedupe {
+        /// Also merge locked groups
+        #[arg(long)]
+        force: bool,
+    },
+    /// Run gum as a long-running server
+    ///
+    /// With `--mcp`, speaks the Model Context Protocol on stdin/stdout so AI
+    /// coding agents can list groups, resolve the identity of a repository,
+    /// and apply a group before committing.
+    ///
+    /// With `--addr`, serves a local HTTP API for GUI frontends; requests
+    /// must send the token of `--token-file` as `Authorization: Bearer`.
+    #[command(group(ArgGroup::new("mode").required(true)))]
+    Serve {
+        /// Serve the Model Context Protocol over stdio
+        #[arg(long, group = "mode")]
+        mcp: bool,
+        /// Serve the HTTP API on this address, e.g. 127.0.0.1:7337
+        #[arg(long, group = "mode", value_name = "ADDR")]
+        addr: Option<SocketAddr>,
+        /// File holding the API token, created if missing
+        /// (default: gum's state directory)
+        #[arg(long, requires = "addr", value_name = "PATH")]
+        token_file: Option<PathBuf>,
+        /// Allow an `--addr` that is not a loopback address, exposing the
+        /// API to the network
+        #[arg(long, requires = "addr")]
+        allow_remote: bool,
+    },
+    /// Apply rule-resolved identities to repositories created in a workspace
+    ///
+    /// Checks the directories every few seconds for new repositories (a new
+    /// `.git` directory or file) and applies the group their remote resolves
+    /// to, before the first commit. Repositories that already have a local
+    /// identity are left alone. Runs until interrupted.
+    Watch {
+        /// Workspace directories to watch
+        #[arg(required = true, value_name = "DIR")]
+        dirs: Vec<PathBuf>,
+        /// Only report the resolved group instead of applying it
+        #[arg(long)]
+        notify: bool,
+        /// Seconds between checks
+        #[arg(long, value_name = "SECONDS", default_value_t = 2)]
+        interval: u64,
+        /// How many directory levels below each workspace to look
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        depth: usize,
+    },
+    /// Find commits authored with the wrong group
+    ///
+    /// A commit is reported when one of your groups authored it but the
+    /// repository resolves to another group through its remotes. Exits with
+    /// an error when any is found.
+    Audit {
+        /// Audit every repository under the directories instead of the
+        /// current one
+        #[arg(long)]
+        all_repos: bool,
+        /// Directories to scan with --all-repos (default: current directory)
+        #[arg(value_name = "DIR", requires = "all_repos")]
+        dirs: Vec<PathBuf>,
+        /// How many directory levels below each directory to look
+        #[arg(long, value_name = "N", default_value_t = 3, requires = "all_repos")]
+        depth: usize,
+        /// Number of most recent commits to check per repository
+        #[arg(long, value_name = "N", default_value_t = 1000)]
+        max_count: usize,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Suggest the group of the current repository from its history
+    ///
+    /// Weighs the recent commits authored by each group's identities, the
+    /// host rules the remotes resolve to and the remote owner (organization)
+    /// appearing in a group's email domain.
+    Infer {
+        /// Number of most recent commits to look at
+        #[arg(long, value_name = "N", default_value_t = 200)]
+        max_count: usize,
+        /// Use the suggested group in the repository
+        #[arg(long, conflicts_with = "json")]
+        apply: bool,
+        /// Print the candidates as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check commit signatures against the groups' signing keys
+    ///
+    /// A commit authored by a group with a signing key is flagged when it is
+    /// unsigned, its signature is invalid or cannot be checked, or it was
+    /// signed with another key. Exits with an error when any is flagged.
+    VerifyCommits {
+        /// Check the commits after this ref (`<ref>..HEAD`) instead of the
+        /// latest ones
+        #[arg(long, value_name = "REF")]
+        since: Option<String>,
+        /// Maximum number of commits to check
+        #[arg(long, value_name = "N", default_value_t = 1000)]
+        max_count: usize,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show a group with its inherited values resolved
+    Show {
+        /// Name of the configuration group to show
+        group_name: String,
+        /// Print the group with a template, e.g. '{name} <{email}>'
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// Manage short aliases for group names
+    ///
+    /// Aliases are accepted everywhere a group name is, e.g. `gum use w`
+    /// after `gum alias add w work`.
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommands,
+    },
+    /// Manage the rules picking a group from a repository's remotes
+    Rule {
+        #[command(subcommand)]
+        command: RuleCommands,
+    },
+    /// Manage the policies forbidding emails to push to remotes
+    ///
+    /// Enforced on pushed commits by the pre-push hook (`gum hook install
+    /// pre-push`) and on the current identity by `gum check`.
+    Policy {
+        #[command(subcommand)]
+        command: PolicyCommands,
+    },
+    /// Check the repository's identity against the push policies and the
+    /// rule of the current branch
+    ///
+    /// Fails when the identity may not push to one of the remotes or may
+    /// not commit to the current branch.
+    Check,
+    /// Manage the git template directory
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+    /// Pair or mob program: commit as one group, crediting the others
+    Pair {
+        #[command(subcommand)]
+        command: PairCommands,
+    },
+    /// Install gum's git hooks
+    Hook {
+        #[command(subcommand)]
+        command: HookCommands,
+    },
+    /// Manage SSH settings of groups
+    Ssh {
+        #[command(subcommand)]
+        command: SshCommands,
+    },
+    /// Offer to delete groups that have not been used for a long time
+    ///
+    /// Usage is taken from the history of `use`; groups still configured
+    /// globally or in a repository they were applied to, and groups other
+    /// groups inherit from, are kept.
+    Prune {
+        /// Minimum number of months (30 days) since the group was last used
+        #[arg(long, default_value_t = 6)]
+        months: u32,
+        /// Also offer locked groups
+        #[arg(long)]
+        force: bool,
+    },
+    /// Apply configuration changes from a JSON array, all or nothing
+    ///
+    /// Each element is an operation: `{"op": "set", "group": ..., "name":
+    /// ..., "email": ...}`, `{"op": "delete", "group": ...}` or
+    /// `{"op": "rule-add", "host": ..., "group": ...}`. When one operation
+    /// fails, the config file is left untouched.
+    Batch {
+        /// File holding the operations, `-` for stdin
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+    },
+    /// Save the current global and repository identities as groups
+    ///
+    /// Creates `<name>-global` and, inside a repository, `<name>-local`
+    /// holding the identity and the settings `use` manages, so the machine
+    /// can be put back with `gum use <name>-global --global`.
+    Snapshot {
+        /// Prefix of the group names (default: `snapshot-<date>-<time>`)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Move every group email on one domain to another
+    ///
+    /// Primary, committer and alternate emails of user groups are updated.
+    /// Repositories whose last recorded group changed are listed, and
+    /// re-applied with `--apply`. Combine with `--dry-run` to preview.
+    MigrateDomain {
+        /// Domain the emails are on, e.g. `old-corp.com`
+        old: String,
+        /// Domain they move to
+        new: String,
+        /// Apply the updated groups to the listed repositories
+        #[arg(long)]
+        apply: bool,
+        /// Also update locked groups
+        #[arg(long)]
+        force: bool,
+    },
+    /// Turn the repository's local identity into a group
+    ///
+    /// Matches the group already holding the identity or creates one, named
+    /// after the email domain unless a name is given. The repository is
+    /// recorded in the history as using the group.
+    Adopt {
+        /// Name of the group to match or create
+        group_name: Option<String>,
+        /// Also add a rule mapping the repository's `host/owner` to the group
+        #[arg(long)]
+        rule: bool,
+    },
+    /// Restore the identity that was set before the last `use`
+    ///
+    /// Settings that were not set before are removed again. Undoing again
+    /// goes further back in the history.
+    Undo,
+    /// Show the identity changes made by gum
+    History {
+        /// Only show changes to the repository containing this directory
+        #[arg(long, value_name = "DIR")]
+        repo: Option<PathBuf>,
+        /// Number of most recent entries to show
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        limit: usize,
+    },
+    /// Clean up the state gum accumulates
+    ///
+    /// Drops old config file versions, trims the `use` history, rotates the
+    /// change journal and removes stale prompt caches, as set under
+    /// `settings.retention`.
+    Gc,
+    /// Show the journal of every change gum made to git settings and files
+    ///
+    /// Each entry tells when, where and what changed, with the old and new
+    /// value, or the lines of a file that were replaced and added.
+    Log {
+        /// Number of most recent entries to show
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        limit: usize,
+        /// Print the entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the group matching the current git identity
+    ///
+    /// Prints one group name per line, or fails when no group holds the
+    /// identity, so it can be used from scripts and prompts.
+    Which {
+        /// Read the identity from one scope instead of the effective one
+        #[arg(long, value_enum)]
+        scope: Option<Scope>,
+    },
+    /// Print one field of a group, for scripts
+    ///
+    /// Prints the bare value, e.g. `gum get work email`, and fails when the
+    /// field is not set.
+    Get {
+        /// Name of the configuration group
+        #[arg(required_unless_present = "current")]
+        group_name: Option<String>,
+        /// Field to print (a key of the group in the config file)
+        #[arg(required_unless_present = "current")]
+        field: Option<String>,
+        /// Print a field of the current git identity instead
+        ///
+        /// `name` and `email` come from git; other fields from the group
+        /// holding the identity.
+        #[arg(long, value_name = "FIELD", conflicts_with_all = ["group_name", "field"])]
+        current: Option<String>,
+    },
+    /// Show what `use` would change in the git configuration
+    ///
+    /// Compares the group's identity and settings with the effective git
+    /// configuration, field by field.
+    Diff {
+        /// Name of the configuration group
+        group_name: String,
+        /// Compare against the global configuration
+        #[arg(long)]
+        global: bool,
+    },
+    /// Check that a group's commit signing key works
+    ///
+    /// Signs a throwaway payload with the group's key the way git would and
+    /// reports missing, expired or revoked keys and agent problems.
+    VerifySigning {
+        /// Name of the configuration group (default: the group of the
+        /// current identity)
+        group_name: Option<String>,
+    },
+    /// Create groups from code hosting accounts or other identity switchers
+    ///
+    /// Reads the name and email of each account (the noreply address on
+    /// GitHub, the commit email on GitLab) and stores them as a group named
+    /// after the login. With `--from`, the profiles saved by another tool
+    /// are imported instead. Existing groups and identities are
+    /// left untouched.
+    Import(ImportArgs),
+    /// Print groups in a format other tools can load
+    ///
+    /// With `--shell-aliases`, prints a `git-as-<group>` alias per group that
+    /// runs git with the group's identity set through environment variables,
+    /// e.g. `eval "$(gum export --shell-aliases)"`.
+    #[command(group(ArgGroup::new("format").required(true)))]
+    Export {
+        /// Print `alias git-as-<group>=...` definitions for POSIX shells
+        #[arg(long, group = "format")]
+        shell_aliases: bool,
+    },
+    /// Set gum up step by step
+    ///
+    /// Turns the global identity into a group, optionally creates a work
+    /// group, applies a default group globally, installs shell completions
+    /// and optionally the guard hook for new repositories. Offered on the
+    /// first run without a config file.
+    Onboard,
+    /// Update gum to the latest release
+    ///
+    /// Downloads the release archive for this platform from GitHub, verifies
+    /// its minisign signature and replaces the running executable.
+    SelfUpdate {
+        /// Only check whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
+    /// Inspect or maintain the configuration file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Manage workspaces, separate sets of groups, rules and history
+    ///
+    /// Each workspace has its own configuration file and state directory, so
+    /// the groups of one client never show up while working for another.
+    /// `default` stands for the files used without a workspace.
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommands,
+    },
+    /// External subcommand, executed as `gum-<name>` from PATH
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+/// A git configuration scope gum writes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scope {
+    Global,
+    Local,
+}
+
+/// Output format of listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// Arguments of the `set` command
+#[derive(Args, Debug)]
+pub struct SetArgs {
+    /// Name of the configuration group, used to identify different user configurations
+    pub group_name: String,
+    /// Optional username, if provided will set the username for this group
+    #[arg(long, conflicts_with = "from_current")]
+    pub name: Option<String>,
+    /// Optional email, if provided will set the email for this group
+    #[arg(long, conflicts_with = "from_current")]
+    pub email: Option<String>,
+    /// Committer name when it differs from the author, e.g. a bot committing
+    /// on your behalf (empty to remove)
+    #[arg(long, value_name = "NAME")]
+    pub committer_name: Option<String>,
+    /// Committer email when it differs from the author (empty to remove)
+    #[arg(long, value_name = "EMAIL")]
+    pub committer_email: Option<String>,
+    /// Take username and email from the git identity currently configured
+    #[arg(long)]
+    pub from_current: bool,
+    /// With --from-current, read the global identity only
+    #[arg(long, requires = "from_current", conflicts_with = "local")]
+    pub global: bool,
+    /// With --from-current, read the identity of the current repository only
+    #[arg(long, requires = "from_current")]
+    pub local: bool,
+    /// Commit message template applied as `commit.template` by `use` (empty to remove)
+    #[arg(long, value_name = "PATH")]
+    pub commit_template: Option<String>,
+    /// Add an alternate email, e.g. `noreply=li@users.noreply.github.com` (`noreply=` removes it)
+    #[arg(long = "email-variant", value_name = "VARIANT=EMAIL")]
+    pub email_variants: Vec<String>,
+    /// Proxy applied as `http.proxy` by `use` (empty to remove)
+    #[arg(long, value_name = "URL")]
+    pub http_proxy: Option<String>,
+    /// CA bundle applied as `http.sslCAInfo` by `use` (empty to remove)
+    #[arg(long, value_name = "PATH")]
+    pub ssl_ca_info: Option<String>,
+    /// Credential helper applied as `credential.helper` by `use` (empty to remove)
+    #[arg(long, value_name = "HELPER")]
+    pub credential_helper: Option<String>,
+    /// Sender of `git send-email`, applied as `sendemail.from` (empty to remove)
+    #[arg(long, value_name = "ADDRESS")]
+    pub sendemail_from: Option<String>,
+    /// SMTP server applied as `sendemail.smtpServer` (empty to remove)
+    #[arg(long, value_name = "HOST")]
+    pub smtp_server: Option<String>,
+    /// SMTP port applied as `sendemail.smtpServerPort` (empty to remove)
+    #[arg(long, value_name = "PORT")]
+    pub smtp_server_port: Option<String>,
+    /// SMTP login applied as `sendemail.smtpUser` (empty to remove)
+    #[arg(long, value_name = "USER")]
+    pub smtp_user: Option<String>,
+    /// SMTP encryption applied as `sendemail.smtpEncryption`: ssl or tls (empty to remove)
+    #[arg(long, value_name = "ssl|tls")]
+    pub smtp_encryption: Option<String>,
+    /// Add a workstyle setting, e.g. `pull.rebase=true` (`pull.rebase=` removes it)
+    ///
+    /// Accepts `alias.*`, `core.editor`, `pull.rebase` and `push.default`;
+    /// applied by `use --with-workstyle`.
+    #[arg(long = "workstyle", value_name = "KEY=VALUE")]
+    pub workstyle: Vec<String>,
+    /// Rewrite remote URLs starting with a prefix, e.g.
+    /// `git@github.com:acme/=git@github-work:acme/` (`PREFIX=` removes it)
+    ///
+    /// Applied as `url.<replacement>.insteadOf` by `use`.
+    #[arg(long = "url-rewrite", value_name = "PREFIX=REPLACEMENT")]
+    pub url_rewrites: Vec<String>,
+    /// Commit trailer added by the commit-msg hook, e.g. `Signed-off-by: {name} <{email}>`
+    ///
+    /// Repeat for several trailers; the ones given replace the group's list
+    /// (`--trailer ""` removes them). `{name}` and `{email}` stand for the
+    /// committer. Install the hook with `gum hook install commit-msg`.
+    #[arg(long = "trailer", value_name = "TEMPLATE")]
+    pub trailers: Vec<String>,
+    /// Sign off the group's commits and patches (`format.signOff` and a
+    /// `Signed-off-by` trailer from the commit-msg hook)
+    #[arg(long, value_name = "true|false")]
+    pub signoff: Option<bool>,
+    /// Take fields this group leaves empty from another group (empty to remove)
+    #[arg(long, value_name = "GROUP")]
+    pub inherit: Option<String>,
+    /// Key signing the group's commits (empty to remove)
+    ///
+    /// An OpenPGP key must carry a user ID with the group's email.
+    #[arg(long, value_name = "KEY", conflicts_with = "pick_gpg_key")]
+    pub signing_key: Option<String>,
+    /// Choose the signing key among the gpg secret keys matching the email
+    #[arg(long)]
+    pub pick_gpg_key: bool,
+    /// Signature format of the signing key (`gpg.format`); kept when only
+    /// the key changes
+    ///
+    /// Only OpenPGP keys are checked against the group's email.
+    #[arg(long, value_name = "FORMAT", value_parser = ["openpgp", "ssh", "x509"],
+          conflicts_with = "pick_gpg_key")]
+    pub signing_format: Option<String>,
+    /// Protect the group from `set` and `delete` without `--force`
+    #[arg(long, value_name = "true|false")]
+    pub locked: Option<bool>,
+    /// Last day the group may be used, e.g. `2025-12-31` (empty to remove)
+    ///
+    /// `list` flags the group afterwards, `use` warns and `prune` offers to
+    /// delete it.
+    #[arg(long, value_name = "DATE")]
+    pub expires: Option<String>,
+    /// Tag sorting the group under a heading in `list --tree`, e.g. `work`
+    ///
+    /// Repeat for several tags; the ones given replace the group's list
+    /// (`--tag ""` removes them).
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Note on what the group is for, shown by `show` and `list --long`
+    /// (empty to remove)
+    #[arg(long, value_name = "TEXT")]
+    pub description: Option<String>,
+    /// Skip email and signing key validation, and change a locked group
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Arguments of the `import` command
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("source").required(true)))]
+pub struct ImportArgs {
+    /// Import the accounts logged in to the GitHub CLI (`gh`)
+    #[arg(long, group = "source")]
+    pub from_gh: bool,
+    /// Import the current GitLab account (via `glab` or the API)
+    #[arg(long, group = "source")]
+    pub from_gitlab: bool,
+    /// Import the profiles of another identity switcher
+    #[arg(long, value_name = "TOOL", group = "source")]
+    pub from: Option<ImportTool>,
+    /// With --from, read this file instead of the tool's default location
+    #[arg(long, value_name = "PATH", requires = "from")]
+    pub file: Option<PathBuf>,
+    /// Host of a self-managed GitLab instance (default: gitlab.com)
+    #[arg(long, value_name = "HOST", requires = "from_gitlab")]
+    pub host: Option<String>,
+    /// Read the account owning the API token in this file (`-` for stdin)
+    /// instead of using the CLI
+    ///
+    /// The token is never taken as an argument, where other users could see
+    /// it in the process list.
+    #[arg(long, value_name = "PATH", conflicts_with = "from")]
+    pub token_file: Option<PathBuf>,
+}
+
+/// Identity switchers `import --from` understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportTool {
+    /// `git-user-switch` (`gus`)
+    GitUserSwitch,
+    /// `git-profile` (`~/.gitprofile`)
+    Gitprofile,
+    /// `git-identity` (`identity.*` in the global git config)
+    GitIdentity,
+}
+
+/// SSH subcommands
+#[derive(Subcommand, Debug)]
+pub enum SshCommands {
+    /// Route a host alias to a forge with the group's key
+    ///
+    /// Writes a `Host` block into `~/.ssh/config` (inside a section marked as
+    /// managed by gum) and records the alias on the group. Clone with
+    /// `git@<alias>:owner/repo.git` to use the key.
+    Setup {
+        /// Name of the configuration group
         group_name: String,
+        /// Private key file to use
+        #[arg(long, value_name = "PATH")]
+        key: String,
+        /// Forge host the alias points to
+        #[arg(long, value_name = "HOST", default_value = "github.com")]
+        host: String,
+        /// Host alias to write (default: `<forge>-<group>`, e.g. github-work)
+        #[arg(long, value_name = "ALIAS")]
+        alias: Option<String>,
+        /// Change the group even if it is locked
+        #[arg(long)]
+        force: bool,
+    },
+    /// Generate an ed25519 keypair for a group
+    ///
+    /// The key is recorded on the group, and `use` points `core.sshCommand`
+    /// at it.
+    Keygen {
+        /// Name of the configuration group
+        group_name: String,
+        /// Private key file to create (default: ~/.ssh/id_ed25519_<group>)
+        #[arg(long, value_name = "PATH")]
+        path: Option<String>,
+        /// Print the public key, e.g. to paste it into the forge
+        #[arg(long)]
+        print: bool,
+        /// Also sign commits with the key (`gpg.format ssh`)
+        #[arg(long)]
+        signing: bool,
+        /// Create the key without a passphrase instead of asking for one
+        #[arg(long)]
+        no_passphrase: bool,
+        /// Change the group even if it is locked
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Workspace subcommands
+#[derive(Subcommand, Debug)]
+pub enum WorkspaceCommands {
+    /// List the workspaces, marking the active one
+    List,
+    /// Create an empty workspace
+    New {
+        /// Workspace name (letters, digits, `-`, `_` and `.`)
+        name: String,
+    },
+    /// Make a workspace active for later invocations
+    Use {
+        /// Workspace name, or `default` for the files used without one
+        name: String,
     },
 }
+
+/// Group alias subcommands
+#[derive(Subcommand, Debug)]
+pub enum AliasCommands {
+    /// Add or update an alias for a group
+    Add {
+        /// Alias name
+        alias: String,
+        /// Group the alias points to
+        group_name: String,
+    },
+    /// Remove an alias
+    Remove {
+        /// Alias name
+        alias: String,
+    },
+    /// List all aliases
+    List,
+}
+
+/// Host and branch rule subcommands
+#[derive(Subcommand, Debug)]
+pub enum RuleCommands {
+    /// Add or update a rule
+    Add {
+        /// Host (`github.com`), subdomains (`*.acme.com`) or owner on a
+        /// host (`github.com/acme`); with `--branch`, a branch glob
+        pattern: String,
+        /// Group the rule picks
+        group_name: String,
+        /// Require commits to branches matching the pattern (e.g.
+        /// `release/*`) to be authored as the group
+        ///
+        /// Enforced by the pre-commit and pre-push hooks (`gum hook install
+        /// pre-commit`), which read the rules gum compiles into the git
+        /// directory.
+        #[arg(long)]
+        branch: bool,
+    },
+    /// Remove a rule
+    Remove {
+        /// Pattern of the rule, or its priority as shown by `rule list`
+        rule: String,
+    },
+    /// List the rules in the order they are consulted, with how often each
+    /// picked the group that was applied
+    List {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+}
+
+/// Push policy subcommands
+#[derive(Subcommand, Debug)]
+pub enum PolicyCommands {
+    /// Never allow emails matching a glob to push to remotes matching another
+    Add {
+        /// Glob of the forbidden emails, e.g. `*@gmail.com`
+        #[arg(long)]
+        email: String,
+        /// Glob of the protected remote URLs, e.g. `*gitlab.acme.com*`
+        #[arg(long)]
+        remote: String,
+    },
+    /// Remove a policy
+    Remove {
+        /// Number of the policy as shown by `policy list`
+        number: usize,
+    },
+    /// List the policies
+    List,
+}
+
+/// Git template directory subcommands
+#[derive(Subcommand, Debug)]
+pub enum TemplateCommands {
+    /// Install gum's guard hook into the git template directory
+    ///
+    /// New and cloned repositories then refuse to commit until `gum use`
+    /// sets an identity for them. Reuses an existing `init.templateDir`,
+    /// otherwise creates one next to the config file and registers it.
+    Install {
+        /// Template directory to use instead of the configured one
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+}
+
+/// Pairing subcommands
+#[derive(Subcommand, Debug)]
+pub enum PairCommands {
+    /// Apply the first group to the repository and credit the others
+    ///
+    /// Every commit gets a `Co-authored-by` trailer for each other group,
+    /// added by gum's commit-msg hook (installed into the repository), until
+    /// `gum pair stop`.
+    Start {
+        /// Groups pairing, the committing one first
+        #[arg(required = true, num_args = 2.., value_name = "GROUP")]
+        groups: Vec<String>,
+    },
+    /// Stop crediting co-authors in the repository
+    ///
+    /// The identity applied by `pair start` is kept.
+    Stop,
+}
+
+/// Git hook subcommands
+#[derive(Subcommand, Debug)]
+pub enum HookCommands {
+    /// Install a hook into the current repository
+    ///
+    /// `commit-msg` appends the trailers of the group applied by `use` (see
+    /// `set --trailer`) to every commit message. `pre-commit` and `pre-push`
+    /// refuse commits breaking a branch rule (see `rule add --branch`).
+    Install {
+        /// Hook to install
+        #[arg(value_enum)]
+        hook: HookKind,
+        /// Install into the git template directory instead, for new repositories
+        #[arg(long)]
+        template: bool,
+    },
+}
+
+/// Configuration file subcommands
+///
+/// Operations that work on the configuration file as a whole rather than on
+/// individual configuration groups.
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Validate the configuration file
+    ///
+    /// Reports syntax errors, duplicate groups, empty names, malformed emails
+    /// and unknown keys with their line and column. Exits with an error if
+    /// any error-level problem is found.
+    Lint,
+    /// Restore the config file as it was a number of saves ago
+    ///
+    /// gum keeps the last 50 states of the config file, including hand
+    /// edits it replaced. The rollback is saved as a new state, so running
+    /// it again undoes it.
+    Rollback {
+        /// Number of saves to go back
+        #[arg(long, value_name = "N", default_value_t = 1,
+              value_parser = clap::value_parser!(u16).range(1..))]
+        steps: u16,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_and_prefixes() {
+        let parse = |args: &[&str]| match Cli::try_parse_from(args).unwrap().command {
+            Some(Command::Configured(command)) => Some(command),
+            _ => None,
+        };
+        assert!(matches!(parse(&["gum", "ls"]), Some(Commands::List { .. })));
+        assert!(matches!(
+            parse(&["gum", "sw", "work"]),
+            Some(Commands::Use { .. })
+        ));
+        assert!(matches!(
+            parse(&["gum", "u", "work"]),
+            Some(Commands::Use { .. })
+        ));
+        assert!(matches!(
+            parse(&["gum", "rm", "work"]),
+            Some(Commands::Delete { .. })
+        ));
+        assert!(matches!(
+            parse(&["gum", "add", "work"]),
+            Some(Commands::Set(_))
+        ));
+        assert!(matches!(parse(&["gum", "li"]), Some(Commands::List { .. })));
+        assert!(matches!(parse(&["gum", "s"]), Some(Commands::External(_))));
+        assert!(matches!(
+            Cli::try_parse_from(["gum", "prompt"]).unwrap().command,
+            Some(Command::Standalone(StandaloneCommands::Prompt { .. }))
+        ));
+        assert_eq!(commands_starting_with("sh"), ["shell", "show"]);
+    }
+}
@@ -0,0 +1,99 @@
+//! # Completions Module
+//!
+//! Shell completion scripts generated by `clap_complete` from the command
+//! line definition. They complete subcommands (and their visible aliases)
+//! and short and long options, in bash, zsh, fish, PowerShell and Elvish.
+
+use crate::utils;
+use clap::Command;
+use std::fs;
+use std::path::PathBuf;
+
+pub use clap_complete::Shell;
+
+/// Completion script of `command` for `shell`
+pub fn script(command: &Command, shell: Shell) -> String {
+    let mut command = command.clone();
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+/// File the completion script of `shell` is loaded from automatically,
+/// `None` when the shell has no such location (zsh, PowerShell, Elvish)
+pub fn install_path(shell: Shell) -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let xdg = |var: &str, default: &str| {
+        std::env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| home.join(default))
+    };
+    match shell {
+        Shell::Bash => Some(
+            xdg("XDG_DATA_HOME", ".local/share")
+                .join("bash-completion")
+                .join("completions")
+                .join("gum"),
+        ),
+        Shell::Fish => Some(
+            xdg("XDG_CONFIG_HOME", ".config")
+                .join("fish")
+                .join("completions")
+                .join("gum.fish"),
+        ),
+        _ => None,
+    }
+}
+
+/// Write the completion script of `shell` where the shell loads it
+///
+/// Returns the file written, `None` for shells without such a location.
+/// Under `--dry-run` the change is printed instead.
+pub fn install(command: &Command, shell: Shell) -> anyhow::Result<Option<PathBuf>> {
+    let Some(path) = install_path(shell) else {
+        return Ok(None);
+    };
+    let content = script(command, shell);
+    let old = fs::read_to_string(&path).unwrap_or_default();
+    if utils::preview_write(&path, &old, &content) {
+        return Ok(Some(path));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn command() -> Command {
+        Command::new("gum")
+            .arg(Arg::new("config").long("config"))
+            .subcommand(
+                Command::new("use")
+                    .visible_alias("sw")
+                    .arg(Arg::new("group_name"))
+                    .arg(Arg::new("global").short('g').long("global")),
+            )
+    }
+
+    #[test]
+    fn test_script() {
+        let bash = script(&command(), Shell::Bash);
+        assert!(bash.contains("complete -F _gum"));
+        assert!(bash.contains("opts=\"-g -h --global --help\""));
+
+        let zsh = script(&command(), Shell::Zsh);
+        assert!(zsh.starts_with("#compdef gum\n"));
+
+        let powershell = script(&command(), Shell::PowerShell);
+        assert!(powershell.contains("Register-ArgumentCompleter"));
+        assert!(powershell.contains("'sw'"));
+    }
+}
@@ -4,17 +4,495 @@
 //! loading, and operations on user configurations. Uses parallel loading strategy
 //! to fetch all needed configuration information at once during initialization.
 
-use crate::utils;
+use crate::history;
+use crate::hooks;
+use crate::journal;
+use crate::policy::Policy;
+use crate::timings;
+use crate::undo;
+use crate::utils::{self, git_command};
+use crate::validate::{self, Severity};
+use crate::versions;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::process::Command;
-use std::thread;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+/// A git setting before and after applying a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingChange {
+    pub key: &'static str,
+    /// Effective value now
+    pub current: Option<String>,
+    /// Effective value once the group is applied
+    pub planned: Option<String>,
+}
+
+impl SettingChange {
+    /// Whether applying the group changes the effective value
+    pub fn changes(&self) -> bool {
+        self.current != self.planned
+    }
+}
+
 /// User configuration struct
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct UserConfig {
     pub name: String,
     pub email: String,
+    /// Committer name when it differs from the author (`name`)
+    ///
+    /// Applied as `committer.name` by `use`, and as `GIT_COMMITTER_NAME` by
+    /// `exec` and the shell aliases.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committer_name: Option<String>,
+    /// Committer email when it differs from the author (`email`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committer_email: Option<String>,
+    /// Commit message template applied as `commit.template` by `use`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_template: Option<String>,
+    /// Group to take fields left empty from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherit: Option<String>,
+    /// Alternate emails selectable at use time (variant -> email)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub email_variants: BTreeMap<String, String>,
+    /// `Host` alias written to `~/.ssh/config` by `ssh setup`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_alias: Option<String>,
+    /// SSH private key used by the group, applied as `core.sshCommand`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<String>,
+    /// Commit signing key, applied as `user.signingkey`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+    /// Signature format (`gpg.format`: openpgp, ssh or x509)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_format: Option<String>,
+    /// Proxy applied as `http.proxy` by `use`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+    /// CA bundle applied as `http.sslCAInfo` by `use`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_ca_info: Option<String>,
+    /// Credential helper applied as `credential.helper` by `use`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_helper: Option<String>,
+    /// Sender of `git send-email`, applied as `sendemail.from`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sendemail_from: Option<String>,
+    /// SMTP server of `git send-email`, applied as `sendemail.smtpServer`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_server: Option<String>,
+    /// SMTP port, applied as `sendemail.smtpServerPort`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_server_port: Option<String>,
+    /// SMTP login, applied as `sendemail.smtpUser`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_user: Option<String>,
+    /// SMTP encryption (`ssl` or `tls`), applied as `sendemail.smtpEncryption`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_encryption: Option<String>,
+    /// Workstyle git settings applied by `use --with-workstyle` (key -> value)
+    ///
+    /// Only the keys accepted by [`is_workstyle_key`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub workstyle: BTreeMap<String, String>,
+    /// Remote URL prefixes rewritten by git (original prefix -> replacement),
+    /// applied as `url.<replacement>.insteadOf` by `use`
+    ///
+    /// Routes an account's repositories through its SSH host alias, e.g.
+    /// `git@github.com:acme/` -> `git@github-work:acme/`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub url_rewrites: BTreeMap<String, String>,
+    /// Commit trailer templates added by gum's commit-msg hook
+    ///
+    /// Applied as `gum.trailers` by `use`, one template per line, e.g.
+    /// `Signed-off-by: {name} <{email}>`; the hook fills in the committer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trailers: Vec<String>,
+    /// Sign off commits and patches (Developer Certificate of Origin)
+    ///
+    /// Applied as `format.signOff` by `use`, and adds [`SIGNOFF_TRAILER`] to
+    /// the trailers of the commit-msg hook.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signoff: Option<bool>,
+    /// Protect the group from `set` and `delete` without `--force`, e.g.
+    /// for an identity provisioned by IT; not inherited
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    /// Last day the group may be used (`YYYY-MM-DD`), e.g. for a contractor
+    /// engagement; `list` flags it afterwards and `prune` offers to delete
+    /// it. Not inherited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    /// Labels sorting the group under headings in `list --tree`, e.g.
+    /// `work`, `oss` or `clients`; not inherited
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Free-form note shown by `show` and `list --long`, e.g. what the
+    /// account is for; not inherited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Git setting holding the trailer templates read by the commit-msg hook
+pub const TRAILERS_KEY: &str = "gum.trailers";
+
+/// Trailer template added to the commits of groups that sign off
+pub const SIGNOFF_TRAILER: &str = "Signed-off-by: {name} <{email}>";
+
+/// Git settings a workstyle may carry besides `alias.*`
+pub const WORKSTYLE_KEYS: &[&str] = &["core.editor", "pull.rebase", "push.default"];
+
+/// Whether a git key may be part of a group's workstyle
+pub fn is_workstyle_key(key: &str) -> bool {
+    WORKSTYLE_KEYS.contains(&key)
+        || key
+            .strip_prefix("alias.")
+            .is_some_and(|alias| !alias.is_empty())
+}
+
+impl UserConfig {
+    /// Whether the group expired before `now` (seconds since the Unix
+    /// epoch); the expiry day itself is still valid, in UTC
+    ///
+    /// Malformed dates never expire, `config lint` reports them.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires
+            .as_deref()
+            .and_then(utils::parse_date)
+            .is_some_and(|day| now >= day + 86_400)
+    }
+
+    /// Fill fields left empty from `base`
+    fn fill_from(&mut self, base: &UserConfig) {
+        if self.name.is_empty() {
+            self.name = base.name.clone();
+        }
+        if self.email.is_empty() {
+            self.email = base.email.clone();
+        }
+        if self.commit_template.is_none() {
+            self.commit_template = base.commit_template.clone();
+        }
+        if self.http_proxy.is_none() {
+            self.http_proxy = base.http_proxy.clone();
+        }
+        if self.ssl_ca_info.is_none() {
+            self.ssl_ca_info = base.ssl_ca_info.clone();
+        }
+        if self.credential_helper.is_none() {
+            self.credential_helper = base.credential_helper.clone();
+        }
+        // The format belongs to the key; a key of its own keeps its format
+        if self.signing_key.is_none() {
+            self.signing_key = base.signing_key.clone();
+            self.signing_format = base.signing_format.clone();
+        }
+        for (field, inherited) in [
+            (&mut self.ssh_key, &base.ssh_key),
+            (&mut self.ssh_alias, &base.ssh_alias),
+            (&mut self.committer_name, &base.committer_name),
+            (&mut self.committer_email, &base.committer_email),
+            (&mut self.sendemail_from, &base.sendemail_from),
+            (&mut self.smtp_server, &base.smtp_server),
+            (&mut self.smtp_server_port, &base.smtp_server_port),
+            (&mut self.smtp_user, &base.smtp_user),
+            (&mut self.smtp_encryption, &base.smtp_encryption),
+        ] {
+            if field.is_none() {
+                *field = inherited.clone();
+            }
+        }
+        if self.trailers.is_empty() {
+            self.trailers = base.trailers.clone();
+        }
+        if self.signoff.is_none() {
+            self.signoff = base.signoff;
+        }
+        for (variant, email) in &base.email_variants {
+            self.email_variants
+                .entry(variant.clone())
+                .or_insert_with(|| email.clone());
+        }
+        for (key, value) in &base.workstyle {
+            self.workstyle
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        for (original, replacement) in &base.url_rewrites {
+            self.url_rewrites
+                .entry(original.clone())
+                .or_insert_with(|| replacement.clone());
+        }
+    }
+
+    /// `url.<replacement>.insteadOf` settings of the URL rewrites (key ->
+    /// original prefixes)
+    ///
+    /// Several prefixes sharing one replacement are all kept, as values of
+    /// the same multi-valued key.
+    pub fn url_settings(&self) -> BTreeMap<String, Vec<String>> {
+        let mut settings: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (original, replacement) in &self.url_rewrites {
+            settings
+                .entry(url_rewrite_key(replacement))
+                .or_default()
+                .push(original.clone());
+        }
+        settings
+    }
+
+    /// Git settings applied by `use` besides the identity
+    ///
+    /// A `None` value means the group leaves the setting alone, in which case
+    /// a value left by another group is removed.
+    pub fn git_settings(&self) -> [(&'static str, Option<String>); 17] {
+        let signing = self.signing_key.is_some();
+        let trailers = self.commit_trailers();
+        [
+            ("commit.template", self.commit_template.clone()),
+            ("core.sshCommand", self.ssh_key.as_deref().map(ssh_command)),
+            ("user.signingkey", self.signing_key.clone()),
+            (
+                "gpg.format",
+                self.signing_format.clone().filter(|_| signing),
+            ),
+            ("commit.gpgsign", signing.then(|| "true".to_string())),
+            ("http.proxy", self.http_proxy.clone()),
+            ("http.sslCAInfo", self.ssl_ca_info.clone()),
+            ("credential.helper", self.credential_helper.clone()),
+            (
+                TRAILERS_KEY,
+                (!trailers.is_empty()).then(|| trailers.join("\n")),
+            ),
+            (
+                "format.signOff",
+                self.signs_off().then(|| "true".to_string()),
+            ),
+            ("sendemail.from", self.sendemail_from.clone()),
+            ("sendemail.smtpServer", self.smtp_server.clone()),
+            ("sendemail.smtpServerPort", self.smtp_server_port.clone()),
+            ("sendemail.smtpUser", self.smtp_user.clone()),
+            ("sendemail.smtpEncryption", self.smtp_encryption.clone()),
+            ("committer.name", self.committer_name.clone()),
+            ("committer.email", self.committer_email.clone()),
+        ]
+    }
+
+    /// Name and email of the committer, the author's unless set apart
+    pub fn committer(&self) -> (&str, &str) {
+        (
+            self.committer_name.as_deref().unwrap_or(&self.name),
+            self.committer_email.as_deref().unwrap_or(&self.email),
+        )
+    }
+
+    /// Whether the group signs off its commits
+    pub fn signs_off(&self) -> bool {
+        self.signoff == Some(true)
+    }
+
+    /// Trailer templates of the commit-msg hook, the sign-off first
+    pub fn commit_trailers(&self) -> Vec<String> {
+        let signoff = self.signs_off() && !self.trailers.iter().any(|t| t == SIGNOFF_TRAILER);
+        signoff
+            .then(|| SIGNOFF_TRAILER.to_string())
+            .into_iter()
+            .chain(self.trailers.iter().cloned())
+            .collect()
+    }
+
+    /// Copy of this identity using one of its alternate emails
+    pub fn with_email_variant(&self, variant: &str) -> anyhow::Result<UserConfig> {
+        let email = self.email_variants.get(variant).ok_or_else(|| {
+            let known: Vec<&str> = self.email_variants.keys().map(String::as_str).collect();
+            if known.is_empty() {
+                anyhow::anyhow!("Unknown email variant {} (the group has none)", variant)
+            } else {
+                anyhow::anyhow!(
+                    "Unknown email variant {} (available: {})",
+                    variant,
+                    known.join(", ")
+                )
+            }
+        })?;
+        Ok(UserConfig {
+            email: email.clone(),
+            ..self.clone()
+        })
+    }
+
+    /// Whether `user` is this identity with its primary or an alternate email
+    pub fn holds_identity(&self, user: &UserConfig) -> bool {
+        self.same_identity(user)
+            || (self.name == user.name
+                && self
+                    .email_variants
+                    .values()
+                    .any(|email| email.eq_ignore_ascii_case(&user.email)))
+    }
+
+    /// Whether both configurations describe the same identity
+    ///
+    /// Names are compared exactly, emails case-insensitively.
+    pub fn same_identity(&self, other: &UserConfig) -> bool {
+        self.name == other.name && self.email.eq_ignore_ascii_case(&other.email)
+    }
+
+    /// Whether both configurations most likely describe the same person
+    ///
+    /// Besides [`same_identity`](Self::same_identity), names may differ in
+    /// case and spacing, and emails in a `+tag` of the local part or by being
+    /// an alternate email of the other configuration.
+    pub fn similar_identity(&self, other: &UserConfig) -> bool {
+        fn name_key(name: &str) -> String {
+            name.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        }
+        fn email_key(email: &str) -> String {
+            let email = email.trim().to_lowercase();
+            match email.rsplit_once('@') {
+                Some((local, domain)) => {
+                    let local = local.split_once('+').map_or(local, |(base, _)| base);
+                    format!("{}@{}", local, domain)
+                }
+                None => email,
+            }
+        }
+        if self.same_identity(other) {
+            return true;
+        }
+        name_key(&self.name) == name_key(&other.name)
+            && (email_key(&self.email) == email_key(&other.email)
+                || self.holds_identity(&UserConfig {
+                    name: self.name.clone(),
+                    ..other.clone()
+                })
+                || other.holds_identity(&UserConfig {
+                    name: other.name.clone(),
+                    ..self.clone()
+                }))
+    }
+}
+
+/// Application settings stored in the configuration file
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Email domains accepted by `set` (empty means any domain)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_email_domains: Vec<String>,
+    /// Maximum length of new group names (default: 64)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_group_name_length: Option<usize>,
+    /// Store and look up group names in lowercase
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub lowercase_group_names: bool,
+    /// Make git refuse to commit without an identity set by gum
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub use_config_only: bool,
+    /// Language of user-facing messages, e.g. "en" or "zh" (default: from LANG)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Suggest a host rule when a repository matches none (default: true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggest_rules: Option<bool>,
+    /// Always mask emails in output and logs, as with `--redact`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub redact: bool,
+    /// Always print without colors and box drawing, as with `--plain`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub plain: bool,
+    /// How much of its own state `gum gc` keeps
+    #[serde(skip_serializing_if = "Retention::is_default")]
+    pub retention: Retention,
+    /// How gum runs git
+    #[serde(skip_serializing_if = "GitSettings::is_default")]
+    pub git: GitSettings,
+}
+
+impl Settings {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Retention of the state gum accumulates, applied by `gum gc`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Retention {
+    /// Config file versions kept for `config rollback` (default: 50)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versions: Option<usize>,
+    /// Days of `use` history kept (default: 365)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_days: Option<u64>,
+    /// Size in KiB past which the change journal is rotated (default: 1024)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal_kib: Option<u64>,
+    /// Days after which cached prompt segments are removed (default: 7)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_days: Option<u64>,
+}
+
+impl Retention {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Config file versions to keep
+    pub fn versions(&self) -> usize {
+        self.versions.unwrap_or(versions::MAX_VERSIONS)
+    }
+
+    /// Days of history to keep
+    pub fn history_days(&self) -> u64 {
+        self.history_days.unwrap_or(365)
+    }
+
+    /// Journal size that triggers a rotation, in KiB
+    pub fn journal_kib(&self) -> u64 {
+        self.journal_kib.unwrap_or(1024)
+    }
+
+    /// Age in days of the cache files to remove
+    pub fn cache_days(&self) -> u64 {
+        self.cache_days.unwrap_or(7)
+    }
+}
+
+/// How gum runs git
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct GitSettings {
+    /// Git program, e.g. a portable git on Windows (default: `git` from
+    /// `PATH`; `GUM_GIT` takes precedence)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Seconds a git invocation may take before it is killed, `0` for no
+    /// limit (default: 10)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl GitSettings {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Time a git invocation may take, `None` for no limit
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.timeout_secs.unwrap_or(utils::DEFAULT_GIT_TIMEOUT_SECS) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
 }
 
 /// Main configuration struct
@@ -22,16 +500,141 @@ pub struct UserConfig {
 pub struct Config {
     /// User defined configuration groups
     pub groups: HashMap<String, UserConfig>,
+    /// Groups provided by the system-wide configuration file and the
+    /// drop-in files of `profiles.d` (read-only)
+    pub system_groups: HashMap<String, UserConfig>,
+    /// Short names for groups (alias -> group name)
+    pub aliases: HashMap<String, String>,
+    /// Default groups for git hosts (host, `*.domain` or `host/owner` -> group
+    /// name)
+    pub hosts: HashMap<String, String>,
+    /// Groups commits to matching branches must use (branch glob -> group
+    /// name), enforced by gum's pre-commit and pre-push hooks
+    pub branches: HashMap<String, String>,
+    /// Emails that must never be pushed to some remotes
+    pub policies: Vec<Policy>,
+    /// Additional configuration files whose groups are merged read-only,
+    /// as written in the file
+    pub includes: Vec<String>,
+    /// Application settings
+    pub settings: Settings,
     /// Global git user configuration (cached)
     pub global_user: Option<UserConfig>,
     /// Project level git user configuration (cached)
     pub project_user: Option<UserConfig>,
+    /// Parse error of the configuration file, if it exists but could not be read.
+    /// While set, `save` refuses to overwrite the file.
+    pub file_error: Option<String>,
+    /// Non-fatal validation findings for the configuration file
+    pub file_warnings: Vec<String>,
+    /// Configuration file given to [`ConfigBuilder::config_path`], which
+    /// `save` writes instead of the default path
+    pub path: Option<PathBuf>,
+    /// Backend given to [`ConfigBuilder::backend`]
+    backend: Option<Arc<dyn GitBackend>>,
+}
+
+/// `email` moved to domain `new`, when it is on domain `old`
+pub fn migrate_email(email: &str, old: &str, new: &str) -> Option<String> {
+    let (local, domain) = email.rsplit_once('@')?;
+    domain
+        .eq_ignore_ascii_case(old.trim_start_matches('@'))
+        .then(|| format!("{}@{}", local, new.trim_start_matches('@')))
+}
+
+/// Group names under each tag, tags and names sorted, followed by the
+/// untagged groups under `None`
+///
+/// A group with several tags is listed under each of them. Empty sections
+/// are left out.
+pub fn tag_sections(groups: &HashMap<String, UserConfig>) -> Vec<(Option<String>, Vec<String>)> {
+    let mut tagged: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut untagged = Vec::new();
+    for (group_name, user) in groups {
+        let tags: BTreeSet<&str> = user
+            .tags
+            .iter()
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if tags.is_empty() {
+            untagged.push(group_name.clone());
+        }
+        for tag in tags {
+            tagged.entry(tag).or_default().push(group_name.clone());
+        }
+    }
+    let mut sections: Vec<(Option<String>, Vec<String>)> = tagged
+        .into_iter()
+        .map(|(tag, names)| (Some(tag.to_string()), names))
+        .collect();
+    if !untagged.is_empty() {
+        sections.push((None, untagged));
+    }
+    for (_, names) in &mut sections {
+        names.sort();
+    }
+    sections
+}
+
+/// Directory of drop-in profile files, next to the configuration file
+pub const PROFILES_DIR: &str = "profiles.d";
+
+/// Groups of each drop-in profile file, with the file they came from
+type ProfileGroups = Vec<(PathBuf, HashMap<String, UserConfig>)>;
+
+/// Drop-in profile file of `profiles.d`; only its groups are used
+#[derive(Deserialize, Debug, Default)]
+struct ProfileFile {
+    #[serde(default)]
+    groups: HashMap<String, UserConfig>,
 }
 
 /// Configuration file struct (only used for serialization/deserialization)
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 struct ConfigFile {
+    #[serde(serialize_with = "sorted_map")]
     groups: HashMap<String, UserConfig>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted_map"
+    )]
+    aliases: HashMap<String, String>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted_map"
+    )]
+    hosts: HashMap<String, String>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted_map"
+    )]
+    branches: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    policies: Vec<Policy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    includes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    settings: Settings,
+}
+
+/// Serialize a map with sorted keys, so that saving the configuration file
+/// only changes the lines of the entries that changed
+fn sorted_map<S, V>(map: &HashMap<String, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Config {
@@ -39,74 +642,109 @@ impl Config {
     pub fn new() -> Self {
         Self {
             groups: HashMap::new(),
+            system_groups: HashMap::new(),
+            aliases: HashMap::new(),
+            hosts: HashMap::new(),
+            branches: HashMap::new(),
+            policies: Vec::new(),
+            includes: Vec::new(),
+            settings: Settings::default(),
             global_user: None,
             project_user: None,
+            file_error: None,
+            file_warnings: Vec::new(),
+            path: None,
+            backend: None,
         }
     }
 
-    /// Load all configurations in parallel
+    /// Load all configurations in parallel from the default locations
     ///
-    /// Executes three operations simultaneously:
-    /// 1. Load user configuration groups from file
-    /// 2. Get global git configuration
-    /// 3. Get project git configuration
+    /// Same as `Config::builder().build()`; see [`ConfigBuilder`] to read
+    /// other files, only some sources or git through another backend.
     pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        log::debug!("Starting parallel config loading");
-
-        // Start three parallel tasks
-        let file_handle = thread::spawn(|| load_config_file());
-        let global_handle = thread::spawn(|| get_git_user_batch(true));
-        let project_handle = thread::spawn(|| get_git_user_batch(false));
-
-        // Wait for all tasks to complete
-        let groups = file_handle
-            .join()
-            .map_err(|_| "Config file loading thread panicked")?
-            .unwrap_or_else(|e| {
-                log::warn!("Failed to load config file: {}", e);
-                HashMap::new()
-            });
-
-        let global_user = global_handle
-            .join()
-            .map_err(|_| "Global git config loading thread panicked")?
-            .ok();
-
-        let project_user = project_handle
-            .join()
-            .map_err(|_| "Project git config loading thread panicked")?
-            .ok();
+        Self::builder().build()
+    }
 
-        log::debug!(
-            "Config loading complete: {} groups, global user: {}, project user: {}",
-            groups.len(),
-            global_user.is_some(),
-            project_user.is_some()
-        );
+    /// Start configuring where a configuration is loaded from
+    ///
+    /// ```no_run
+    /// use gum_rs::config::{Config, Scopes};
+    ///
+    /// let config = Config::builder()
+    ///     .config_path("/tmp/gum/config.jsonc")
+    ///     .load_scopes(Scopes::FILE_ONLY)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
 
+    /// Load only the configuration groups from the user and system files
+    ///
+    /// Skips git and validation entirely; used by fast paths such as the
+    /// shell prompt segment. An unparsable file is an error.
+    pub fn load_files_only() -> anyhow::Result<Self> {
+        let config_path = utils::get_config_path()?;
+        let config_file = read_config_file(&config_path)?;
+        apply_git_settings(&config_file.settings);
+        let system_groups = load_system_config_file(utils::get_system_config_path())
+            .map(|file| file.groups)
+            .unwrap_or_default();
+        let mut profiles = load_profile_files(&config_path).0;
+        profiles.extend(load_include_files(&config_path, &config_file.includes).0);
+        let (system_groups, _) =
+            merge_read_only_groups(system_groups, profiles, &config_file.groups);
         Ok(Config {
-            groups,
-            global_user,
-            project_user,
+            groups: config_file.groups,
+            system_groups,
+            aliases: config_file.aliases,
+            hosts: config_file.hosts,
+            branches: config_file.branches,
+            policies: config_file.policies,
+            includes: config_file.includes,
+            settings: config_file.settings,
+            ..Config::new()
         })
     }
 
     /// Save configuration to file
+    ///
+    /// Refuses to run when the existing file could not be parsed, so that
+    /// unreadable data is never silently replaced by an empty configuration.
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
         log::debug!("Saving configuration to file");
-        let config_path = utils::get_config_path()?;
-
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
+        if let Some(ref e) = self.file_error {
+            return Err(format!(
+                "Refusing to overwrite unparsable configuration file ({}). Fix or restore it first",
+                e
+            )
+            .into());
         }
 
+        let config_path = match self.path {
+            Some(ref path) => path.clone(),
+            None => utils::get_config_path()?,
+        };
         let config_file = ConfigFile {
             groups: self.groups.clone(),
+            aliases: self.aliases.clone(),
+            hosts: self.hosts.clone(),
+            branches: self.branches.clone(),
+            policies: self.policies.clone(),
+            includes: self.includes.clone(),
+            settings: self.settings.clone(),
         };
-
-        let content = serde_json::to_string_pretty(&config_file)?;
-        fs::write(config_path, content)?;
+        write_config_file(&config_path, &config_file)?;
         log::debug!("Configuration saved successfully");
+        // The hooks of every repository read the rules compiled from the
+        // active configuration, emails included
+        if self.path.is_none()
+            && let Err(e) = hooks::write_shared_rules(self)
+        {
+            log::warn!("Cannot update the rules read by the git hooks: {}", e);
+        }
         Ok(())
     }
 
@@ -120,130 +758,1541 @@ impl Config {
             .ok_or_else(|| "No git user configuration found".into())
     }
 
-    /// Get all configuration information (including global configuration)
-    pub fn get_all_config_info(&self) -> HashMap<String, UserConfig> {
-        let mut all_info = self.groups.clone();
-        if let Some(ref global_user) = self.global_user {
-            all_info.insert("global".to_string(), global_user.clone());
+    /// Get a configuration group by name
+    ///
+    /// User groups take precedence over system groups with the same name.
+    pub fn get_group(&self, group_name: &str) -> Option<&UserConfig> {
+        self.groups
+            .get(group_name)
+            .or_else(|| self.system_groups.get(group_name))
+    }
+
+    /// Normalize a group name given on the command line
+    ///
+    /// Surrounding whitespace is removed, and the name is lowercased when the
+    /// `lowercase_group_names` setting is enabled. An alias is replaced by
+    /// the group it points to, unless a group has the same name.
+    pub fn normalize_group_name(&self, group_name: &str) -> String {
+        let name = group_name.trim();
+        let name = if self.settings.lowercase_group_names {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        };
+        if self.get_group(&name).is_none()
+            && let Some(target) = self.aliases.get(&name)
+        {
+            log::debug!("Resolved alias {} to group {}", name, target);
+            return target.clone();
         }
-        all_info
+        name
     }
 
-    /// Refresh global git configuration
-    pub fn refresh_global_user(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.global_user = get_git_user_batch(true).ok();
-        Ok(())
+    /// Aliases pointing to a group, sorted
+    pub fn aliases_of(&self, group_name: &str) -> Vec<String> {
+        let mut aliases: Vec<String> = self
+            .aliases
+            .iter()
+            .filter(|(_, target)| target.as_str() == group_name)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        aliases.sort();
+        aliases
     }
 
-    /// Refresh project git configuration
-    pub fn refresh_project_user(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.project_user = get_git_user_batch(false).ok();
-        Ok(())
+    /// Remove a user group together with the aliases and rules pointing to it
+    pub fn remove_group(&mut self, group_name: &str) -> Option<UserConfig> {
+        let removed = self.groups.remove(group_name)?;
+        self.aliases.retain(|_, target| target != group_name);
+        self.hosts.retain(|_, target| target != group_name);
+        self.branches.retain(|_, target| target != group_name);
+        Some(removed)
     }
-}
 
-/// Load configuration groups from file
-fn load_config_file() -> anyhow::Result<HashMap<String, UserConfig>> {
-    log::debug!("Loading configuration groups from file");
-    let config_path = utils::get_config_path()?;
+    /// Move the emails of user groups on domain `old` to domain `new`
+    ///
+    /// Primary, committer and alternate emails are updated; domains are
+    /// compared case-insensitively and subdomains do not match. Returns the
+    /// changes as `(group, old email, new email)`, sorted by group. Nothing
+    /// changes when a group to update is locked and `force` is not given.
+    pub fn migrate_domain(
+        &mut self,
+        old: &str,
+        new: &str,
+        force: bool,
+    ) -> anyhow::Result<Vec<(String, String, String)>> {
+        let mut groups = self.groups.clone();
+        let mut changes = Vec::new();
+        for (group_name, group) in groups.iter_mut() {
+            let emails = std::iter::once(&mut group.email)
+                .chain(group.committer_email.iter_mut())
+                .chain(group.email_variants.values_mut());
+            for email in emails {
+                if let Some(migrated) = migrate_email(email, old, new) {
+                    changes.push((group_name.clone(), email.clone(), migrated.clone()));
+                    *email = migrated;
+                }
+            }
+        }
+        for (group_name, _, _) in &changes {
+            self.check_unlocked(group_name, force)?;
+        }
+        self.groups = groups;
+        changes.sort();
+        Ok(changes)
+    }
 
-    if !config_path.exists() {
-        log::debug!("Configuration file does not exist");
-        return Ok(HashMap::new());
+    /// Merge user groups into `survivor`, deleting them
+    ///
+    /// Aliases, host and branch rules and `inherit` of the merged groups
+    /// point to the survivor afterwards. An email of a merged group the
+    /// survivor does not hold is kept as an alternate email named after the
+    /// merged group. Nothing changes when the survivor or a merged group is
+    /// locked and `force` is not given.
+    pub fn merge_groups(
+        &mut self,
+        survivor: &str,
+        merged: &[String],
+        force: bool,
+    ) -> anyhow::Result<()> {
+        for group_name in std::iter::once(survivor).chain(merged.iter().map(String::as_str)) {
+            self.check_unlocked(group_name, force)?;
+        }
+        for group_name in merged.iter().filter(|name| name.as_str() != survivor) {
+            let Some(group) = self.groups.remove(group_name) else {
+                continue;
+            };
+            if let Some(kept) = self.groups.get_mut(survivor) {
+                let email = UserConfig {
+                    name: kept.name.clone(),
+                    email: group.email.clone(),
+                    ..Default::default()
+                };
+                if !group.email.is_empty() && !kept.holds_identity(&email) {
+                    kept.email_variants
+                        .insert(group_name.clone(), group.email.clone());
+                }
+                // The survivor keeps what it inherited from the merged group
+                if kept.inherit.as_deref() == Some(group_name) {
+                    kept.fill_from(&group);
+                    kept.inherit = group.inherit.clone();
+                }
+            }
+            for target in self
+                .aliases
+                .values_mut()
+                .chain(self.hosts.values_mut())
+                .chain(self.branches.values_mut())
+            {
+                if target == group_name {
+                    *target = survivor.to_string();
+                }
+            }
+            for (name, other) in self.groups.iter_mut() {
+                if other.inherit.as_deref() == Some(group_name) {
+                    other.inherit = (name != survivor).then(|| survivor.to_string());
+                }
+            }
+        }
+        if let Some(kept) = self.groups.get_mut(survivor)
+            && kept.inherit.as_deref() == Some(survivor)
+        {
+            kept.inherit = None;
+        }
+        Ok(())
     }
 
-    let content = fs::read_to_string(&config_path)?;
-    let config_file: ConfigFile = serde_json::from_str(&content)?;
-    log::debug!(
-        "Successfully loaded {} configuration groups",
-        config_file.groups.len()
-    );
+    /// Group names and aliases close to a name that does not exist
+    ///
+    /// Names within a third of their length in edits (at least one) are
+    /// returned, closest first, at most three. Case is ignored.
+    pub fn similar_group_names(&self, group_name: &str) -> Vec<String> {
+        let wanted = group_name.to_lowercase();
+        let mut similar: Vec<(usize, &String)> = self
+            .groups
+            .keys()
+            .chain(self.system_groups.keys())
+            .chain(self.aliases.keys())
+            .filter_map(|name| {
+                let distance = utils::edit_distance(&wanted, &name.to_lowercase());
+                let limit = (name.chars().count() / 3).max(1);
+                (distance <= limit).then_some((distance, name))
+            })
+            .collect();
+        similar.sort();
+        similar.dedup();
+        similar
+            .into_iter()
+            .take(3)
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
 
-    Ok(config_file.groups)
-}
+    /// Check that a name can be used for a new group
+    pub fn check_new_group_name(&self, group_name: &str) -> Result<(), String> {
+        validate::check_group_name(
+            group_name,
+            self.settings
+                .max_group_name_length
+                .unwrap_or(validate::DEFAULT_MAX_GROUP_NAME_LENGTH),
+        )
+    }
 
-/// Batch get git user configuration
-///
-/// Uses single git command to get name and email, avoiding multiple calls
-fn get_git_user_batch(global: bool) -> anyhow::Result<UserConfig> {
-    let scope = if global { "--global" } else { "--local" };
-    log::debug!("Batch fetching git user configuration ({})", scope);
+    /// Inheritance chain of a group, starting with the group itself
+    ///
+    /// Fails if a group in the chain does not exist or the chain loops.
+    pub fn inheritance_chain(
+        &self,
+        group_name: &str,
+    ) -> anyhow::Result<Vec<(String, &UserConfig)>> {
+        let mut chain: Vec<(String, &UserConfig)> = Vec::new();
+        let mut next = Some(group_name.to_string());
+        while let Some(name) = next {
+            if chain.iter().any(|(n, _)| *n == name) {
+                let path: Vec<&str> = chain.iter().map(|(n, _)| n.as_str()).collect();
+                anyhow::bail!("Inheritance cycle: {} -> {}", path.join(" -> "), name);
+            }
+            let group = self.get_group(&name).ok_or_else(|| match chain.last() {
+                Some((child, _)) => {
+                    anyhow::anyhow!("{} inherits from unknown group {}", child, name)
+                }
+                None => anyhow::anyhow!("{} group not found", name),
+            })?;
+            next = group.inherit.clone();
+            chain.push((name, group));
+        }
+        Ok(chain)
+    }
 
-    let output = Command::new("git")
-        .args(["config", scope, "--get-regexp", "^user\\.(name|email)$"])
-        .output()?;
+    /// Get a group with the fields it leaves empty filled from its parents
+    pub fn resolve_group(&self, group_name: &str) -> anyhow::Result<UserConfig> {
+        let chain = self.inheritance_chain(group_name)?;
+        let ((_, first), parents) = chain
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("{} group not found", group_name))?;
+        let mut user = (*first).clone();
+        for (_, base) in parents {
+            user.fill_from(base);
+        }
+        Ok(user)
+    }
 
-    if !output.status.success() {
-        return Err(anyhow::format_err!(
-            "Failed to get git configuration: {}",
-            scope
-        ));
+    /// Resolved group, or the group as stored when it cannot be resolved
+    fn resolved(&self, group_name: &str, group: &UserConfig) -> UserConfig {
+        self.resolve_group(group_name).unwrap_or_else(|e| {
+            log::warn!("{}", e);
+            group.clone()
+        })
     }
 
-    let stdout = String::from_utf8(output.stdout)?;
-    let mut name = String::new();
-    let mut email = String::new();
+    /// User groups inheriting directly from a group, sorted
+    pub fn children_of(&self, group_name: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| group.inherit.as_deref() == Some(group_name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
 
-    for line in stdout.lines() {
-        if let Some((key, value)) = line.split_once(' ') {
-            match key {
-                "user.name" => name = value.to_string(),
-                "user.email" => email = value.to_string(),
-                _ => {}
-            }
+    /// Drop from `deleting` the groups still inherited by groups that stay
+    ///
+    /// Dropping a group keeps it, so the groups it inherits from are checked
+    /// again until nothing changes. Returns the dropped groups with the
+    /// children that keep them, in the order they were dropped.
+    pub fn retain_uninherited(&self, deleting: &mut Vec<String>) -> Vec<(String, Vec<String>)> {
+        let mut dropped = Vec::new();
+        loop {
+            let inherited = deleting.iter().find_map(|group_name| {
+                let mut children = self.children_of(group_name);
+                children.retain(|child| !deleting.contains(child));
+                (!children.is_empty()).then(|| (group_name.clone(), children))
+            });
+            let Some((group_name, children)) = inherited else {
+                return dropped;
+            };
+            deleting.retain(|name| *name != group_name);
+            dropped.push((group_name, children));
         }
     }
 
-    if name.is_empty() && email.is_empty() {
-        return Err(anyhow::anyhow!("Git user configuration is empty"));
+    /// Find user groups holding the same identity as `user`
+    ///
+    /// The group named `exclude` is skipped so a group is not reported as a
+    /// duplicate of itself. Returned names are sorted.
+    pub fn find_identity(&self, user: &UserConfig, exclude: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(name, other)| {
+                name.as_str() != exclude && self.resolved(name, other).same_identity(user)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
     }
 
-    log::debug!("Retrieved user configuration: {} <{}>", name, email);
-    Ok(UserConfig { name, email })
-}
-
-/// Set git user configuration
-pub fn set_git_user(user: &UserConfig, global: bool) -> anyhow::Result<()> {
-    let scope = if global { "--global" } else { "--local" };
-    log::debug!(
-        "Setting git user configuration ({}): {} <{}>",
-        scope,
-        user.name,
-        user.email
-    );
+    /// Find all groups (user and system) holding the given identity
+    ///
+    /// An identity using one of a group's alternate emails matches it too.
+    /// Returned names are sorted; the `global` pseudo group is not included.
+    pub fn match_groups(&self, user: &UserConfig) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .groups
+            .keys()
+            .chain(self.system_groups.keys())
+            .filter(|name| {
+                self.get_group(name)
+                    .is_some_and(|g| self.resolved(name, g).holds_identity(user))
+            })
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
 
-    // Set name
-    let status = Command::new("git")
-        .args(["config", scope, "user.name", &user.name])
-        .status()?;
+    /// Sets of user groups that share an identity, exactly or nearly (see
+    /// [`UserConfig::similar_identity`])
+    ///
+    /// Each set has at least two sorted group names; sets are ordered by their
+    /// first group name.
+    pub fn duplicate_identities(&self) -> Vec<Vec<String>> {
+        let mut names: Vec<&String> = self.groups.keys().collect();
+        names.sort();
+        let resolved: HashMap<&String, UserConfig> = names
+            .iter()
+            .map(|name| (*name, self.resolved(name, &self.groups[*name])))
+            .collect();
 
-    if !status.success() {
-        return Err(anyhow::anyhow!("Failed to set git user.name"));
+        let mut sets: Vec<Vec<String>> = Vec::new();
+        for name in names {
+            let user = &resolved[name];
+            match sets
+                .iter_mut()
+                .find(|set| resolved[&set[0]].similar_identity(user))
+            {
+                Some(set) => set.push(name.clone()),
+                None => sets.push(vec![name.clone()]),
+            }
+        }
+        sets.retain(|set| set.len() > 1);
+        sets
     }
 
-    // Set email
-    let status = Command::new("git")
-        .args(["config", scope, "user.email", &user.email])
-        .status()?;
+    /// Free name for a new group holding `user`, after its email domain
+    ///
+    /// `li@acme.com` gives `acme`, then `acme-2` and so on when taken; an
+    /// email without a domain gives its local part.
+    pub fn suggest_group_name(&self, user: &UserConfig) -> String {
+        let (local, domain) = user
+            .email
+            .rsplit_once('@')
+            .unwrap_or((user.email.as_str(), ""));
+        let base = domain
+            .split('.')
+            .rev()
+            .nth(1)
+            .or_else(|| domain.split('.').next())
+            .filter(|label| !label.is_empty())
+            .unwrap_or(local)
+            .to_lowercase();
+        let base = if base.is_empty() {
+            "adopted".to_string()
+        } else {
+            base
+        };
+        (1..)
+            .map(|i| {
+                if i == 1 {
+                    base.clone()
+                } else {
+                    format!("{}-{}", base, i)
+                }
+            })
+            .find(|name| self.get_group(name).is_none())
+            .unwrap_or(base)
+    }
 
-    if !status.success() {
-        return Err(anyhow::anyhow!("Failed to set git user.email"));
+    /// Whether the group is marked `locked`
+    pub fn is_locked(&self, group_name: &str) -> bool {
+        self.get_group(group_name).is_some_and(|group| group.locked)
     }
 
-    log::debug!("Git user configuration set successfully");
-    Ok(())
-}
+    /// Fail when the group is locked and `force` is not given
+    ///
+    /// Every command changing or deleting groups goes through this check.
+    pub fn check_unlocked(&self, group_name: &str, force: bool) -> anyhow::Result<()> {
+        if self.is_locked(group_name) && !force {
+            log::warn!("Refusing to change locked group {}", group_name);
+            anyhow::bail!(crate::t!("group_locked", group_name));
+        }
+        Ok(())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Whether the group only exists in the read-only system configuration
+    pub fn is_system_group(&self, group_name: &str) -> bool {
+        !self.groups.contains_key(group_name) && self.system_groups.contains_key(group_name)
+    }
 
-    #[test]
-    fn test_config_new() {
-        let config = Config::new();
-        assert!(config.groups.is_empty());
-        assert!(config.global_user.is_none());
-        assert!(config.project_user.is_none());
+    /// Apply the git settings of `user` besides the identity
+    ///
+    /// See [`UserConfig::git_settings`] and [`UserConfig::url_settings`];
+    /// values set by any group count as managed by gum and are removed when
+    /// switching to a group without them.
+    pub fn apply_group_settings(
+        &self,
+        user: &UserConfig,
+        global: bool,
+        dir: Option<&Path>,
+    ) -> anyhow::Result<()> {
+        let managed = self.managed_settings();
+        for (i, (key, value)) in user.git_settings().into_iter().enumerate() {
+            apply_managed_setting(key, value.as_deref(), &managed[i], global, dir)?;
+        }
+        let groups = self.get_all_config_info();
+        let url_settings = user.url_settings();
+        for key in self.url_rewrite_keys() {
+            let managed: Vec<String> = groups
+                .values()
+                .filter_map(|group| group.url_settings().remove(&key))
+                .flatten()
+                .collect();
+            let values = url_settings
+                .get(&key)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            apply_managed_values(&key, values, &managed, global, dir)?;
+        }
+        Ok(())
+    }
+
+    /// `url.<replacement>.insteadOf` keys set by any group, sorted
+    ///
+    /// `use` writes or removes each of them.
+    pub fn url_rewrite_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .get_all_config_info()
+            .into_values()
+            .flat_map(|group| group.url_settings().into_keys())
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// Workstyle keys set by any group, sorted
+    ///
+    /// `use --with-workstyle` writes or removes each of them.
+    pub fn workstyle_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .get_all_config_info()
+            .into_values()
+            .flat_map(|group| group.workstyle.into_keys())
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// Apply the workstyle of `user`
+    ///
+    /// Keys the group leaves out are removed when their value was set by
+    /// another group's workstyle, and kept when configured by hand.
+    pub fn apply_workstyle(
+        &self,
+        user: &UserConfig,
+        global: bool,
+        dir: Option<&Path>,
+    ) -> anyhow::Result<()> {
+        let groups = self.get_all_config_info();
+        for key in self.workstyle_keys() {
+            let managed: Vec<String> = groups
+                .values()
+                .filter_map(|group| group.workstyle.get(&key).cloned())
+                .collect();
+            let value = user.workstyle.get(&key).map(String::as_str);
+            apply_managed_setting(&key, value, &managed, global, dir)?;
+        }
+        Ok(())
+    }
+
+    /// Effective git settings before and after applying `user`
+    ///
+    /// Covers the identity and [`UserConfig::git_settings`], in that order,
+    /// without changing anything.
+    pub fn plan_use(
+        &self,
+        user: &UserConfig,
+        global: bool,
+        dir: Option<&Path>,
+    ) -> Vec<SettingChange> {
+        let scope = if global { "--global" } else { "--local" };
+        let managed = self.managed_settings();
+        let identity = [
+            ("user.name", Some(user.name.clone())),
+            ("user.email", Some(user.email.clone())),
+        ];
+        let settings = user
+            .git_settings()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, value))| {
+                let value = value.or_else(|| {
+                    // Values left by other groups are removed, others kept
+                    get_git_config(Some(scope), key, dir).filter(|v| !managed[i].contains(v))
+                });
+                (key, value)
+            });
+
+        identity
+            .into_iter()
+            .chain(settings)
+            .map(|(key, value)| {
+                let planned = ["--system", "--global", "--local"]
+                    .iter()
+                    .filter_map(|s| {
+                        if *s == scope {
+                            value.clone()
+                        } else {
+                            get_git_config(Some(s), key, dir)
+                        }
+                    })
+                    .next_back();
+                SettingChange {
+                    key,
+                    current: get_git_config(None, key, dir),
+                    planned,
+                }
+            })
+            .collect()
+    }
+
+    /// Values of each of [`UserConfig::git_settings`] set by any group
+    fn managed_settings(&self) -> Vec<Vec<String>> {
+        let groups: Vec<_> = self
+            .get_all_config_info()
+            .values()
+            .map(UserConfig::git_settings)
+            .collect();
+        (0..UserConfig::default().git_settings().len())
+            .map(|i| {
+                groups
+                    .iter()
+                    .filter_map(|settings| settings[i].1.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Get all configuration information (including global configuration)
+    ///
+    /// System groups are merged beneath the user's groups, and inherited
+    /// fields are resolved.
+    pub fn get_all_config_info(&self) -> HashMap<String, UserConfig> {
+        let mut all_info: HashMap<String, UserConfig> = self
+            .system_groups
+            .iter()
+            .chain(self.groups.iter())
+            .map(|(name, group)| (name.clone(), self.resolved(name, group)))
+            .collect();
+        if let Some(ref global_user) = self.global_user {
+            all_info.insert("global".to_string(), global_user.clone());
+        }
+        all_info
+    }
+
+    /// Refresh global git configuration
+    pub fn refresh_global_user(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.global_user = self.backend().user(true).ok();
+        Ok(())
+    }
+
+    /// Refresh project git configuration
+    pub fn refresh_project_user(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.project_user = self.backend().user(false).ok();
+        Ok(())
+    }
+
+    /// Backend given to [`ConfigBuilder::backend`], or git itself
+    fn backend(&self) -> Arc<dyn GitBackend> {
+        self.backend
+            .clone()
+            .unwrap_or_else(|| Arc::new(GitCli::new()))
+    }
+}
+
+/// Sources of a configuration, combined with `|`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scopes(u8);
+
+impl Scopes {
+    /// The user's configuration file
+    pub const USER_FILE: Scopes = Scopes(1);
+    /// The system-wide configuration file
+    pub const SYSTEM_FILE: Scopes = Scopes(1 << 1);
+    /// The drop-in files of `profiles.d` and the `includes` list
+    pub const PROFILES: Scopes = Scopes(1 << 2);
+    /// The global git identity
+    pub const GIT_GLOBAL: Scopes = Scopes(1 << 3);
+    /// The repository's git identity
+    pub const GIT_LOCAL: Scopes = Scopes(1 << 4);
+    /// Every configuration file, without running git
+    pub const FILE_ONLY: Scopes = Scopes(0b111);
+    /// Both git identities, without reading configuration files
+    pub const GIT_ONLY: Scopes = Scopes(0b11000);
+    /// Every source
+    pub const ALL: Scopes = Scopes(0b11111);
+
+    /// Whether every source of `other` is included
+    pub fn contains(self, other: Scopes) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for Scopes {
+    type Output = Scopes;
+
+    fn bitor(self, other: Scopes) -> Scopes {
+        Scopes(self.0 | other.0)
+    }
+}
+
+/// Where the git identities of a configuration come from
+///
+/// [`GitCli`] runs git; embedders and tests can answer from memory instead.
+pub trait GitBackend: std::fmt::Debug + Send + Sync {
+    /// `user.name` and `user.email` of the global scope, or of the
+    /// repository's local scope when `global` is false
+    fn user(&self, global: bool) -> anyhow::Result<UserConfig>;
+}
+
+/// Backend running `git config`, in the current directory by default
+#[derive(Debug, Clone, Default)]
+pub struct GitCli {
+    dir: Option<PathBuf>,
+}
+
+impl GitCli {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the local scope of the repository at `dir`
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir.into()),
+        }
+    }
+}
+
+impl GitBackend for GitCli {
+    fn user(&self, global: bool) -> anyhow::Result<UserConfig> {
+        get_git_user_in(global, self.dir.as_deref())
+    }
+}
+
+/// Loads a [`Config`] from chosen files, sources and git backend
+///
+/// Unset options fall back to what `gum` itself uses: the config path from
+/// `--config`, `GUM_CONFIG` or the platform default, the system file of
+/// the platform, every source and git in the current directory.
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config_path: Option<PathBuf>,
+    system_config_path: Option<PathBuf>,
+    backend: Option<Arc<dyn GitBackend>>,
+    scopes: Scopes,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self {
+            config_path: None,
+            system_config_path: None,
+            backend: None,
+            scopes: Scopes::ALL,
+        }
+    }
+}
+
+impl ConfigBuilder {
+    /// Read (and save to) this configuration file; `profiles.d` and relative
+    /// includes are looked up next to it
+    pub fn config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// Read this system-wide configuration file
+    pub fn system_config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.system_config_path = Some(path.into());
+        self
+    }
+
+    /// Read the git identities through `backend`
+    pub fn backend(mut self, backend: impl GitBackend + 'static) -> Self {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Read only these sources; the others are left empty
+    pub fn load_scopes(mut self, scopes: Scopes) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Load the configuration, reading the sources in parallel
+    ///
+    /// An unparsable user file does not fail the load: it is reported in
+    /// [`Config::file_error`] and `save` refuses to overwrite it.
+    pub fn build(self) -> Result<Config, Box<dyn std::error::Error>> {
+        log::debug!("Starting parallel config loading");
+        let started = Instant::now();
+        let scopes = self.scopes;
+        let custom_path = self.config_path.is_some();
+        let config_path = match self.config_path {
+            Some(path) => Ok(path),
+            None => utils::get_config_path().map_err(|e| e.to_string()),
+        };
+        let system_path = self
+            .system_config_path
+            .or_else(utils::get_system_config_path)
+            .filter(|_| scopes.contains(Scopes::SYSTEM_FILE));
+        let backend = self.backend.clone();
+        let git = backend
+            .clone()
+            .unwrap_or_else(|| Arc::new(GitCli::new()) as Arc<dyn GitBackend>);
+
+        // Start the tasks of the selected sources in parallel
+        let file_handle = {
+            let config_path = config_path.clone();
+            utils::spawn(move || match config_path {
+                _ if !scopes.contains(Scopes::USER_FILE) => Ok((ConfigFile::default(), Vec::new())),
+                Ok(path) => load_config_file(&path),
+                Err(e) => Err(anyhow::anyhow!(e)),
+            })
+        };
+        let system_handle = utils::spawn(move || load_system_config_file(system_path));
+        let profiles_handle = {
+            let config_path = config_path.clone();
+            utils::spawn(move || match config_path {
+                Ok(path) if scopes.contains(Scopes::PROFILES) => load_profile_files(&path),
+                _ => (Vec::new(), Vec::new()),
+            })
+        };
+
+        // The git program may be configured in the file, so git only starts
+        // once the file is read
+        let (config_file, file_warnings, file_error) = match file_handle
+            .join()
+            .map_err(|_| "Config file loading thread panicked")?
+        {
+            Ok((config_file, warnings)) => (config_file, warnings, None),
+            Err(e) => {
+                log::warn!("Failed to load config file: {}", e);
+                (ConfigFile::default(), Vec::new(), Some(e.to_string()))
+            }
+        };
+        apply_git_settings(&config_file.settings);
+        let global_handle = {
+            let git = git.clone();
+            utils::spawn(move || {
+                scopes
+                    .contains(Scopes::GIT_GLOBAL)
+                    .then(|| git.user(true).ok())
+                    .flatten()
+            })
+        };
+        let project_handle = utils::spawn(move || {
+            scopes
+                .contains(Scopes::GIT_LOCAL)
+                .then(|| git.user(false).ok())
+                .flatten()
+        });
+
+        // Wait for the remaining tasks to complete
+
+        let (system_groups, system_warning) = match system_handle
+            .join()
+            .map_err(|_| "System config file loading thread panicked")?
+        {
+            Ok(config_file) => (config_file.groups, None),
+            Err(e) => {
+                log::warn!("Failed to load system config file: {}", e);
+                (
+                    HashMap::new(),
+                    Some(format!("Ignoring system config file: {}", e)),
+                )
+            }
+        };
+        let (mut profiles, mut profile_warnings) = profiles_handle
+            .join()
+            .map_err(|_| "Profile files loading thread panicked")?;
+        if let Ok(ref path) = config_path
+            && scopes.contains(Scopes::PROFILES)
+        {
+            let (included, include_warnings) = load_include_files(path, &config_file.includes);
+            profiles.extend(included);
+            profile_warnings.extend(include_warnings);
+        }
+        let (system_groups, conflicts) =
+            merge_read_only_groups(system_groups, profiles, &config_file.groups);
+        let mut file_warnings = file_warnings;
+        file_warnings.extend(system_warning);
+        file_warnings.extend(profile_warnings);
+        file_warnings.extend(conflicts);
+
+        let global_user = global_handle
+            .join()
+            .map_err(|_| "Global git config loading thread panicked")?;
+
+        let project_user = project_handle
+            .join()
+            .map_err(|_| "Project git config loading thread panicked")?;
+
+        log::debug!(
+            "Config loading complete: {} groups, {} system groups, global user: {}, project user: {}",
+            config_file.groups.len(),
+            system_groups.len(),
+            global_user.is_some(),
+            project_user.is_some()
+        );
+        timings::record("load config (in parallel)", started.elapsed());
+
+        Ok(Config {
+            groups: config_file.groups,
+            system_groups,
+            aliases: config_file.aliases,
+            hosts: config_file.hosts,
+            branches: config_file.branches,
+            policies: config_file.policies,
+            includes: config_file.includes,
+            settings: config_file.settings,
+            global_user,
+            project_user,
+            file_error,
+            file_warnings,
+            path: config_path.ok().filter(|_| custom_path),
+            backend,
+        })
+    }
+}
+
+/// Make the git program and timeout of the settings the ones gum uses
+fn apply_git_settings(settings: &Settings) {
+    utils::set_git_timeout(settings.git.timeout());
+    if let Some(path) = settings
+        .git
+        .path
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+    {
+        utils::set_git_path(PathBuf::from(utils::expand_home(path.trim())));
+    }
+}
+
+/// Load configuration file, along with validation warnings
+fn load_config_file(config_path: &Path) -> anyhow::Result<(ConfigFile, Vec<String>)> {
+    log::debug!("Loading configuration groups from file");
+    let config_file = read_config_file(config_path)?;
+
+    let mut warnings = Vec::new();
+    if let Ok(content) = fs::read_to_string(config_path) {
+        for issue in validate::lint(&content) {
+            warnings.push(format!("{}:{}", config_path.display(), issue));
+        }
+    }
+    Ok((config_file, warnings))
+}
+
+/// Load the system-wide configuration file, if there is one
+fn load_system_config_file(path: Option<PathBuf>) -> anyhow::Result<ConfigFile> {
+    match path {
+        Some(path) => {
+            log::debug!("Loading system configuration from {}", path.display());
+            read_config_file(&path)
+        }
+        None => Ok(ConfigFile::default()),
+    }
+}
+
+/// Load the drop-in files of `profiles.d` next to the configuration file
+///
+/// `*.json` and `*.toml` files are read in file name order. Files that
+/// cannot be read are skipped and reported in the returned warnings.
+fn load_profile_files(config_path: &Path) -> (ProfileGroups, Vec<String>) {
+    let dir = config_path.with_file_name(PROFILES_DIR);
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == "json" || ext == "toml")
+        })
+        .collect();
+    paths.sort();
+
+    let mut profiles = Vec::new();
+    let mut warnings = Vec::new();
+    for path in paths {
+        log::debug!("Loading profile file {}", path.display());
+        match read_profile_file(&path) {
+            Ok(file) => profiles.push((path, file.groups)),
+            Err(e) => warnings.push(format!("Ignoring profile file {}", e)),
+        }
+    }
+    (profiles, warnings)
+}
+
+/// Load the files of the `includes` list of the configuration file
+///
+/// Paths are tilde-expanded, and relative ones are taken from the directory
+/// of the configuration file. Missing files (e.g. on an unmounted volume) are
+/// skipped quietly, unreadable ones with a warning.
+fn load_include_files(config_path: &Path, includes: &[String]) -> (ProfileGroups, Vec<String>) {
+    let Some(config_dir) = config_path.parent() else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut profiles = Vec::new();
+    let mut warnings = Vec::new();
+    for include in includes {
+        // Joining an absolute path replaces the directory
+        let path = config_dir.join(utils::expand_home(include));
+        if !path.exists() {
+            log::info!("Skipping missing include {}", path.display());
+            continue;
+        }
+        log::debug!("Loading include {}", path.display());
+        match read_profile_file(&path) {
+            Ok(file) => profiles.push((path, file.groups)),
+            Err(e) => warnings.push(format!("Ignoring included file {}", e)),
+        }
+    }
+    (profiles, warnings)
+}
+
+/// Read one drop-in profile or included file, TOML when its extension says
+/// so and JSON otherwise
+fn read_profile_file(path: &Path) -> anyhow::Result<ProfileFile> {
+    let content = timings::time(
+        || format!("read {}", path.display()),
+        || fs::read_to_string(path),
+    )
+    .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    timings::time(
+        || format!("parse {}", path.display()),
+        || {
+            let value = if path.extension().is_some_and(|ext| ext == "toml") {
+                toml_to_json(&content).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?
+            } else {
+                serde_json::from_str(&content)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?
+            };
+            serde_json::from_value(value).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+        },
+    )
+}
+
+/// Parse a TOML document into the JSON value profile files are read from
+///
+/// Dates and date-times are kept as strings, e.g. `expires = 2025-12-31`.
+fn toml_to_json(content: &str) -> Result<serde_json::Value, toml::de::Error> {
+    fn convert(value: toml::Value) -> serde_json::Value {
+        match value {
+            toml::Value::String(s) => serde_json::Value::String(s),
+            toml::Value::Integer(i) => i.into(),
+            toml::Value::Float(f) => f.into(),
+            toml::Value::Boolean(b) => b.into(),
+            toml::Value::Datetime(d) => d.to_string().into(),
+            toml::Value::Array(values) => values.into_iter().map(convert).collect(),
+            toml::Value::Table(table) => {
+                serde_json::Value::Object(table.into_iter().map(|(k, v)| (k, convert(v))).collect())
+            }
+        }
+    }
+    Ok(convert(toml::Value::Table(content.parse()?)))
+}
+
+/// Stack the read-only layers: the system groups, then the groups of each
+/// profile file in order (`profiles.d`, then `includes`), later layers
+/// winning
+///
+/// Returns the merged groups and a warning for each group of a profile file
+/// that redefines a system group or one of an earlier file differently, or
+/// that the user's `groups` redefine.
+fn merge_read_only_groups(
+    system_groups: HashMap<String, UserConfig>,
+    profiles: ProfileGroups,
+    user_groups: &HashMap<String, UserConfig>,
+) -> (HashMap<String, UserConfig>, Vec<String>) {
+    let same = |a: &UserConfig, b: &UserConfig| {
+        serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+    };
+    let mut merged = system_groups;
+    let mut sources: HashMap<String, PathBuf> = HashMap::new();
+    let mut conflicts = Vec::new();
+    for (path, groups) in profiles {
+        for (name, group) in groups {
+            if let Some(previous) = merged.get(&name)
+                && !same(previous, &group)
+            {
+                conflicts.push(match sources.get(&name) {
+                    Some(source) => format!(
+                        "Group \"{}\" of {} overrides the one of {}",
+                        name,
+                        path.display(),
+                        source.display()
+                    ),
+                    None => format!(
+                        "Group \"{}\" of {} overrides the system one",
+                        name,
+                        path.display()
+                    ),
+                });
+            }
+            sources.insert(name.clone(), path.clone());
+            merged.insert(name, group);
+        }
+    }
+    let mut overridden: Vec<(&String, &PathBuf)> = sources
+        .iter()
+        .filter(|(name, _)| {
+            user_groups
+                .get(*name)
+                .is_some_and(|group| !same(group, &merged[*name]))
+        })
+        .collect();
+    overridden.sort();
+    for (name, source) in overridden {
+        conflicts.push(format!(
+            "Group \"{}\" of {} is overridden by the configuration file",
+            name,
+            source.display()
+        ));
+    }
+    (merged, conflicts)
+}
+
+/// Read the configuration file at the given path
+///
+/// A missing file yields an empty configuration. Parse errors are reported
+/// through the validator, so they point at the offending line and column.
+fn read_config_file(path: &Path) -> anyhow::Result<ConfigFile> {
+    if !path.exists() {
+        log::debug!("Configuration file does not exist");
+        return Ok(ConfigFile::default());
+    }
+
+    let content = timings::time(
+        || format!("read {}", path.display()),
+        || fs::read_to_string(path),
+    )?;
+    let parsed = timings::time(
+        || format!("parse {}", path.display()),
+        || serde_json::from_str::<ConfigFile>(&content),
+    );
+    let config_file = parsed.map_err(|e| {
+        match validate::lint(&content)
+            .into_iter()
+            .find(|issue| issue.severity == Severity::Error)
+        {
+            Some(issue) => anyhow::anyhow!("{}:{}", path.display(), issue),
+            None => anyhow::anyhow!("{} is not valid: {}", path.display(), e),
+        }
+    })?;
+    log::debug!(
+        "Successfully loaded {} configuration groups",
+        config_file.groups.len()
+    );
+
+    Ok(config_file)
+}
+
+/// Write the configuration file to the given path
+///
+/// The previous file (if any) is kept as a backup next to it, and the new
+/// content is written to a temporary file first and then renamed into place.
+fn write_config_file(path: &Path, config_file: &ConfigFile) -> anyhow::Result<()> {
+    let content = serde_json::to_string_pretty(config_file)?;
+    write_config_content(path, &content)
+}
+
+/// Replace the configuration file with raw content
+///
+/// Used for content edited by hand; callers are expected to have validated
+/// it. Keeps a backup of the previous file like `save` does.
+pub fn write_config_content(path: &Path, content: &str) -> anyhow::Result<()> {
+    let old = fs::read_to_string(path).unwrap_or_default();
+    if utils::preview_write(path, &old, content) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if path.exists() {
+        fs::copy(path, backup_path(path))?;
+    }
+
+    let tmp_path = path.with_extension("jsonc.tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)?;
+    journal::record_config_write(path, &old, content);
+    record_version(path, &old, content);
+    Ok(())
+}
+
+/// Keep the new state of the configuration file in its versions, logging
+/// instead of failing since the file is already written
+///
+/// As many states are kept as the new content's `settings.retention`
+/// allows.
+fn record_version(path: &Path, old: &str, new: &str) {
+    let keep = serde_json::from_str::<ConfigFile>(new)
+        .map(|config_file| config_file.settings.retention.versions())
+        .unwrap_or(versions::MAX_VERSIONS);
+    if let Err(e) = versions::record(path, old, new, keep) {
+        log::warn!("Cannot record config file version: {}", e);
+    }
+}
+
+/// Replace the configuration file with the state written `steps` saves ago
+///
+/// The state must parse; the rollback is itself recorded as a new state, so
+/// it can be rolled back too. Returns the version restored.
+pub fn rollback(steps: usize) -> anyhow::Result<versions::Version> {
+    let config_path = utils::get_config_path()?;
+    let current = fs::read_to_string(&config_path).unwrap_or_default();
+    let version = versions::before(&config_path, &current, steps)?;
+    let content = versions::read(&config_path, &version)?;
+    serde_json::from_str::<ConfigFile>(&content)
+        .map_err(|e| anyhow::anyhow!("version {} is not valid: {}", version.hash, e))?;
+    write_config_content(&config_path, &content)?;
+    log::debug!("Rolled back configuration to {}", version.hash);
+    Ok(version)
+}
+
+/// Path of the backup kept alongside the configuration file
+pub fn backup_path(config_path: &Path) -> PathBuf {
+    let mut name = config_path.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// Restore the configuration file from its backup
+///
+/// The backup must parse successfully, otherwise nothing is changed.
+/// The unparsable file is kept with a `.corrupt` suffix.
+pub fn restore_backup() -> anyhow::Result<usize> {
+    let config_path = utils::get_config_path()?;
+    let backup = backup_path(&config_path);
+    if !backup.exists() {
+        return Err(anyhow::anyhow!("No backup found at {}", backup.display()));
+    }
+
+    let groups = read_config_file(&backup)?.groups;
+    let current = fs::read_to_string(&config_path).unwrap_or_default();
+    let restored = fs::read_to_string(&backup)?;
+    if utils::preview_write(&config_path, &current, &restored) {
+        return Ok(groups.len());
+    }
+    if config_path.exists() {
+        let mut corrupt = config_path.as_os_str().to_owned();
+        corrupt.push(".corrupt");
+        fs::rename(&config_path, PathBuf::from(corrupt))?;
+    }
+    fs::copy(&backup, &config_path)?;
+    journal::record_config_write(&config_path, &current, &restored);
+    record_version(&config_path, &current, &restored);
+    log::debug!("Restored configuration from {}", backup.display());
+    Ok(groups.len())
+}
+
+/// Batch get git user configuration of the repository at `dir`
+///
+/// `None` uses the current directory.
+pub fn get_git_user_in(global: bool, dir: Option<&Path>) -> anyhow::Result<UserConfig> {
+    let scope = if global { "--global" } else { "--local" };
+    get_git_user_scoped(Some(scope), dir)
+}
+
+/// Get the effective git user configuration of the repository at `dir`
+///
+/// Lets git merge all scopes (system, global, local, worktree) in a single
+/// call; later scopes override earlier ones. `None` uses the current directory.
+pub fn get_effective_git_user(dir: Option<&Path>) -> anyhow::Result<UserConfig> {
+    get_git_user_scoped(None, dir)
+}
+
+/// Read user.name and user.email from one scope, or from all when `None`
+fn get_git_user_scoped(scope: Option<&str>, dir: Option<&Path>) -> anyhow::Result<UserConfig> {
+    let scope_label = scope.unwrap_or("effective");
+    log::debug!("Batch fetching git user configuration ({})", scope_label);
+
+    let mut command = git_command(dir);
+    command.arg("config");
+    if let Some(scope) = scope {
+        command.arg(scope);
+    }
+    let output = utils::output(command.args(["--get-regexp", "^user\\.(name|email)$"]))?;
+
+    if !output.status.success() {
+        return Err(anyhow::format_err!(
+            "Failed to get git configuration: {}",
+            scope_label
+        ));
+    }
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let mut name = String::new();
+    let mut email = String::new();
+
+    for line in stdout.lines() {
+        if let Some((key, value)) = line.split_once(' ') {
+            match key {
+                "user.name" => name = value.to_string(),
+                "user.email" => email = value.to_string(),
+                _ => {}
+            }
+        }
+    }
+
+    if name.is_empty() && email.is_empty() {
+        return Err(anyhow::anyhow!("Git user configuration is empty"));
+    }
+
+    log::debug!("Retrieved user configuration: {} <{}>", name, email);
+    Ok(UserConfig {
+        name,
+        email,
+        ..Default::default()
+    })
+}
+
+/// Set git user configuration
+pub fn set_git_user(user: &UserConfig, global: bool) -> anyhow::Result<()> {
+    set_git_user_in(user, global, None)
+}
+
+/// Set git user configuration of the repository at `dir`
+///
+/// `None` uses the current directory.
+pub fn set_git_user_in(user: &UserConfig, global: bool, dir: Option<&Path>) -> anyhow::Result<()> {
+    let scope = if global { "--global" } else { "--local" };
+    log::debug!(
+        "Setting git user configuration ({}): {} <{}>",
+        scope,
+        user.name,
+        user.email
+    );
+
+    // Set name
+    let status =
+        utils::run_change(git_command(dir).args(["config", scope, "user.name", &user.name]))?;
+    if let Some(status) = status
+        && !status.success()
+    {
+        return Err(anyhow::anyhow!("Failed to set git user.name"));
+    }
+
+    // Set email
+    let status =
+        utils::run_change(git_command(dir).args(["config", scope, "user.email", &user.email]))?;
+    if let Some(status) = status
+        && !status.success()
+    {
+        return Err(anyhow::anyhow!("Failed to set git user.email"));
+    }
+
+    log::debug!("Git user configuration set successfully");
+    Ok(())
+}
+
+/// Make git refuse to commit anywhere gum has not set an identity
+///
+/// Sets the global `user.useConfigOnly` so git stops guessing an identity
+/// from the host name, and removes the global `user.name`/`user.email`,
+/// which would otherwise silently apply to every repository. The removal is
+/// recorded in the history, so `gum undo` puts the identity back. Returns
+/// the removed global identity, if there was one.
+pub fn enforce_use_config_only() -> anyhow::Result<Option<UserConfig>> {
+    let status = utils::run_change(git_command(None).args([
+        "config",
+        "--global",
+        "user.useConfigOnly",
+        "true",
+    ]))?;
+    if let Some(status) = status
+        && !status.success()
+    {
+        return Err(anyhow::anyhow!("Failed to set git user.useConfigOnly"));
+    }
+
+    let removed = get_git_user_scoped(Some("--global"), None).ok();
+    if removed.is_some() {
+        log::info!("Removing global git identity (use_config_only)");
+        let before = undo::capture(true, None, &[]);
+        unset_git_user_in(true, None)?;
+        match before {
+            Ok(before) => history::record(history::Action::Unset, before, None, None),
+            Err(e) => log::warn!("Cannot capture the global identity: {}", e),
+        }
+    }
+    // Nothing was removed when the commands were only printed
+    Ok(removed.filter(|_| !utils::is_dry_run()))
+}
+
+/// Remove `user.name` and `user.email` from a scope
+pub fn unset_git_user_in(global: bool, dir: Option<&Path>) -> anyhow::Result<()> {
+    let scope = if global { "--global" } else { "--local" };
+    for key in ["user.name", "user.email"] {
+        // Exit code 5 means the key was not set
+        let status =
+            utils::run_change(git_command(dir).args(["config", scope, "--unset-all", key]))?;
+        if let Some(status) = status
+            && !status.success()
+            && status.code() != Some(5)
+        {
+            return Err(anyhow::anyhow!("Failed to unset git {}", key));
+        }
+    }
+    Ok(())
+}
+
+/// Read a git setting from one scope, or the effective value when `None`
+pub fn get_git_config(scope: Option<&str>, key: &str, dir: Option<&Path>) -> Option<String> {
+    let mut command = git_command(dir);
+    command.arg("config");
+    if let Some(scope) = scope {
+        command.arg(scope);
+    }
+    let output = utils::output(command.args(["--get", key])).ok()?;
+    let value = String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
+/// Read every value of a multi-valued git setting from one scope, or from
+/// all scopes when `None`
+pub fn get_git_config_all(scope: Option<&str>, key: &str, dir: Option<&Path>) -> Vec<String> {
+    let mut command = git_command(dir);
+    command.arg("config");
+    if let Some(scope) = scope {
+        command.arg(scope);
+    }
+    // NUL-terminated, since a value may span several lines
+    match utils::output(command.args(["-z", "--get-all", key])) {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split_terminator('\0')
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Apply a git setting managed by groups
+///
+/// Sets `key` when the group has a value. Otherwise a value left by another
+/// group (one of `managed`) is unset, while a value the user configured by
+/// hand is kept. When `key` has several values, e.g. a chain of
+/// `credential.helper`s, only the managed ones are replaced or removed.
+pub fn apply_managed_setting(
+    key: &str,
+    value: Option<&str>,
+    managed: &[String],
+    global: bool,
+    dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    let scope = if global { "--global" } else { "--local" };
+    let current = get_git_config_all(Some(scope), key, dir);
+
+    if let Some(value) = value {
+        log::debug!("Setting {} ({}): {}", key, scope, value);
+        let mut command = git_command(dir);
+        command.args(["config", scope]);
+        if current.len() > 1 {
+            let replaced = managed.iter().map(String::as_str).chain([value]);
+            command.args(["--replace-all", key, value, &value_pattern(replaced)]);
+        } else {
+            command.args([key, value]);
+        }
+        let status = utils::run_change(&mut command)?;
+        if let Some(status) = status
+            && !status.success()
+        {
+            return Err(anyhow::anyhow!("Failed to set git {}", key));
+        }
+        return Ok(());
+    }
+
+    let stale: Vec<&str> = current
+        .iter()
+        .filter(|v| !v.is_empty() && managed.contains(v))
+        .map(String::as_str)
+        .collect();
+    if stale.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!("Unsetting {} ({}): {}", key, scope, stale.join(", "));
+    let pattern = value_pattern(stale);
+    let status =
+        utils::run_change(git_command(dir).args(["config", scope, "--unset-all", key, &pattern]))?;
+    if let Some(status) = status
+        && !status.success()
+    {
+        return Err(anyhow::anyhow!("Failed to unset git {}", key));
+    }
+    Ok(())
+}
+
+/// Apply a multi-valued git setting managed by groups
+///
+/// Adds each of `values` that is missing and removes the values left by
+/// other groups (those in `managed`), keeping values the user added by hand.
+pub fn apply_managed_values(
+    key: &str,
+    values: &[String],
+    managed: &[String],
+    global: bool,
+    dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    let scope = if global { "--global" } else { "--local" };
+    let current = get_git_config_all(Some(scope), key, dir);
+
+    for stale in current
+        .iter()
+        .filter(|v| managed.contains(v) && !values.contains(v))
+    {
+        log::debug!("Unsetting {} ({}): {}", key, scope, stale);
+        let pattern = value_pattern([stale.as_str()]);
+        let status = utils::run_change(git_command(dir).args([
+            "config",
+            scope,
+            "--unset-all",
+            key,
+            &pattern,
+        ]))?;
+        if let Some(status) = status
+            && !status.success()
+        {
+            return Err(anyhow::anyhow!("Failed to unset git {}", key));
+        }
+    }
+    for value in values.iter().filter(|v| !current.contains(v)) {
+        log::debug!("Adding {} ({}): {}", key, scope, value);
+        let status =
+            utils::run_change(git_command(dir).args(["config", scope, "--add", key, value]))?;
+        if let Some(status) = status
+            && !status.success()
+        {
+            return Err(anyhow::anyhow!("Failed to set git {}", key));
+        }
+    }
+    Ok(())
+}
+
+/// Git value pattern matching exactly one of `values`
+fn value_pattern<'a>(values: impl IntoIterator<Item = &'a str>) -> String {
+    let alternatives: Vec<String> = values
+        .into_iter()
+        .map(|value| {
+            let mut escaped = String::new();
+            for c in value.chars() {
+                if "\\.^$|?*+()[]{}".contains(c) {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            escaped
+        })
+        .collect();
+    format!("^({})$", alternatives.join("|"))
+}
+
+/// Git setting making `replacement` stand in for the prefixes it holds
+pub fn url_rewrite_key(replacement: &str) -> String {
+    format!("url.{}.insteadOf", replacement)
+}
+
+/// `core.sshCommand` value using only the key at `key`
+pub fn ssh_command(key: &str) -> String {
+    let key = utils::expand_home(key).replace('\\', "/");
+    format!(
+        "ssh -i '{}' -o IdentitiesOnly=yes",
+        key.replace('\'', "'\\''")
+    )
+}
+
+/// Key path of a `core.sshCommand` value written by [`ssh_command`]
+pub fn ssh_command_key(command: &str) -> Option<String> {
+    let quoted = command
+        .strip_prefix("ssh -i '")?
+        .strip_suffix("' -o IdentitiesOnly=yes")?;
+    Some(quoted.replace("'\\''", "'"))
+}
+
+/// Identity and settings configured in one git scope, as a group
+///
+/// `None` when neither `user.name` nor `user.email` is set in the scope.
+/// A `core.sshCommand` not written by gum is not captured.
+pub fn git_scope_as_group(scope: &str, dir: Option<&Path>) -> Option<UserConfig> {
+    let get = |key: &str| get_git_config(Some(scope), key, dir);
+    let (name, email) = (get("user.name"), get("user.email"));
+    if name.is_none() && email.is_none() {
+        return None;
+    }
+    let signoff = get("format.signOff").is_some_and(|value| value == "true");
+    Some(UserConfig {
+        name: name.unwrap_or_default(),
+        email: email.unwrap_or_default(),
+        commit_template: get("commit.template"),
+        ssh_key: get("core.sshCommand").and_then(|command| ssh_command_key(&command)),
+        signing_key: get("user.signingkey"),
+        signing_format: get("gpg.format"),
+        http_proxy: get("http.proxy"),
+        ssl_ca_info: get("http.sslCAInfo"),
+        credential_helper: get("credential.helper"),
+        committer_name: get("committer.name"),
+        committer_email: get("committer.email"),
+        sendemail_from: get("sendemail.from"),
+        smtp_server: get("sendemail.smtpServer"),
+        smtp_server_port: get("sendemail.smtpServerPort"),
+        smtp_user: get("sendemail.smtpUser"),
+        smtp_encryption: get("sendemail.smtpEncryption"),
+        trailers: get(TRAILERS_KEY)
+            .map(|value| {
+                value
+                    .lines()
+                    .filter(|line| !(signoff && *line == SIGNOFF_TRAILER))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        signoff: signoff.then_some(true),
+        ..UserConfig::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_new() {
+        let config = Config::new();
+        assert!(config.groups.is_empty());
+        assert!(config.global_user.is_none());
+        assert!(config.project_user.is_none());
     }
 
     #[test]
@@ -251,6 +2300,7 @@ mod tests {
         let user = UserConfig {
             name: "Test User".to_string(),
             email: "test@example.com".to_string(),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&user).unwrap();
@@ -259,4 +2309,963 @@ mod tests {
         assert_eq!(deserialized.name, "Test User");
         assert_eq!(deserialized.email, "test@example.com");
     }
+
+    #[test]
+    fn test_is_expired() {
+        let day = utils::parse_date("2025-12-31").unwrap();
+        let mut user = UserConfig {
+            expires: Some("2025-12-31".to_string()),
+            ..Default::default()
+        };
+        assert!(!user.is_expired(day + 86_399));
+        assert!(user.is_expired(day + 86_400));
+        user.expires = Some("someday".to_string());
+        assert!(!user.is_expired(day + 86_400));
+        user.expires = None;
+        assert!(!user.is_expired(day + 86_400));
+    }
+
+    #[test]
+    fn test_read_config_file_reports_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.jsonc");
+        fs::write(&path, "{\n  \"groups\": {\n    \"a\": }\n}").unwrap();
+
+        let err = read_config_file(&path).unwrap_err().to_string();
+        assert!(err.contains("config.jsonc:3:10: error"), "{}", err);
+    }
+
+    #[test]
+    fn test_write_config_file_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.jsonc");
+        let mut config_file = ConfigFile::default();
+        config_file.groups.insert(
+            "a".to_string(),
+            UserConfig {
+                name: "A".to_string(),
+                email: "a@example.com".to_string(),
+                ..Default::default()
+            },
+        );
+        write_config_file(&path, &config_file).unwrap();
+        assert!(!backup_path(&path).exists());
+
+        config_file.groups.clear();
+        write_config_file(&path, &config_file).unwrap();
+        let backup = read_config_file(&backup_path(&path)).unwrap();
+        assert_eq!(backup.groups.len(), 1);
+        assert!(read_config_file(&path).unwrap().groups.is_empty());
+    }
+
+    #[test]
+    fn test_system_groups_merged_beneath_user_groups() {
+        let user = |email: &str| UserConfig {
+            name: "A".to_string(),
+            email: email.to_string(),
+            ..Default::default()
+        };
+        let mut config = Config::new();
+        config
+            .system_groups
+            .insert("corp".to_string(), user("it@corp.com"));
+        config
+            .system_groups
+            .insert("shared".to_string(), user("system@corp.com"));
+        config
+            .groups
+            .insert("shared".to_string(), user("me@corp.com"));
+
+        assert!(config.is_system_group("corp"));
+        assert!(!config.is_system_group("shared"));
+        assert_eq!(config.get_group("shared").unwrap().email, "me@corp.com");
+        let all = config.get_all_config_info();
+        assert_eq!(all["corp"].email, "it@corp.com");
+        assert_eq!(all["shared"].email, "me@corp.com");
+    }
+
+    #[test]
+    fn test_profile_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("10-corp.json");
+        fs::write(
+            &json,
+            r#"{ "groups": { "corp": { "name": "Li", "email": "li@corp.com" } } }"#,
+        )
+        .unwrap();
+        let toml = dir.path().join("20-client.toml");
+        fs::write(
+            &toml,
+            "[groups.client]\nname = \"Li\"\nemail = \"li@client.com\"\n\n\
+             [groups.corp]\nname = \"Li\"\nemail = \"li@corp.example\"\n",
+        )
+        .unwrap();
+        let profiles = [&json, &toml]
+            .into_iter()
+            .map(|path| (path.clone(), read_profile_file(path).unwrap().groups))
+            .collect();
+        let user = HashMap::from([(
+            "client".to_string(),
+            UserConfig {
+                name: "Li".to_string(),
+                email: "me@client.com".to_string(),
+                ..Default::default()
+            },
+        )]);
+
+        let system = HashMap::from([(
+            "corp".to_string(),
+            UserConfig {
+                name: "Li".to_string(),
+                email: "li@corp.internal".to_string(),
+                ..Default::default()
+            },
+        )]);
+
+        let (merged, conflicts) = merge_read_only_groups(system, profiles, &user);
+        assert_eq!(merged["corp"].email, "li@corp.example");
+        assert_eq!(merged["client"].email, "li@client.com");
+        assert_eq!(
+            conflicts,
+            [
+                format!(
+                    "Group \"corp\" of {} overrides the system one",
+                    json.display()
+                ),
+                format!(
+                    "Group \"corp\" of {} overrides the one of {}",
+                    toml.display(),
+                    json.display()
+                ),
+                format!(
+                    "Group \"client\" of {} is overridden by the configuration file",
+                    toml.display()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_toml_to_json() {
+        let content = r#"
+# Provisioned by IT
+[groups.corp]
+name = "Li Si"   # display name
+email = 'li@corp.example'
+expires = 2025-12-31
+trailers = [
+  "Signed-off-by: {name} <{email}>",
+  "On-behalf-of: @corp",
+]
+workstyle = { "pull.rebase" = "true", core.autocrlf = "input" }
+
+[groups."build bot"]
+email = "bot@corp.example"
+ssh.port = 2_222
+"#;
+        assert_eq!(
+            toml_to_json(content).unwrap(),
+            serde_json::json!({
+                "groups": {
+                    "corp": {
+                        "name": "Li Si",
+                        "email": "li@corp.example",
+                        "expires": "2025-12-31",
+                        "trailers": [
+                            "Signed-off-by: {name} <{email}>",
+                            "On-behalf-of: @corp"
+                        ],
+                        "workstyle": {
+                            "pull.rebase": "true",
+                            "core": { "autocrlf": "input" }
+                        }
+                    },
+                    "build bot": {
+                        "email": "bot@corp.example",
+                        "ssh": { "port": 2222 }
+                    }
+                }
+            })
+        );
+        assert!(toml_to_json("[groups.a]\nname = \"A\"\nname = \"B\"\n").is_err());
+        assert!(toml_to_json("[groups.a]\nname = \"A\"\n[groups.a]\nemail = \"a@b.c\"\n").is_err());
+        assert!(toml_to_json("a = \"unterminated\n").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_identities() {
+        let user = |name: &str, email: &str| UserConfig {
+            name: name.to_string(),
+            email: email.to_string(),
+            ..Default::default()
+        };
+        let mut config = Config::new();
+        config
+            .groups
+            .insert("a".to_string(), user("Li", "li@x.com"));
+        config
+            .groups
+            .insert("b".to_string(), user("Li", "LI@x.com"));
+        config
+            .groups
+            .insert("c".to_string(), user("Wang", "li@x.com"));
+        config
+            .groups
+            .insert("d".to_string(), user("Li", "li@x.com"));
+
+        assert_eq!(
+            config.duplicate_identities(),
+            vec![vec!["a".to_string(), "b".to_string(), "d".to_string()]]
+        );
+        assert_eq!(
+            config.find_identity(&user("Li", "li@x.com"), "a"),
+            vec!["b".to_string(), "d".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_similar_identity() {
+        let user = |name: &str, email: &str| UserConfig {
+            name: name.to_string(),
+            email: email.to_string(),
+            ..Default::default()
+        };
+        let li = user("Li Si", "li@x.com");
+        assert!(li.similar_identity(&user("li  si", "Li@x.com")));
+        assert!(li.similar_identity(&user("Li Si", "li+gum@x.com")));
+        assert!(!li.similar_identity(&user("Li Si", "li@y.com")));
+        assert!(!li.similar_identity(&user("Wang Wu", "li@x.com")));
+
+        let mut with_variant = li.clone();
+        with_variant
+            .email_variants
+            .insert("oss".to_string(), "li@oss.dev".to_string());
+        assert!(user("Li Si", "li@oss.dev").similar_identity(&with_variant));
+    }
+
+    #[test]
+    fn test_migrate_domain() {
+        let mut config = Config::new();
+        config.groups.insert(
+            "work".to_string(),
+            UserConfig {
+                name: "Li".to_string(),
+                email: "li@Old-Corp.com".to_string(),
+                committer_email: Some("ci@old-corp.com".to_string()),
+                email_variants: BTreeMap::from([
+                    ("oss".to_string(), "li@oss.dev".to_string()),
+                    ("ops".to_string(), "li@ops.old-corp.com".to_string()),
+                ]),
+                ..Default::default()
+            },
+        );
+        config.groups.get_mut("work").unwrap().locked = true;
+        assert!(
+            config
+                .migrate_domain("old-corp.com", "new-corp.com", false)
+                .is_err()
+        );
+        assert_eq!(config.groups["work"].email, "li@Old-Corp.com");
+        let changes = config
+            .migrate_domain("old-corp.com", "new-corp.com", true)
+            .unwrap();
+        let change = |old: &str, new: &str| ("work".to_string(), old.to_string(), new.to_string());
+        assert_eq!(
+            changes,
+            [
+                change("ci@old-corp.com", "ci@new-corp.com"),
+                change("li@Old-Corp.com", "li@new-corp.com"),
+            ]
+        );
+        let work = &config.groups["work"];
+        assert_eq!(work.email, "li@new-corp.com");
+        assert_eq!(work.email_variants["ops"], "li@ops.old-corp.com");
+        assert!(
+            config
+                .migrate_domain("old-corp.com", "x.com", false)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_merge_groups() {
+        let user = |email: &str, inherit: Option<&str>| UserConfig {
+            name: "Li".to_string(),
+            email: email.to_string(),
+            inherit: inherit.map(str::to_string),
+            ..Default::default()
+        };
+        let mut config = Config::new();
+        config
+            .groups
+            .insert("work".to_string(), user("li@x.com", Some("base")));
+        config.groups.insert(
+            "base".to_string(),
+            UserConfig {
+                trailers: vec!["On-behalf-of: @x".to_string()],
+                http_proxy: Some("http://proxy.x.com".to_string()),
+                ..user("li@x.com", None)
+            },
+        );
+        config
+            .groups
+            .insert("work2".to_string(), user("li+2@x.com", None));
+        config
+            .groups
+            .insert("child".to_string(), user("", Some("work2")));
+        config.aliases.insert("w".to_string(), "work2".to_string());
+        config
+            .hosts
+            .insert("github.com".to_string(), "base".to_string());
+        config
+            .branches
+            .insert("main".to_string(), "work2".to_string());
+
+        let merged = ["base".to_string(), "work2".to_string()];
+        config.groups.get_mut("work2").unwrap().locked = true;
+        assert!(config.merge_groups("work", &merged, false).is_err());
+        assert_eq!(config.groups.len(), 4);
+        config.groups.get_mut("work2").unwrap().locked = false;
+        config.merge_groups("work", &merged, false).unwrap();
+
+        let mut names: Vec<&String> = config.groups.keys().collect();
+        names.sort();
+        assert_eq!(names, ["child", "work"]);
+        let work = &config.groups["work"];
+        assert_eq!(work.inherit, None);
+        assert_eq!(work.trailers, ["On-behalf-of: @x"]);
+        assert_eq!(work.http_proxy.as_deref(), Some("http://proxy.x.com"));
+        assert_eq!(
+            work.email_variants,
+            BTreeMap::from([("work2".to_string(), "li+2@x.com".to_string())])
+        );
+        assert_eq!(config.groups["child"].inherit.as_deref(), Some("work"));
+        assert_eq!(config.aliases["w"], "work");
+        assert_eq!(config.hosts["github.com"], "work");
+        assert_eq!(config.branches["main"], "work");
+    }
+
+    #[test]
+    fn test_normalize_group_name() {
+        let mut config = Config::new();
+        assert_eq!(config.normalize_group_name(" Work "), "Work");
+        config.settings.lowercase_group_names = true;
+        assert_eq!(config.normalize_group_name(" Work "), "work");
+    }
+
+    #[test]
+    fn test_similar_group_names() {
+        let mut config = Config::new();
+        for name in ["work", "personal", "oss", "Worker"] {
+            config
+                .groups
+                .insert(name.to_string(), UserConfig::default());
+        }
+        config
+            .aliases
+            .insert("p".to_string(), "personal".to_string());
+        assert_eq!(config.similar_group_names("wrok"), ["work"]);
+        assert_eq!(config.similar_group_names("persnal"), ["personal"]);
+        assert_eq!(config.similar_group_names("WORKER"), ["Worker"]);
+        assert!(config.similar_group_names("zzz").is_empty());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut config = Config::new();
+        config
+            .groups
+            .insert("work".to_string(), UserConfig::default());
+        config.groups.insert("w".to_string(), UserConfig::default());
+        config.aliases.insert("wk".to_string(), "work".to_string());
+        config.aliases.insert("w".to_string(), "work".to_string());
+
+        assert_eq!(config.normalize_group_name(" wk "), "work");
+        // A group with the alias name wins
+        assert_eq!(config.normalize_group_name("w"), "w");
+        assert_eq!(config.aliases_of("work"), ["w", "wk"]);
+
+        config.remove_group("work");
+        assert!(config.aliases.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_group_inheritance() {
+        let mut config = Config::new();
+        let group = |name: &str, email: &str, inherit: Option<&str>| UserConfig {
+            name: name.to_string(),
+            email: email.to_string(),
+            inherit: inherit.map(str::to_string),
+            ..Default::default()
+        };
+        config
+            .groups
+            .insert("work".to_string(), group("Li", "li@acme.com", None));
+        config
+            .groups
+            .insert("oss".to_string(), group("", "oss@acme.com", Some("work")));
+
+        let work = config.groups.get_mut("work").unwrap();
+        work.signing_key = Some("~/.ssh/id_ed25519_work.pub".to_string());
+        work.signing_format = Some("ssh".to_string());
+        work.ssh_key = Some("~/.ssh/id_ed25519_work".to_string());
+        work.ssh_alias = Some("github-work".to_string());
+
+        let resolved = config.resolve_group("oss").unwrap();
+        assert_eq!(resolved.name, "Li");
+        assert_eq!(resolved.email, "oss@acme.com");
+        assert_eq!(
+            resolved.signing_key.as_deref(),
+            Some("~/.ssh/id_ed25519_work.pub")
+        );
+        assert_eq!(resolved.signing_format.as_deref(), Some("ssh"));
+        assert_eq!(resolved.ssh_key.as_deref(), Some("~/.ssh/id_ed25519_work"));
+        assert_eq!(resolved.ssh_alias.as_deref(), Some("github-work"));
+
+        // A key of its own does not take the parent's format
+        config.groups.get_mut("oss").unwrap().signing_key = Some("89ABCDEF01234567".to_string());
+        let resolved = config.resolve_group("oss").unwrap();
+        assert_eq!(resolved.signing_key.as_deref(), Some("89ABCDEF01234567"));
+        assert_eq!(resolved.signing_format, None);
+        assert_eq!(config.children_of("work"), ["oss"]);
+        assert_eq!(config.get_all_config_info()["oss"].name, "Li");
+
+        config
+            .groups
+            .insert("work".to_string(), group("Li", "li@acme.com", Some("oss")));
+        let err = config.resolve_group("oss").unwrap_err().to_string();
+        assert_eq!(err, "Inheritance cycle: oss -> work -> oss");
+    }
+
+    #[test]
+    fn test_retain_uninherited() {
+        let mut config = Config::new();
+        let group = |inherit: Option<&str>| UserConfig {
+            inherit: inherit.map(str::to_string),
+            ..Default::default()
+        };
+        config.groups.insert("base".to_string(), group(None));
+        config
+            .groups
+            .insert("parent".to_string(), group(Some("base")));
+        config
+            .groups
+            .insert("child".to_string(), group(Some("parent")));
+
+        let mut deleting = vec![
+            "base".to_string(),
+            "parent".to_string(),
+            "child".to_string(),
+        ];
+        assert!(config.retain_uninherited(&mut deleting).is_empty());
+        assert_eq!(deleting.len(), 3);
+
+        // child stays (e.g. it is locked), so parent and then base stay too
+        let mut deleting = vec!["base".to_string(), "parent".to_string()];
+        let dropped = config.retain_uninherited(&mut deleting);
+        assert!(deleting.is_empty());
+        assert_eq!(
+            dropped,
+            [
+                ("parent".to_string(), vec!["child".to_string()]),
+                ("base".to_string(), vec!["parent".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_email_variants() {
+        let mut config = Config::new();
+        let mut work = UserConfig {
+            name: "Li".to_string(),
+            email: "li@acme.com".to_string(),
+            ..Default::default()
+        };
+        work.email_variants.insert(
+            "noreply".to_string(),
+            "li@users.noreply.github.com".to_string(),
+        );
+        config.groups.insert("work".to_string(), work.clone());
+
+        let noreply = work.with_email_variant("noreply").unwrap();
+        assert_eq!(noreply.email, "li@users.noreply.github.com");
+        assert!(work.with_email_variant("list").is_err());
+        // The identity set with a variant still belongs to the group
+        assert_eq!(config.match_groups(&noreply), ["work"]);
+    }
+
+    /// Answers from memory instead of running git
+    #[derive(Debug)]
+    struct FakeGit;
+
+    impl GitBackend for FakeGit {
+        fn user(&self, global: bool) -> anyhow::Result<UserConfig> {
+            Ok(UserConfig {
+                name: "Li".to_string(),
+                email: if global { "li@ex.org" } else { "li@acme.com" }.to_string(),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.jsonc");
+        fs::write(
+            &path,
+            r#"{ "groups": { "oss": { "name": "Li", "email": "li@ex.org" } } }"#,
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join(PROFILES_DIR)).unwrap();
+        fs::write(
+            dir.path().join(PROFILES_DIR).join("corp.json"),
+            r#"{ "groups": { "corp": { "name": "Li", "email": "li@corp.com" } } }"#,
+        )
+        .unwrap();
+
+        let config = Config::builder()
+            .config_path(&path)
+            .backend(FakeGit)
+            .load_scopes(Scopes::USER_FILE | Scopes::PROFILES | Scopes::GIT_LOCAL)
+            .build()
+            .unwrap();
+        assert!(config.groups.contains_key("oss"));
+        assert!(config.system_groups.contains_key("corp"));
+        assert!(config.global_user.is_none());
+        assert_eq!(config.project_user.unwrap().email, "li@acme.com");
+
+        let mut config = Config::builder()
+            .config_path(&path)
+            .load_scopes(Scopes::FILE_ONLY)
+            .build()
+            .unwrap();
+        assert!(config.global_user.is_none() && config.project_user.is_none());
+        config.groups.remove("oss");
+        config.save().unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("oss"));
+    }
+
+    /// A git that cannot run, e.g. missing from PATH
+    #[derive(Debug)]
+    struct BrokenGit;
+
+    impl GitBackend for BrokenGit {
+        fn user(&self, _global: bool) -> anyhow::Result<UserConfig> {
+            Err(anyhow::anyhow!("git not found"))
+        }
+    }
+
+    /// Bad environments end in errors or warnings, never in a panic
+    #[test]
+    fn test_bad_environment_is_no_panic() {
+        let dir = tempfile::tempdir().unwrap();
+        let load = |path: &Path| {
+            Config::builder()
+                .config_path(path)
+                .system_config_path(dir.path().join("system.json"))
+                .backend(BrokenGit)
+                .build()
+                .unwrap()
+        };
+
+        // Missing directory: an empty configuration, and no git identities
+        let config = load(&dir.path().join("missing").join("config.jsonc"));
+        assert!(config.groups.is_empty() && config.file_error.is_none());
+        assert!(config.global_user.is_none() && config.project_user.is_none());
+        assert!(config.resolve_group("oss").is_err());
+
+        // A directory or invalid UTF-8 where the file should be
+        assert!(load(dir.path()).file_error.is_some());
+        let binary = dir.path().join("binary.jsonc");
+        fs::write(&binary, [0xff, 0xfe, 0x00, 0x7b]).unwrap();
+        assert!(load(&binary).file_error.is_some());
+
+        // Unreadable system file: a warning
+        fs::write(dir.path().join("system.json"), "{ not json").unwrap();
+        let config = load(&dir.path().join("config.jsonc"));
+        assert!(config.file_warnings.iter().any(|w| w.contains("system")));
+
+        // Saving below a file cannot create the directory
+        let mut config = load(&binary.join("config.jsonc"));
+        config.file_error = None;
+        assert!(config.save().is_err());
+    }
+
+    #[test]
+    fn test_save_refuses_after_parse_error() {
+        let mut config = Config::new();
+        config.file_error = Some("broken".to_string());
+        assert!(config.save().is_err());
+    }
+
+    #[test]
+    fn test_git_settings() {
+        let user = UserConfig {
+            ssh_key: Some("/keys/it's".to_string()),
+            signing_key: Some("/keys/it's.pub".to_string()),
+            signing_format: Some("ssh".to_string()),
+            ..UserConfig::default()
+        };
+        let settings = user.git_settings();
+        assert_eq!(
+            settings[1].1.as_deref(),
+            Some("ssh -i '/keys/it'\\''s' -o IdentitiesOnly=yes")
+        );
+        assert_eq!(
+            ssh_command_key(settings[1].1.as_deref().unwrap()).as_deref(),
+            Some("/keys/it's")
+        );
+        assert_eq!(ssh_command_key("ssh -v"), None);
+        assert_eq!(settings[3].1.as_deref(), Some("ssh"));
+        assert_eq!(settings[4].1.as_deref(), Some("true"));
+        assert_eq!(settings[5], ("http.proxy", None));
+        let corporate = UserConfig {
+            credential_helper: Some("manager".to_string()),
+            ..UserConfig::default()
+        };
+        assert_eq!(
+            corporate.git_settings()[7],
+            ("credential.helper", Some("manager".to_string()))
+        );
+        let dco = UserConfig {
+            trailers: vec![
+                "Signed-off-by: {name} <{email}>".to_string(),
+                "On-behalf-of: @acme".to_string(),
+            ],
+            ..UserConfig::default()
+        };
+        assert_eq!(
+            dco.git_settings()[8].1.as_deref(),
+            Some("Signed-off-by: {name} <{email}>\nOn-behalf-of: @acme")
+        );
+        let signing_off = UserConfig {
+            trailers: vec!["On-behalf-of: @acme".to_string()],
+            signoff: Some(true),
+            ..UserConfig::default()
+        };
+        assert_eq!(signing_off.git_settings()[8], dco.git_settings()[8]);
+        assert_eq!(
+            signing_off.git_settings()[9],
+            ("format.signOff", Some("true".to_string()))
+        );
+        let not_signing_off = UserConfig {
+            signoff: Some(false),
+            ..UserConfig::default()
+        };
+        assert_eq!(not_signing_off.git_settings()[8].1, None);
+        let kernel = UserConfig {
+            smtp_server: Some("smtp.kernel.org".to_string()),
+            smtp_encryption: Some("tls".to_string()),
+            ..UserConfig::default()
+        };
+        let settings = kernel.git_settings();
+        assert_eq!(
+            settings[11],
+            ("sendemail.smtpServer", Some("smtp.kernel.org".to_string()))
+        );
+        assert_eq!(
+            settings[14],
+            ("sendemail.smtpEncryption", Some("tls".to_string()))
+        );
+        let bot = UserConfig {
+            name: "Li Si".to_string(),
+            email: "li@acme.com".to_string(),
+            committer_name: Some("Release Bot".to_string()),
+            ..UserConfig::default()
+        };
+        assert_eq!(bot.committer(), ("Release Bot", "li@acme.com"));
+        assert_eq!(
+            bot.git_settings()[15],
+            ("committer.name", Some("Release Bot".to_string()))
+        );
+        assert_eq!(bot.git_settings()[16], ("committer.email", None));
+        assert!(
+            UserConfig::default()
+                .git_settings()
+                .iter()
+                .all(|(_, value)| value.is_none())
+        );
+    }
+
+    #[test]
+    fn test_plan_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Some(dir.path());
+        git_command(repo).arg("init").output().unwrap();
+        for (key, value) in [
+            ("user.name", "Li Si"),
+            ("user.email", "old@example.com"),
+            ("commit.template", "work.txt"),
+        ] {
+            git_command(repo)
+                .args(["config", "--local", key, value])
+                .status()
+                .unwrap();
+        }
+
+        let mut config = Config::new();
+        let work = UserConfig {
+            name: "Li Si".to_string(),
+            email: "li@acme.com".to_string(),
+            commit_template: Some("work.txt".to_string()),
+            ..UserConfig::default()
+        };
+        config.groups.insert("work".to_string(), work);
+        let personal = UserConfig {
+            name: "Li Si".to_string(),
+            email: "li@example.com".to_string(),
+            ..UserConfig::default()
+        };
+
+        let plan = config.plan_use(&personal, false, repo);
+        let changed: Vec<_> = plan.iter().filter(|c| c.changes()).collect();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[0].key, "user.email");
+        assert_eq!(changed[0].planned.as_deref(), Some("li@example.com"));
+        // The template left by the work group is removed
+        assert_eq!(changed[1].key, "commit.template");
+        assert_eq!(changed[1].current.as_deref(), Some("work.txt"));
+        assert_ne!(changed[1].planned.as_deref(), Some("work.txt"));
+    }
+
+    #[test]
+    fn test_apply_managed_setting() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Some(dir.path());
+        assert!(
+            git_command(repo)
+                .arg("init")
+                .output()
+                .unwrap()
+                .status
+                .success()
+        );
+        let current = || {
+            let output = git_command(repo)
+                .args(["config", "--local", "--get", "commit.template"])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        let managed = vec!["~/.gum/work.txt".to_string()];
+
+        apply_managed_setting(
+            "commit.template",
+            Some("~/.gum/work.txt"),
+            &managed,
+            false,
+            repo,
+        )
+        .unwrap();
+        assert_eq!(current(), "~/.gum/work.txt");
+        apply_managed_setting("commit.template", None, &managed, false, repo).unwrap();
+        assert_eq!(current(), "");
+
+        // A template set by hand survives switching to a group without one
+        git_command(repo)
+            .args(["config", "--local", "commit.template", "mine.txt"])
+            .status()
+            .unwrap();
+        apply_managed_setting("commit.template", None, &managed, false, repo).unwrap();
+        assert_eq!(current(), "mine.txt");
+    }
+
+    #[test]
+    fn test_apply_workstyle() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Some(dir.path());
+        git_command(repo).arg("init").output().unwrap();
+        let value = |key: &str| get_git_config(Some("--local"), key, repo);
+
+        let mut config = Config::new();
+        let mut work = UserConfig {
+            name: "Li".to_string(),
+            email: "li@acme.com".to_string(),
+            ..Default::default()
+        };
+        work.workstyle
+            .insert("pull.rebase".to_string(), "true".to_string());
+        work.workstyle
+            .insert("alias.st".to_string(), "status -sb".to_string());
+        config.groups.insert("work".to_string(), work.clone());
+        let oss = UserConfig {
+            name: "Li".to_string(),
+            email: "li@example.com".to_string(),
+            inherit: Some("work".to_string()),
+            workstyle: BTreeMap::from([("pull.rebase".to_string(), "false".to_string())]),
+            ..Default::default()
+        };
+        config.groups.insert("oss".to_string(), oss);
+        assert_eq!(config.workstyle_keys(), ["alias.st", "pull.rebase"]);
+        assert!(is_workstyle_key("alias.co") && !is_workstyle_key("user.email"));
+
+        config.apply_workstyle(&work, false, repo).unwrap();
+        assert_eq!(value("alias.st").as_deref(), Some("status -sb"));
+        // Workstyles are inherited key by key
+        let oss = config.resolve_group("oss").unwrap();
+        assert_eq!(oss.workstyle["alias.st"], "status -sb");
+        config.apply_workstyle(&oss, false, repo).unwrap();
+        assert_eq!(value("pull.rebase").as_deref(), Some("false"));
+
+        // Keys set by another group's workstyle are removed
+        let plain = UserConfig::default();
+        config.apply_workstyle(&plain, false, repo).unwrap();
+        assert_eq!(value("pull.rebase"), None);
+        assert_eq!(value("alias.st"), None);
+    }
+
+    #[test]
+    fn test_apply_managed_setting_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Some(dir.path());
+        git_command(repo).arg("init").output().unwrap();
+        let key = "credential.helper";
+        for helper in ["", "store"] {
+            git_command(repo)
+                .args(["config", "--local", "--add", key, helper])
+                .output()
+                .unwrap();
+        }
+        let managed = ["cache".to_string(), "osxkeychain".to_string()];
+
+        // Only the managed helpers change, the chain set by hand stays
+        apply_managed_setting(key, Some("cache"), &managed, false, repo).unwrap();
+        assert_eq!(
+            get_git_config_all(Some("--local"), key, repo),
+            ["", "store", "cache"]
+        );
+        apply_managed_setting(key, Some("osxkeychain"), &managed, false, repo).unwrap();
+        assert_eq!(
+            get_git_config_all(Some("--local"), key, repo),
+            ["", "store", "osxkeychain"]
+        );
+        apply_managed_setting(key, None, &managed, false, repo).unwrap();
+        assert_eq!(
+            get_git_config_all(Some("--local"), key, repo),
+            ["", "store"]
+        );
+    }
+
+    #[test]
+    fn test_apply_url_rewrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Some(dir.path());
+        git_command(repo).arg("init").output().unwrap();
+        git_command(repo)
+            .args(["remote", "add", "origin", "git@github.com:acme/app.git"])
+            .output()
+            .unwrap();
+        let url = || {
+            let output = git_command(repo)
+                .args(["remote", "get-url", "origin"])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+
+        let mut config = Config::new();
+        let work = UserConfig {
+            name: "Li".to_string(),
+            email: "li@acme.com".to_string(),
+            url_rewrites: BTreeMap::from([(
+                "git@github.com:acme/".to_string(),
+                "git@github-work:acme/".to_string(),
+            )]),
+            ..Default::default()
+        };
+        config.groups.insert("work".to_string(), work.clone());
+        let key = "url.git@github-work:acme/.insteadOf";
+        assert_eq!(config.url_rewrite_keys(), [key]);
+
+        config.apply_group_settings(&work, false, repo).unwrap();
+        assert_eq!(url(), "git@github-work:acme/app.git");
+
+        // Prefixes sharing a replacement are all applied, next to one added
+        // by hand
+        git_command(repo)
+            .args(["config", "--add", key, "https://example.com/acme/"])
+            .output()
+            .unwrap();
+        let mut both = work.clone();
+        both.url_rewrites.insert(
+            "https://github.com/acme/".to_string(),
+            "git@github-work:acme/".to_string(),
+        );
+        config.groups.insert("work".to_string(), both.clone());
+        config.apply_group_settings(&both, false, repo).unwrap();
+        let values = || {
+            let output = git_command(repo)
+                .args(["config", "--local", "--get-all", key])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        assert_eq!(
+            values(),
+            "git@github.com:acme/\nhttps://example.com/acme/\nhttps://github.com/acme/"
+        );
+        config.apply_group_settings(&work, false, repo).unwrap();
+        assert_eq!(values(), "git@github.com:acme/\nhttps://example.com/acme/");
+        git_command(repo)
+            .args(["config", "--unset-all", key, "example"])
+            .output()
+            .unwrap();
+
+        // Switching to a group without the rewrite removes it
+        config
+            .apply_group_settings(&UserConfig::default(), false, repo)
+            .unwrap();
+        assert_eq!(url(), "git@github.com:acme/app.git");
+        assert_eq!(get_git_config(Some("--local"), key, repo), None);
+    }
+
+    #[test]
+    fn test_suggest_group_name() {
+        let mut config = Config::new();
+        let user = |email: &str| UserConfig {
+            name: "Li".to_string(),
+            email: email.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.suggest_group_name(&user("li@mail.Acme.com")), "acme");
+        assert_eq!(
+            config.suggest_group_name(&user("li@localhost")),
+            "localhost"
+        );
+        assert_eq!(config.suggest_group_name(&user("li")), "li");
+
+        config.groups.insert("acme".to_string(), user("a@acme.com"));
+        config
+            .groups
+            .insert("acme-2".to_string(), user("b@acme.com"));
+        assert_eq!(config.suggest_group_name(&user("li@acme.com")), "acme-3");
+    }
+
+    #[test]
+    fn test_tag_sections() {
+        let tagged = |tags: &[&str]| UserConfig {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+        let groups: HashMap<String, UserConfig> = [
+            ("acme".to_string(), tagged(&["work", "clients"])),
+            ("beta".to_string(), tagged(&["clients", " "])),
+            ("home".to_string(), tagged(&[])),
+            ("global".to_string(), tagged(&[])),
+        ]
+        .into();
+        assert_eq!(
+            tag_sections(&groups),
+            [
+                (
+                    Some("clients".to_string()),
+                    vec!["acme".to_string(), "beta".to_string()]
+                ),
+                (Some("work".to_string()), vec!["acme".to_string()]),
+                (None, vec!["global".to_string(), "home".to_string()]),
+            ]
+        );
+    }
 }
@@ -0,0 +1,95 @@
+//! # Editor Integration Module
+//!
+//! Long-running JSON-RPC mode (`gum --rpc`) for editor extensions. Keeping one
+//! process alive lets a status bar item query and switch the identity without
+//! paying process startup on every request.
+//!
+//! ## Methods
+//! - `list`: All configuration groups and the effective identity
+//! - `status`: Identity of the repository at `path` (default: working directory)
+//! - `use`: Apply a group (`group`, `path`, `global`, `email_variant`)
+//! - `rules.test`: Group the rules pick for a remote (`url`) or repository
+//!   (`path`), and the branch rule matching `branch`
+//! - `version`: gum version
+
+use crate::rpc::{self, Handler, RpcError};
+use crate::{resolve, service};
+use serde_json::{Value, json};
+use std::path::PathBuf;
+
+/// Editor JSON-RPC request handler
+#[derive(Default)]
+pub struct EditorServer;
+
+impl EditorServer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Serve requests on stdin/stdout until stdin is closed
+    pub fn serve(&mut self) -> anyhow::Result<()> {
+        rpc::serve_stdio(self)
+    }
+}
+
+impl Handler for EditorServer {
+    fn handle(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "list" => Ok(service::list_profiles()?),
+            "status" => {
+                let dir = match params.get("path").and_then(Value::as_str) {
+                    Some(path) => PathBuf::from(path),
+                    None => std::env::current_dir().map_err(anyhow::Error::from)?,
+                };
+                Ok(service::resolve_identity(&dir)?)
+            }
+            "use" => {
+                if params.get("group").and_then(Value::as_str).is_none() {
+                    return Err(RpcError::invalid_params("Missing string parameter: group"));
+                }
+                Ok(service::apply_profile(&params)?)
+            }
+            "rules.test" => {
+                for key in ["url", "path", "branch"] {
+                    if params.get(key).is_some_and(|value| !value.is_string()) {
+                        return Err(RpcError::invalid_params(format!(
+                            "Parameter {} must be a string",
+                            key
+                        )));
+                    }
+                }
+                let remotes = match params.get("url").and_then(Value::as_str) {
+                    Some(url) => vec![("url".to_string(), url.to_string())],
+                    None => {
+                        let dir = match params.get("path").and_then(Value::as_str) {
+                            Some(path) => PathBuf::from(path),
+                            None => std::env::current_dir().map_err(anyhow::Error::from)?,
+                        };
+                        resolve::remote_urls(Some(&dir))
+                    }
+                };
+                let branch = params.get("branch").and_then(Value::as_str);
+                Ok(service::test_rules(&remotes, branch)?)
+            }
+            "version" => Ok(json!({"version": env!("CARGO_PKG_VERSION")})),
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_methods() {
+        let mut server = EditorServer::new();
+        let version = server.handle("version", Value::Null).unwrap();
+        assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+
+        let err = server.handle("use", json!({})).unwrap_err();
+        assert_eq!(err.code, rpc::INVALID_PARAMS);
+        let err = server.handle("rules.test", json!({"url": 1})).unwrap_err();
+        assert_eq!(err.code, rpc::INVALID_PARAMS);
+    }
+}
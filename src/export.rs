@@ -0,0 +1,222 @@
+//! # Export Module
+//!
+//! Renders groups for use outside gum. Shell aliases run git as a group
+//! through environment variables and `-c` options, without touching any git
+//! configuration file, e.g. `git-as-work commit` in an ad-hoc shell. `gum
+//! exec` and `gum shell` use the same environment.
+
+use crate::config::UserConfig;
+use crate::utils::shell_quote;
+
+/// Prefix of the alias names
+pub const ALIAS_PREFIX: &str = "git-as-";
+
+/// Environment variable naming the group of a `gum shell` session
+pub const SHELL_GROUP_ENV: &str = "GUM_SHELL_GROUP";
+
+/// `alias` definitions for the groups, in the given order
+///
+/// Meant to be evaluated by a POSIX shell:
+/// `eval "$(gum export --shell-aliases)"`.
+pub fn shell_aliases<'a>(groups: impl IntoIterator<Item = (&'a str, &'a UserConfig)>) -> String {
+    let mut out = String::from("# Generated by gum export --shell-aliases\n");
+    for (group_name, user) in groups {
+        out.push_str(&format!(
+            "alias {}{}={}\n",
+            ALIAS_PREFIX,
+            alias_name(group_name),
+            shell_quote(&git_command(user))
+        ));
+    }
+    out
+}
+
+/// Environment variables committing as the group
+///
+/// The committer takes the group's committer name and email when set apart
+/// from the author. Empty values are left out.
+pub fn identity_environment(user: &UserConfig) -> Vec<(&'static str, String)> {
+    let (committer_name, committer_email) = user.committer();
+    [
+        ("GIT_AUTHOR_NAME", user.name.as_str()),
+        ("GIT_COMMITTER_NAME", committer_name),
+        ("GIT_AUTHOR_EMAIL", user.email.as_str()),
+        ("GIT_COMMITTER_EMAIL", committer_email),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| (key, value.to_string()))
+    .collect()
+}
+
+/// Environment variables passing the group's git settings to git
+///
+/// Uses `GIT_CONFIG_COUNT`, `GIT_CONFIG_KEY_<n>` and `GIT_CONFIG_VALUE_<n>`,
+/// numbered after the `existing` entries already in the environment.
+pub fn config_environment(user: &UserConfig, existing: usize) -> Vec<(String, String)> {
+    let settings: Vec<(&str, String)> = user
+        .git_settings()
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect();
+    if settings.is_empty() {
+        return Vec::new();
+    }
+    let mut env = vec![(
+        "GIT_CONFIG_COUNT".to_string(),
+        (existing + settings.len()).to_string(),
+    )];
+    for (i, (key, value)) in settings.into_iter().enumerate() {
+        env.push((format!("GIT_CONFIG_KEY_{}", existing + i), key.to_string()));
+        env.push((format!("GIT_CONFIG_VALUE_{}", existing + i), value));
+    }
+    env
+}
+
+/// `export` statements giving a POSIX shell session the group's identity
+///
+/// Meant to be evaluated: `eval "$(gum shell work --print)"`. The
+/// `GIT_CONFIG_*` entries are numbered after the `existing` ones.
+pub fn shell_exports(group_name: &str, user: &UserConfig, existing: usize) -> String {
+    let mut out = format!("# Generated by gum shell {} --print\n", group_name);
+    let identity = identity_environment(user)
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value));
+    for (key, value) in identity.chain(config_environment(user, existing)) {
+        out.push_str(&format!("export {}={}\n", key, shell_quote(&value)));
+    }
+    out.push_str(&format!(
+        "export {}={}\n",
+        SHELL_GROUP_ENV,
+        shell_quote(group_name)
+    ));
+    out
+}
+
+/// `git` invocation committing as the group
+fn git_command(user: &UserConfig) -> String {
+    let mut words: Vec<String> = identity_environment(user)
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, shell_quote(&value)))
+        .collect();
+    words.push("git".to_string());
+    for (key, value) in user.git_settings() {
+        if let Some(value) = value {
+            words.push(format!("-c {}", shell_quote(&format!("{}={}", key, value))));
+        }
+    }
+    words.join(" ")
+}
+
+/// Group name usable in an alias name
+fn alias_name(group_name: &str) -> String {
+    group_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_aliases() {
+        let work = UserConfig {
+            name: "Jane Doe".to_string(),
+            email: "jane@acme.com".to_string(),
+            signing_key: Some("ABCD1234".to_string()),
+            ..UserConfig::default()
+        };
+        let oss = UserConfig {
+            email: "jd@example.org".to_string(),
+            ..UserConfig::default()
+        };
+        let out = shell_aliases([("work", &work), ("my oss", &oss)]);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "alias git-as-work='GIT_AUTHOR_NAME='\\''Jane Doe'\\'' \
+             GIT_COMMITTER_NAME='\\''Jane Doe'\\'' \
+             GIT_AUTHOR_EMAIL=jane@acme.com GIT_COMMITTER_EMAIL=jane@acme.com \
+             git -c user.signingkey=ABCD1234 -c commit.gpgsign=true'"
+        );
+        assert_eq!(
+            lines[2],
+            "alias git-as-my-oss='GIT_AUTHOR_EMAIL=jd@example.org \
+             GIT_COMMITTER_EMAIL=jd@example.org git'"
+        );
+    }
+
+    #[test]
+    fn test_identity_environment() {
+        let user = UserConfig {
+            name: "Li Si".to_string(),
+            email: "li@acme.com".to_string(),
+            committer_name: Some("Release Bot".to_string()),
+            committer_email: Some("bot@acme.com".to_string()),
+            ..UserConfig::default()
+        };
+        assert_eq!(
+            identity_environment(&user),
+            [
+                ("GIT_AUTHOR_NAME", "Li Si".to_string()),
+                ("GIT_COMMITTER_NAME", "Release Bot".to_string()),
+                ("GIT_AUTHOR_EMAIL", "li@acme.com".to_string()),
+                ("GIT_COMMITTER_EMAIL", "bot@acme.com".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_environment() {
+        let user = UserConfig {
+            http_proxy: Some("http://proxy:8080".to_string()),
+            ..UserConfig::default()
+        };
+        assert_eq!(
+            config_environment(&user, 1),
+            [
+                ("GIT_CONFIG_COUNT".to_string(), "2".to_string()),
+                ("GIT_CONFIG_KEY_1".to_string(), "http.proxy".to_string()),
+                (
+                    "GIT_CONFIG_VALUE_1".to_string(),
+                    "http://proxy:8080".to_string()
+                ),
+            ]
+        );
+        assert!(config_environment(&UserConfig::default(), 0).is_empty());
+    }
+
+    #[test]
+    fn test_shell_exports() {
+        let user = UserConfig {
+            name: "Li Si".to_string(),
+            email: "li@acme.com".to_string(),
+            http_proxy: Some("http://proxy:8080".to_string()),
+            ..UserConfig::default()
+        };
+        let out = shell_exports("work", &user, 0);
+        let lines: Vec<&str> = out.lines().skip(1).collect();
+        assert_eq!(
+            lines,
+            [
+                "export GIT_AUTHOR_NAME='Li Si'",
+                "export GIT_COMMITTER_NAME='Li Si'",
+                "export GIT_AUTHOR_EMAIL=li@acme.com",
+                "export GIT_COMMITTER_EMAIL=li@acme.com",
+                "export GIT_CONFIG_COUNT=1",
+                "export GIT_CONFIG_KEY_0=http.proxy",
+                "export GIT_CONFIG_VALUE_0=http://proxy:8080",
+                "export GUM_SHELL_GROUP=work",
+            ]
+        );
+    }
+}
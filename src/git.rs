@@ -22,20 +22,15 @@
 //! - Get project level git user configuration
 //! - Set git user configuration (supports global or local)
 
-use std::process::Command;
-
 use crate::config::UserConfig;
+use crate::utils;
 
 pub fn get_global_git_user() -> Result<UserConfig, Box<dyn std::error::Error>> {
     log::debug!("Executing git config --global user.name");
-    let name_output = Command::new("git")
-        .args(["config", "--global", "user.name"])
-        .output()?;
+    let name_output = utils::output(utils::git().args(["config", "--global", "user.name"]))?;
 
     log::debug!("Executing git config --global user.email");
-    let email_output = Command::new("git")
-        .args(["config", "--global", "user.email"])
-        .output()?;
+    let email_output = utils::output(utils::git().args(["config", "--global", "user.email"]))?;
 
     let name = String::from_utf8_lossy(&name_output.stdout)
         .trim()
@@ -49,18 +44,20 @@ pub fn get_global_git_user() -> Result<UserConfig, Box<dyn std::error::Error>> {
     if name.is_empty() || email.is_empty() {
         Err("Global git user not configured".into())
     } else {
-        Ok(UserConfig { name, email })
+        Ok(UserConfig {
+            name,
+            email,
+            ..Default::default()
+        })
     }
 }
 
 pub fn get_project_git_user() -> Result<UserConfig, Box<dyn std::error::Error>> {
     log::debug!("Executing git config user.name");
-    let name_output = Command::new("git").args(["config", "user.name"]).output()?;
+    let name_output = utils::output(utils::git().args(["config", "user.name"]))?;
 
     log::debug!("Executing git config user.email");
-    let email_output = Command::new("git")
-        .args(["config", "user.email"])
-        .output()?;
+    let email_output = utils::output(utils::git().args(["config", "user.email"]))?;
 
     let name = String::from_utf8_lossy(&name_output.stdout)
         .trim()
@@ -74,7 +71,11 @@ pub fn get_project_git_user() -> Result<UserConfig, Box<dyn std::error::Error>>
     if name.is_empty() || email.is_empty() {
         Err("Project git user not configured".into())
     } else {
-        Ok(UserConfig { name, email })
+        Ok(UserConfig {
+            name,
+            email,
+            ..Default::default()
+        })
     }
 }
 
@@ -97,10 +98,7 @@ pub fn set_git_user(user: &UserConfig, global: bool) -> Result<(), Box<dyn std::
         if global { "--global" } else { "" },
         user.name
     );
-    let name_status = Command::new("git")
-        .args(&args)
-        .arg(&user.name)
-        .status()
+    let name_status = utils::status(utils::git().args(&args).arg(&user.name))
         .map_err(|e| format!("Failed to set git user.name: {}", e))?;
 
     if !name_status.success() {
@@ -122,10 +120,7 @@ pub fn set_git_user(user: &UserConfig, global: bool) -> Result<(), Box<dyn std::
         if global { "--global" } else { "" },
         user.email
     );
-    let email_status = Command::new("git")
-        .args(&args)
-        .arg(&user.email)
-        .status()
+    let email_status = utils::status(utils::git().args(&args).arg(&user.email))
         .map_err(|e| format!("Failed to set git user.email: {}", e))?;
 
     if !email_status.success() {
@@ -0,0 +1,185 @@
+//! # GPG Module
+//!
+//! Discovers OpenPGP secret keys through the system `gpg`, so a group's
+//! signing key can be chosen from the keys that actually carry its email.
+
+use crate::utils;
+use std::process::Command;
+
+/// A secret key from the local keyring
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretKey {
+    /// Long key ID (16 hex digits)
+    pub key_id: String,
+    pub fingerprint: String,
+    /// Creation date, `YYYY-MM-DD`
+    pub created: String,
+    /// User IDs, e.g. `Li Si <lisi@example.com>`
+    pub uids: Vec<String>,
+}
+
+impl SecretKey {
+    /// Whether one of the user IDs carries `email`
+    pub fn has_email(&self, email: &str) -> bool {
+        self.uids
+            .iter()
+            .filter_map(|uid| uid_email(uid))
+            .any(|uid| uid.eq_ignore_ascii_case(email))
+    }
+
+    /// Whether `spec` (key ID or fingerprint, optionally `0x`-prefixed)
+    /// names this key
+    pub fn matches(&self, spec: &str) -> bool {
+        let spec = spec.trim().trim_start_matches("0x").replace(' ', "");
+        spec.len() >= 8
+            && (self
+                .fingerprint
+                .to_uppercase()
+                .ends_with(&spec.to_uppercase())
+                || self.key_id.eq_ignore_ascii_case(&spec))
+    }
+}
+
+/// Usable secret keys in the local keyring
+pub fn list_secret_keys() -> anyhow::Result<Vec<SecretKey>> {
+    log::debug!("Listing gpg secret keys");
+    let output = utils::output(Command::new("gpg").args([
+        "--list-secret-keys",
+        "--with-colons",
+        "--fixed-list-mode",
+    ]))
+    .map_err(|e| anyhow::anyhow!("Failed to run gpg (is it installed?): {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "gpg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_colons(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `gpg --with-colons` output
+///
+/// Revoked, expired and disabled keys are skipped, as are revoked user IDs.
+fn parse_colons(output: &str) -> Vec<SecretKey> {
+    let mut keys: Vec<SecretKey> = Vec::new();
+    // Whether the current primary key is usable
+    let mut usable = false;
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+        match field(0) {
+            "sec" => {
+                usable = !matches!(field(1), "r" | "e" | "d" | "i");
+                if usable {
+                    keys.push(SecretKey {
+                        key_id: field(4).to_string(),
+                        fingerprint: String::new(),
+                        created: format_date(field(5)),
+                        uids: Vec::new(),
+                    });
+                }
+            }
+            // Subkeys carry their own fingerprints; the primary one comes first
+            "fpr" if usable => {
+                if let Some(key) = keys.last_mut()
+                    && key.fingerprint.is_empty()
+                {
+                    key.fingerprint = field(9).to_string();
+                }
+            }
+            "uid" if usable && field(1) != "r" => {
+                if let Some(key) = keys.last_mut() {
+                    key.uids.push(unescape(field(9)));
+                }
+            }
+            _ => {}
+        }
+    }
+    keys
+}
+
+/// Email part of a user ID (`Name <email>`)
+pub fn uid_email(uid: &str) -> Option<&str> {
+    let start = uid.rfind('<')?;
+    let end = uid[start..].find('>')? + start;
+    Some(&uid[start + 1..end])
+}
+
+/// Undo the `\xNN` escaping gpg applies to colon-listing fields
+fn unescape(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let raw = value.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'\\'
+            && raw.get(i + 1) == Some(&b'x')
+            && let Some(byte) = value
+                .get(i + 2..i + 4)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            bytes.push(byte);
+            i += 4;
+            continue;
+        }
+        bytes.push(raw[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Format a gpg timestamp (seconds since the epoch) as `YYYY-MM-DD`
+fn format_date(timestamp: &str) -> String {
+    match timestamp.parse::<i64>() {
+        Ok(seconds) => utils::format_timestamp(seconds)[..10].to_string(),
+        // Newer gpg versions may already print ISO dates
+        Err(_) => timestamp.chars().take(10).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTING: &str = "\
+sec:u:255:22:AAAABBBBCCCCDDDD:1700000000:::u:::scESC:::+:::ed25519:::0:
+fpr:::::::::0123456789ABCDEF0123AAAABBBBCCCCDDDD:
+grp:::::::::0000000000000000000000000000000000000000:
+uid:u::::1700000000::HASH::Li Si <lisi@example.com>::::::::::0:
+uid:r::::1700000000::HASH::Li Si <old@example.com>::::::::::0:
+uid:u::::1700000000::HASH::Li Si (work\\x3a acme) <li@acme.com>::::::::::0:
+ssb:u:255:18:1111222233334444:1700000000::::::e:::+:::cv25519::
+fpr:::::::::99999999999999999999111122223333444:
+sec:e:255:22:EEEEFFFF00001111:1500000000:1600000000::u:::scESC:::+:::ed25519:::0:
+fpr:::::::::FFFFFFFFFFFFFFFFFFFFEEEEFFFF00001111:
+uid:e::::1500000000::HASH::Li Si <lisi@example.com>::::::::::0:
+";
+
+    #[test]
+    fn test_parse_colons() {
+        let keys = parse_colons(LISTING);
+        assert_eq!(keys.len(), 1);
+        let key = &keys[0];
+        assert_eq!(key.key_id, "AAAABBBBCCCCDDDD");
+        assert_eq!(key.fingerprint, "0123456789ABCDEF0123AAAABBBBCCCCDDDD");
+        assert_eq!(key.created, "2023-11-14");
+        assert_eq!(
+            key.uids,
+            [
+                "Li Si <lisi@example.com>",
+                "Li Si (work: acme) <li@acme.com>"
+            ]
+        );
+        assert!(key.has_email("LI@acme.com"));
+        assert!(!key.has_email("old@example.com"));
+    }
+
+    #[test]
+    fn test_key_matches() {
+        let key = &parse_colons(LISTING)[0];
+        assert!(key.matches("0xaaaabbbbccccdddd"));
+        assert!(key.matches("0123 4567 89AB CDEF 0123 AAAA BBBB CCCC DDDD"));
+        assert!(key.matches("CCCCDDDD"));
+        assert!(!key.matches("DDDD"));
+    }
+}
@@ -0,0 +1,257 @@
+//! # Health Module
+//!
+//! Problems of a group that only show when it is used: a signing key that is
+//! not in the gpg keyring or not loaded in ssh-agent, an email the config
+//! would reject, host rules that no repository ended up using, or an expiry
+//! date that has passed. `list
+//! --long` shows them next to each group.
+
+use crate::config::{Config, UserConfig};
+use crate::gpg::{self, SecretKey};
+use crate::ssh;
+use crate::utils;
+use crate::validate;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// A problem of a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The OpenPGP signing key is not in the secret keyring
+    GpgKeyMissing(String),
+    /// The SSH signing key file does not exist
+    SshKeyMissing(String),
+    /// The SSH signing key is not loaded in the running ssh-agent
+    SshKeyNotLoaded(String),
+    /// An email fails validation, with the reason
+    InvalidEmail(String),
+    /// Host rules pick the group, but no repository recorded in the history
+    /// uses it
+    UnusedRules(usize),
+    /// The group expired on this date
+    Expired(String),
+}
+
+/// What the groups are checked against, gathered once per listing
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// Secret keys in the gpg keyring, `None` when gpg cannot be asked
+    pub gpg_keys: Option<Vec<SecretKey>>,
+    /// Public keys loaded in ssh-agent (`type base64` per key), `None` when
+    /// no agent runs
+    pub agent_keys: Option<Vec<String>>,
+    /// Number of repositories in the history whose latest change applied
+    /// each group
+    pub repos: HashMap<String, usize>,
+}
+
+impl Environment {
+    /// Ask gpg and ssh-agent only when a group signs with them
+    pub fn detect(config: &Config, repos: HashMap<String, usize>) -> Self {
+        let groups = config.get_all_config_info();
+        let format = |format: &str| {
+            groups.values().any(|group| {
+                group.signing_key.is_some()
+                    && group.signing_format.as_deref().unwrap_or("openpgp") == format
+            })
+        };
+        let gpg_keys = format("openpgp")
+            .then(|| {
+                gpg::list_secret_keys()
+                    .map_err(|e| log::debug!("Cannot list gpg keys: {}", e))
+                    .ok()
+            })
+            .flatten();
+        let agent_keys = format("ssh").then(agent_keys).flatten();
+        Environment {
+            gpg_keys,
+            agent_keys,
+            repos,
+        }
+    }
+}
+
+/// Problems of the group `group_name`, resolved as `user`
+pub fn check(
+    config: &Config,
+    group_name: &str,
+    user: &UserConfig,
+    env: &Environment,
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if let Some(ref key) = user.signing_key {
+        match user.signing_format.as_deref().unwrap_or("openpgp") {
+            "openpgp" => {
+                let known = env.gpg_keys.as_ref().is_none_or(|keys| {
+                    keys.iter()
+                        .any(|secret| secret.matches(key) || secret.has_email(key))
+                });
+                if !known {
+                    problems.push(Problem::GpgKeyMissing(key.clone()));
+                }
+            }
+            "ssh" => problems.extend(check_ssh_key(key, env.agent_keys.as_deref())),
+            _ => {}
+        }
+    }
+
+    let emails = std::iter::once(&user.email)
+        .chain(user.committer_email.iter())
+        .chain(user.email_variants.values())
+        .filter(|email| !email.is_empty());
+    for email in emails {
+        if let Err(reason) = validate::check_email(email, &config.settings.allowed_email_domains) {
+            problems.push(Problem::InvalidEmail(reason));
+        }
+    }
+
+    let rules = config.hosts.values().filter(|g| *g == group_name).count();
+    if rules > 0 && env.repos.get(group_name).copied().unwrap_or(0) == 0 {
+        problems.push(Problem::UnusedRules(rules));
+    }
+    if user.is_expired(utils::now())
+        && let Some(ref expires) = user.expires
+    {
+        problems.push(Problem::Expired(expires.clone()));
+    }
+    problems
+}
+
+/// Problems of an SSH signing key, a key file or a `key::` literal
+fn check_ssh_key(key: &str, agent_keys: Option<&[String]>) -> Option<Problem> {
+    let public = match key.strip_prefix("key::") {
+        Some(literal) => literal.to_string(),
+        None => {
+            let path = utils::expand_home(key);
+            let path = Path::new(&path);
+            let public = if path.extension().is_some_and(|ext| ext == "pub") {
+                path.to_path_buf()
+            } else {
+                ssh::public_key_path(path)
+            };
+            if !path.exists() {
+                return Some(Problem::SshKeyMissing(key.to_string()));
+            }
+            // Without the public half the agent cannot be compared
+            fs::read_to_string(public).ok()?
+        }
+    };
+    let loaded = agent_keys?
+        .iter()
+        .any(|agent_key| key_blob(agent_key) == key_blob(&public));
+    (!loaded).then(|| Problem::SshKeyNotLoaded(key.to_string()))
+}
+
+/// `type base64` of an OpenSSH public key line, without the comment
+fn key_blob(line: &str) -> String {
+    line.split_whitespace()
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Keys loaded in ssh-agent, `None` when no agent can be reached
+fn agent_keys() -> Option<Vec<String>> {
+    let output = utils::output(Command::new("ssh-add").arg("-L"))
+        .map_err(|e| log::debug!("Cannot run ssh-add: {}", e))
+        .ok()?;
+    // Exit status 1 means an agent without keys, 2 no agent
+    match output.status.code() {
+        Some(0) => Some(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::to_string)
+                .collect(),
+        ),
+        Some(1) => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let mut config = Config::new();
+        config.settings.allowed_email_domains = vec!["acme.com".to_string()];
+        config
+            .hosts
+            .insert("github.com/acme".to_string(), "work".to_string());
+        let user = UserConfig {
+            name: "Li".to_string(),
+            email: "li@acme.com".to_string(),
+            signing_key: Some("89ABCDEF01234567".to_string()),
+            email_variants: [("oss".to_string(), "li@example.org".to_string())].into(),
+            ..Default::default()
+        };
+        config.groups.insert("work".to_string(), user.clone());
+
+        let mut env = Environment {
+            gpg_keys: Some(vec![SecretKey {
+                key_id: "0000000000000000".to_string(),
+                fingerprint: "0000000000000000000000000000000000000000".to_string(),
+                created: "2024-01-01".to_string(),
+                uids: vec!["Li <li@acme.com>".to_string()],
+            }]),
+            ..Default::default()
+        };
+        let problems = check(&config, "work", &user, &env);
+        assert_eq!(problems.len(), 3);
+        assert_eq!(
+            problems[0],
+            Problem::GpgKeyMissing("89ABCDEF01234567".to_string())
+        );
+        assert!(matches!(problems[1], Problem::InvalidEmail(_)));
+        assert_eq!(problems[2], Problem::UnusedRules(1));
+
+        // Unknown keyring, valid emails and a repository using the group
+        env.gpg_keys = None;
+        env.repos.insert("work".to_string(), 2);
+        config.settings.allowed_email_domains.clear();
+        assert!(check(&config, "work", &user, &env).is_empty());
+
+        let expired = UserConfig {
+            expires: Some("1970-01-01".to_string()),
+            ..user
+        };
+        assert_eq!(
+            check(&config, "work", &expired, &env),
+            [Problem::Expired("1970-01-01".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_check_ssh_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = dir.path().join("id_work");
+        let key_name = key.to_string_lossy().to_string();
+        assert_eq!(
+            check_ssh_key(&key_name, Some(&[])),
+            Some(Problem::SshKeyMissing(key_name.clone()))
+        );
+
+        fs::write(&key, "private").unwrap();
+        fs::write(
+            dir.path().join("id_work.pub"),
+            "ssh-ed25519 AAAA li@acme.com\n",
+        )
+        .unwrap();
+        assert_eq!(
+            check_ssh_key(&key_name, Some(&["ssh-ed25519 BBBB other".to_string()])),
+            Some(Problem::SshKeyNotLoaded(key_name.clone()))
+        );
+        assert_eq!(
+            check_ssh_key(
+                &key_name,
+                Some(&["ssh-ed25519 AAAA agent comment".to_string()])
+            ),
+            None
+        );
+        // No agent: nothing to compare with
+        assert_eq!(check_ssh_key(&key_name, None), None);
+    }
+}
@@ -0,0 +1,363 @@
+//! # History Module
+//!
+//! Append-only log of the identity changes gum makes, one JSON object per
+//! line in `history.jsonl` inside the state directory. Each entry keeps the
+//! values it replaced, which is what `gum undo` restores.
+
+use crate::config::UserConfig;
+use crate::undo::Snapshot;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File holding the history, inside the state directory
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// What caused an identity change
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Use,
+    Undo,
+    Unset,
+    /// A repository's existing identity taken over as a group; nothing changed
+    Adopt,
+}
+
+/// One identity change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Seconds since the Unix epoch
+    pub timestamp: i64,
+    pub action: Action,
+    /// Group applied, if the new identity belongs to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Identity before the change, `Name <email>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// Identity after the change, `Name <email>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// Values the change replaced
+    pub before: Snapshot,
+}
+
+impl Entry {
+    /// Entry for a change away from the values in `before`
+    pub fn new(
+        action: Action,
+        before: Snapshot,
+        to: Option<&UserConfig>,
+        group: Option<String>,
+    ) -> Self {
+        Entry {
+            timestamp: utils::now(),
+            action,
+            group,
+            from: before.identity().map(|user| describe(&user)),
+            to: to.map(describe),
+            before,
+        }
+    }
+
+    /// `global`, or the repository the change applied to
+    pub fn scope(&self) -> String {
+        match self.before.repo {
+            Some(ref repo) => repo.display().to_string(),
+            None => "global".to_string(),
+        }
+    }
+}
+
+/// `Name <email>` of an identity
+fn describe(user: &UserConfig) -> String {
+    format!("{} <{}>", user.name, user.email)
+}
+
+/// Append an entry to the history
+pub fn append(entry: &Entry) -> anyhow::Result<()> {
+    let path = history_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    log::debug!("Appended {:?} entry to {}", entry.action, path.display());
+    Ok(())
+}
+
+/// Record a change, logging instead of failing when the history cannot be
+/// written, since the change itself already happened
+///
+/// Nothing is recorded under `--dry-run`.
+pub fn record(action: Action, before: Snapshot, to: Option<&UserConfig>, group: Option<String>) {
+    if utils::is_dry_run() {
+        return;
+    }
+    if let Err(e) = append(&Entry::new(action, before, to, group)) {
+        log::warn!("Cannot record history: {}", e);
+    }
+}
+
+/// All entries, oldest first
+///
+/// Lines that do not parse (e.g. written by a newer gum) are skipped.
+pub fn load() -> anyhow::Result<Vec<Entry>> {
+    let content = match fs::read_to_string(history_path()?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Entries of one repository, or of every scope when `repo` is `None`
+pub fn filter(entries: Vec<Entry>, repo: Option<&Path>) -> Vec<Entry> {
+    entries
+        .into_iter()
+        .filter(|entry| repo.is_none() || entry.before.repo.as_deref() == repo)
+        .collect()
+}
+
+/// Repositories whose latest recorded change applied one of `groups`, with
+/// that group, sorted by path
+///
+/// The history is the registry of the repositories gum manages; a later
+/// `unset`, `undo` or other group takes a repository off the list.
+pub fn repos_using(entries: &[Entry], groups: &[String]) -> Vec<(PathBuf, String)> {
+    let mut latest: std::collections::BTreeMap<&Path, Option<&String>> = Default::default();
+    for entry in entries {
+        if let Some(ref repo) = entry.before.repo {
+            latest.insert(repo, entry.group.as_ref());
+        }
+    }
+    latest
+        .into_iter()
+        .filter_map(|(repo, group)| {
+            let group = group.filter(|group| groups.contains(group))?;
+            Some((repo.to_path_buf(), group.clone()))
+        })
+        .collect()
+}
+
+/// The most recent `use` or `unset` that has not been undone yet
+///
+/// Each undo cancels the latest change before it that is still in effect, so
+/// repeated undos walk further back in time.
+pub fn undo_target(entries: &[Entry]) -> Option<&Entry> {
+    let mut undone = 0;
+    for entry in entries.iter().rev() {
+        match entry.action {
+            Action::Undo => undone += 1,
+            Action::Use | Action::Unset if undone > 0 => undone -= 1,
+            Action::Use | Action::Unset => return Some(entry),
+            Action::Adopt => {}
+        }
+    }
+    None
+}
+
+/// Drop the entries older than `cutoff` (seconds since the Unix epoch),
+/// returning how many there were
+///
+/// Lines that do not parse are kept. Under `--dry-run` nothing is written.
+pub fn trim(cutoff: i64) -> anyhow::Result<usize> {
+    let path = history_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let (kept, dropped) = trim_lines(&content, cutoff);
+    if dropped > 0 && !utils::is_dry_run() {
+        fs::write(&path, kept)?;
+    }
+    Ok(dropped)
+}
+
+/// Lines of entries from `cutoff` on, and the number of entries dropped
+fn trim_lines(content: &str, cutoff: i64) -> (String, usize) {
+    let mut kept = String::new();
+    let mut dropped = 0;
+    for line in content.lines() {
+        match serde_json::from_str::<Entry>(line) {
+            Ok(entry) if entry.timestamp < cutoff => dropped += 1,
+            _ => {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+    }
+    (kept, dropped)
+}
+
+/// Point the entries of the groups in `merged` to `survivor`, returning how
+/// many were changed
+///
+/// Keeps the repositories recorded as using a merged group attached to the
+/// group that replaced it. Under `--dry-run` nothing is written.
+pub fn retarget(merged: &[String], survivor: &str) -> anyhow::Result<usize> {
+    let path = history_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let (updated, changed) = retarget_lines(&content, merged, survivor);
+    if changed > 0 && !utils::is_dry_run() {
+        fs::write(&path, updated)?;
+    }
+    Ok(changed)
+}
+
+/// Lines with the group of matching entries replaced, and how many there were
+fn retarget_lines(content: &str, merged: &[String], survivor: &str) -> (String, usize) {
+    let mut updated = String::new();
+    let mut changed = 0;
+    for line in content.lines() {
+        match serde_json::from_str::<Entry>(line) {
+            Ok(mut entry) if entry.group.as_ref().is_some_and(|g| merged.contains(g)) => {
+                entry.group = Some(survivor.to_string());
+                match serde_json::to_string(&entry) {
+                    Ok(json) => {
+                        updated.push_str(&json);
+                        changed += 1;
+                    }
+                    Err(_) => updated.push_str(line),
+                }
+            }
+            _ => updated.push_str(line),
+        }
+        updated.push('\n');
+    }
+    (updated, changed)
+}
+
+fn history_path() -> anyhow::Result<PathBuf> {
+    Ok(utils::get_state_dir()?.join(HISTORY_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(action: Action, email: &str) -> Entry {
+        let before = Snapshot {
+            global: true,
+            repo: None,
+            values: vec![
+                ("user.name".to_string(), Some("Li Si".to_string())),
+                ("user.email".to_string(), Some(email.to_string())),
+            ],
+        };
+        Entry::new(action, before, None, None)
+    }
+
+    #[test]
+    fn test_undo_target() {
+        let entries = vec![
+            entry(Action::Use, "a@example.com"),
+            entry(Action::Use, "b@example.com"),
+            entry(Action::Use, "c@example.com"),
+        ];
+        let target = undo_target(&entries).unwrap();
+        assert_eq!(target.from.as_deref(), Some("Li Si <c@example.com>"));
+
+        let mut entries = entries;
+        entries.push(entry(Action::Undo, "d@example.com"));
+        let target = undo_target(&entries).unwrap();
+        assert_eq!(target.from.as_deref(), Some("Li Si <b@example.com>"));
+
+        entries.push(entry(Action::Undo, "c@example.com"));
+        entries.push(entry(Action::Undo, "b@example.com"));
+        assert!(undo_target(&entries).is_none());
+
+        // An unset is undone like a use
+        entries.push(entry(Action::Unset, "e@example.com"));
+        let target = undo_target(&entries).unwrap();
+        assert_eq!(target.action, Action::Unset);
+    }
+
+    #[test]
+    fn test_entry_round_trip() {
+        let entry = entry(Action::Use, "a@example.com");
+        let line = serde_json::to_string(&entry).unwrap();
+        assert!(line.contains("\"action\":\"use\""));
+        assert_eq!(serde_json::from_str::<Entry>(&line).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_trim_lines() {
+        let mut old = entry(Action::Use, "a@example.com");
+        old.timestamp = 100;
+        let mut new = entry(Action::Use, "b@example.com");
+        new.timestamp = 200;
+        let content = format!(
+            "{}\n{{\"from a newer gum\": 1}}\n{}\n",
+            serde_json::to_string(&old).unwrap(),
+            serde_json::to_string(&new).unwrap()
+        );
+        let (kept, dropped) = trim_lines(&content, 150);
+        assert_eq!(dropped, 1);
+        assert_eq!(
+            kept,
+            format!(
+                "{{\"from a newer gum\": 1}}\n{}\n",
+                serde_json::to_string(&new).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_retarget_lines() {
+        let mut merged = entry(Action::Use, "a@example.com");
+        merged.group = Some("work-old".to_string());
+        let mut other = entry(Action::Use, "b@example.com");
+        other.group = Some("oss".to_string());
+        let content = format!(
+            "{}\n{}\n",
+            serde_json::to_string(&merged).unwrap(),
+            serde_json::to_string(&other).unwrap()
+        );
+        let (updated, changed) = retarget_lines(&content, &["work-old".to_string()], "work");
+        assert_eq!(changed, 1);
+        let groups: Vec<Option<String>> = updated
+            .lines()
+            .map(|line| serde_json::from_str::<Entry>(line).unwrap().group)
+            .collect();
+        assert_eq!(groups, [Some("work".to_string()), Some("oss".to_string())]);
+    }
+
+    #[test]
+    fn test_repos_using() {
+        let local = |action: Action, repo: &str, group: Option<&str>| {
+            let mut entry = entry(action, "li@old.com");
+            entry.before.global = false;
+            entry.before.repo = Some(PathBuf::from(repo));
+            entry.group = group.map(str::to_string);
+            entry
+        };
+        let entries = vec![
+            local(Action::Use, "/src/b", Some("work")),
+            local(Action::Use, "/src/a", Some("oss")),
+            local(Action::Adopt, "/src/a", Some("work")),
+            local(Action::Use, "/src/c", Some("work")),
+            local(Action::Unset, "/src/c", None),
+            local(Action::Use, "/src/d", Some("oss")),
+            entry(Action::Use, "li@old.com"),
+        ];
+        assert_eq!(
+            repos_using(&entries, &["work".to_string()]),
+            [
+                (PathBuf::from("/src/a"), "work".to_string()),
+                (PathBuf::from("/src/b"), "work".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,590 @@
+//! # Git Hooks Module
+//!
+//! Hook scripts gum installs into repositories and the git template directory
+//! (`init.templateDir`) that copies them into every new or cloned repository.
+//!
+//! Hooks are plain POSIX shell so they keep working when gum is not on the
+//! `PATH` of the process running git (GUI clients, IDEs).
+
+use crate::config::Config;
+use crate::journal;
+use crate::policy;
+use crate::rules;
+use crate::utils;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marker line identifying hooks written by gum
+pub const HOOK_MARKER: &str = "# installed by gum";
+
+/// pre-commit hook refusing commits until an identity is set for the repository
+pub const GUARD_HOOK: &str = r#"#!/bin/sh
+# installed by gum
+# Refuses to commit until a git identity is set for this repository, so a
+# global or guessed identity is never used by accident.
+# Bypass once with `git commit --no-verify`.
+if [ -z "$(git config --local user.email)" ]; then
+  echo "gum: no git identity is set for this repository." >&2
+  echo "gum: run \`gum use <group>\` (see \`gum list\`) and commit again." >&2
+  exit 1
+fi
+"#;
+
+/// commit-msg hook appending the trailers of the applied group
+///
+/// Reads the templates `use` stores in `gum.trailers`, plus the co-authors
+/// of `gum pair` in `gum.coauthors`, and fills in the committer identity
+/// with awk, whose `index` does no pattern matching.
+pub const TRAILERS_HOOK: &str = r#"#!/bin/sh
+# installed by gum
+# Appends the commit trailers of the group applied by `gum use` (git config
+# gum.trailers, one template per line) and the co-authors of `gum pair`
+# (gum.coauthors). {name} and {email} stand for the committer. Trailers
+# already present are not repeated.
+templates=$(git config --get gum.trailers; git config --get gum.coauthors)
+[ -n "$templates" ] || exit 0
+ident=$(git var GIT_COMMITTER_IDENT) || exit 0
+name=${ident%% <*}
+email=${ident#*<}
+email=${email%%>*}
+printf '%s
+' "$templates" | while IFS= read -r template; do
+  [ -n "$template" ] || continue
+  trailer=$(T="$template" N="$name" E="$email" awk '
+    function fill(s, key, value,  out, i) {
+      out = ""
+      while ((i = index(s, key)) > 0) {
+        out = out substr(s, 1, i - 1) value
+        s = substr(s, i + length(key))
+      }
+      return out s
+    }
+    BEGIN { print fill(fill(ENVIRON["T"], "{name}", ENVIRON["N"]), "{email}", ENVIRON["E"]) }')
+  git interpret-trailers --in-place --if-exists addIfDifferent --trailer "$trailer" "$1" || exit 1
+done
+"#;
+
+/// File in the rules directory holding the compiled branch rules
+pub const BRANCH_RULES_FILE: &str = "gum-branches";
+
+/// File in the rules directory holding the compiled push policies
+pub const POLICIES_FILE: &str = "gum-policies";
+
+/// Directory inside gum's configuration directory holding the compiled
+/// rules, shared by the hooks of every repository
+const RULES_DIR_NAME: &str = "hook-rules";
+
+/// Stands for the quoted rules directory in the hook scripts
+const RULES_DIR_PLACEHOLDER: &str = "@GUM_RULES_DIR@";
+
+/// pre-commit hook enforcing the branch rules on new commits
+pub const BRANCH_COMMIT_HOOK: &str = r#"#!/bin/sh
+# installed by gum
+# Refuses commits on a branch reserved for a group (`gum rule add --branch`)
+# unless they are authored with that group's email. gum compiles the rules
+# into gum-branches in its configuration directory whenever they or the
+# groups change, one "pattern<TAB>group<TAB>email" line each; the first
+# pattern matching the branch decides.
+rules=@GUM_RULES_DIR@/gum-branches
+[ -f "$rules" ] || exit 0
+branch=$(git symbolic-ref --short -q HEAD) || exit 0
+ident=$(git var GIT_AUTHOR_IDENT) || exit 0
+email=${ident#*<}
+email=$(printf '%s' "${email%%>*}" | tr '[:upper:]' '[:lower:]')
+tab=$(printf '\t')
+while IFS=$tab read -r pattern group expected; do
+  case "$branch" in
+    $pattern)
+      [ "$email" = "$expected" ] && exit 0
+      echo "gum: commits to $branch must be authored as $group <$expected>, not <$email>." >&2
+      echo "gum: run \`gum use $group\` and commit again." >&2
+      exit 1 ;;
+  esac
+done < "$rules"
+"#;
+
+/// pre-push hook enforcing the branch rules and push policies on pushed
+/// commits
+pub const PUSH_HOOK: &str = r#"#!/bin/sh
+# installed by gum
+# Refuses to push commits that break a branch rule (`gum rule add --branch`)
+# or a push policy (`gum policy add`). gum compiles them into gum-branches
+# ("pattern<TAB>group<TAB>email" lines) and gum-policies ("email<TAB>remote"
+# globs) in its configuration directory whenever they or the groups change.
+# Only commits the remote does not have yet are checked.
+set -f
+rules=@GUM_RULES_DIR@/gum-branches
+policies=@GUM_RULES_DIR@/gum-policies
+[ -f "$rules" ] || [ -f "$policies" ] || exit 0
+url=$(printf '%s' "$2" | tr '[:upper:]' '[:lower:]')
+tab=$(printf '\t')
+status=0
+while read -r local_ref local_sha remote_ref remote_sha; do
+  case "$local_sha" in *[!0]*) ;; *) continue ;; esac
+  case "$remote_sha" in
+    *[!0]*) range="$remote_sha..$local_sha" ;;
+    *) range="$local_sha --not --remotes" ;;
+  esac
+  if [ -f "$policies" ]; then
+    emails=$(git log --format='%ae%n%ce' $range | tr '[:upper:]' '[:lower:]' | sort -u)
+    while IFS=$tab read -r email_glob remote_glob; do
+      case "$url" in $remote_glob) ;; *) continue ;; esac
+      for email in $emails; do
+        case "$email" in
+          $email_glob)
+            echo "gum: <$email> must not push to $2 (policy $email_glob -> $remote_glob)." >&2
+            status=1 ;;
+        esac
+      done
+    done < "$policies"
+  fi
+  case "$remote_ref" in refs/heads/*) branch=${remote_ref#refs/heads/} ;; *) continue ;; esac
+  [ -f "$rules" ] || continue
+  rule=$(while IFS=$tab read -r pattern group expected; do
+    case "$branch" in $pattern) printf '%s %s\n' "$group" "$expected"; break ;; esac
+  done < "$rules")
+  [ -n "$rule" ] || continue
+  group=${rule% *}
+  expected=${rule##* }
+  wrong=$(git log --format='%h %ae' $range | awk -v e="$expected" 'tolower($2) != e')
+  [ -n "$wrong" ] || continue
+  echo "gum: commits pushed to $branch must be authored as $group <$expected>:" >&2
+  printf '%s\n' "$wrong" | sed 's/^/gum:   /' >&2
+  status=1
+done
+exit $status
+"#;
+
+/// Hooks `gum hook install` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HookKind {
+    /// Appends the trailers of the applied group
+    CommitMsg,
+    /// Refuses commits breaking a branch rule
+    PreCommit,
+    /// Refuses pushes of commits breaking a branch rule or push policy
+    PrePush,
+}
+
+impl HookKind {
+    /// File name of the hook
+    pub fn file_name(self) -> &'static str {
+        match self {
+            HookKind::CommitMsg => "commit-msg",
+            HookKind::PreCommit => "pre-commit",
+            HookKind::PrePush => "pre-push",
+        }
+    }
+
+    /// Script of the hook, reading the compiled rules from `rules_dir`
+    pub fn script(self, rules_dir: &Path) -> String {
+        let script = match self {
+            HookKind::CommitMsg => TRAILERS_HOOK,
+            HookKind::PreCommit => BRANCH_COMMIT_HOOK,
+            HookKind::PrePush => PUSH_HOOK,
+        };
+        // sh on Windows (Git for Windows) takes forward slashes
+        let dir = rules_dir.to_string_lossy().replace('\\', "/");
+        script.replace(RULES_DIR_PLACEHOLDER, &utils::shell_quote(&dir))
+    }
+
+    /// Whether the hook reads the compiled branch rules or policies
+    pub fn uses_compiled_rules(self) -> bool {
+        matches!(self, HookKind::PreCommit | HookKind::PrePush)
+    }
+}
+
+/// Default git template directory next to the configuration file
+pub fn default_template_dir() -> anyhow::Result<PathBuf> {
+    let config_path = utils::get_config_path()?;
+    let dir = config_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Cannot obtain configuration directory"))?;
+    Ok(dir.join("git-template"))
+}
+
+/// Currently configured global `init.templateDir`
+pub fn configured_template_dir() -> Option<PathBuf> {
+    let output =
+        utils::output(utils::git().args(["config", "--global", "--get", "init.templateDir"]))
+            .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!value.is_empty()).then(|| PathBuf::from(utils::expand_home(&value)))
+}
+
+/// Write the guard hook into `template_dir/hooks/pre-commit`
+///
+/// An existing pre-commit hook not written by gum is left alone and
+/// reported as an error.
+pub fn write_guard_hook(template_dir: &Path) -> anyhow::Result<PathBuf> {
+    let hook = template_dir.join("hooks").join("pre-commit");
+    write_hook(&hook, GUARD_HOOK)?;
+    Ok(hook)
+}
+
+/// Write an executable hook script, refusing to replace a foreign hook
+pub fn write_hook(path: &Path, script: &str) -> anyhow::Result<()> {
+    if let Ok(existing) = fs::read_to_string(path)
+        && !existing.contains(HOOK_MARKER)
+    {
+        anyhow::bail!(
+            "{} already exists and was not installed by gum",
+            path.display()
+        );
+    }
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    if utils::preview_write(path, &existing, script) {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, script)?;
+    journal::record_write(path, &existing, script);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    log::debug!("Wrote hook {}", path.display());
+    Ok(())
+}
+
+/// Write a hook into the hooks directory of the repository at `dir`
+///
+/// Honors `core.hooksPath`. Returns the path of the hook.
+pub fn install_repository_hook(kind: HookKind, dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+    let hook = git_path(&format!("hooks/{}", kind.file_name()), dir)?;
+    write_hook(&hook, &kind.script(&rules_dir()?))?;
+    Ok(hook)
+}
+
+/// Write a hook into the repository at `dir` unless gum's is already there
+///
+/// Returns the path of the hook when it was written.
+pub fn ensure_repository_hook(
+    kind: HookKind,
+    dir: Option<&Path>,
+) -> anyhow::Result<Option<PathBuf>> {
+    let hook = git_path(&format!("hooks/{}", kind.file_name()), dir)?;
+    if is_gum_hook(&hook) {
+        return Ok(None);
+    }
+    install_repository_hook(kind, dir).map(Some)
+}
+
+/// Whether gum's hook of this kind is in the configured git template
+/// directory, so new and cloned repositories get it
+pub fn has_template_hook(kind: HookKind) -> bool {
+    configured_template_dir()
+        .is_some_and(|dir| is_gum_hook(&dir.join("hooks").join(kind.file_name())))
+}
+
+/// Whether the file at `path` is a hook written by gum
+fn is_gum_hook(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|script| script.contains(HOOK_MARKER))
+}
+
+/// Directory the pre-commit and pre-push hooks of every repository read the
+/// compiled branch rules and push policies from
+///
+/// It lies in gum's configuration directory, whatever the workspace or
+/// `--config`, so hooks installed once keep reading current rules.
+pub fn rules_dir() -> anyhow::Result<PathBuf> {
+    Ok(utils::get_config_base_dir()?.join(RULES_DIR_NAME))
+}
+
+/// Compile the branch rules and push policies into [`rules_dir`]
+///
+/// Called whenever the configuration file is saved, since the rules embed
+/// the groups' emails.
+pub fn write_shared_rules(config: &Config) -> anyhow::Result<()> {
+    write_hook_rules(config, &rules_dir()?)
+}
+
+/// Compile the branch rules and push policies into `dir`
+///
+/// A file is removed once nothing is left to write into it.
+pub fn write_hook_rules(config: &Config, dir: &Path) -> anyhow::Result<()> {
+    write_compiled(
+        &dir.join(BRANCH_RULES_FILE),
+        &rules::compile_branch_rules(config),
+    )?;
+    write_compiled(&dir.join(POLICIES_FILE), &policy::compile(config))
+}
+
+/// Write a compiled rule file, removing it when `compiled` is empty
+fn write_compiled(path: &Path, compiled: &str) -> anyhow::Result<()> {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    if existing == compiled || utils::preview_write(path, &existing, compiled) {
+        return Ok(());
+    }
+    if compiled.is_empty() {
+        fs::remove_file(path)?;
+    } else {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, compiled)?;
+    }
+    journal::record_write(path, &existing, compiled);
+    log::debug!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Path of `name` inside the git directory of the repository at `dir`
+///
+/// Honors `core.hooksPath` and linked worktrees like git does.
+fn git_path(name: &str, dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+    let mut command = utils::git();
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    let output = utils::output(command.args(["rev-parse", "--git-path", name]))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Cannot find the git directory: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    // The path is printed relative to the directory git ran in
+    let base = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir()?,
+    };
+    Ok(base.join(String::from_utf8_lossy(&output.stdout).trim_end()))
+}
+
+/// Install the guard hook into the git template directory
+///
+/// Reuses the `init.templateDir` already configured, otherwise creates
+/// `dir` (default: `git-template` next to the config file) and registers it
+/// globally. Returns the template directory.
+pub fn install_template(dir: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    install_template_hook(dir, "pre-commit", GUARD_HOOK)
+}
+
+/// Install a hook script into the git template directory
+///
+/// See [`install_template`] for the choice of the directory.
+pub fn install_template_hook(
+    dir: Option<PathBuf>,
+    file_name: &str,
+    script: &str,
+) -> anyhow::Result<PathBuf> {
+    let configured = configured_template_dir();
+    let template_dir = match (dir, configured.clone()) {
+        (Some(dir), _) => dir,
+        (None, Some(configured)) => configured,
+        (None, None) => default_template_dir()?,
+    };
+    write_hook(&template_dir.join("hooks").join(file_name), script)?;
+
+    if configured.as_deref() != Some(template_dir.as_path()) {
+        log::info!("Registering init.templateDir {}", template_dir.display());
+        let status = utils::run_change(
+            utils::git()
+                .args(["config", "--global", "init.templateDir"])
+                .arg(&template_dir),
+        )?;
+        if let Some(status) = status
+            && !status.success()
+        {
+            anyhow::bail!("Failed to set git init.templateDir");
+        }
+    }
+    Ok(template_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_write_guard_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = write_guard_hook(dir.path()).unwrap();
+        assert_eq!(hook, dir.path().join("hooks").join("pre-commit"));
+        assert!(fs::read_to_string(&hook).unwrap().contains(HOOK_MARKER));
+        // Rewriting our own hook is fine
+        write_guard_hook(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_trailers_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        git(&["config", "user.name", "Li & Si"]);
+        git(&["config", "user.email", "li@acme.com"]);
+        git(&[
+            "config",
+            "gum.trailers",
+            "Signed-off-by: {name} <{email}>\nOn-behalf-of: @acme",
+        ]);
+        git(&[
+            "config",
+            "gum.coauthors",
+            "Co-authored-by: Wang Er <wanger@acme.com>",
+        ]);
+        let hook = install_repository_hook(HookKind::CommitMsg, Some(dir.path())).unwrap();
+        assert!(hook.ends_with("hooks/commit-msg"));
+        assert!(
+            ensure_repository_hook(HookKind::CommitMsg, Some(dir.path()))
+                .unwrap()
+                .is_none()
+        );
+
+        let message = dir.path().join("MSG");
+        fs::write(&message, "Fix a bug\n").unwrap();
+        for _ in 0..2 {
+            let status = Command::new("sh")
+                .arg(&hook)
+                .arg(&message)
+                .current_dir(dir.path())
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+        assert_eq!(
+            fs::read_to_string(&message).unwrap(),
+            "Fix a bug\n\nSigned-off-by: Li & Si <li@acme.com>\nOn-behalf-of: @acme\n\
+             Co-authored-by: Wang Er <wanger@acme.com>\n"
+        );
+    }
+
+    #[test]
+    fn test_branch_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .env("GIT_AUTHOR_NAME", "Li")
+                .env("GIT_AUTHOR_EMAIL", "li@example.org")
+                .env("GIT_COMMITTER_NAME", "Li")
+                .env("GIT_COMMITTER_EMAIL", "li@example.org")
+                .status()
+                .unwrap()
+                .success()
+        };
+        assert!(git(&["init", "-q", "-b", "main"]));
+        let mut config = Config::new();
+        config.groups.insert(
+            "bot".to_string(),
+            crate::config::UserConfig {
+                name: "Bot".to_string(),
+                email: "bot@acme.com".to_string(),
+                ..Default::default()
+            },
+        );
+        config
+            .branches
+            .insert("release/*".to_string(), "bot".to_string());
+        // Shared by every repository, outside their git directories
+        let rules_dir = tempfile::tempdir().unwrap();
+        let rules_dir = rules_dir.path().join("hook rules");
+        write_hook_rules(&config, &rules_dir).unwrap();
+        let rules = rules_dir.join(BRANCH_RULES_FILE);
+        assert_eq!(
+            fs::read_to_string(&rules).unwrap(),
+            "release/*\tbot\tbot@acme.com\n"
+        );
+        let hook = git_path("hooks/pre-commit", Some(dir.path())).unwrap();
+        write_hook(&hook, &HookKind::PreCommit.script(&rules_dir)).unwrap();
+
+        assert!(git(&["commit", "-q", "--allow-empty", "-m", "on main"]));
+        assert!(git(&["checkout", "-q", "-b", "release/1.0"]));
+        assert!(!git(&["commit", "-q", "--allow-empty", "-m", "on release"]));
+
+        // No rule left, no file left
+        config.branches.clear();
+        write_hook_rules(&config, &rules_dir).unwrap();
+        assert!(!rules.exists());
+        assert!(git(&["commit", "-q", "--allow-empty", "-m", "on release"]));
+    }
+
+    #[test]
+    fn test_push_hook_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .env("GIT_AUTHOR_NAME", "Li")
+                .env("GIT_AUTHOR_EMAIL", "li@example.org")
+                .env("GIT_COMMITTER_NAME", "Li")
+                .env("GIT_COMMITTER_EMAIL", "li@example.org")
+                .output()
+                .unwrap()
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "personal"]);
+        let head = String::from_utf8(git(&["rev-parse", "HEAD"]).stdout).unwrap();
+
+        let mut config = Config::new();
+        config.policies.push(crate::policy::Policy {
+            email: "*@example.org".to_string(),
+            remote: "*gitlab.acme.com*".to_string(),
+        });
+        let rules_dir = tempfile::tempdir().unwrap();
+        write_hook_rules(&config, rules_dir.path()).unwrap();
+        assert_eq!(
+            fs::read_to_string(rules_dir.path().join(POLICIES_FILE)).unwrap(),
+            "*@example.org\t*gitlab.acme.com*\n"
+        );
+        let hook = dir.path().join("pre-push");
+        write_hook(&hook, &HookKind::PrePush.script(rules_dir.path())).unwrap();
+
+        let push = || {
+            let mut child = Command::new("sh")
+                .arg(&hook)
+                .args(["origin", "https://gitlab.acme.com/app.git"])
+                .current_dir(dir.path())
+                .stdin(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::null())
+                .spawn()
+                .unwrap();
+            let line = format!(
+                "refs/heads/main {} refs/heads/main {}\n",
+                head.trim(),
+                "0".repeat(40)
+            );
+            use std::io::Write;
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(line.as_bytes())
+                .unwrap();
+            child.wait().unwrap().success()
+        };
+        assert!(!push());
+
+        // Recompiled once, every hook reading the directory follows
+        config.policies.clear();
+        write_hook_rules(&config, rules_dir.path()).unwrap();
+        assert!(push());
+    }
+
+    #[test]
+    fn test_write_hook_keeps_foreign_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = dir.path().join("pre-commit");
+        fs::write(&hook, "#!/bin/sh\nmake lint\n").unwrap();
+        assert!(write_hook(&hook, GUARD_HOOK).is_err());
+        assert_eq!(fs::read_to_string(&hook).unwrap(), "#!/bin/sh\nmake lint\n");
+    }
+}
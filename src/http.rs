@@ -0,0 +1,599 @@
+//! # HTTP API Module
+//!
+//! A small local HTTP server for GUI frontends (tray apps, web dashboards),
+//! started with `gum serve --addr 127.0.0.1:7337`. Each connection is read
+//! on its own thread, within a size limit and a deadline, so a slow client
+//! does not hold up the others; the operations themselves run one at a
+//! time. Every response closes the connection. Only loopback addresses are
+//! served unless `--allow-remote` is given.
+//!
+//! Every request must carry `Authorization: Bearer <token>`, where the token
+//! is read from a file only the user can read (created on first start).
+//!
+//! ## Endpoints
+//! - `GET /v1/profiles`: All groups and the effective identity
+//! - `PUT /v1/profiles/{group}`: Create or update a group (fields of a batch `set`)
+//! - `DELETE /v1/profiles/{group}`: Delete a group
+//! - `GET /v1/rules`: Host rules
+//! - `POST /v1/rules`: Add a host rule (`host`, `group`)
+//! - `DELETE /v1/rules/{pattern}`: Remove a host rule (by pattern or priority)
+//! - `GET /v1/status?path=<dir>`: Identity of the repository at `path`
+//! - `POST /v1/apply`: Apply a group (`group`, `path`, `global`, `email_variant`)
+
+use crate::batch::{Operation, SetFields};
+use crate::service;
+use crate::utils;
+use serde_json::{Value, json};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token file name inside the state directory
+const TOKEN_FILE_NAME: &str = "http-token";
+
+/// Largest request body accepted
+const MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// Largest request line and headers accepted
+const MAX_HEAD_LEN: usize = 16 * 1024;
+
+/// Time a client may take to send its whole request
+const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+
+/// Time a client may take to receive the response
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections read at the same time; more are closed right away
+const MAX_CONNECTIONS: usize = 32;
+
+/// Default token file path
+pub fn token_path() -> anyhow::Result<PathBuf> {
+    Ok(utils::get_state_dir()?.join(TOKEN_FILE_NAME))
+}
+
+/// Read the token from `path`, creating the file with a new token if missing
+///
+/// An existing file that other users can read or write is refused.
+pub fn load_or_create_token(path: &Path) -> anyhow::Result<String> {
+    if path.exists() {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path)?.permissions().mode();
+            if mode & 0o077 != 0 {
+                anyhow::bail!(
+                    "Token file {} is accessible by other users (mode {:o}); run chmod 600 on it",
+                    path.display(),
+                    mode & 0o777
+                );
+            }
+        }
+        let token = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        let token = token.trim();
+        if token.is_empty() {
+            anyhow::bail!("Token file {} is empty", path.display());
+        }
+        return Ok(token.to_string());
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let token = new_token()?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Cannot create {}: {}", path.display(), e))?;
+    writeln!(file, "{}", token)?;
+    log::info!("Created API token file {}", path.display());
+    Ok(token)
+}
+
+/// 128 random bits from the operating system's CSPRNG, as hex
+fn new_token() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("Cannot generate a random token: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// HTTP API server
+pub struct HttpServer {
+    listener: TcpListener,
+    token: Arc<str>,
+    /// Held while an operation runs, so they do not interleave
+    operations: Arc<Mutex<()>>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl HttpServer {
+    /// Listen on `addr`, authenticating requests with `token`
+    ///
+    /// Non-loopback addresses expose the API to the network and are refused
+    /// unless `allow_remote` is set.
+    pub fn bind(addr: SocketAddr, token: String, allow_remote: bool) -> anyhow::Result<Self> {
+        if !addr.ip().is_loopback() {
+            if !allow_remote {
+                anyhow::bail!(
+                    "{} is not a loopback address; pass --allow-remote to serve the API to the network",
+                    addr
+                );
+            }
+            log::warn!("Serving the HTTP API on non-loopback address {}", addr);
+        }
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| anyhow::anyhow!("Cannot listen on {}: {}", addr, e))?;
+        Ok(Self {
+            listener,
+            token: token.into(),
+            operations: Arc::new(Mutex::new(())),
+            connections: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Address actually listened on (useful with port 0)
+    pub fn local_addr(&self) -> anyhow::Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serve requests until the process is stopped
+    pub fn serve(&self) -> anyhow::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!("Cannot accept connection: {}", e);
+                    continue;
+                }
+            };
+            if self.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                self.connections.fetch_sub(1, Ordering::SeqCst);
+                log::warn!("Too many connections, closing a new one");
+                continue;
+            }
+            let token = Arc::clone(&self.token);
+            let operations = Arc::clone(&self.operations);
+            let connections = Arc::clone(&self.connections);
+            // The task runs detached; its result is only logged
+            let _ = utils::spawn(move || {
+                if let Err(e) = handle_connection(stream, &token, &operations) {
+                    log::warn!("HTTP connection failed: {}", e);
+                }
+                connections.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Read one request, handle it and write the response
+fn handle_connection(stream: TcpStream, token: &str, operations: &Mutex<()>) -> anyhow::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(Deadline {
+        stream: stream.try_clone()?,
+        deadline: Instant::now() + REQUEST_DEADLINE,
+    });
+    let response = match read_request(&mut reader) {
+        Ok(request) => {
+            log::debug!("HTTP {} {}", request.method, request.path);
+            let _running = operations.lock().unwrap_or_else(|e| e.into_inner());
+            handle(token, &request)
+        }
+        Err(response) => response,
+    };
+    write_response(&stream, &response)
+}
+
+/// A stream whose reads fail once `deadline` has passed, however slowly
+/// the bytes trickle in
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "request deadline exceeded",
+            ));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+/// A parsed HTTP request
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Header value, by case-insensitive name
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query_value(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Body parsed as a JSON object
+    fn json_body(&self) -> Result<Value, Response> {
+        let body: Value = serde_json::from_slice(&self.body)
+            .map_err(|e| Response::error(400, &format!("Invalid JSON body: {}", e)))?;
+        if !body.is_object() {
+            return Err(Response::error(400, "Body must be a JSON object"));
+        }
+        Ok(body)
+    }
+}
+
+/// A JSON response
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    body: Option<Value>,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self {
+            status: 200,
+            body: Some(body),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: Some(json!({"error": message})),
+        }
+    }
+
+    /// Response of a service operation; failures are the client's (400)
+    fn from_result(result: anyhow::Result<Value>) -> Self {
+        match result {
+            Ok(body) => Self::ok(body),
+            Err(e) => Self::error(400, &e.to_string()),
+        }
+    }
+}
+
+/// Read a request, or the error response to send instead
+fn read_request(reader: &mut impl BufRead) -> Result<Request, Response> {
+    let bad_request = |message: &str| Response::error(400, message);
+    let too_large = || Response::error(431, "Request headers too large");
+    let mut head = (&mut *reader).take(MAX_HEAD_LEN as u64);
+    let mut line = String::new();
+    head.read_line(&mut line)
+        .map_err(|_| bad_request("Cannot read request"))?;
+    if !line.ends_with('\n') && head.limit() == 0 {
+        return Err(too_large());
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(bad_request("Malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: percent_decode(path).ok_or_else(|| bad_request("Malformed path"))?,
+        query: parse_query(query).ok_or_else(|| bad_request("Malformed query"))?,
+        ..Request::default()
+    };
+
+    loop {
+        line.clear();
+        head.read_line(&mut line)
+            .map_err(|_| bad_request("Cannot read headers"))?;
+        if !line.ends_with('\n') && head.limit() == 0 {
+            return Err(too_large());
+        }
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| bad_request("Malformed header"))?;
+        request
+            .headers
+            .push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let len = match request.header("Content-Length") {
+        Some(len) => len
+            .parse::<usize>()
+            .map_err(|_| bad_request("Invalid Content-Length"))?,
+        None => 0,
+    };
+    if len > MAX_BODY_LEN {
+        return Err(Response::error(413, "Request body too large"));
+    }
+    request.body = vec![0; len];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|_| bad_request("Incomplete request body"))?;
+    Ok(request)
+}
+
+/// Authenticate and route a request
+fn handle(token: &str, request: &Request) -> Response {
+    // CORS preflight of browser dashboards carries no credentials
+    if request.method == "OPTIONS" {
+        return Response {
+            status: 204,
+            body: None,
+        };
+    }
+    let presented = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    if !presented.is_some_and(|presented| constant_time_eq(presented, token)) {
+        return Response::error(401, "Missing or invalid token");
+    }
+    route(request).unwrap_or_else(|response| response)
+}
+
+/// Dispatch an authenticated request to its endpoint
+fn route(request: &Request) -> Result<Response, Response> {
+    let segments: Vec<&str> = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let method = request.method.as_str();
+    let response = match (method, segments.as_slice()) {
+        ("GET", ["v1", "profiles"]) => Response::from_result(service::list_profiles()),
+        ("PUT", ["v1", "profiles", group]) => {
+            let mut fields = request.json_body()?;
+            fields["group"] = json!(group);
+            let fields: SetFields = serde_json::from_value(fields)
+                .map_err(|e| Response::error(400, &format!("Invalid group fields: {}", e)))?;
+            Response::from_result(service::apply_operations(&[Operation::Set(Box::new(
+                fields,
+            ))]))
+        }
+        ("DELETE", ["v1", "profiles", group]) => {
+            Response::from_result(service::apply_operations(&[Operation::Delete {
+                group: group.to_string(),
+            }]))
+        }
+        ("GET", ["v1", "rules"]) => Response::from_result(service::list_rules()),
+        ("POST", ["v1", "rules"]) => {
+            let body = request.json_body()?;
+            let field = |key| {
+                service::string_arg(&body, key)
+                    .map(str::to_string)
+                    .map_err(|e| Response::error(400, &e.to_string()))
+            };
+            Response::from_result(service::apply_operations(&[Operation::RuleAdd {
+                host: field("host")?,
+                group: field("group")?,
+            }]))
+        }
+        ("DELETE", ["v1", "rules", pattern @ ..]) if !pattern.is_empty() => {
+            Response::from_result(service::remove_rule(&pattern.join("/")))
+        }
+        ("GET", ["v1", "status"]) => {
+            let path = request
+                .query_value("path")
+                .ok_or_else(|| Response::error(400, "Missing query parameter: path"))?;
+            Response::from_result(service::resolve_identity(Path::new(path)))
+        }
+        ("POST", ["v1", "apply"]) => {
+            Response::from_result(service::apply_profile(&request.json_body()?))
+        }
+        (
+            _,
+            ["v1", "profiles"]
+            | ["v1", "profiles", _]
+            | ["v1", "rules"]
+            | ["v1", "rules", _, ..]
+            | ["v1", "status"]
+            | ["v1", "apply"],
+        ) => Response::error(405, &format!("Method {} not allowed", method)),
+        _ => Response::error(404, &format!("No endpoint {}", request.path)),
+    };
+    Ok(response)
+}
+
+/// Write a response and close the connection
+fn write_response(mut stream: &TcpStream, response: &Response) -> anyhow::Result<()> {
+    let body = response
+        .body
+        .as_ref()
+        .map(|body| body.to_string())
+        .unwrap_or_default();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+         Access-Control-Allow-Methods: GET, POST, PUT, DELETE\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    }
+}
+
+/// Compare tokens without leaking the position of the first difference
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Parse `key=value&...` pairs
+fn parse_query(query: &str) -> Option<Vec<(String, String)>> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((
+                percent_decode(&key.replace('+', " "))?,
+                percent_decode(&value.replace('+', " "))?,
+            ))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes; `None` for malformed escapes or invalid UTF-8
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(raw: &str) -> Result<Request, Response> {
+        read_request(&mut raw.as_bytes())
+    }
+
+    #[test]
+    fn test_read_request() {
+        let request = request(
+            "POST /v1/status%2Fx?path=%2Fhome%2Fli+si&flag HTTP/1.1\r\n\
+             Authorization: Bearer abc\r\n\
+             content-length: 2\r\n\r\n{}",
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/status/x");
+        assert_eq!(request.query_value("path"), Some("/home/li si"));
+        assert_eq!(request.query_value("flag"), Some(""));
+        assert_eq!(request.header("Content-Length"), Some("2"));
+        assert_eq!(request.body, b"{}");
+
+        assert_eq!(self::request("GET\r\n\r\n").unwrap_err().status, 400);
+        let too_large = format!("PUT / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", 1 << 30);
+        assert_eq!(self::request(&too_large).unwrap_err().status, 413);
+        let long_header = format!(
+            "GET / HTTP/1.1\r\nX-Pad: {}\r\n\r\n",
+            "a".repeat(MAX_HEAD_LEN)
+        );
+        assert_eq!(self::request(&long_header).unwrap_err().status, 431);
+    }
+
+    #[test]
+    fn test_handle_auth_and_routing() {
+        let get = |path: &str, auth: &str| {
+            let raw = format!("GET {} HTTP/1.1\r\nAuthorization: {}\r\n\r\n", path, auth);
+            handle("secret", &request(&raw).unwrap()).status
+        };
+        assert_eq!(get("/v1/profiles", "Bearer wrong"), 401);
+        assert_eq!(get("/v1/profiles", "secret"), 401);
+        assert_eq!(get("/v1/nothing", "Bearer secret"), 404);
+        assert_eq!(get("/v1/apply", "Bearer secret"), 405);
+        assert_eq!(get("/v1/status", "Bearer secret"), 400);
+
+        let put = "PUT /v1/profiles/work HTTP/1.1\r\n\
+                   Authorization: Bearer secret\r\nContent-Length: 5\r\n\r\n[1,2]";
+        assert_eq!(handle("secret", &request(put).unwrap()).status, 400);
+        let preflight = "OPTIONS /v1/profiles HTTP/1.1\r\n\r\n";
+        assert_eq!(handle("secret", &request(preflight).unwrap()).status, 204);
+    }
+
+    #[test]
+    fn test_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("token");
+        let token = load_or_create_token(&path).unwrap();
+        assert_eq!(token.len(), 32);
+        assert_eq!(load_or_create_token(&path).unwrap(), token);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_ne!(new_token().unwrap(), token);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(load_or_create_token(&path).is_err());
+        }
+    }
+
+    #[test]
+    fn test_bind_remote() {
+        let remote: SocketAddr = "0.0.0.0:0".parse().unwrap();
+        assert!(HttpServer::bind(remote, "t".to_string(), false).is_err());
+        let local: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        assert!(HttpServer::bind(local, "t".to_string(), false).is_ok());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("abc", "abc"));
+        assert!(!constant_time_eq("abc", "abd"));
+        assert!(!constant_time_eq("abc", "ab"));
+    }
+}
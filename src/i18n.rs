@@ -0,0 +1,1165 @@
+//! # Localization Module
+//!
+//! Message catalog for user-facing strings. Handlers look messages up by key
+//! with the `t!` macro instead of hard-coding English text, so every locale
+//! lives in one table below.
+//!
+//! The locale comes from `settings.locale` in the config file, else from the
+//! `LC_ALL`, `LC_MESSAGES` and `LANG` environment variables, else English.
+//! Messages missing from a locale fall back to English.
+//!
+//! Placeholders are positional (`{0}`, `{1}`, ...) so translations may
+//! reorder them.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Supported locales
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    /// English (default)
+    En,
+    /// Simplified Chinese
+    Zh,
+}
+
+impl Locale {
+    /// Parse a locale tag such as `zh`, `zh_CN.UTF-8` or `en-US`
+    ///
+    /// Only the language part is considered. Returns `None` for languages
+    /// without a catalog.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "zh" => Some(Locale::Zh),
+            _ => None,
+        }
+    }
+
+    /// Detect the locale from the environment, defaulting to English
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::from_tag(&value))
+            .unwrap_or(Locale::En)
+    }
+}
+
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::En as u8);
+
+/// Set the locale used by `tr` and `t!`
+pub fn set_locale(locale: Locale) {
+    log::debug!("Using locale {:?}", locale);
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+/// Get the current locale
+pub fn locale() -> Locale {
+    match LOCALE.load(Ordering::Relaxed) {
+        x if x == Locale::Zh as u8 => Locale::Zh,
+        _ => Locale::En,
+    }
+}
+
+/// Message catalog: (key, English, Chinese)
+const MESSAGES: &[(&str, &str, &str)] = &[
+    ("error", "Error: {0}", "错误：{0}"),
+    (
+        "rpc_with_subcommand",
+        "--rpc cannot be combined with a subcommand",
+        "--rpc 不能与子命令同时使用",
+    ),
+    (
+        "config_load_failed",
+        "Failed to load config file: {0}",
+        "加载配置文件失败：{0}",
+    ),
+    (
+        "no_backup",
+        "No backup available, fix the file by hand before making changes",
+        "没有可用的备份，请先手动修复配置文件再进行修改",
+    ),
+    (
+        "restore_backup_confirm",
+        "Restore from backup {0}?",
+        "是否从备份 {0} 恢复？",
+    ),
+    (
+        "config_untouched",
+        "Config file left untouched, changes will not be saved",
+        "配置文件保持不变，修改将不会被保存",
+    ),
+    (
+        "restored_groups",
+        "Restored {0} groups from backup",
+        "已从备份恢复 {0} 个分组",
+    ),
+    (
+        "currently_using",
+        "Currently using: {0} <{1}>",
+        "当前使用：{0} <{1}>",
+    ),
+    (
+        "currently_using_none",
+        "Currently using: none",
+        "当前使用：无",
+    ),
+    (
+        "reserved_group_name",
+        "Group name cannot be '{0}'",
+        "分组名不能为 '{0}'",
+    ),
+    (
+        "invalid_group_name",
+        "Invalid group name: {0}",
+        "无效的分组名：{0}",
+    ),
+    (
+        "missing_user_fields",
+        "Must provide at least one of username or email",
+        "必须至少提供用户名或邮箱中的一项",
+    ),
+    ("invalid_email", "Invalid email: {0}", "无效的邮箱：{0}"),
+    (
+        "invalid_identity",
+        "Invalid identity: {0}",
+        "无效的身份：{0}",
+    ),
+    ("group_locked", "{0} is locked", "{0} 已锁定"),
+    (
+        "invalid_expiry",
+        "Invalid expiry date \"{0}\", expected a date like 2025-12-31",
+        "无效的过期日期 \"{0}\"，应为 2025-12-31 这样的日期",
+    ),
+    (
+        "group_expired",
+        "Group {0} expired on {1}; run `gum prune` to delete it",
+        "分组 {0} 已于 {1} 过期；运行 `gum prune` 删除它",
+    ),
+    ("expired_marker", "(expired)", "（已过期）"),
+    ("locked_marker", "(locked)", "（已锁定）"),
+    ("active_local", "(local)", "（本地）"),
+    ("health_ok", "ok", "正常"),
+    (
+        "health_gpg_key_missing",
+        "signing key {0} not in gpg keyring",
+        "签名密钥 {0} 不在 gpg 密钥环中",
+    ),
+    (
+        "health_ssh_key_missing",
+        "signing key file {0} missing",
+        "签名密钥文件 {0} 不存在",
+    ),
+    (
+        "health_ssh_key_not_loaded",
+        "signing key {0} not in ssh-agent",
+        "签名密钥 {0} 未加载到 ssh-agent",
+    ),
+    (
+        "health_unused_rules",
+        "{0} rule(s), used by no repository",
+        "{0} 条规则，但没有仓库使用",
+    ),
+    ("health_expired", "expired on {0}", "已于 {0} 过期"),
+    ("active_global", "(global)", "（全局）"),
+    (
+        "active_overridden",
+        "(global, overridden)",
+        "（全局，已被覆盖）",
+    ),
+    (
+        "expired_groups",
+        "{0} group(s) expired: {1}. Run `gum prune` to delete them",
+        "{0} 个分组已过期：{1}。运行 `gum prune` 删除它们",
+    ),
+    (
+        "use_force_locked",
+        "Use --force to change it anyway",
+        "使用 --force 强制修改",
+    ),
+    (
+        "use_force",
+        "Use --force to set it anyway",
+        "使用 --force 强制设置",
+    ),
+    (
+        "identity_exists",
+        "{0} <{1}> is already stored in group {2}, consider `gum use {3}` instead (run `gum dedupe` to merge)",
+        "{0} <{1}> 已保存在分组 {2} 中，可以直接使用 `gum use {3}`（运行 `gum dedupe` 合并重复分组）",
+    ),
+    (
+        "set_success",
+        "Successfully set {0} group",
+        "已设置分组 {0}",
+    ),
+    ("prompt_name", "Name", "用户名"),
+    ("prompt_email", "Email", "邮箱"),
+    (
+        "group_resolved",
+        "Using group {0} for {1}",
+        "根据 {1} 使用分组 {0}",
+    ),
+    (
+        "exec_no_group",
+        "No group given: name one, or set GUM_GROUP, or GUM_NAME and GUM_EMAIL",
+        "未指定分组：请指定一个分组，或设置 GUM_GROUP，或同时设置 GUM_NAME 和 GUM_EMAIL",
+    ),
+    (
+        "no_group_resolved",
+        "No group given and no entry in \"hosts\" matches this repository's remotes",
+        "未指定分组，且 \"hosts\" 中没有与此仓库远程地址匹配的条目",
+    ),
+    ("import_failed", "Import failed: {0}", "导入失败: {0}"),
+    (
+        "import_no_home",
+        "Cannot locate the home directory, pass --file",
+        "无法找到主目录，请使用 --file 指定",
+    ),
+    (
+        "import_no_profiles",
+        "No profiles with an email were found",
+        "未找到带有邮箱的配置",
+    ),
+    (
+        "import_identity_exists",
+        "Skipped {0}: identity already stored in {1}",
+        "已跳过 {0}: 该身份已存在于 {1}",
+    ),
+    (
+        "import_group_taken",
+        "Skipped {0}: group {1} already exists or is not a valid name, add it with `gum set`",
+        "已跳过 {0}: 分组 {1} 已存在或名称无效，请使用 `gum set` 添加",
+    ),
+    (
+        "imported",
+        "Imported {0} as group {1}: {2} <{3}>",
+        "已将 {0} 导入为分组 {1}: {2} <{3}>",
+    ),
+    (
+        "ssh_key_missing",
+        "Warning: {0} does not exist yet",
+        "警告: {0} 尚不存在",
+    ),
+    (
+        "ssh_host_written",
+        "Wrote Host {0} to {1}, use git@{0}:owner/repo.git to reach {2} with this group's key",
+        "已将 Host {0} 写入 {1}，使用 git@{0}:owner/repo.git 以该分组的密钥访问 {2}",
+    ),
+    (
+        "ssh_key_generated",
+        "Generated {0}, `gum use {1}` applies it",
+        "已生成 {0}，执行 `gum use {1}` 以应用",
+    ),
+    (
+        "gpg_key_not_found",
+        "No gpg secret key matches {0}",
+        "没有与 {0} 匹配的 gpg 私钥",
+    ),
+    (
+        "gpg_key_email_mismatch",
+        "Key {0} has no user ID with {1}, add one or use --force",
+        "密钥 {0} 没有包含 {1} 的用户 ID，请添加或使用 --force",
+    ),
+    (
+        "gpg_no_keys",
+        "No gpg secret key has a user ID with {0}",
+        "没有包含 {0} 用户 ID 的 gpg 私钥",
+    ),
+    (
+        "gpg_key_ambiguous",
+        "Several gpg secret keys match {0}, pass one with --signing-key",
+        "有多个 gpg 私钥匹配 {0}，请使用 --signing-key 指定",
+    ),
+    (
+        "gpg_key_selected",
+        "Using signing key {0}",
+        "使用签名密钥 {0}",
+    ),
+    ("gpg_pick_key", "Select a key [1-{0}]:", "选择密钥 [1-{0}]:"),
+    (
+        "no_matching_group",
+        "No group matches the current identity",
+        "没有与当前身份匹配的分组",
+    ),
+    (
+        "no_signing_key",
+        "Group {0} has no signing key, set one with `gum set {0} --pick-gpg-key`",
+        "分组 {0} 没有签名密钥，请使用 `gum set {0} --pick-gpg-key` 设置",
+    ),
+    (
+        "signing_ok",
+        "Signing works for {0} with {1} ({2})",
+        "{0} 使用 {1} ({2}) 签名正常",
+    ),
+    (
+        "signing_failed",
+        "Signing with {0} failed: {1}",
+        "{0} 签名失败: {1}",
+    ),
+    (
+        "no_differences",
+        "`gum use {0}` would not change anything",
+        "`gum use {0}` 不会做任何更改",
+    ),
+    ("nothing_to_undo", "Nothing to undo", "没有可撤销的操作"),
+    ("undo_restored", "Restored {0}: {1}", "已恢复 {0}: {1}"),
+    (
+        "undo_unset",
+        "Restored {0}: no identity set",
+        "已恢复 {0}: 未设置身份",
+    ),
+    (
+        "no_history",
+        "No identity changes recorded",
+        "没有身份变更记录",
+    ),
+    ("history_none", "(none)", "(无)"),
+    (
+        "rolled_back",
+        "Restored the config file saved at {0} (version {1})",
+        "已恢复保存于 {0} 的配置文件（版本 {1}）",
+    ),
+    ("rollback_failed", "Cannot roll back: {0}", "无法回滚：{0}"),
+    ("gc_nothing", "Nothing to clean up", "没有需要清理的内容"),
+    ("gc_done", "Cleaned up:", "已清理："),
+    ("gc_would", "Would clean up:", "将清理："),
+    (
+        "gc_versions",
+        "{0} old config file version(s)",
+        "{0} 个旧的配置文件版本",
+    ),
+    (
+        "gc_history",
+        "{0} history entr(ies) older than {1} days",
+        "{0} 条超过 {1} 天的历史记录",
+    ),
+    (
+        "gc_journal",
+        "change journal rotated to {0}",
+        "变更日志已轮转至 {0}",
+    ),
+    (
+        "gc_cache",
+        "{0} prompt cache file(s) unused for {1} days",
+        "{0} 个超过 {1} 天未使用的提示符缓存文件",
+    ),
+    ("no_journal", "No changes recorded", "没有变更记录"),
+    (
+        "batch_failed",
+        "{0}; the config file was not changed",
+        "{0}；配置文件未修改",
+    ),
+    (
+        "batch_applied",
+        "Applied {0} operation(s)",
+        "已执行 {0} 个操作",
+    ),
+    (
+        "snapshot_exists",
+        "Group {0} already exists, choose another --name",
+        "分组 {0} 已存在，请使用其他 --name",
+    ),
+    (
+        "snapshot_empty",
+        "No git identity is configured, nothing to snapshot",
+        "未配置 git 身份，无需快照",
+    ),
+    (
+        "until_shell_exit_unsupported",
+        "--until-shell-exit is not supported on this platform",
+        "此平台不支持 --until-shell-exit",
+    ),
+    (
+        "temporary_until",
+        "Temporary: the previous identity comes back after {0} UTC",
+        "临时身份：{0} UTC 之后恢复之前的身份",
+    ),
+    (
+        "temporary_until_shell_exit",
+        "Temporary: the previous identity comes back when this shell exits",
+        "临时身份：当前 shell 退出后恢复之前的身份",
+    ),
+    (
+        "temporary_reverted",
+        "Temporary identity {0} ended, the previous identity is back ({1})",
+        "临时身份 {0} 已结束，已恢复之前的身份（{1}）",
+    ),
+    (
+        "temporary_kept",
+        "Temporary identity {0} ended but was changed since, left as is ({1})",
+        "临时身份 {0} 已结束，但之后已被更改，保持不变（{1}）",
+    ),
+    (
+        "no_domain_emails",
+        "No group email is on domain {0}",
+        "没有分组邮箱属于域名 {0}",
+    ),
+    (
+        "domain_migrated",
+        "Moved {0} email(s) from {1} to {2}:",
+        "已将 {0} 个邮箱从 {1} 迁移到 {2}：",
+    ),
+    (
+        "no_affected_repos",
+        "No repository in the history uses the changed groups",
+        "历史记录中没有仓库使用这些分组",
+    ),
+    (
+        "affected_repos",
+        "{0} repositories use the changed groups:",
+        "{0} 个仓库使用这些分组：",
+    ),
+    (
+        "migrate_apply_hint",
+        "They keep the old emails until `gum use <group>` runs in each; --apply does it during the migration",
+        "在各仓库中运行 `gum use <分组>` 之前它们仍使用旧邮箱；迁移时加上 --apply 可自动完成",
+    ),
+    (
+        "repo_gone",
+        "Skipped {0}: no longer a git repository",
+        "已跳过 {0}：不再是 git 仓库",
+    ),
+    (
+        "repo_reapplied",
+        "{0} now uses {1} <{2}>",
+        "{0} 现在使用 {1} <{2}>",
+    ),
+    (
+        "adopt_no_identity",
+        "The repository has no local identity to adopt",
+        "仓库没有可接管的本地身份",
+    ),
+    (
+        "adopt_group_differs",
+        "Group {0} holds another identity than the repository",
+        "分组 {0} 的身份与仓库的不同",
+    ),
+    (
+        "adopt_created",
+        "Created group {0} from the repository: {1} <{2}>",
+        "已根据仓库创建分组 {0}: {1} <{2}>",
+    ),
+    (
+        "adopt_matched",
+        "The repository's identity is group {0}: {1} <{2}>",
+        "仓库的身份属于分组 {0}: {1} <{2}>",
+    ),
+    (
+        "adopt_no_rule",
+        "No rule added: a rule already resolves the repository, or its remotes have no host/owner",
+        "未添加规则：已有规则匹配此仓库，或其远程仓库没有 host/owner",
+    ),
+    (
+        "snapshot_saved",
+        "Saved {0}: {1} <{2}>",
+        "已保存 {0}: {1} <{2}>",
+    ),
+    (
+        "no_current_identity",
+        "No {0} git identity is configured",
+        "未配置 {0} git 身份",
+    ),
+    (
+        "prune_nothing",
+        "Every group was used in the last {0} months and none has expired",
+        "所有分组在最近 {0} 个月内都使用过，且均未过期",
+    ),
+    (
+        "prune_candidates",
+        "{0} group(s) expired or not used in the last {1} months:",
+        "{0} 个分组已过期或在最近 {1} 个月内未使用：",
+    ),
+    ("prune_last_used", "last used {0}", "上次使用 {0}"),
+    ("prune_expired", "expired on {0}", "已于 {0} 过期"),
+    ("prune_never", "never", "从未"),
+    ("prune_confirm", "Delete group '{0}'?", "删除分组 '{0}'？"),
+    ("prune_kept", "No groups deleted", "未删除任何分组"),
+    ("pruned", "Deleted groups: {0}", "已删除分组：{0}"),
+    ("timings_header", "Timings:", "耗时："),
+    (
+        "timings_commands",
+        "in {0} external command(s)",
+        "用于 {0} 个外部命令",
+    ),
+    ("timings_total", "total", "总计"),
+    (
+        "dry_run_done",
+        "Dry run: nothing was changed",
+        "试运行：未做任何修改",
+    ),
+    (
+        "not_git_repository",
+        "Current project is not a git repository",
+        "当前项目不是 git 仓库",
+    ),
+    ("global_use", "Global use: {0} <{1}>", "全局使用：{0} <{1}>"),
+    ("cannot_delete", "Cannot delete {0}", "不能删除 {0}"),
+    (
+        "system_group_delete",
+        "{0} is provided by the system config or profiles.d and cannot be deleted",
+        "{0} 由系统配置或 profiles.d 提供，不能删除",
+    ),
+    (
+        "system_group_readonly",
+        "{0} is a read-only group of the system config or profiles.d",
+        "{0} 是系统配置或 profiles.d 中的只读分组",
+    ),
+    (
+        "delete_success",
+        "Successfully deleted {0} group",
+        "已删除分组 {0}",
+    ),
+    ("group_not_found", "{0} group not found", "未找到分组 {0}"),
+    ("field_not_set", "{0} is not set for {1}", "{1} 未设置 {0}"),
+    (
+        "invalid_output_template",
+        "Invalid template: {0}",
+        "模板无效：{0}",
+    ),
+    ("did_you_mean", "Did you mean: {0}?", "你是不是想用：{0}？"),
+    ("changes_discarded", "Changes discarded", "已放弃修改"),
+    ("no_changes", "No changes", "没有修改"),
+    ("saved", "Successfully saved {0}", "已保存 {0}"),
+    (
+        "edit_invalid",
+        "The edited file is invalid. (r)eopen or (d)iscard? [r/d]",
+        "编辑后的文件无效。(r) 重新打开 或 (d) 放弃？[r/d]",
+    ),
+    (
+        "no_duplicates",
+        "No duplicate identities found",
+        "没有发现重复的身份",
+    ),
+    (
+        "identity_stored_in",
+        "{0} <{1}> is stored in:",
+        "{0} <{1}> 保存在以下分组中：",
+    ),
+    (
+        "keep_which",
+        "Keep which group? [1-{0}, Enter to skip]",
+        "保留哪个分组？[1-{0}，回车跳过]",
+    ),
+    ("skipped", "Skipped", "已跳过"),
+    (
+        "delete_partial",
+        "{0} of {1} group(s) could not be deleted",
+        "{1} 个分组中有 {0} 个未能删除",
+    ),
+    (
+        "no_groups_to_delete",
+        "No groups to delete",
+        "没有可删除的分组",
+    ),
+    (
+        "select_groups_to_delete",
+        "Check or uncheck groups (numbers and ranges, e.g. 1 3 5-7, or all; Enter when done):",
+        "勾选或取消勾选分组（编号或范围，例如 1 3 5-7，或 all；按回车完成）：",
+    ),
+    (
+        "delete_selected_confirm",
+        "Delete the {0} selected group(s)?",
+        "删除选中的 {0} 个分组？",
+    ),
+    (
+        "history_retargeted",
+        "{0} history entries now refer to group {1}",
+        "{0} 条历史记录已改为指向分组 {1}",
+    ),
+    (
+        "deleted_groups",
+        "Successfully deleted {0} group(s): {1}",
+        "已删除 {0} 个分组：{1}",
+    ),
+    (
+        "missing_subcommand",
+        "Missing subcommand name",
+        "缺少子命令名称",
+    ),
+    (
+        "unknown_subcommand",
+        "unrecognized subcommand '{0}' (no {1} found in PATH)",
+        "无法识别的子命令 '{0}'（PATH 中未找到 {1}）",
+    ),
+    (
+        "ambiguous_subcommand",
+        "'{0}' could be any of {1}; type more of the name",
+        "'{0}' 可能是 {1} 中的任意一个；请输入更多字符",
+    ),
+    (
+        "plugin_failed",
+        "Failed to run {0}: {1}",
+        "运行 {0} 失败：{1}",
+    ),
+    (
+        "no_config_file",
+        "No config file at {0}",
+        "{0} 处没有配置文件",
+    ),
+    ("config_valid", "{0} is valid", "{0} 有效"),
+    (
+        "lint_summary",
+        "{0} error(s), {1} warning(s)",
+        "{0} 个错误，{1} 个警告",
+    ),
+    ("config_invalid", "{0} is invalid", "{0} 无效"),
+    (
+        "input_required",
+        "{0} needs an answer, but gum runs non-interactively (--non-interactive or no terminal on stdin)",
+        "{0} 需要回答，但 gum 正以非交互模式运行（--non-interactive 或标准输入不是终端）",
+    ),
+    (
+        "git_not_found",
+        "git not found ({0}) — install it or set settings.git.path or GUM_GIT",
+        "未找到 git（{0}）— 请安装 git，或设置 settings.git.path 或 GUM_GIT",
+    ),
+    (
+        "git_timed_out",
+        "git did not finish within {0} s and was stopped: {1} (see settings.git.timeout_secs)",
+        "git 未在 {0} 秒内完成，已被终止：{1}（参见 settings.git.timeout_secs）",
+    ),
+    (
+        "serve_listening",
+        "Serving the HTTP API on http://{0} (token in {1})",
+        "HTTP API 已在 http://{0} 上提供服务（令牌位于 {1}）",
+    ),
+    (
+        "rule_added",
+        "Repositories at {0} now use group {1}",
+        "{0} 下的仓库现在使用分组 {1}",
+    ),
+    (
+        "rule_removed",
+        "Removed rule {0} -> {1}",
+        "已移除规则 {0} -> {1}",
+    ),
+    (
+        "branch_rule_added",
+        "Commits to branches matching {0} must now be authored as {1} (enforced by `gum hook install pre-commit` and `pre-push`)",
+        "提交到匹配 {0} 的分支现在必须使用分组 {1} 的身份（由 `gum hook install pre-commit` 和 `pre-push` 安装的钩子检查）",
+    ),
+    (
+        "branch_rule_whitespace",
+        "Branch pattern \"{0}\" must not contain whitespace",
+        "分支模式 \"{0}\" 不能包含空白字符",
+    ),
+    ("rule_not_found", "No rule {0}", "没有规则 {0}"),
+    (
+        "policy_added",
+        "Emails matching {0} may no longer push to remotes matching {1}",
+        "匹配 {0} 的邮箱不能再推送到匹配 {1} 的远程仓库",
+    ),
+    (
+        "policy_removed",
+        "Removed policy {0} -> {1}",
+        "已移除策略 {0} -> {1}",
+    ),
+    ("policy_exists", "The policy already exists", "该策略已存在"),
+    (
+        "policy_invalid",
+        "Invalid policy pattern \"{0}\": it must be non-empty and without whitespace",
+        "无效的策略模式 \"{0}\"：不能为空，也不能包含空白字符",
+    ),
+    ("policy_not_found", "No policy {0}", "没有策略 {0}"),
+    ("no_policies", "No policies", "没有策略"),
+    (
+        "check_no_identity",
+        "No git identity is set for this repository",
+        "此仓库没有设置 git 身份",
+    ),
+    (
+        "check_policy_violation",
+        "{0} must not push to remote {1} ({2}), policy {3} -> {4}",
+        "{0} 不能推送到远程仓库 {1}（{2}），策略 {3} -> {4}",
+    ),
+    (
+        "check_branch_violation",
+        "Commits to {0} must be authored as {1} <{2}>, not <{3}>",
+        "提交到 {0} 必须使用分组 {1} 的身份 <{2}>，而不是 <{3}>",
+    ),
+    ("check_failed", "{0} problem(s) found", "发现 {0} 个问题"),
+    (
+        "check_passed",
+        "{0} may push to all {1} remote(s) and commit to this branch",
+        "{0} 可以推送到全部 {1} 个远程仓库，也可以提交到当前分支",
+    ),
+    ("no_rules", "No rules", "没有规则"),
+    ("rule_empty", "The rule pattern is empty", "规则模式为空"),
+    (
+        "no_remotes",
+        "The repository has no remotes",
+        "仓库没有远程仓库",
+    ),
+    ("ssh_alias_rule", "SSH alias", "SSH 别名"),
+    ("chosen_marker", "(chosen)", "（选用）"),
+    (
+        "remotes_disagree",
+        "The remotes imply different groups: {0}. {1} is chosen, from remote {2}, the first that matches a rule",
+        "远程仓库对应不同的分组：{0}。将选用 {1}，来自第一个匹配规则的远程仓库 {2}",
+    ),
+    (
+        "rule_suggestion",
+        "Looks like {0}, which has no rule. Create one with: gum rule add {0} <group>",
+        "这里看起来是 {0}，还没有对应的规则。可以运行：gum rule add {0} <group>",
+    ),
+    (
+        "onboard_offer",
+        "No configuration yet. Set gum up now (about a minute)?",
+        "尚无配置。现在设置 gum 吗（约一分钟）？",
+    ),
+    (
+        "onboard_welcome",
+        "Let's set gum up; Enter keeps the default answer of each question",
+        "开始设置 gum；按回车即采用每个问题的默认回答",
+    ),
+    (
+        "onboard_global_known",
+        "Your global identity is already group {0}",
+        "你的全局身份已是分组 {0}",
+    ),
+    (
+        "onboard_no_global",
+        "No global git identity to import",
+        "没有可导入的全局 git 身份",
+    ),
+    (
+        "onboard_import_global",
+        "Save your global identity {0} <{1}> as a group?",
+        "将全局身份 {0} <{1}> 保存为分组吗？",
+    ),
+    ("onboard_group_name", "Group name", "分组名"),
+    (
+        "onboard_group_exists",
+        "Group {0} already exists",
+        "分组 {0} 已存在",
+    ),
+    (
+        "onboard_create_work",
+        "Create a group for work as well?",
+        "同时创建一个工作分组吗？",
+    ),
+    (
+        "onboard_default",
+        "Number of the group to use by default (Enter to skip):",
+        "默认使用的分组编号（回车跳过）：",
+    ),
+    (
+        "onboard_completions",
+        "Install {0} completions for gum?",
+        "为 gum 安装 {0} 补全吗？",
+    ),
+    (
+        "completions_installed",
+        "Completions installed in {0}; they apply to new shells",
+        "补全已安装到 {0}；在新的 shell 中生效",
+    ),
+    (
+        "completions_eval_hint",
+        "Load the output of `gum completions {0}` in your shell's startup file to complete gum",
+        "在 shell 启动文件中加载 `gum completions {0}` 的输出以补全 gum",
+    ),
+    (
+        "onboard_template",
+        "Make new repositories refuse commits until gum sets an identity (guard hook)?",
+        "让新仓库在 gum 设置身份前拒绝提交吗（守卫钩子）？",
+    ),
+    (
+        "onboard_done",
+        "All set. `gum list` shows your groups, `gum use <group>` switches",
+        "设置完成。`gum list` 显示分组，`gum use <分组>` 切换身份",
+    ),
+    (
+        "infer_nothing",
+        "Nothing in the {0} recent commit(s) or the remotes points to a group",
+        "最近 {0} 个提交和远程地址均未指向任何分组",
+    ),
+    (
+        "infer_tied",
+        "No single group stands out; choose one with `gum use <group>`",
+        "没有明显占优的分组；请用 `gum use <分组>` 选择",
+    ),
+    (
+        "infer_suggested",
+        "Suggested group: {0} (apply with `gum infer --apply` or `gum use {0}`)",
+        "建议的分组：{0}（可用 `gum infer --apply` 或 `gum use {0}` 应用）",
+    ),
+    (
+        "audit_skipped",
+        "{0}: cannot be audited, skipped: {1}",
+        "{0}：无法审计，已跳过：{1}",
+    ),
+    (
+        "journal_write_failed",
+        "Warning: changes are not recorded in the journal: {0}",
+        "警告：变更未记录到日志中：{0}",
+    ),
+    (
+        "audit_unresolved",
+        "{0}: no group resolves from the remotes, skipped",
+        "{0}：远程地址无法解析出分组，已跳过",
+    ),
+    (
+        "audit_clean",
+        "{0}: {2} commit(s) checked, all fit group {1}",
+        "{0}：已检查 {2} 个提交，均符合分组 {1}",
+    ),
+    (
+        "audit_wrong",
+        "{0}: {2} commit(s) made with another group than {1}",
+        "{0}：{2} 个提交使用了 {1} 以外的分组",
+    ),
+    (
+        "verify_clean",
+        "{0} commit(s) checked, all signed with their group's key",
+        "已检查 {0} 个提交，均使用所属分组的密钥签名",
+    ),
+    (
+        "verify_flagged",
+        "{0} of {1} commit(s) checked are not signed with their group's key:",
+        "已检查的 {1} 个提交中有 {0} 个未使用所属分组的密钥签名：",
+    ),
+    ("verify_unsigned", "[unsigned]", "[未签名]"),
+    ("verify_invalid", "[bad signature]", "[签名无效]"),
+    (
+        "verify_unverifiable",
+        "[cannot check signature]",
+        "[无法校验签名]",
+    ),
+    (
+        "verify_expired",
+        "[expired signature or key]",
+        "[签名或密钥已过期]",
+    ),
+    (
+        "verify_untrusted",
+        "[key of unknown validity]",
+        "[密钥有效性未知]",
+    ),
+    ("verify_wrong_key", "[signed with {0}]", "[使用 {0} 签名]"),
+    (
+        "verify_found",
+        "Found {0} commit(s) not signed with their group's key",
+        "发现 {0} 个未使用所属分组密钥签名的提交",
+    ),
+    (
+        "audit_found",
+        "Found {0} commit(s) made with the wrong identity",
+        "发现 {0} 个使用错误身份的提交",
+    ),
+    (
+        "pair_duplicate",
+        "Group {0} is listed twice",
+        "分组 {0} 被列出了两次",
+    ),
+    (
+        "pair_started",
+        "Committing as {0}, with {1} as co-authors until gum pair stop",
+        "将以 {0} 身份提交，并将 {1} 列为共同作者，直到运行 gum pair stop",
+    ),
+    (
+        "pair_stopped",
+        "Stopped crediting {0} co-author(s)",
+        "已停止添加 {0} 位共同作者",
+    ),
+    (
+        "pair_not_started",
+        "No pairing is active in this repository",
+        "此仓库中没有进行中的结对",
+    ),
+    ("tag_heading", "{0} ({1})", "{0}（{1}）"),
+    ("untagged_heading", "untagged ({0})", "无标签（{0}）"),
+    ("workspace_current", "Workspace: {0}", "工作区：{0}"),
+    (
+        "workspace_not_found",
+        "Workspace {0} does not exist (create it with `gum workspace new {0}`)",
+        "工作区 {0} 不存在（使用 `gum workspace new {0}` 创建）",
+    ),
+    (
+        "workspace_exists",
+        "Workspace {0} already exists",
+        "工作区 {0} 已存在",
+    ),
+    (
+        "workspace_created",
+        "Created workspace {0} in {1}",
+        "已在 {1} 创建工作区 {0}",
+    ),
+    (
+        "workspace_used",
+        "Now using workspace {0}",
+        "已切换到工作区 {0}",
+    ),
+    (
+        "workspace_env_wins",
+        "{0} is set in this shell and takes precedence",
+        "当前 shell 设置了 {0}，它的优先级更高",
+    ),
+    (
+        "shell_started",
+        "Starting a shell as {0} ({1} <{2}>), exit it to return",
+        "以 {0}（{1} <{2}>）启动 shell，退出即可返回",
+    ),
+    (
+        "shell_exited",
+        "Left the shell of {0}",
+        "已退出 {0} 的 shell",
+    ),
+    (
+        "exec_failed",
+        "Failed to run {0}: {1}",
+        "运行 {0} 失败：{1}",
+    ),
+    (
+        "watch_not_directory",
+        "{0} is not a directory",
+        "{0} 不是目录",
+    ),
+    (
+        "watch_started",
+        "Watching for new repositories, press Ctrl-C to stop",
+        "正在监视新仓库，按 Ctrl-C 停止",
+    ),
+    (
+        "watch_applied",
+        "New repository {0}: applied group {1} ({2})",
+        "新仓库 {0}：已应用分组 {1}（{2}）",
+    ),
+    (
+        "watch_resolved",
+        "New repository {0}: matches group {1} ({2}), run gum use {1}",
+        "新仓库 {0}：匹配分组 {1}（{2}），请运行 gum use {1}",
+    ),
+    (
+        "watch_unresolved",
+        "New repository {0}: no rule matches its remotes, run gum use <group>",
+        "新仓库 {0}：没有规则匹配其远程仓库，请运行 gum use <分组>",
+    ),
+    (
+        "watch_apply_failed",
+        "New repository {0}: cannot apply its group: {1}",
+        "新仓库 {0}：无法应用分组：{1}",
+    ),
+    (
+        "unset_done",
+        "Removed the {0} git identity and group settings",
+        "已移除 {0} git 身份及分组设置",
+    ),
+    ("hook_installed", "Installed {0}", "已安装 {0}"),
+    ("token_empty", "No token in {0}", "{0} 中没有令牌"),
+    (
+        "trailers_hook_missing",
+        "The group's trailers and sign-off need gum's commit-msg hook: {0}",
+        "该分组的提交尾注和签署需要 gum 的 commit-msg 钩子：{0}",
+    ),
+    (
+        "trailers_hook_hint",
+        "The group's trailers and sign-off are added by gum's commit-msg hook; install it in other repositories with `gum hook install commit-msg`, and for new ones with `--template`",
+        "该分组的提交尾注和签署由 gum 的 commit-msg 钩子添加；在其他仓库中用 `gum hook install commit-msg` 安装，新仓库加 `--template`",
+    ),
+    (
+        "hook_installed_template",
+        "Installed the {0} hook into {1}; new and cloned repositories will use it",
+        "已将 {0} 钩子安装到 {1}，新建和克隆的仓库将使用它",
+    ),
+    (
+        "invalid_trailer",
+        "Invalid trailer: {0}",
+        "无效的提交尾注：{0}",
+    ),
+    (
+        "invalid_workstyle",
+        "Invalid workstyle setting {0}, expected KEY=VALUE with alias.*, core.editor, pull.rebase or push.default",
+        "无效的工作习惯设置 {0}，格式应为 KEY=VALUE，键为 alias.*、core.editor、pull.rebase 或 push.default",
+    ),
+    (
+        "invalid_url_rewrite",
+        "Invalid URL rewrite {0}, expected PREFIX=REPLACEMENT",
+        "无效的 URL 重写 {0}，格式应为 PREFIX=REPLACEMENT",
+    ),
+    (
+        "invalid_email_variant",
+        "Invalid email variant {0}, expected VARIANT=EMAIL",
+        "无效的邮箱变体 {0}，格式应为 VARIANT=EMAIL",
+    ),
+    (
+        "group_inherited",
+        "Cannot delete {0}, it is inherited by {1}",
+        "不能删除 {0}，它被 {1} 继承",
+    ),
+    ("inherited_from", "from {0}", "继承自 {0}"),
+    (
+        "alias_is_group",
+        "{0} is already a group name",
+        "{0} 已经是分组名",
+    ),
+    ("alias_added", "Alias {0} -> {1}", "已添加别名 {0} -> {1}"),
+    ("alias_removed", "Removed alias {0}", "已删除别名 {0}"),
+    ("alias_not_found", "Alias {0} not found", "未找到别名 {0}"),
+    ("no_aliases", "No aliases", "没有别名"),
+    (
+        "template_installed",
+        "Installed the guard hook in {0}, new and cloned repositories will require `gum use` before committing",
+        "已在 {0} 安装守卫钩子，新建或克隆的仓库需先执行 `gum use` 才能提交",
+    ),
+    (
+        "use_config_only_global",
+        "use_config_only is enabled, groups can only be used in a repository",
+        "已启用 use_config_only，只能在仓库中使用分组",
+    ),
+    (
+        "capture_identity_failed",
+        "Cannot capture the previous identity: {0}",
+        "无法记录之前的身份：{0}",
+    ),
+    (
+        "use_config_only_removed",
+        "Removed global identity {0} <{1}> because use_config_only is on; `gum undo` puts it back",
+        "已移除全局身份 {0} <{1}>（因为开启了 use_config_only）；`gum undo` 可将其恢复",
+    ),
+    (
+        "up_to_date",
+        "gum {0} is up to date",
+        "gum {0} 已是最新版本",
+    ),
+    (
+        "update_available",
+        "gum {0} is available (current: {1})",
+        "gum {0} 可更新（当前版本：{1}）",
+    ),
+    (
+        "update_managed",
+        "gum was installed by {0}, update it with {1}",
+        "gum 由 {0} 安装，请使用 {1} 更新",
+    ),
+    (
+        "update_success",
+        "Updated {0} to {1}",
+        "已将 {0} 更新到 {1}",
+    ),
+];
+
+/// Look up a message in the current locale
+///
+/// Unknown keys are returned unchanged so a typo shows up instead of an
+/// empty line.
+pub fn tr(key: &str) -> &str {
+    translate(locale(), key)
+}
+
+/// Look up a message and fill in its positional placeholders
+pub fn tr_args(key: &str, args: &[&dyn Display]) -> String {
+    format_message(tr(key), args)
+}
+
+fn translate(locale: Locale, key: &str) -> &str {
+    let Some(&(_, en, zh)) = MESSAGES.iter().find(|(k, _, _)| *k == key) else {
+        log::warn!("Missing message: {}", key);
+        return key;
+    };
+    match locale {
+        Locale::Zh if !zh.is_empty() => zh,
+        _ => en,
+    }
+}
+
+/// Replace `{0}`, `{1}`, ... in `template` with `args`
+///
+/// Placeholders without a matching argument are kept as is.
+fn format_message(template: &str, args: &[&dyn Display]) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        let placeholder = rest[start..]
+            .find('}')
+            .map(|end| &rest[start..=start + end]);
+        match placeholder
+            .and_then(|p| p[1..p.len() - 1].parse::<usize>().ok())
+            .and_then(|i| args.get(i))
+        {
+            Some(arg) => {
+                message.push_str(&arg.to_string());
+                rest = &rest[start + placeholder.map_or(1, str::len)..];
+            }
+            None => {
+                message.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    message.push_str(rest);
+    message
+}
+
+/// Translate a message key, optionally filling in positional arguments
+///
+/// ```ignore
+/// utils::printer(&t!("set_success", group_name), "green");
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::tr($key).to_string()
+    };
+    ($key:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::tr_args($key, &[$(&$arg as &dyn std::fmt::Display),+])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(message: &str) -> Vec<usize> {
+        let mut found: Vec<usize> = (0..10)
+            .filter(|i| message.contains(&format!("{{{}}}", i)))
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn test_catalog_placeholders_match() {
+        for (i, (key, en, zh)) in MESSAGES.iter().enumerate() {
+            assert!(
+                MESSAGES[..i].iter().all(|(k, _, _)| k != key),
+                "duplicate key {}",
+                key
+            );
+            if !zh.is_empty() {
+                assert_eq!(placeholders(en), placeholders(zh), "key {}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(translate(Locale::En, "skipped"), "Skipped");
+        assert_eq!(translate(Locale::Zh, "skipped"), "已跳过");
+        assert_eq!(translate(Locale::Zh, "no_such_key"), "no_such_key");
+        assert_eq!(
+            format_message(
+                translate(Locale::En, "currently_using"),
+                &[&"li si", &"a@b.c"]
+            ),
+            "Currently using: li si <a@b.c>"
+        );
+        assert_eq!(format_message("{1} {0} {2}", &[&"{1}", &"b"]), "b {1} {2}");
+    }
+
+    #[test]
+    fn test_locale_from_tag() {
+        assert_eq!(Locale::from_tag("zh_CN.UTF-8"), Some(Locale::Zh));
+        assert_eq!(Locale::from_tag("zh-TW"), Some(Locale::Zh));
+        assert_eq!(Locale::from_tag("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("C"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr_FR"), None);
+    }
+}
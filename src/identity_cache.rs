@@ -0,0 +1,282 @@
+//! # Identity Cache Module
+//!
+//! Caches the effective git identity of each repository, with its remotes,
+//! for the shell prompt, which runs before every command line. An entry
+//! stays valid while
+//! the git configuration files it was read from keep their modification
+//! time and size, so an unchanged repository is answered without starting
+//! git at all.
+//!
+//! The files watched are the repository's `config` (and `config.worktree`),
+//! its `HEAD` for `includeIf "onbranch:"`, the global `~/.gitconfig` and
+//! `$XDG_CONFIG_HOME/git/config`, `/etc/gitconfig`, and the files these
+//! include. When `GIT_DIR` or a `GIT_CONFIG*` variable is set the cache is
+//! bypassed, since git would read other files.
+
+use crate::config::{self, UserConfig};
+use crate::{resolve, utils};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Directory of the cached identities, inside the user cache directory
+pub const CACHE_DIR: &str = "identity";
+
+/// Variables making git read configuration files the cache does not watch
+const BYPASS_ENV: &[&str] = &[
+    "GIT_DIR",
+    "GIT_CONFIG",
+    "GIT_CONFIG_GLOBAL",
+    "GIT_CONFIG_SYSTEM",
+    "GIT_CONFIG_NOSYSTEM",
+    "GIT_CONFIG_COUNT",
+    "GIT_CONFIG_PARAMETERS",
+];
+
+/// Modification time (seconds, nanoseconds) and size of a watched file,
+/// `None` while it does not exist
+type Stamp = Option<(u64, u32, u64)>;
+
+/// A cached identity and remotes with the state of the files they were read
+/// from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    stamps: Vec<(PathBuf, Stamp)>,
+    name: String,
+    email: String,
+    remotes: Vec<(String, String)>,
+}
+
+/// Effective identity of a repository and its remotes
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub user: UserConfig,
+    /// Remote names and URLs, `origin` first (see [`resolve::remote_urls`])
+    pub remotes: Vec<(String, String)>,
+}
+
+/// Effective identity and remotes of the repository containing `dir` (the
+/// current directory when `None`), from the cache when no configuration file
+/// changed
+pub fn lookup(dir: Option<&Path>) -> anyhow::Result<Snapshot> {
+    if BYPASS_ENV
+        .iter()
+        .any(|name| std::env::var_os(name).is_some())
+    {
+        log::debug!("Identity cache bypassed by the environment");
+        return Ok(Snapshot {
+            user: config::get_effective_git_user(dir)?,
+            remotes: resolve::remote_urls(dir),
+        });
+    }
+    let start = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir()?,
+    };
+    let start = start.canonicalize().unwrap_or(start);
+    let git_dir = find_git_dir(&start);
+    let stamps = stamps(&watched_files(git_dir.as_deref()));
+    let cache_file = cache_file(git_dir.as_deref())?;
+
+    if let Some(entry) = fs::read_to_string(&cache_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Entry>(&content).ok())
+        .filter(|entry| entry.stamps == stamps)
+    {
+        log::debug!("Using cached identity from {}", cache_file.display());
+        return Ok(Snapshot {
+            user: UserConfig {
+                name: entry.name,
+                email: entry.email,
+                ..UserConfig::default()
+            },
+            remotes: entry.remotes,
+        });
+    }
+
+    let user = config::get_effective_git_user(dir)?;
+    let remotes = resolve::remote_urls(dir);
+    let entry = Entry {
+        stamps,
+        name: user.name.clone(),
+        email: user.email.clone(),
+        remotes: remotes.clone(),
+    };
+    let written = cache_file
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&cache_file, serde_json::to_string(&entry)?));
+    if let Err(e) = written {
+        log::debug!(
+            "Failed to write identity cache {}: {}",
+            cache_file.display(),
+            e
+        );
+    }
+    Ok(Snapshot { user, remotes })
+}
+
+/// Git directory of the repository containing `start`, found the way git
+/// searches: a `.git` directory or `gitdir:` file, or a bare repository
+fn find_git_dir(start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return Some(dot_git);
+        }
+        if dot_git.is_file() {
+            let content = fs::read_to_string(&dot_git).ok()?;
+            let target = content.strip_prefix("gitdir:")?.trim();
+            return Some(dir.join(target));
+        }
+        if dir.join("HEAD").is_file() && dir.join("objects").is_dir() {
+            return Some(dir.to_path_buf());
+        }
+    }
+    None
+}
+
+/// Configuration files git reads for a repository, and those they include
+fn watched_files(git_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Some(git_dir) = git_dir {
+        // Linked worktrees keep the shared config in the common directory
+        let common_dir = fs::read_to_string(git_dir.join("commondir"))
+            .map(|common| git_dir.join(common.trim()))
+            .unwrap_or_else(|_| git_dir.to_path_buf());
+        files.push(common_dir.join("config"));
+        files.push(git_dir.join("config.worktree"));
+        files.push(git_dir.join("HEAD"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        files.push(home.join(".gitconfig"));
+    }
+    let xdg = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")));
+    if let Some(xdg) = xdg {
+        files.push(xdg.join("git").join("config"));
+    }
+    files.push(PathBuf::from("/etc/gitconfig"));
+
+    let mut included = Vec::new();
+    for file in &files {
+        if let Ok(content) = fs::read_to_string(file) {
+            included.extend(include_paths(file, &content));
+        }
+    }
+    files.extend(included);
+    files
+}
+
+/// Files named by `[include]` and `[includeIf]` sections of a config file
+fn include_paths(file: &Path, content: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut in_include = false;
+    for line in content.lines().map(str::trim) {
+        if let Some(section) = line.strip_prefix('[') {
+            in_include = section.trim_start().to_lowercase().starts_with("include");
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if !in_include || !key.trim().eq_ignore_ascii_case("path") {
+            continue;
+        }
+        let value = utils::expand_home(value.trim().trim_matches('"'));
+        let path = Path::new(&value);
+        paths.push(match file.parent() {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        });
+    }
+    paths
+}
+
+fn stamps(files: &[PathBuf]) -> Vec<(PathBuf, Stamp)> {
+    files
+        .iter()
+        .map(|file| {
+            let stamp = fs::metadata(file).ok().and_then(|metadata| {
+                let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+                Some((modified.as_secs(), modified.subsec_nanos(), metadata.len()))
+            });
+            (file.clone(), stamp)
+        })
+        .collect()
+}
+
+/// Cache file of a repository, or of directories outside any repository
+fn cache_file(git_dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    git_dir.hash(&mut hasher);
+    Ok(utils::get_cache_dir()?
+        .join(CACHE_DIR)
+        .join(format!("{:016x}.json", hasher.finish())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_git_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src/deep")).unwrap();
+        assert_eq!(
+            find_git_dir(&repo.join("src/deep")),
+            Some(repo.join(".git"))
+        );
+
+        // A worktree points at its git directory through a `.git` file
+        let worktree = dir.path().join("worktree");
+        fs::create_dir_all(&worktree).unwrap();
+        let linked = repo.join(".git/worktrees/wt");
+        fs::create_dir_all(&linked).unwrap();
+        fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", linked.display()),
+        )
+        .unwrap();
+        fs::write(linked.join("commondir"), "../..\n").unwrap();
+        let git_dir = find_git_dir(&worktree).unwrap();
+        assert_eq!(git_dir, linked);
+        let files = watched_files(Some(&git_dir));
+        assert_eq!(files[0], linked.join("../..").join("config"));
+        assert_eq!(files[1], linked.join("config.worktree"));
+    }
+
+    #[test]
+    fn test_include_paths() {
+        let content = "[user]\n\tpath = not-an-include\n[include]\n\tpath = shared.inc\n\
+                       [includeIf \"gitdir:~/work/\"]\n\tpath = \"/etc/work.inc\"\n";
+        assert_eq!(
+            include_paths(Path::new("/home/li/.gitconfig"), content),
+            [
+                PathBuf::from("/home/li/shared.inc"),
+                PathBuf::from("/etc/work.inc")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stamps_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config");
+        let files = vec![file.clone()];
+        let missing = stamps(&files);
+        assert_eq!(missing[0].1, None);
+        fs::write(&file, "[user]\n\tname = Li\n").unwrap();
+        let written = stamps(&files);
+        assert!(written[0].1.is_some());
+        fs::write(&file, "[user]\n\tname = Li Si\n").unwrap();
+        assert_ne!(stamps(&files), written);
+    }
+}
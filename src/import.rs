@@ -0,0 +1,283 @@
+//! # Import Module
+//!
+//! Builds groups from accounts on code hosting services, so a new machine can
+//! be set up without typing names and emails by hand.
+//!
+//! Accounts are read through the service's own CLI when it is logged in
+//! (`gh`, `glab`), which already holds the credentials, or through the REST
+//! API with a token read from a file or stdin.
+
+use crate::config::UserConfig;
+use crate::utils;
+use serde_json::Value;
+use std::process::Command;
+
+/// Host of the public GitHub service
+pub const GITHUB_HOST: &str = "github.com";
+
+/// Host of the public GitLab service
+pub const GITLAB_HOST: &str = "gitlab.com";
+
+/// An identity read from a code hosting service
+#[derive(Debug, Clone)]
+pub struct ImportedIdentity {
+    /// Suggested group name (the account login)
+    pub group: String,
+    pub user: UserConfig,
+    /// Where the identity came from, e.g. `github.com account octocat`
+    pub source: String,
+}
+
+/// Identities of the GitHub accounts available to gum
+///
+/// With a token, the account owning it is read from the API. Otherwise
+/// every account logged in to `gh` is imported; `GH_TOKEN` or
+/// `GITHUB_TOKEN` are used when `gh` is not installed.
+pub fn from_gh(token: Option<&str>) -> anyhow::Result<Vec<ImportedIdentity>> {
+    if let Some(token) = token {
+        return Ok(vec![github_api_identity(token)?]);
+    }
+    if utils::output(Command::new("gh").arg("--version")).is_err() {
+        let token = std::env::var("GH_TOKEN")
+            .or_else(|_| std::env::var("GITHUB_TOKEN"))
+            .map_err(|_| {
+                anyhow::anyhow!("gh is not installed; pass --token-file or set GH_TOKEN to import")
+            })?;
+        return Ok(vec![github_api_identity(&token)?]);
+    }
+
+    let accounts = gh_accounts();
+    if accounts.is_empty() {
+        // Older gh releases cannot list accounts; use the active one
+        let user = gh_api_user(None)?;
+        return github_identity(GITHUB_HOST, &user).map(|identity| vec![identity]);
+    }
+    accounts
+        .iter()
+        .map(|(host, login)| {
+            let token = gh(&["auth", "token", "--hostname", host, "--user", login])?;
+            let user = gh_api_user(Some((host, token.trim())))?;
+            github_identity(host, &user)
+        })
+        .collect()
+}
+
+/// Identity of the GitLab account on `host` (default: gitlab.com)
+///
+/// With a token the REST API is queried directly, otherwise `glab` is
+/// used; `GITLAB_TOKEN` is used when `glab` is not installed.
+pub fn from_gitlab(host: Option<&str>, token: Option<&str>) -> anyhow::Result<ImportedIdentity> {
+    let host = host.unwrap_or(GITLAB_HOST).trim_end_matches('/');
+    let host = host
+        .strip_prefix("https://")
+        .or_else(|| host.strip_prefix("http://"))
+        .unwrap_or(host);
+    let token = match token {
+        Some(token) => Some(token.to_string()),
+        None if utils::output(Command::new("glab").arg("--version")).is_err() => {
+            Some(std::env::var("GITLAB_TOKEN").map_err(|_| {
+                anyhow::anyhow!(
+                    "glab is not installed; pass --token-file or set GITLAB_TOKEN to import"
+                )
+            })?)
+        }
+        None => None,
+    };
+
+    let user: Value = match token {
+        Some(token) => {
+            let url = format!("https://{}/api/v4/user", host);
+            let body =
+                utils::curl_with_header(&["-fsSL", &url], &format!("PRIVATE-TOKEN: {}", token))?;
+            serde_json::from_slice(&body)?
+        }
+        None => serde_json::from_str(&glab(&["api", "user", "--hostname", host])?)?,
+    };
+    gitlab_identity(host, &user)
+}
+
+/// Logged in `gh` accounts as `(host, login)` pairs
+fn gh_accounts() -> Vec<(String, String)> {
+    let Ok(output) = gh(&["auth", "status", "--json", "hosts"]) else {
+        return Vec::new();
+    };
+    let Ok(status) = serde_json::from_str::<Value>(&output) else {
+        return Vec::new();
+    };
+    let mut accounts = Vec::new();
+    for (host, entries) in status["hosts"].as_object().into_iter().flatten() {
+        for entry in entries.as_array().into_iter().flatten() {
+            if entry["state"].as_str() != Some("success") {
+                continue;
+            }
+            if let Some(login) = entry["login"].as_str() {
+                accounts.push((host.clone(), login.to_string()));
+            }
+        }
+    }
+    accounts
+}
+
+/// `gh api user`, optionally as a specific account
+fn gh_api_user(account: Option<(&str, &str)>) -> anyhow::Result<Value> {
+    let mut command = Command::new("gh");
+    command.args(["api", "user"]);
+    if let Some((host, token)) = account {
+        command
+            .args(["--hostname", host])
+            .env("GH_TOKEN", token)
+            .env("GH_ENTERPRISE_TOKEN", token);
+    }
+    let output = utils::output(&mut command)?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "gh api user failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Run `gh` and return its standard output
+fn gh(args: &[&str]) -> anyhow::Result<String> {
+    let output = utils::output(Command::new("gh").args(args))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "gh {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Run `glab` and return its standard output
+fn glab(args: &[&str]) -> anyhow::Result<String> {
+    let output = utils::output(Command::new("glab").args(args))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "glab {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Read the account owning `token` from the GitHub API
+fn github_api_identity(token: &str) -> anyhow::Result<ImportedIdentity> {
+    let body = utils::curl_with_header(
+        &[
+            "-fsSL",
+            "-H",
+            "Accept: application/vnd.github+json",
+            "https://api.github.com/user",
+        ],
+        &format!("Authorization: Bearer {}", token),
+    )?;
+    github_identity(GITHUB_HOST, &serde_json::from_slice(&body)?)
+}
+
+/// Build an identity from a GitHub `/user` response
+///
+/// Uses the account's noreply address, which GitHub attributes to the
+/// account without exposing a private email. The display name falls back to
+/// the login when the profile has none.
+fn github_identity(host: &str, user: &Value) -> anyhow::Result<ImportedIdentity> {
+    let login = user["login"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("GitHub response has no login"))?;
+    let id = user["id"]
+        .as_u64()
+        .ok_or_else(|| anyhow::anyhow!("GitHub response has no account id"))?;
+    let name = user["name"]
+        .as_str()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or(login);
+    Ok(ImportedIdentity {
+        group: login.to_string(),
+        user: UserConfig {
+            name: name.to_string(),
+            email: format!("{}+{}@users.noreply.{}", id, login, host),
+            ..UserConfig::default()
+        },
+        source: format!("{} account {}", host, login),
+    })
+}
+
+/// Build an identity from a GitLab `/user` response
+///
+/// Prefers the commit email chosen in the profile, then the primary email.
+fn gitlab_identity(host: &str, user: &Value) -> anyhow::Result<ImportedIdentity> {
+    let username = user["username"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("GitLab response has no username"))?;
+    let email = ["commit_email", "email"]
+        .iter()
+        .filter_map(|key| user[key].as_str())
+        .find(|email| !email.trim().is_empty())
+        .ok_or_else(|| anyhow::anyhow!("GitLab account {} has no visible email", username))?;
+    let name = user["name"]
+        .as_str()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or(username);
+    Ok(ImportedIdentity {
+        group: username.to_string(),
+        user: UserConfig {
+            name: name.to_string(),
+            email: email.to_string(),
+            ..UserConfig::default()
+        },
+        source: format!("{} account {}", host, username),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_github_identity() {
+        let user = json!({"login": "octocat", "id": 583231, "name": "The Octocat"});
+        let identity = github_identity(GITHUB_HOST, &user).unwrap();
+        assert_eq!(identity.group, "octocat");
+        assert_eq!(identity.user.name, "The Octocat");
+        assert_eq!(
+            identity.user.email,
+            "583231+octocat@users.noreply.github.com"
+        );
+
+        let user = json!({"login": "ghost", "id": 10137, "name": null});
+        let identity = github_identity("github.acme.com", &user).unwrap();
+        assert_eq!(identity.user.name, "ghost");
+        assert_eq!(
+            identity.user.email,
+            "10137+ghost@users.noreply.github.acme.com"
+        );
+
+        assert!(github_identity(GITHUB_HOST, &json!({"login": "x"})).is_err());
+    }
+
+    #[test]
+    fn test_gitlab_identity() {
+        let user = json!({
+            "username": "jdoe",
+            "name": "Jane Doe",
+            "email": "jane@example.com",
+            "commit_email": "jane.doe@acme.com",
+        });
+        let identity = gitlab_identity("gitlab.acme.com", &user).unwrap();
+        assert_eq!(identity.group, "jdoe");
+        assert_eq!(identity.user.email, "jane.doe@acme.com");
+        assert_eq!(identity.source, "gitlab.acme.com account jdoe");
+
+        let user =
+            json!({"username": "jdoe", "name": "Jane", "commit_email": "", "email": "j@x.org"});
+        assert_eq!(
+            gitlab_identity(GITLAB_HOST, &user).unwrap().user.email,
+            "j@x.org"
+        );
+        assert!(gitlab_identity(GITLAB_HOST, &json!({"username": "jdoe"})).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+//! # Infer Module
+//!
+//! Suggests the group a repository should use, for adopting gum in
+//! repositories that existed before it. Three kinds of evidence count:
+//!
+//! - the recent commits authored by an identity a group holds, weighted by
+//!   their share of the commits looked at
+//! - a `hosts` rule or SSH host alias the remotes resolve to (see the
+//!   `resolve` module)
+//! - the owner of a remote (the organization, e.g. `acme` in
+//!   `github.com/acme/app`) appearing in a group's email domain
+//!
+//! The group with the most evidence is suggested; groups without any are
+//! left out.
+
+use crate::audit;
+use crate::config::{Config, UserConfig};
+use crate::resolve::{self, Resolution};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Score of a remote resolving to the group, the same as all commits
+const RULE_SCORE: u32 = 100;
+
+/// Score of a remote owner matching the group's email domain
+const OWNER_SCORE: u32 = 50;
+
+/// A group that may fit the repository and why
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub group: String,
+    /// Weight of the evidence; the commits count up to 100, a rule 100 and
+    /// a matching owner 50
+    pub score: u32,
+    /// Number of the commits looked at that the group's identities authored
+    pub commits: usize,
+    /// Human readable evidence, e.g. `host github.com (remote origin)`
+    pub reasons: Vec<String>,
+}
+
+/// Groups suggested for one repository, best first
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Inference {
+    pub path: PathBuf,
+    /// Number of commits looked at
+    pub checked: usize,
+    pub candidates: Vec<Candidate>,
+}
+
+impl Inference {
+    /// Group to use, `None` when nothing points to one or two groups are
+    /// tied
+    pub fn suggestion(&self) -> Option<&Candidate> {
+        let best = self.candidates.first()?;
+        match self.candidates.get(1) {
+            Some(second) if second.score == best.score => None,
+            _ => Some(best),
+        }
+    }
+}
+
+/// Infer the group of the repository at `dir` from its latest `max_count`
+/// commits and its remotes
+pub fn infer(config: &Config, dir: &Path, max_count: usize) -> anyhow::Result<Inference> {
+    let commits = audit::recent_commits(dir, max_count)?;
+    let authors: Vec<UserConfig> = commits.into_iter().map(|(_, author, _)| author).collect();
+    let remotes = resolve::remote_urls(Some(dir));
+    let resolution = resolve::resolve_remotes(config, &remotes);
+    let mut owners: Vec<String> = remotes
+        .iter()
+        .filter_map(|(_, url)| resolve::parse_owner(url))
+        .collect();
+    owners.sort();
+    owners.dedup();
+    Ok(Inference {
+        path: dir.to_path_buf(),
+        checked: authors.len(),
+        candidates: rank(config, &authors, resolution.as_ref(), &owners),
+    })
+}
+
+/// Rank the groups by the evidence for them, best first
+pub fn rank(
+    config: &Config,
+    authors: &[UserConfig],
+    resolution: Option<&Resolution>,
+    owners: &[String],
+) -> Vec<Candidate> {
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+
+    let mut commits: HashMap<String, usize> = HashMap::new();
+    for author in authors {
+        for group in config.match_groups(author) {
+            *commits.entry(group).or_default() += 1;
+        }
+    }
+    for (group, count) in commits {
+        let entry = entry(&mut candidates, &group);
+        entry.commits = count;
+        entry.score += (count * 100 / authors.len()) as u32;
+        entry
+            .reasons
+            .push(format!("{} of {} recent commits", count, authors.len()));
+    }
+
+    if let Some(resolution) = resolution {
+        let entry = entry(&mut candidates, &resolution.group);
+        entry.score += RULE_SCORE;
+        entry.reasons.push(resolution.reason.clone());
+    }
+
+    for (group, user) in config.get_all_config_info() {
+        if group == "global" {
+            continue;
+        }
+        let Some((_, domain)) = user.email.rsplit_once('@') else {
+            continue;
+        };
+        let domain = domain.to_lowercase();
+        if let Some(owner) = owners
+            .iter()
+            .find(|owner| domain.split('.').any(|label| label == owner.as_str()))
+        {
+            let entry = entry(&mut candidates, &group);
+            entry.score += OWNER_SCORE;
+            entry
+                .reasons
+                .push(format!("owner {} in email domain {}", owner, domain));
+        }
+    }
+
+    let mut candidates: Vec<Candidate> = candidates.into_values().collect();
+    candidates.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.group.cmp(&b.group)));
+    candidates
+}
+
+/// Candidate of `group`, added without evidence when missing
+fn entry<'a>(candidates: &'a mut HashMap<String, Candidate>, group: &str) -> &'a mut Candidate {
+    candidates
+        .entry(group.to_string())
+        .or_insert_with(|| Candidate {
+            group: group.to_string(),
+            score: 0,
+            commits: 0,
+            reasons: Vec::new(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: &str, email: &str) -> UserConfig {
+        UserConfig {
+            name: name.to_string(),
+            email: email.to_string(),
+            ..UserConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let mut config = Config::new();
+        config
+            .groups
+            .insert("work".to_string(), user("Li", "li@acme.com"));
+        config
+            .groups
+            .insert("oss".to_string(), user("Li", "li@example.org"));
+        let authors = [
+            user("Li", "li@example.org"),
+            user("Li", "li@example.org"),
+            user("Li", "li@example.org"),
+            user("Li", "li@acme.com"),
+            user("Someone", "someone@acme.com"),
+        ];
+
+        let ranked = rank(&config, &authors, None, &[]);
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|c| (c.group.as_str(), c.score, c.commits))
+                .collect::<Vec<_>>(),
+            [("oss", 60, 3), ("work", 20, 1)]
+        );
+
+        let ranked = rank(&config, &authors, None, &["acme".to_string()]);
+        assert_eq!(ranked[0].group, "work");
+        assert_eq!(ranked[0].score, 70);
+        assert_eq!(
+            ranked[0].reasons,
+            [
+                "1 of 5 recent commits",
+                "owner acme in email domain acme.com"
+            ]
+        );
+
+        let resolution = Resolution {
+            group: "oss".to_string(),
+            reason: "host github.com (remote origin)".to_string(),
+            rule: Some("github.com".to_string()),
+        };
+        let inference = Inference {
+            path: PathBuf::from("."),
+            checked: authors.len(),
+            candidates: rank(&config, &[], Some(&resolution), &[]),
+        };
+        assert_eq!(inference.suggestion().unwrap().group, "oss");
+
+        let tied = Inference {
+            path: PathBuf::from("."),
+            checked: 2,
+            candidates: rank(
+                &config,
+                &[user("Li", "li@acme.com"), user("Li", "li@example.org")],
+                None,
+                &[],
+            ),
+        };
+        assert_eq!(tied.suggestion(), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! # Interop Module
+//!
+//! Reads the identities stored by other git identity switchers, so users can
+//! move to gum without typing their profiles again.
+//!
+//! - `git-user-switch` (`gus`) keeps a `users` list in a JSON file written by
+//!   the `conf` package.
+//! - `git-profile` keeps named profiles of git config entries in
+//!   `~/.gitprofile`.
+//! - `git-identity` stores `identity.<id>.*` entries in the global git
+//!   configuration.
+
+use crate::config::{self, UserConfig};
+use crate::import::ImportedIdentity;
+use crate::utils;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Name `git-user-switch` stores its configuration under
+const GIT_USER_SWITCH_NAME: &str = "git-user-switch-nodejs";
+
+/// Default configuration file of `git-user-switch`
+///
+/// The `conf` package keeps it in the platform's preferences directory,
+/// with `-nodejs` appended to the project name.
+pub fn git_user_switch_path() -> Option<PathBuf> {
+    let dir = if cfg!(target_os = "macos") {
+        dirs::home_dir()?.join("Library/Preferences")
+    } else {
+        dirs::config_dir()?
+    };
+    let dir = dir.join(GIT_USER_SWITCH_NAME);
+    Some(if cfg!(windows) {
+        dir.join("Config").join("config.json")
+    } else {
+        dir.join("config.json")
+    })
+}
+
+/// Default profile file of `git-profile`
+pub fn gitprofile_path() -> Option<PathBuf> {
+    Some(dirs::home_dir()?.join(".gitprofile"))
+}
+
+/// Identities saved by `git-user-switch`
+pub fn from_git_user_switch(path: &Path) -> anyhow::Result<Vec<ImportedIdentity>> {
+    parse_git_user_switch(&read(path)?, path)
+}
+
+/// Identities saved by `git-profile`
+pub fn from_gitprofile(path: &Path) -> anyhow::Result<Vec<ImportedIdentity>> {
+    parse_gitprofile(&read(path)?, path)
+}
+
+/// Identities defined with `git-identity`
+///
+/// Read from the global git configuration, or from `path` when given.
+pub fn from_git_identity(path: Option<&Path>) -> anyhow::Result<Vec<ImportedIdentity>> {
+    let mut command = utils::git();
+    command.arg("config");
+    match path {
+        Some(path) => command.arg("--file").arg(path),
+        None => command.arg("--global"),
+    };
+    let output = utils::output(command.args(["--get-regexp", "^identity\\."]))?;
+    // Exit status 1 means no entry matched
+    if !output.status.success() && output.status.code() != Some(1) {
+        anyhow::bail!(
+            "git config failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let source = path.map_or_else(
+        || "global git config".to_string(),
+        |path| path.display().to_string(),
+    );
+    Ok(parse_git_identity(
+        &String::from_utf8_lossy(&output.stdout),
+        &source,
+    ))
+}
+
+/// Read a file, naming it in the error
+fn read(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))
+}
+
+/// Parse the `conf` store of `git-user-switch`
+///
+/// Users have no label there, so the group is named after the local part of
+/// the email.
+fn parse_git_user_switch(content: &str, path: &Path) -> anyhow::Result<Vec<ImportedIdentity>> {
+    let store: Value = serde_json::from_str(content)
+        .map_err(|e| anyhow::anyhow!("{} is not valid JSON: {}", path.display(), e))?;
+    let users = store["users"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("{} has no users list", path.display()))?;
+    Ok(users
+        .iter()
+        .filter_map(|user| {
+            let field = |key: &str| {
+                user[key]
+                    .as_str()
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+            };
+            let email = field("email")?;
+            let name = field("name").unwrap_or_default();
+            let group = email.split('@').next().filter(|local| !local.is_empty());
+            Some(ImportedIdentity {
+                group: group.unwrap_or(name).to_string(),
+                user: UserConfig {
+                    name: name.to_string(),
+                    email: email.to_string(),
+                    signing_key: field("signingkey")
+                        .or_else(|| field("signingKey"))
+                        .map(str::to_string),
+                    ..UserConfig::default()
+                },
+                source: format!("git-user-switch user {}", email),
+            })
+        })
+        .collect())
+}
+
+/// Parse the profiles of `git-profile`
+///
+/// Each profile is a list of `{"key": ..., "value": ...}` git config entries.
+/// Entries gum does not manage are ignored.
+fn parse_gitprofile(content: &str, path: &Path) -> anyhow::Result<Vec<ImportedIdentity>> {
+    let store: Value = serde_json::from_str(content)
+        .map_err(|e| anyhow::anyhow!("{} is not valid JSON: {}", path.display(), e))?;
+    let profiles = store["profiles"]
+        .as_object()
+        .ok_or_else(|| anyhow::anyhow!("{} has no profiles", path.display()))?;
+    Ok(profiles
+        .iter()
+        .filter_map(|(profile, entries)| {
+            let entries = entries.as_array()?.iter().filter_map(|entry| {
+                Some((entry["key"].as_str()?, entry["value"].as_str()?.trim()))
+            });
+            let user = user_from_entries(entries)?;
+            Some(ImportedIdentity {
+                group: profile.clone(),
+                user,
+                source: format!("git-profile {}", profile),
+            })
+        })
+        .collect())
+}
+
+/// Parse `git config --get-regexp ^identity\.` output of `git-identity`
+fn parse_git_identity(output: &str, source: &str) -> Vec<ImportedIdentity> {
+    // Identities in the order they first appear, with their entries
+    let mut identities: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    for line in output.lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        let Some((id, field)) = key
+            .strip_prefix("identity.")
+            .and_then(|rest| rest.rsplit_once('.'))
+        else {
+            continue;
+        };
+        let entry = (field.to_lowercase(), value.trim().to_string());
+        match identities.iter_mut().find(|(name, _)| name == id) {
+            Some((_, entries)) => entries.push(entry),
+            None => identities.push((id.to_string(), vec![entry])),
+        }
+    }
+
+    identities
+        .into_iter()
+        .filter_map(|(id, entries)| {
+            let git_entries = entries.iter().map(|(field, value)| {
+                let key = match field.as_str() {
+                    "name" => "user.name",
+                    "email" => "user.email",
+                    "signingkey" | "gpgkey" => "user.signingkey",
+                    _ => "",
+                };
+                (key, value.as_str())
+            });
+            let mut user = user_from_entries(git_entries)?;
+            user.ssh_key = entries
+                .iter()
+                .find(|(field, value)| field == "sshkey" && !value.is_empty())
+                .map(|(_, value)| value.clone());
+            Some(ImportedIdentity {
+                group: id.clone(),
+                user,
+                source: format!("git-identity {} ({})", id, source),
+            })
+        })
+        .collect()
+}
+
+/// Build a group from git config entries
+///
+/// `None` without an email.
+fn user_from_entries<'a>(entries: impl Iterator<Item = (&'a str, &'a str)>) -> Option<UserConfig> {
+    let mut user = UserConfig::default();
+    for (key, value) in entries {
+        if value.is_empty() {
+            continue;
+        }
+        match key.to_lowercase().as_str() {
+            "user.name" => user.name = value.to_string(),
+            "user.email" => user.email = value.to_string(),
+            "committer.name" => user.committer_name = Some(value.to_string()),
+            "committer.email" => user.committer_email = Some(value.to_string()),
+            "user.signingkey" => user.signing_key = Some(value.to_string()),
+            "gpg.format" => user.signing_format = Some(value.to_string()),
+            "commit.template" => user.commit_template = Some(value.to_string()),
+            "http.proxy" => user.http_proxy = Some(value.to_string()),
+            "http.sslcainfo" => user.ssl_ca_info = Some(value.to_string()),
+            "credential.helper" => user.credential_helper = Some(value.to_string()),
+            "sendemail.from" => user.sendemail_from = Some(value.to_string()),
+            "sendemail.smtpserver" => user.smtp_server = Some(value.to_string()),
+            "sendemail.smtpserverport" => user.smtp_server_port = Some(value.to_string()),
+            "sendemail.smtpuser" => user.smtp_user = Some(value.to_string()),
+            "sendemail.smtpencryption" => user.smtp_encryption = Some(value.to_string()),
+            "core.sshcommand" => user.ssh_key = config::ssh_command_key(value),
+            _ => {}
+        }
+    }
+    (!user.email.is_empty()).then_some(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_user_switch() {
+        let content = r#"{"users": [
+            {"name": "Jane Doe", "email": "jane@acme.com", "signingkey": "ABCD1234"},
+            {"name": "Jane", "email": "  "},
+            {"email": "jd@example.org"}
+        ]}"#;
+        let identities = parse_git_user_switch(content, Path::new("config.json")).unwrap();
+        assert_eq!(identities.len(), 2);
+        assert_eq!(identities[0].group, "jane");
+        assert_eq!(identities[0].user.name, "Jane Doe");
+        assert_eq!(identities[0].user.signing_key.as_deref(), Some("ABCD1234"));
+        assert_eq!(identities[1].group, "jd");
+        assert!(parse_git_user_switch("{}", Path::new("config.json")).is_err());
+    }
+
+    #[test]
+    fn test_parse_gitprofile() {
+        let content = r#"{"profiles": {
+            "work": [
+                {"key": "user.name", "value": "Jane Doe"},
+                {"key": "user.email", "value": "jane@acme.com"},
+                {"key": "core.sshCommand", "value": "ssh -i '/keys/work' -o IdentitiesOnly=yes"},
+                {"key": "core.editor", "value": "vim"}
+            ],
+            "empty": [{"key": "user.name", "value": "Nobody"}]
+        }}"#;
+        let identities = parse_gitprofile(content, Path::new(".gitprofile")).unwrap();
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].group, "work");
+        assert_eq!(identities[0].user.email, "jane@acme.com");
+        assert_eq!(identities[0].user.ssh_key.as_deref(), Some("/keys/work"));
+        assert_eq!(identities[0].source, "git-profile work");
+    }
+
+    #[test]
+    fn test_parse_git_identity() {
+        let output = "identity.work.name Jane Doe\n\
+                      identity.work.email jane@acme.com\n\
+                      identity.oss.email jd@example.org\n\
+                      identity.work.sshkey /keys/work\n\
+                      identity.oss.gpgkey ABCD1234\n\
+                      identity.nomail.name Nobody\n";
+        let identities = parse_git_identity(output, "global git config");
+        assert_eq!(identities.len(), 2);
+        assert_eq!(identities[0].group, "work");
+        assert_eq!(identities[0].user.name, "Jane Doe");
+        assert_eq!(identities[0].user.ssh_key.as_deref(), Some("/keys/work"));
+        assert_eq!(identities[1].group, "oss");
+        assert_eq!(identities[1].user.signing_key.as_deref(), Some("ABCD1234"));
+    }
+}
@@ -0,0 +1,308 @@
+//! # Journal Module
+//!
+//! Append-only record of every change gum makes, for teams that must be
+//! able to review what an identity switcher did: `git config` changes and
+//! writes to the files gum manages (the configuration file, hooks, compiled
+//! rules and `~/.ssh/config`). One JSON object per line in `changes.jsonl`,
+//! next to the configuration file; `gum log` prints it.
+//!
+//! Recording never fails a command, since the change already happened.
+
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// File holding the journal, inside the configuration directory
+pub const JOURNAL_FILE: &str = "changes.jsonl";
+
+/// What was changed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// A `git config` setting
+    Git,
+    /// A file written or removed
+    File,
+}
+
+/// One change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Seconds since the Unix epoch
+    pub timestamp: i64,
+    pub kind: ChangeKind,
+    /// `global`, the repository of a local setting, or the file path
+    pub target: String,
+    /// Git key; absent for files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Value replaced, or the lines of a file that were replaced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    /// Value set, or the lines of a file that were added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+}
+
+/// The change a `git config` command is about to make, with the value it
+/// replaces read from git; `None` for other commands
+pub fn git_change(command: &Command) -> Option<Change> {
+    if !utils::is_git(command) {
+        return None;
+    }
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let mut args = args.iter().map(String::as_str);
+    let mut repo = None;
+    loop {
+        match args.next()? {
+            "-C" => repo = Some(PathBuf::from(args.next()?)),
+            "config" => break,
+            _ => return None,
+        }
+    }
+    let mut scope = None;
+    let mut unset = false;
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg {
+            "--global" | "--local" | "--system" | "--worktree" => scope = Some(arg),
+            "--unset" | "--unset-all" => unset = true,
+            "--add" | "--replace-all" => {}
+            _ if arg.starts_with("--") => return None,
+            _ => positional.push(arg),
+        }
+    }
+    let key = *positional.first()?;
+    let new = if unset {
+        None
+    } else {
+        Some(positional.get(1)?.to_string())
+    };
+
+    let mut read = utils::git();
+    let dir = repo.or_else(|| command.get_current_dir().map(Path::to_path_buf));
+    if let Some(ref dir) = dir {
+        read.arg("-C").arg(dir);
+    }
+    read.arg("config");
+    read.args(scope);
+    let old = utils::output(read.args(["--get-all", key]))
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .trim_end()
+                .to_string()
+        });
+
+    let target = match scope {
+        Some("--global") => "global".to_string(),
+        Some("--system") => "system".to_string(),
+        _ => {
+            let dir = dir
+                .or_else(|| std::env::current_dir().ok())
+                .unwrap_or_default();
+            crate::repo::detect(Some(&dir))
+                .map(|repo| repo.root().to_path_buf())
+                .unwrap_or(dir)
+                .display()
+                .to_string()
+        }
+    };
+    Some(Change {
+        timestamp: utils::now(),
+        kind: ChangeKind::Git,
+        target,
+        key: Some(key.to_string()),
+        old,
+        new,
+    })
+}
+
+/// Record a write of `new` over `old` at `path`, keeping only the lines
+/// that changed
+pub fn record_write(path: &Path, old: &str, new: &str) {
+    if let Some(change) = file_change(path, old, new) {
+        record(&change);
+    }
+}
+
+/// Record a write of the configuration file at `config_path` in the
+/// journal next to it, which is not the default journal for a
+/// configuration loaded from a custom path
+pub fn record_config_write(config_path: &Path, old: &str, new: &str) {
+    if let Some(change) = file_change(config_path, old, new) {
+        record_in(&journal_path_for(config_path), &change);
+    }
+}
+
+fn file_change(path: &Path, old: &str, new: &str) -> Option<Change> {
+    let (removed, added) = utils::changed_lines(old, new);
+    if removed.is_empty() && added.is_empty() {
+        return None;
+    }
+    let lines = |lines: Vec<&str>| Some(lines.join("\n")).filter(|text| !text.is_empty());
+    Some(Change {
+        timestamp: utils::now(),
+        kind: ChangeKind::File,
+        target: path.display().to_string(),
+        key: None,
+        old: lines(removed),
+        new: lines(added),
+    })
+}
+
+/// Append a change, warning instead of failing when the journal cannot be
+/// written
+///
+/// Settings set to the value they had are not recorded, nor is anything
+/// under `--dry-run` or by unit tests, which must not write into the user's
+/// journal.
+pub fn record(change: &Change) {
+    match journal_path() {
+        Ok(journal) => record_in(&journal, change),
+        Err(e) => report_failure(&e),
+    }
+}
+
+fn record_in(journal: &Path, change: &Change) {
+    if change.old == change.new || utils::is_dry_run() || cfg!(test) {
+        return;
+    }
+    if let Err(e) = append(journal, change) {
+        report_failure(&e);
+    }
+}
+
+/// Whether a journal failure was already shown on stderr
+static FAILURE_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Tell the user on stderr that changes go unrecorded
+///
+/// Shown once per run, since a command usually makes several changes.
+fn report_failure(e: &anyhow::Error) {
+    log::warn!("Cannot record change in the journal: {}", e);
+    if !FAILURE_REPORTED.swap(true, Ordering::Relaxed) {
+        eprintln!("{}", crate::t!("journal_write_failed", e));
+    }
+}
+
+fn append(path: &Path, change: &Change) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(change)?)?;
+    Ok(())
+}
+
+/// All changes, oldest first
+///
+/// Lines that do not parse (e.g. written by a newer gum) are skipped.
+pub fn load() -> anyhow::Result<Vec<Change>> {
+    let content = match fs::read_to_string(journal_path()?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Move the journal aside once it is larger than `max_bytes`, returning
+/// the path it was moved to
+///
+/// The rotated file is named after the day, e.g. `changes-2025-06-30.jsonl`,
+/// and is never deleted by gum. Under `--dry-run` nothing is moved.
+pub fn rotate(max_bytes: u64) -> anyhow::Result<Option<PathBuf>> {
+    let path = journal_path()?;
+    let size = match fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if size <= max_bytes {
+        return Ok(None);
+    }
+    let day = &utils::format_timestamp(utils::now())[..10];
+    let rotated = (1..)
+        .map(|n| match n {
+            1 => path.with_file_name(format!("changes-{}.jsonl", day)),
+            n => path.with_file_name(format!("changes-{}-{}.jsonl", day, n)),
+        })
+        .find(|rotated| !rotated.exists())
+        .unwrap_or_default();
+    if !utils::is_dry_run() {
+        fs::rename(&path, &rotated)?;
+    }
+    Ok(Some(rotated))
+}
+
+/// Path of the journal, next to the configuration file
+pub fn journal_path() -> anyhow::Result<PathBuf> {
+    Ok(journal_path_for(&utils::get_config_path()?))
+}
+
+/// Path of the journal next to the configuration file at `config_path`
+pub fn journal_path_for(config_path: &Path) -> PathBuf {
+    config_path
+        .parent()
+        .map(|dir| dir.join(JOURNAL_FILE))
+        .unwrap_or_else(|| PathBuf::from(JOURNAL_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = Command::new("git")
+            .args(["init", "-q"])
+            .arg(dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let set = |args: &[&str]| {
+            let mut command = Command::new("git");
+            command.arg("-C").arg(dir.path()).arg("config").args(args);
+            command
+        };
+        assert!(
+            set(&["--local", "user.name", "Li"])
+                .status()
+                .unwrap()
+                .success()
+        );
+
+        let change = git_change(&set(&["--local", "user.name", "Li Si"])).unwrap();
+        assert_eq!(change.kind, ChangeKind::Git);
+        assert_eq!(change.key.as_deref(), Some("user.name"));
+        assert_eq!(change.old.as_deref(), Some("Li"));
+        assert_eq!(change.new.as_deref(), Some("Li Si"));
+
+        let change = git_change(&set(&["--local", "--unset-all", "user.email"])).unwrap();
+        assert_eq!((change.old, change.new), (None, None));
+
+        assert_eq!(git_change(&set(&["--local", "--get", "user.name"])), None);
+        assert_eq!(git_change(Command::new("ssh-keygen").arg("-q")), None);
+    }
+
+    #[test]
+    fn test_journal_path_for() {
+        assert_eq!(
+            journal_path_for(Path::new("/work/gum/config.jsonc")),
+            Path::new("/work/gum").join(JOURNAL_FILE)
+        );
+    }
+}
@@ -3,17 +3,150 @@
 //! This is a Rust library for managing multiple Git user configurations.
 //! Allows users to easily switch between different Git username and email configurations.
 //!
+//! Failures of the environment (no home directory, unreadable or invalid
+//! files, git missing, no thread available, stdout closed) are returned as
+//! errors or warnings; library functions do not panic on them.
+//!
 //! ## Module Structure
+//! - `audit`: Commits made with the wrong identity
+//! - `batch`: Configuration changes applied from JSON
 //! - `cli`: Command line interface definition
+//! - `completions`: Shell completion scripts
 //! - `config`: Configuration management functionality
+//! - `editor`: JSON-RPC mode for editor integrations
+//! - `export`: Groups rendered as shell aliases
 //! - `git`: Git configuration operations
+//! - `gpg`: OpenPGP secret key discovery
+//! - `health`: Problems of groups shown in listings
+//! - `history`: Log of identity changes
+//! - `http`: Local HTTP API for GUI frontends
+//! - `hooks`: Git hook scripts and template directory
+//! - `i18n`: Localized user-facing messages
+//! - `identity_cache`: Effective identities cached until git config files change
+//! - `import`: Groups imported from code hosting services
+//! - `infer`: Groups suggested from a repository's commits and remotes
+//! - `interop`: Profiles stored by other identity switchers
+//! - `journal`: Append-only record of the changes gum makes
+//! - `logging`: Log backend setup
+//! - `mcp`: Model Context Protocol server
+//! - `output`: User-defined output templates
+//! - `pager`: Paging long output
+//! - `pair`: Co-author trailers for pair programming
+//! - `plugin`: External `gum-<name>` subcommands
+//! - `policy`: Push policies protecting remotes from identities
+//! - `prompt`: Shell prompt segment
+//! - `repo`: Repository detection (worktrees, bare repositories, `.git` files)
+//! - `prune`: Detection of unused groups
+//! - `resolve`: Group resolution from repository remotes
+//! - `rpc`: JSON-RPC 2.0 transport over stdin/stdout
+//! - `rules`: Host and branch rule listing and hit counts
+//! - `service`: Operations shared by the MCP, editor and HTTP modes
+//! - `signing`: Commit signing checks
+//! - `ssh`: SSH host aliases per group
+//! - `table`: Table rendering for listings
+//! - `temporary`: Identities reverted after a time or when the shell exits
+//! - `term`: Terminal color and width support
+//! - `timings`: Durations of the phases of a run for `--timings`
+//! - `undo`: Restoring the identity before the last `use`
+//! - `update`: Self update from GitHub releases
 //! - `utils`: Utility functions
+//! - `validate`: Configuration file validation
+//! - `verify`: Commit signatures checked against the groups' signing keys
+//! - `versions`: Content-addressed states of the configuration file
+//! - `watch`: Detection of new repositories in workspaces
+//! - `workspace`: Separate sets of groups, rules and history
 
+/// Commit audit module
+pub mod audit;
+/// Batch operations module
+pub mod batch;
 /// Command line interface module
 pub mod cli;
+/// Shell completion module
+pub mod completions;
 /// Configuration management module
 pub mod config;
+/// Editor integration module
+pub mod editor;
+/// Group export module
+pub mod export;
 /// Git operations module
 pub mod git;
+/// GPG key discovery module
+pub mod gpg;
+/// Group health module
+pub mod health;
+/// Identity change history module
+pub mod history;
+/// Git hooks module
+pub mod hooks;
+/// HTTP API module
+pub mod http;
+/// Localization module
+pub mod i18n;
+/// Identity cache module
+pub mod identity_cache;
+/// Account import module
+pub mod import;
+/// Group inference module
+pub mod infer;
+/// Identity switcher interop module
+pub mod interop;
+/// Change journal module
+pub mod journal;
+/// Logging setup module
+pub mod logging;
+/// MCP server module
+pub mod mcp;
+/// Output template module
+pub mod output;
+/// Pager module
+pub mod pager;
+/// Pair programming module
+pub mod pair;
+/// External subcommand module
+pub mod plugin;
+/// Push policy module
+pub mod policy;
+/// Prompt segment module
+pub mod prompt;
+/// Unused group detection module
+pub mod prune;
+/// Repository detection module
+pub mod repo;
+/// Group resolution module
+pub mod resolve;
+/// JSON-RPC transport module
+pub mod rpc;
+/// Host rules module
+pub mod rules;
+/// Operations shared by the server modes
+pub mod service;
+/// Signing check module
+pub mod signing;
+/// SSH configuration module
+pub mod ssh;
+/// Table rendering module
+pub mod table;
+/// Temporary identity module
+pub mod temporary;
+/// Terminal capability module
+pub mod term;
+/// Timing diagnostics module
+pub mod timings;
+/// Undo module
+pub mod undo;
+/// Self update module
+pub mod update;
 /// Utility functions module
 pub mod utils;
+/// Configuration validation module
+pub mod validate;
+/// Commit signature verification module
+pub mod verify;
+/// Config file versions module
+pub mod versions;
+/// Workspace watching module
+pub mod watch;
+/// Workspace selection module
+pub mod workspace;
@@ -0,0 +1,130 @@
+//! # Logging Module
+//!
+//! Sets up the `log` backend from the global command line flags. Verbosity
+//! comes from `-v`/`-q` (falling back to `RUST_LOG` when neither is given),
+//! records are written as text or JSON lines, to stderr or appended to a file.
+
+use env_logger::{Builder, Target};
+use log::LevelFilter;
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Format of log records
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// `<timestamp> [LEVEL] message`
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Logging options taken from the command line
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Number of `-v` flags
+    pub verbose: u8,
+    /// Disable logging entirely
+    pub quiet: bool,
+    /// Record format
+    pub format: LogFormat,
+    /// Append records to this file instead of stderr
+    pub file: Option<PathBuf>,
+}
+
+/// Level selected by the verbosity flags, `None` to defer to `RUST_LOG`
+pub fn level_filter(verbose: u8, quiet: bool) -> Option<LevelFilter> {
+    match (quiet, verbose) {
+        (true, _) => Some(LevelFilter::Off),
+        (false, 0) => None,
+        (false, 1) => Some(LevelFilter::Info),
+        (false, 2) => Some(LevelFilter::Debug),
+        (false, _) => Some(LevelFilter::Trace),
+    }
+}
+
+/// Initialize the global logger
+pub fn init(options: &LogOptions) -> anyhow::Result<()> {
+    let mut builder = Builder::from_env(env_logger::Env::default());
+    if let Some(level) = level_filter(options.verbose, options.quiet) {
+        builder.filter_level(level);
+    }
+
+    match options.format {
+        LogFormat::Text => builder.format(|buf, record| {
+            writeln!(
+                buf,
+                "{} [{}] {}",
+                buf.timestamp_micros(),
+                record.level(),
+                crate::utils::redact(&record.args().to_string())
+            )
+        }),
+        LogFormat::Json => builder.format(|buf, record| {
+            let line = json_record(
+                &buf.timestamp_micros().to_string(),
+                record.level(),
+                record.target(),
+                &crate::utils::redact(&record.args().to_string()),
+            );
+            writeln!(buf, "{}", line)
+        }),
+    };
+
+    if let Some(ref path) = options.file {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Cannot open log file {}: {}", path.display(), e))?;
+        builder.target(Target::Pipe(Box::new(file)));
+    }
+
+    builder.try_init()?;
+    Ok(())
+}
+
+/// Render one record as a JSON line
+fn json_record(timestamp: &str, level: log::Level, target: &str, message: &str) -> String {
+    json!({
+        "timestamp": timestamp,
+        "level": level.as_str(),
+        "target": target,
+        "message": message,
+        "pid": std::process::id(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_filter() {
+        assert_eq!(level_filter(0, false), None);
+        assert_eq!(level_filter(1, false), Some(LevelFilter::Info));
+        assert_eq!(level_filter(2, false), Some(LevelFilter::Debug));
+        assert_eq!(level_filter(5, false), Some(LevelFilter::Trace));
+        assert_eq!(level_filter(2, true), Some(LevelFilter::Off));
+    }
+
+    #[test]
+    fn test_json_record() {
+        let line = json_record(
+            "2024-01-01T00:00:00.000000Z",
+            log::Level::Warn,
+            "gum_rs::config",
+            "say \"hi\"",
+        );
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "gum_rs::config");
+        assert_eq!(value["message"], "say \"hi\"");
+    }
+}
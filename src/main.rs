@@ -3,69 +3,433 @@
 //! Responsible for parsing command line arguments and dispatching to corresponding handlers.
 //! Supports listing, setting, using, and deleting Git user configuration groups.
 
-use clap::Parser;
-use env_logger::Builder;
-use gum_rs::cli::{Cli, Commands};
+use clap::{CommandFactory, Parser};
+use gum_rs::cli::{
+    AliasCommands, Cli, Command, Commands, ConfigCommands, HookCommands, ImportArgs, ImportTool,
+    OutputFormat, PairCommands, PolicyCommands, RuleCommands, Scope, SetArgs, SshCommands,
+    StandaloneCommands, TemplateCommands, WorkspaceCommands,
+};
 use gum_rs::config::{Config, UserConfig};
-use gum_rs::utils;
+use gum_rs::editor::EditorServer;
+use gum_rs::i18n::{self, Locale};
+use gum_rs::logging::{self, LogOptions};
+use gum_rs::mcp::McpServer;
+use gum_rs::output::{self, OutputTemplate};
+use gum_rs::prompt::{self, PromptOptions, PromptStyle};
+use gum_rs::table::Table;
+use gum_rs::validate::{self, Severity};
+use gum_rs::{
+    audit, batch, completions, export, gpg, health, history, hooks, http, import, infer, interop,
+    journal, pager, pair, plugin, prune, repo, resolve, rules, service, signing, ssh, t, temporary,
+    term, timings, undo, update, utils, verify, versions, watch, workspace,
+};
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
+    let started = Instant::now();
+    let result = run();
+    if timings::is_enabled() {
+        eprint!("{}", timings::report(started.elapsed()));
+    }
+    if let Err(e) = result {
+        let e = utils::git_failure().unwrap_or_else(|| e.to_string());
+        eprintln!("{}", utils::redact(&t!("error", e)));
+        if utils::was_input_required() {
+            std::process::exit(utils::EXIT_INPUT_REQUIRED);
+        }
         std::process::exit(1);
     }
 }
 
 fn run() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logger
-    Builder::from_env(env_logger::Env::default())
-        .format(|buf, record| {
-            writeln!(
-                buf,
-                "{} [{}] {}",
-                buf.timestamp_micros(),
-                record.level(),
-                record.args()
-            )
-        })
-        .init();
+    i18n::set_locale(Locale::from_env());
+    let cli = Cli::parse();
 
+    // Initialize logger
+    logging::init(&LogOptions {
+        verbose: cli.verbose,
+        quiet: cli.quiet,
+        format: cli.log_format,
+        file: cli.log_file.clone(),
+    })?;
     log::debug!("Starting gum application");
-
-    let cli = Cli::parse();
     log::debug!("Parsed CLI command: {:?}", cli.command);
+    if let Some(path) = cli.config.clone() {
+        utils::set_config_path_override(path);
+    }
+    if let Some(name) = cli.workspace.clone() {
+        workspace::set_override(name);
+    }
+    utils::set_dry_run(cli.dry_run);
+    utils::set_redact(cli.redact);
+    term::set_plain(cli.plain || std::env::var("TERM").is_ok_and(|term| term == "dumb"));
+    if cli.timings {
+        timings::enable();
+    }
+    utils::set_non_interactive(cli.non_interactive || !std::io::stdin().is_terminal());
+
+    if cli.rpc {
+        if cli.command.is_some() {
+            return Err(t!("rpc_with_subcommand").into());
+        }
+        log::info!("Starting editor JSON-RPC mode");
+        EditorServer::new().serve()?;
+        return Ok(());
+    }
+    update::cleanup_old_exe();
+    let command = match cli.command {
+        // Fast path: the prompt segment runs on every shell prompt
+        Some(Command::Standalone(StandaloneCommands::Prompt {
+            symbol,
+            no_color,
+            tmux,
+            path,
+            cache_ttl,
+        })) => {
+            revert_temporary(true);
+            return handle_prompt(symbol, no_color, tmux, path, cache_ttl);
+        }
+        // Completion scripts are loaded on every shell start
+        Some(Command::Standalone(StandaloneCommands::Completions { shell })) => {
+            print!("{}", completions::script(&Cli::command(), shell));
+            return Ok(());
+        }
+        Some(Command::Configured(command)) => Some(command),
+        None => None,
+    };
+    let onboarding = offer_onboarding(command.as_ref());
+    let Some(command) = command.or(onboarding.then_some(Commands::Onboard)) else {
+        Cli::command().print_help()?;
+        return Ok(());
+    };
+
+    // Output meant to be evaluated by a shell gets the warnings on stderr
+    let evaluated = matches!(
+        command,
+        Commands::Export { .. }
+            | Commands::Get { .. }
+            | Commands::Rule {
+                command: RuleCommands::List {
+                    format: OutputFormat::Json
+                }
+            }
+            | Commands::Audit { json: true, .. }
+            | Commands::Infer { json: true, .. }
+            | Commands::VerifyCommits { json: true, .. }
+            | Commands::Shell { print: true, .. }
+            | Commands::List {
+                template: Some(_),
+                ..
+            }
+            | Commands::Show {
+                template: Some(_),
+                ..
+            }
+    );
+    // A mistyped workspace would otherwise look like an empty one
+    if !matches!(command, Commands::Workspace { .. })
+        && let Some(name) = workspace::active()
+    {
+        check_workspace(&name)?;
+    }
 
     // Load all configurations at once (parallel execution)
     let mut config = Config::load()?;
+    if let Some(locale) = config.settings.locale.as_deref().and_then(Locale::from_tag) {
+        i18n::set_locale(locale);
+    }
+    if config.settings.redact {
+        utils::set_redact(true);
+    }
+    if config.settings.plain {
+        term::set_plain(true);
+    }
+    // Identities used with --for or --until-shell-exit that have ended
+    if revert_temporary(evaluated || matches!(command, Commands::Serve { .. })) {
+        config.refresh_global_user()?;
+        config.refresh_project_user()?;
+    }
+    // These commands report file problems themselves or must keep stdio clean
+    let inspects_file = matches!(
+        command,
+        Commands::Config { .. }
+            | Commands::Edit
+            | Commands::Serve { .. }
+            | Commands::SelfUpdate { .. }
+    );
+    if config.file_error.is_some() && !inspects_file {
+        config = recover_config(config)?;
+    }
+    if !inspects_file {
+        for warning in &config.file_warnings {
+            if evaluated {
+                eprintln!("{}", utils::redact(warning));
+                continue;
+            }
+            utils::printer_no_newline(warning, "yellow");
+            println!();
+        }
+    }
 
-    match cli.command {
-        Commands::List => handle_list(&config),
-        Commands::Set {
+    // The command typed runs once onboarding is done
+    if onboarding && !matches!(command, Commands::Onboard) {
+        handle_onboard(&mut config)?;
+    }
+
+    let result = match command {
+        Commands::List {
+            long,
+            wide,
+            template,
+            remotes,
+            tree,
+        } => {
+            let _pager = (!cli.no_pager).then(pager::start).flatten();
+            handle_list(&config, long, wide, template, remotes, tree)
+        }
+        Commands::Set(args) => handle_set(&mut config, *args),
+        Commands::Show {
             group_name,
-            name,
-            email,
-        } => handle_set(&mut config, group_name, name, email),
-        Commands::Use { group_name, global } => handle_use(&mut config, group_name, global),
-        Commands::Delete { group_name } => handle_delete(&mut config, group_name),
+            template,
+        } => handle_show(&config, group_name, template),
+        Commands::Use {
+            group_name,
+            global,
+            local,
+            all_scopes,
+            email_variant,
+            with_workstyle,
+            for_duration,
+            until_shell_exit,
+        } => temporary_until(for_duration, until_shell_exit).and_then(|until| {
+            handle_use(
+                &mut config,
+                group_name,
+                use_scopes(global, local, all_scopes),
+                email_variant,
+                with_workstyle,
+                until,
+            )
+        }),
+        Commands::Unset { global } => handle_unset(&mut config, global),
+        Commands::Delete {
+            group_names,
+            interactive,
+            force,
+        } => {
+            if interactive {
+                handle_delete_interactive(&mut config, force)
+            } else {
+                handle_delete(&mut config, group_names, force)
+            }
+        }
+        Commands::Edit => handle_edit(),
+        Commands::Serve {
+            mcp,
+            addr,
+            token_file,
+            allow_remote,
+        } => handle_serve(mcp, addr, token_file, allow_remote),
+        Commands::Exec {
+            group_name,
+            command,
+        } => handle_exec(&config, group_name, command),
+        Commands::Shell { group_name, print } => handle_shell(&config, group_name, print),
+        Commands::Watch {
+            dirs,
+            notify,
+            interval,
+            depth,
+        } => handle_watch(dirs, notify, interval, depth),
+        Commands::Get {
+            group_name,
+            field,
+            current,
+        } => handle_get(&config, group_name, field, current),
+        Commands::Audit {
+            all_repos,
+            dirs,
+            depth,
+            max_count,
+            json,
+        } => handle_audit(&config, all_repos, dirs, depth, max_count, json),
+        Commands::Onboard => handle_onboard(&mut config),
+        Commands::Infer {
+            max_count,
+            apply,
+            json,
+        } => handle_infer(&mut config, max_count, apply, json),
+        Commands::VerifyCommits {
+            since,
+            max_count,
+            json,
+        } => handle_verify_commits(&config, since, max_count, json),
+        Commands::External(args) => handle_external(&config, args),
+        Commands::Dedupe { force } => handle_dedupe(&mut config, force),
+        Commands::SelfUpdate { check } => handle_self_update(check),
+        Commands::Export { shell_aliases: _ } => handle_export(&config),
+        Commands::Import(args) => handle_import(&mut config, args),
+        Commands::VerifySigning { group_name } => handle_verify_signing(&config, group_name),
+        Commands::Diff { group_name, global } => handle_diff(&config, group_name, global),
+        Commands::Which { scope } => handle_which(&config, scope),
+        Commands::Prune { months, force } => handle_prune(&mut config, months, force),
+        Commands::Batch { input } => handle_batch(&mut config, input),
+        Commands::Snapshot { name } => handle_snapshot(&mut config, name),
+        Commands::Adopt { group_name, rule } => handle_adopt(&mut config, group_name, rule),
+        Commands::MigrateDomain {
+            old,
+            new,
+            apply,
+            force,
+        } => handle_migrate_domain(&mut config, &old, &new, apply, force),
+        Commands::Undo => handle_undo(&config),
+        Commands::History { repo, limit } => {
+            let _pager = (!cli.no_pager).then(pager::start).flatten();
+            handle_history(repo, limit)
+        }
+        Commands::Gc => handle_gc(&config),
+        Commands::Log { limit, json } => {
+            let _pager = (!cli.no_pager && !json).then(pager::start).flatten();
+            handle_log(limit, json)
+        }
+        Commands::Ssh { command } => match command {
+            SshCommands::Setup {
+                group_name,
+                key,
+                host,
+                alias,
+                force,
+            } => handle_ssh_setup(&mut config, group_name, key, host, alias, force),
+            SshCommands::Keygen {
+                group_name,
+                path,
+                print,
+                signing,
+                no_passphrase,
+                force,
+            } => handle_ssh_keygen(
+                &mut config,
+                group_name,
+                path,
+                print,
+                signing,
+                no_passphrase,
+                force,
+            ),
+        },
+        Commands::Workspace { command } => match command {
+            WorkspaceCommands::List => handle_workspace_list(),
+            WorkspaceCommands::New { name } => handle_workspace_new(name),
+            WorkspaceCommands::Use { name } => handle_workspace_use(name),
+        },
+        Commands::Alias { command } => match command {
+            AliasCommands::Add { alias, group_name } => {
+                handle_alias_add(&mut config, alias, group_name)
+            }
+            AliasCommands::Remove { alias } => handle_alias_remove(&mut config, alias),
+            AliasCommands::List => handle_alias_list(&config),
+        },
+        Commands::Template { command } => match command {
+            TemplateCommands::Install { dir } => handle_template_install(dir),
+        },
+        Commands::Rule { command } => match command {
+            RuleCommands::Add {
+                pattern,
+                group_name,
+                branch,
+            } => handle_rule_add(&mut config, pattern, group_name, branch),
+            RuleCommands::Remove { rule } => handle_rule_remove(&mut config, rule),
+            RuleCommands::List { format } => handle_rule_list(&config, format),
+        },
+        Commands::Policy { command } => match command {
+            PolicyCommands::Add { email, remote } => handle_policy_add(&mut config, email, remote),
+            PolicyCommands::Remove { number } => handle_policy_remove(&mut config, number),
+            PolicyCommands::List => handle_policy_list(&config),
+        },
+        Commands::Check => handle_check(&config),
+        Commands::Pair { command } => match command {
+            PairCommands::Start { groups } => handle_pair_start(&mut config, groups),
+            PairCommands::Stop => handle_pair_stop(),
+        },
+        Commands::Hook { command } => match command {
+            HookCommands::Install { hook, template } => {
+                handle_hook_install(&config, hook, template)
+            }
+        },
+        Commands::Config { command } => match command {
+            ConfigCommands::Lint => handle_config_lint(),
+            ConfigCommands::Rollback { steps } => handle_config_rollback(steps),
+        },
+    };
+    if utils::is_dry_run() && result.is_ok() {
+        utils::printer(&t!("dry_run_done"), "yellow");
+        println!();
+    }
+    result
+}
+
+/// Report an unparsable config file and offer to restore it from backup
+///
+/// If the user declines (or no backup exists) the loaded configuration is
+/// returned unchanged, which keeps read-only commands working while `save`
+/// refuses to overwrite the broken file.
+fn recover_config(config: Config) -> Result<Config, Box<dyn std::error::Error>> {
+    let error = config.file_error.clone().unwrap_or_default();
+    utils::printer(&t!("config_load_failed", error), "red");
+
+    let backup = gum_rs::config::backup_path(&utils::get_config_path()?);
+    if !backup.exists() {
+        utils::printer(&t!("no_backup"), "yellow");
+        return Ok(config);
+    }
+
+    if utils::is_non_interactive()
+        || !utils::confirm(&t!("restore_backup_confirm", backup.display()))
+    {
+        utils::printer(&t!("config_untouched"), "yellow");
+        return Ok(config);
     }
+
+    let count = gum_rs::config::restore_backup()?;
+    utils::printer(&t!("restored_groups", count), "green");
+    Config::load()
 }
 
 /// Handle list command
-fn handle_list(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_list(
+    config: &Config,
+    long: bool,
+    wide: bool,
+    template: Option<String>,
+    remotes: bool,
+    tree: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     log::info!("Executing list command");
+    if let Some(template) = template {
+        let template = parse_output_template(&template)?;
+        let mut groups: Vec<(String, UserConfig)> =
+            config.get_all_config_info().into_iter().collect();
+        groups.sort_by(|a, b| a.0.cmp(&b.0));
+        for (group_name, user) in groups {
+            println!("{}", template.render(&group_name, &user));
+        }
+        return Ok(());
+    }
 
+    if let Some(name) = workspace::active() {
+        utils::printer(&t!("workspace_current", name), "cyan");
+    }
     // Use cached configuration directly
     match config.get_using_git_user() {
         Ok(using) => {
-            utils::printer(
-                &format!("Currently using: {} <{}>", using.name, using.email),
-                "yellow",
-            );
+            utils::printer(&t!("currently_using", using.name, using.email), "yellow");
         }
         Err(_) => {
-            utils::printer("Currently using: none", "yellow");
+            utils::printer(&t!("currently_using_none"), "yellow");
         }
     }
 
@@ -74,46 +438,344 @@ fn handle_list(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     if all_config.is_empty() {
         log::info!("No user configuration found");
         // println!("No user configuration found.");
-        print_config_table(&all_config);
+        print!("{}", group_table(None).render());
         return Ok(());
     }
 
     log::info!("Displaying {} configuration groups", all_config.len());
-    print_config_table(&all_config);
+    let details = long.then(|| ListDetails::gather(config, &all_config));
+    let active = active_groups(config);
+    let mut table = group_table(details.as_ref());
+    if tree {
+        for (tag, names) in gum_rs::config::tag_sections(&all_config) {
+            let heading = match tag {
+                Some(tag) => t!("tag_heading", tag, names.len()),
+                None => t!("untagged_heading", names.len()),
+            };
+            table.push_section(&term::paint(&heading, "cyan"));
+            let groups = names.iter().map(|name| (name, &all_config[name]));
+            push_groups(&mut table, config, &active, groups, details.as_ref());
+        }
+    } else {
+        push_groups(&mut table, config, &active, &all_config, details.as_ref());
+    }
+    let max_width = if wide { None } else { term::width() };
+    print!("{}", table.render_to_fit(max_width));
+    let now = utils::now();
+    let mut expired: Vec<&str> = config
+        .groups
+        .iter()
+        .filter(|(_, user)| user.is_expired(now))
+        .map(|(group_name, _)| group_name.as_str())
+        .collect();
+    if !expired.is_empty() {
+        expired.sort();
+        utils::printer(
+            &t!("expired_groups", expired.len(), expired.join(", ")),
+            "yellow",
+        );
+        println!();
+    }
+    if remotes {
+        print_remote_matches(config, max_width);
+    }
+    suggest_rule(config);
 
     Ok(())
 }
 
+/// Print the remotes of the current repository with the rule each matches
+/// and the group it would choose, warning when they disagree
+fn print_remote_matches(config: &Config, max_width: Option<usize>) {
+    if !utils::is_git_repository() {
+        utils::printer(&t!("not_git_repository"), "yellow");
+        println!();
+        return;
+    }
+    let matches = resolve::remote_matches(config, &resolve::remote_urls(None));
+    if matches.is_empty() {
+        utils::printer(&t!("no_remotes"), "yellow");
+        println!();
+        return;
+    }
+    let mut table = Table::new(&["remote", "url", "rule", "group"]);
+    for m in &matches {
+        let (rule, group) = match m.resolution {
+            Some(ref resolution) => (
+                resolution
+                    .rule
+                    .clone()
+                    .unwrap_or_else(|| t!("ssh_alias_rule")),
+                if m.chosen {
+                    format!("{} {}", resolution.group, t!("chosen_marker"))
+                } else {
+                    resolution.group.clone()
+                },
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        table.push(vec![m.remote.clone(), m.url.clone(), rule, group]);
+    }
+    println!();
+    print!("{}", table.render_to_fit(max_width));
+    let conflicting = resolve::conflicting_groups(&matches);
+    if let Some(chosen) = matches.iter().find(|m| m.chosen)
+        && let Some(ref resolution) = chosen.resolution
+        && !conflicting.is_empty()
+    {
+        utils::printer(
+            &t!(
+                "remotes_disagree",
+                conflicting.join(", "),
+                resolution.group,
+                chosen.remote
+            ),
+            "yellow",
+        );
+        println!();
+    }
+}
+
+/// Suggest a rule for a repository that no rule resolves and whose
+/// identity is none of the groups
+fn suggest_rule(config: &Config) {
+    if config.settings.suggest_rules == Some(false) || !utils::is_git_repository() {
+        return;
+    }
+    let identity = gum_rs::config::get_effective_git_user(None);
+    if identity.is_ok_and(|user| !config.match_groups(&user).is_empty()) {
+        return;
+    }
+    if let Some(pattern) = resolve::suggest_rule(config, None) {
+        utils::printer(&t!("rule_suggestion", pattern), "yellow");
+        println!();
+    }
+}
+
+/// Parse an output template given on the command line
+fn parse_output_template(template: &str) -> Result<OutputTemplate, Box<dyn std::error::Error>> {
+    OutputTemplate::parse(template).map_err(|reason| {
+        utils::printer(&t!("invalid_output_template", reason), "red");
+        println!();
+        t!("invalid_output_template", reason).into()
+    })
+}
+
+/// Report a group that does not exist, suggesting similar names
+fn unknown_group(config: &Config, group_name: &str) -> Box<dyn std::error::Error> {
+    utils::printer(&t!("group_not_found", group_name), "red");
+    println!();
+    let similar = config.similar_group_names(group_name);
+    if !similar.is_empty() {
+        utils::printer(&t!("did_you_mean", similar.join(", ")), "yellow");
+        println!();
+    }
+    t!("group_not_found", group_name).into()
+}
+
 /// Handle set command
-fn handle_set(
-    config: &mut Config,
-    group_name: String,
-    name: Option<String>,
-    email: Option<String>,
-) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_set(config: &mut Config, args: SetArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let SetArgs {
+        group_name,
+        name,
+        email,
+        committer_name,
+        committer_email,
+        from_current,
+        global,
+        local,
+        commit_template,
+        http_proxy,
+        ssl_ca_info,
+        credential_helper,
+        sendemail_from,
+        smtp_server,
+        smtp_server_port,
+        smtp_user,
+        smtp_encryption,
+        email_variants,
+        workstyle,
+        url_rewrites,
+        trailers,
+        signoff,
+        inherit,
+        signing_key,
+        pick_gpg_key,
+        signing_format,
+        locked,
+        expires,
+        tags,
+        description,
+        force,
+    } = args;
     log::info!("Executing set command, target group: {}", group_name);
+    let group_name = config.normalize_group_name(&group_name);
+
+    if validate::is_reserved_group_name(&group_name) {
+        log::warn!("Attempting to set reserved group name '{}'", group_name);
+        utils::printer(&t!("reserved_group_name", group_name), "red");
+        println!();
+        return Err(t!("reserved_group_name", group_name).into());
+    }
+
+    check_unlocked(config, &group_name, force)?;
 
-    if group_name == "global" {
-        log::warn!("Attempting to set reserved group name 'global'");
-        utils::printer("Group name cannot be 'global'", "red");
+    if config.get_group(&group_name).is_none()
+        && let Err(reason) = config.check_new_group_name(&group_name)
+    {
+        log::warn!("Rejected group name: {}", reason);
+        utils::printer(&t!("invalid_group_name", reason), "red");
         println!();
-        return Err("Group name cannot be 'global'".into());
+        return Err(t!("invalid_group_name", reason).into());
     }
 
-    if name.is_none() && email.is_none() {
+    let (name, email) = if from_current {
+        let (scope, label) = match (global, local) {
+            (true, _) => (Some("--global"), "global"),
+            (_, true) => (Some("--local"), "local"),
+            _ => (None, "effective"),
+        };
+        let name = gum_rs::config::get_git_config(scope, "user.name", None);
+        let email = gum_rs::config::get_git_config(scope, "user.email", None);
+        if name.is_none() && email.is_none() {
+            log::warn!("No {} git identity to capture", label);
+            utils::printer(&t!("no_current_identity", label), "red");
+            println!();
+            return Err(t!("no_current_identity", label).into());
+        }
+        log::debug!("Captured {} identity: {:?} <{:?}>", label, name, email);
+        (name, email)
+    } else {
+        (name, email)
+    };
+
+    let only_names = commit_template.is_none()
+        && committer_name.is_none()
+        && committer_email.is_none()
+        && http_proxy.is_none()
+        && ssl_ca_info.is_none()
+        && credential_helper.is_none()
+        && sendemail_from.is_none()
+        && smtp_server.is_none()
+        && smtp_server_port.is_none()
+        && smtp_user.is_none()
+        && smtp_encryption.is_none()
+        && inherit.is_none()
+        && email_variants.is_empty()
+        && workstyle.is_empty()
+        && url_rewrites.is_empty()
+        && trailers.is_empty()
+        && signoff.is_none()
+        && signing_key.is_none()
+        && signing_format.is_none()
+        && locked.is_none()
+        && expires.is_none()
+        && tags.is_empty()
+        && description.is_none()
+        && !pick_gpg_key;
+    let (name, email) =
+        if name.is_none() && email.is_none() && only_names && !utils::is_non_interactive() {
+            prompt_user_fields(config.get_group(&group_name))
+        } else {
+            (name, email)
+        };
+
+    if name.is_none() && email.is_none() && only_names {
         log::warn!("Set command did not provide username or email");
-        utils::printer("Must provide at least one of username or email", "red");
+        utils::printer(&t!("missing_user_fields"), "red");
+        println!();
+        if utils::is_non_interactive() {
+            return Err(utils::input_required("set").into());
+        }
+        return Err(t!("missing_user_fields").into());
+    }
+
+    let mut variants = Vec::new();
+    for spec in &email_variants {
+        let Some((variant, address)) = spec.split_once('=') else {
+            utils::printer(&t!("invalid_email_variant", spec), "red");
+            println!();
+            return Err(t!("invalid_email_variant", spec).into());
+        };
+        variants.push((variant.trim().to_string(), address.trim().to_string()));
+    }
+
+    let mut workstyle_settings = Vec::new();
+    for spec in &workstyle {
+        let Some((key, value)) = spec
+            .split_once('=')
+            .filter(|(key, _)| gum_rs::config::is_workstyle_key(key.trim()))
+        else {
+            utils::printer(&t!("invalid_workstyle", spec), "red");
+            println!();
+            return Err(t!("invalid_workstyle", spec).into());
+        };
+        workstyle_settings.push((key.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut rewrites = Vec::new();
+    for spec in &url_rewrites {
+        let Some((prefix, replacement)) = spec
+            .split_once('=')
+            .filter(|(prefix, _)| !prefix.trim().is_empty())
+        else {
+            utils::printer(&t!("invalid_url_rewrite", spec), "red");
+            println!();
+            return Err(t!("invalid_url_rewrite", spec).into());
+        };
+        rewrites.push((prefix.trim().to_string(), replacement.trim().to_string()));
+    }
+
+    // `--trailer ""` clears the list
+    let set_trailers = !trailers.is_empty();
+    let trailers: Vec<String> = trailers
+        .into_iter()
+        .map(|template| template.trim().to_string())
+        .filter(|template| !template.is_empty())
+        .collect();
+    for template in &trailers {
+        if let Err(reason) = validate::check_trailer(template) {
+            utils::printer(&t!("invalid_trailer", reason), "red");
+            println!();
+            return Err(t!("invalid_trailer", reason).into());
+        }
+    }
+
+    if let Some(date) = expires.as_deref().filter(|date| !date.is_empty())
+        && utils::parse_date(date).is_none()
+    {
+        utils::printer(&t!("invalid_expiry", date), "red");
         println!();
-        return Err("Must provide at least one of username or email".into());
+        return Err(t!("invalid_expiry", date).into());
+    }
+
+    let new_emails = email
+        .iter()
+        .chain(committer_email.iter().filter(|e| !e.is_empty()))
+        .chain(
+            variants
+                .iter()
+                .map(|(_, address)| address)
+                .filter(|a| !a.is_empty()),
+        );
+    for e in new_emails {
+        let Err(reason) = validate::check_email(e, &config.settings.allowed_email_domains) else {
+            continue;
+        };
+        if !force {
+            log::warn!("Rejected email: {}", reason);
+            utils::printer(&t!("invalid_email", reason), "red");
+            utils::printer(&t!("use_force"), "yellow");
+            println!();
+            return Err(t!("invalid_email", reason).into());
+        }
+        log::warn!("Accepting invalid email because of --force: {}", reason);
     }
 
     // Get existing configuration or create new one
-    let mut current_user = config.groups.get(&group_name).cloned().unwrap_or_else(|| {
+    let mut current_user = config.get_group(&group_name).cloned().unwrap_or_else(|| {
         log::debug!("Creating new user config for group: {}", group_name);
-        UserConfig {
-            name: String::new(),
-            email: String::new(),
-        }
+        UserConfig::default()
     });
 
     if let Some(n) = name {
@@ -126,152 +788,3558 @@ fn handle_set(
         current_user.email = e;
     }
 
-    config.groups.insert(group_name.clone(), current_user);
+    if let Some(t) = commit_template {
+        log::debug!("Setting commit template: {}", t);
+        current_user.commit_template = Some(t).filter(|t| !t.is_empty());
+    }
+
+    for (label, value, field) in [
+        (
+            "committer name",
+            committer_name,
+            &mut current_user.committer_name,
+        ),
+        (
+            "committer email",
+            committer_email,
+            &mut current_user.committer_email,
+        ),
+        ("http proxy", http_proxy, &mut current_user.http_proxy),
+        ("CA bundle", ssl_ca_info, &mut current_user.ssl_ca_info),
+        (
+            "credential helper",
+            credential_helper,
+            &mut current_user.credential_helper,
+        ),
+        (
+            "sendemail sender",
+            sendemail_from,
+            &mut current_user.sendemail_from,
+        ),
+        ("SMTP server", smtp_server, &mut current_user.smtp_server),
+        (
+            "SMTP port",
+            smtp_server_port,
+            &mut current_user.smtp_server_port,
+        ),
+        ("SMTP user", smtp_user, &mut current_user.smtp_user),
+        (
+            "SMTP encryption",
+            smtp_encryption,
+            &mut current_user.smtp_encryption,
+        ),
+    ] {
+        if let Some(value) = value {
+            log::debug!("Setting {}: {}", label, value);
+            *field = Some(value).filter(|v| !v.is_empty());
+        }
+    }
+
+    for (variant, address) in variants {
+        log::debug!("Setting email variant {}: {}", variant, address);
+        if address.is_empty() {
+            current_user.email_variants.remove(&variant);
+        } else {
+            current_user.email_variants.insert(variant, address);
+        }
+    }
+
+    for (key, value) in workstyle_settings {
+        log::debug!("Setting workstyle {}: {}", key, value);
+        if value.is_empty() {
+            current_user.workstyle.remove(&key);
+        } else {
+            current_user.workstyle.insert(key, value);
+        }
+    }
+
+    for (prefix, replacement) in rewrites {
+        log::debug!("Setting URL rewrite {}: {}", prefix, replacement);
+        if replacement.is_empty() {
+            current_user.url_rewrites.remove(&prefix);
+        } else {
+            current_user.url_rewrites.insert(prefix, replacement);
+        }
+    }
+
+    if set_trailers {
+        log::debug!("Setting trailers: {:?}", trailers);
+        current_user.trailers = trailers;
+    }
+    if signoff.is_some() {
+        log::debug!("Setting signoff: {:?}", signoff);
+        current_user.signoff = signoff;
+    }
+    if let Some(locked) = locked {
+        log::debug!("Setting locked: {}", locked);
+        current_user.locked = locked;
+    }
+    if let Some(date) = expires {
+        log::debug!("Setting expiry: {}", date);
+        current_user.expires = Some(date.trim().to_string()).filter(|d| !d.is_empty());
+    }
+    // `--tag ""` clears the list
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags
+            .into_iter()
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        log::debug!("Setting tags: {:?}", tags);
+        current_user.tags = tags;
+    }
+    if let Some(text) = description {
+        log::debug!("Setting description: {}", text);
+        current_user.description = Some(text.trim().to_string()).filter(|t| !t.is_empty());
+    }
+
+    if let Some(parent) = inherit {
+        let parent = config.normalize_group_name(&parent);
+        log::debug!("Setting parent group: {}", parent);
+        current_user.inherit = Some(parent).filter(|p| !p.is_empty());
+    }
+
+    let previous = config.groups.insert(group_name.clone(), current_user);
+    let resolved = config.resolve_group(&group_name).and_then(|resolved| {
+        if signing_key.is_none() && !pick_gpg_key && signing_format.is_none() {
+            return Ok(resolved);
+        }
+        let Some(user) = config.groups.get_mut(&group_name) else {
+            return Ok(resolved);
+        };
+        // Keys picked from gpg are OpenPGP keys; otherwise the format only
+        // changes when given
+        let format = match signing_format {
+            Some(ref format) => Some(format.clone()).filter(|f| f != "openpgp"),
+            None if pick_gpg_key => None,
+            None => user.signing_format.clone(),
+        };
+        let key = match signing_key {
+            Some(ref key) if key.is_empty() => None,
+            Some(ref key) if format.is_none() => {
+                Some(check_signing_key(key, &resolved.email, force)?)
+            }
+            Some(ref key) => Some(key.clone()),
+            None if pick_gpg_key => Some(pick_signing_key(&resolved.email)?),
+            None => user.signing_key.clone(),
+        };
+        log::debug!("Setting signing key: {:?} ({:?})", key, format);
+        user.signing_key = key;
+        user.signing_format = format;
+        config.resolve_group(&group_name)
+    });
+    // --force only waives the email checks above, never a missing name or email
+    let resolved = resolved.and_then(|resolved| {
+        validate::check_identity_complete(&resolved.name, &resolved.email)
+            .map_err(|reason| anyhow::anyhow!(t!("invalid_identity", reason)))?;
+        Ok(resolved)
+    });
+    let resolved = match resolved {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            // Leave the configuration as it was
+            match previous {
+                Some(previous) => config.groups.insert(group_name.clone(), previous),
+                None => config.groups.remove(&group_name),
+            };
+            utils::printer(&e.to_string(), "red");
+            println!();
+            return Err(e.into());
+        }
+    };
+
+    let duplicates = config.find_identity(&resolved, &group_name);
+    if !duplicates.is_empty() {
+        log::warn!(
+            "Identity of {} already exists in: {}",
+            group_name,
+            duplicates.join(", ")
+        );
+        utils::printer(
+            &t!(
+                "identity_exists",
+                resolved.name,
+                resolved.email,
+                duplicates.join(", "),
+                duplicates[0]
+            ),
+            "yellow",
+        );
+    }
+
     config.save()?;
 
     log::info!("Successfully set group: {}", group_name);
-    utils::printer(&format!("Successfully set {} group", group_name), "green");
+    utils::printer(&t!("set_success", group_name), "green");
     println!();
 
     Ok(())
 }
 
+/// Check that `spec` names a gpg secret key carrying `email`
+///
+/// Returns the key's fingerprint. With `force` the key is accepted as given
+/// when it cannot be checked.
+fn check_signing_key(spec: &str, email: &str, force: bool) -> anyhow::Result<String> {
+    let checked = gpg::list_secret_keys().and_then(|keys| {
+        let key = keys
+            .into_iter()
+            .find(|key| key.matches(spec))
+            .ok_or_else(|| anyhow::anyhow!(t!("gpg_key_not_found", spec)))?;
+        if !key.has_email(email) {
+            anyhow::bail!(t!("gpg_key_email_mismatch", spec, email));
+        }
+        Ok(key.fingerprint)
+    });
+    match checked {
+        Err(e) if force => {
+            log::warn!("Accepting unchecked signing key because of --force: {}", e);
+            Ok(spec.to_string())
+        }
+        checked => checked,
+    }
+}
+
+/// Let the user choose among the gpg secret keys carrying `email`
+///
+/// A single matching key is taken without asking. Returns the fingerprint.
+fn pick_signing_key(email: &str) -> anyhow::Result<String> {
+    let keys: Vec<_> = gpg::list_secret_keys()?
+        .into_iter()
+        .filter(|key| key.has_email(email))
+        .collect();
+    match keys.len() {
+        0 => anyhow::bail!(t!("gpg_no_keys", email)),
+        1 => {
+            utils::printer(&t!("gpg_key_selected", keys[0].key_id), "yellow");
+            return Ok(keys[0].fingerprint.clone());
+        }
+        _ if utils::is_non_interactive() => {
+            utils::printer(&t!("gpg_key_ambiguous", email), "red");
+            return Err(utils::input_required("set --pick-gpg-key"));
+        }
+        _ => {}
+    }
+
+    for (i, key) in keys.iter().enumerate() {
+        let uids: Vec<&str> = key.uids.iter().map(String::as_str).collect();
+        utils::write_stdout(&format!(
+            "  {}) {}  {}  {}\n",
+            i + 1,
+            key.key_id,
+            key.created,
+            uids.join(", ")
+        ));
+    }
+    loop {
+        let answer = utils::prompt(&t!("gpg_pick_key", keys.len()))
+            .ok_or_else(|| anyhow::anyhow!(t!("changes_discarded")))?;
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=keys.len()).contains(&n) => return Ok(keys[n - 1].fingerprint.clone()),
+            _ => continue,
+        }
+    }
+}
+
+/// Ask for username and email, offering the current values as defaults
+///
+/// An empty answer keeps the current value (or leaves the field unset for a
+/// new group).
+fn prompt_user_fields(current: Option<&UserConfig>) -> (Option<String>, Option<String>) {
+    let ask = |label: &str, current: Option<&str>| match current.filter(|v| !v.is_empty()) {
+        Some(value) => utils::prompt(&format!("{} [{}]:", label, value)),
+        None => utils::prompt(&format!("{}:", label)),
+    };
+    let name = ask(i18n::tr("prompt_name"), current.map(|u| u.name.as_str()));
+    let email = ask(i18n::tr("prompt_email"), current.map(|u| u.email.as_str()));
+    (name, email)
+}
+
 /// Handle use command
 fn handle_use(
     config: &mut Config,
-    group_name: String,
-    global: bool,
+    group_name: Option<String>,
+    scopes: Vec<bool>,
+    email_variant: Option<String>,
+    with_workstyle: bool,
+    until: Option<temporary::Until>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let env_name = utils::env_value(utils::NAME_ENV);
+    let env_email = utils::env_value(utils::EMAIL_ENV);
+    let group_name = group_name.or_else(|| {
+        let group = utils::env_value(utils::GROUP_ENV)?;
+        log::info!("Using group {} from {}", group, utils::GROUP_ENV);
+        Some(group)
+    });
+    // GUM_NAME and GUM_EMAIL alone describe an identity outside any group
+    if group_name.is_none()
+        && let (Some(name), Some(email)) = (env_name.clone(), env_email.clone())
+    {
+        log::info!(
+            "Executing use command with the identity from {} and {}",
+            utils::NAME_ENV,
+            utils::EMAIL_ENV
+        );
+        let user = UserConfig {
+            name,
+            email,
+            ..UserConfig::default()
+        };
+        for global in scopes {
+            apply_use(config, None, &user, global, with_workstyle, until)?;
+        }
+        return Ok(());
+    }
+
+    let (group_name, rule) = match group_name {
+        Some(group_name) => (group_name, None),
+        None => {
+            let local = scopes.contains(&false);
+            let Some(resolution) = local.then(|| resolve::resolve(config, None)).flatten() else {
+                utils::printer(&t!("no_group_resolved"), "red");
+                println!();
+                return Err(t!("no_group_resolved").into());
+            };
+            utils::printer(
+                &t!("group_resolved", resolution.group, resolution.reason),
+                "yellow",
+            );
+            (resolution.group, resolution.rule)
+        }
+    };
     log::info!(
-        "Executing use command, target group: {} (global: {})",
+        "Executing use command, target group: {} (global scopes: {:?})",
         group_name,
-        global
+        scopes
     );
+    let group_name = config.normalize_group_name(&group_name);
 
     let all_config = config.get_all_config_info();
-    let user = all_config
-        .get(&group_name)
-        .ok_or_else(|| format!("{} is an invalid group name", group_name))?;
+    let Some(user) = all_config.get(&group_name) else {
+        return Err(unknown_group(config, &group_name));
+    };
+    if config.get_group(&group_name).is_some() {
+        // Surface broken inheritance instead of applying half a group
+        config.inheritance_chain(&group_name)?;
+    }
+    if user.is_expired(utils::now()) {
+        log::warn!("Group {} expired on {:?}", group_name, user.expires);
+        utils::printer(
+            &t!(
+                "group_expired",
+                group_name,
+                user.expires.as_deref().unwrap_or_default()
+            ),
+            "yellow",
+        );
+    }
+    let mut user = match email_variant {
+        Some(ref variant) => user.with_email_variant(variant)?,
+        None => user.clone(),
+    };
+    override_identity(&mut user, &group_name, env_name, env_email);
+    // The identity no longer belongs to the group once overridden
+    let group = all_config[&group_name]
+        .holds_identity(&user)
+        .then_some(group_name.as_str());
+    for global in scopes {
+        apply_use(config, group, &user, global, with_workstyle, until)?;
+    }
+    if let Some(rule) = rule {
+        rules::record_hit(&rule);
+    }
+    Ok(())
+}
+
+/// Replace the name and email of a group with those from `GUM_NAME` and
+/// `GUM_EMAIL`, when set
+fn override_identity(
+    user: &mut UserConfig,
+    group_name: &str,
+    name: Option<String>,
+    email: Option<String>,
+) {
+    if let Some(name) = name {
+        log::info!(
+            "Overriding the name of {} from {}",
+            group_name,
+            utils::NAME_ENV
+        );
+        user.name = name;
+    }
+    if let Some(email) = email {
+        log::info!(
+            "Overriding the email of {} from {}",
+            group_name,
+            utils::EMAIL_ENV
+        );
+        user.email = email;
+    }
+}
+
+/// Scopes `use` applies to, the global one first, as `global` flags
+///
+/// `--all-scopes` adds the local scope only inside a repository, while an
+/// explicit `--local` requires one.
+fn use_scopes(global: bool, local: bool, all_scopes: bool) -> Vec<bool> {
+    let local = if all_scopes {
+        utils::is_git_repository()
+    } else {
+        local || !global
+    };
+    let global = global || all_scopes;
+    [(global, true), (local, false)]
+        .into_iter()
+        .filter(|(wanted, _)| *wanted)
+        .map(|(_, scope)| scope)
+        .collect()
+}
+
+/// When a temporary `use` ends, from `--for` and `--until-shell-exit`
+fn temporary_until(
+    for_duration: Option<String>,
+    until_shell_exit: bool,
+) -> Result<Option<temporary::Until>, Box<dyn std::error::Error>> {
+    if until_shell_exit {
+        let Some(pid) = temporary::shell_pid() else {
+            utils::printer(&t!("until_shell_exit_unsupported"), "red");
+            println!();
+            return Err(t!("until_shell_exit_unsupported").into());
+        };
+        return Ok(Some(temporary::Until::ShellExit(pid)));
+    }
+    let Some(duration) = for_duration else {
+        return Ok(None);
+    };
+    match temporary::parse_duration(&duration) {
+        Ok(seconds) => Ok(Some(temporary::Until::Time(utils::now() + seconds))),
+        Err(reason) => {
+            utils::printer(&reason, "red");
+            println!();
+            Err(reason.into())
+        }
+    }
+}
+
+/// Revert the temporary identities that have ended, reporting each unless
+/// `quiet`; whether any identity was put back
+fn revert_temporary(quiet: bool) -> bool {
+    let reverted = match temporary::revert_due() {
+        Ok(reverted) => reverted,
+        Err(e) => {
+            log::warn!("Cannot revert temporary identities: {}", e);
+            return false;
+        }
+    };
+    let restored = reverted.iter().any(|revert| revert.restored);
+    for revert in reverted {
+        let scope = match revert.pending.before.repo {
+            Some(ref repo) => repo.display().to_string(),
+            None => "global".to_string(),
+        };
+        log::info!(
+            "Temporary identity {} ended in {} (restored: {})",
+            revert.pending.applied,
+            scope,
+            revert.restored
+        );
+        if quiet {
+            continue;
+        }
+        let key = if revert.restored {
+            "temporary_reverted"
+        } else {
+            "temporary_kept"
+        };
+        utils::printer(&t!(key, revert.pending.applied, scope), "yellow");
+    }
+    restored
+}
+
+/// Apply an identity (of `group`, when it belongs to one) for `use`
+///
+/// With `until`, the previous identity comes back once it has passed.
+fn apply_use(
+    config: &mut Config,
+    group: Option<&str>,
+    user: &UserConfig,
+    global: bool,
+    with_workstyle: bool,
+    until: Option<temporary::Until>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if global && config.settings.use_config_only {
+        log::warn!("Refusing global use because use_config_only is enabled");
+        utils::printer(&t!("use_config_only_global"), "red");
+        println!();
+        return Err(t!("use_config_only_global").into());
+    }
 
     // If not global, check if it's a git repository
     if !global && !utils::is_git_repository() {
         log::warn!("Attempting to use local config in non-git directory");
-        utils::printer("Current project is not a git repository", "red");
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    }
+
+    let mut extra_keys = config.url_rewrite_keys();
+    if with_workstyle {
+        extra_keys.extend(config.workstyle_keys());
+    }
+    let before = undo::capture(global, None, &extra_keys);
+    // A temporary identity that cannot be reverted would stay for good
+    if until.is_some()
+        && let Err(ref e) = before
+    {
+        utils::printer(&t!("capture_identity_failed", e), "red");
         println!();
-        return Err("Current project is not a git repository".into());
+        return Err(t!("capture_identity_failed", e).into());
     }
 
     // Set git user configuration
     gum_rs::config::set_git_user(user, global)?;
+    config.apply_group_settings(user, global, None)?;
+    if with_workstyle {
+        log::debug!("Applying workstyle of group {:?}", group);
+        config.apply_workstyle(user, global, None)?;
+    }
+    match before {
+        Ok(before) => {
+            let scheduled = match until {
+                Some(until) => temporary::schedule(temporary::Pending {
+                    until,
+                    applied: temporary::describe(user),
+                    group: group.map(str::to_string),
+                    before: before.clone(),
+                }),
+                // A regular use makes the identity of the scope permanent
+                None => temporary::cancel(&before),
+            };
+            if let Err(e) = scheduled {
+                log::warn!("Cannot update the temporary identities: {}", e);
+            }
+            history::record(
+                history::Action::Use,
+                before,
+                Some(user),
+                group.map(str::to_string),
+            )
+        }
+        Err(e) => log::warn!("Cannot capture the previous identity: {}", e),
+    }
+    if config.settings.use_config_only
+        && let Some(removed) = gum_rs::config::enforce_use_config_only()?
+    {
+        utils::printer(
+            &t!("use_config_only_removed", removed.name, removed.email),
+            "yellow",
+        );
+        config.refresh_global_user()?;
+    }
+    if utils::is_dry_run() {
+        return Ok(());
+    }
 
     // Refresh corresponding cache
     if global {
         config.refresh_global_user()?;
         if let Some(ref global_user) = config.global_user {
             utils::printer(
-                &format!("Global use: {} <{}>", global_user.name, global_user.email),
+                &t!("global_use", global_user.name, global_user.email),
                 "green",
             );
         }
     } else {
         config.refresh_project_user()?;
     }
+    refresh_hook_rules(config);
+    if !user.commit_trailers().is_empty() {
+        ensure_trailers_hook(global);
+    }
 
     // Display currently used configuration
     let using = config.get_using_git_user()?;
-    utils::printer(
-        &format!("Currently using: {} <{}>", using.name, using.email),
-        "yellow",
-    );
+    utils::printer(&t!("currently_using", using.name, using.email), "yellow");
+    match until {
+        Some(temporary::Until::Time(time)) => utils::printer(
+            &t!("temporary_until", utils::format_timestamp(time)),
+            "yellow",
+        ),
+        Some(temporary::Until::ShellExit(_)) => {
+            utils::printer(&t!("temporary_until_shell_exit"), "yellow")
+        }
+        None => {}
+    }
 
-    log::info!("Successfully set git user for group: {}", group_name);
+    log::info!("Successfully set git user for group: {:?}", group);
     println!();
 
     Ok(())
 }
 
-/// Handle delete command
-fn handle_delete(
-    config: &mut Config,
-    group_name: String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    log::info!("Executing delete command, target group: {}", group_name);
-
-    if group_name == "global" {
-        log::warn!("Attempting to delete reserved group 'global'");
-        utils::printer("Cannot delete global", "red");
-        println!();
-        return Err("Cannot delete global".into());
+/// Install the commit-msg hook adding the trailers and sign-off of the group
+/// just applied, or say where it is missing
+fn ensure_trailers_hook(global: bool) {
+    if utils::is_git_repository() {
+        match hooks::ensure_repository_hook(hooks::HookKind::CommitMsg, None) {
+            Ok(Some(hook)) => {
+                utils::printer(&t!("hook_installed", hook.display()), "green");
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("Cannot install the commit-msg hook: {}", e);
+                utils::printer(&t!("trailers_hook_missing", e), "yellow");
+            }
+        }
+    }
+    if global && !hooks::has_template_hook(hooks::HookKind::CommitMsg) {
+        utils::printer(&t!("trailers_hook_hint"), "yellow");
+    }
+}
+
+/// Handle unset command
+fn handle_unset(config: &mut Config, global: bool) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing unset command (global: {})", global);
+    if !global && !utils::is_git_repository() {
+        log::warn!("Attempting to unset local config in non-git directory");
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    }
+
+    let mut extra_keys = config.url_rewrite_keys();
+    extra_keys.extend(config.workstyle_keys());
+    let before = undo::capture(global, None, &extra_keys);
+    gum_rs::config::unset_git_user_in(global, None)?;
+    // A group without settings removes every value left by a group
+    let nothing = UserConfig::default();
+    config.apply_group_settings(&nothing, global, None)?;
+    config.apply_workstyle(&nothing, global, None)?;
+    match before {
+        Ok(before) => history::record(history::Action::Unset, before, None, None),
+        Err(e) => log::warn!("Cannot capture the previous identity: {}", e),
+    }
+    if utils::is_dry_run() {
+        return Ok(());
+    }
+
+    let scope = if global { "global" } else { "local" };
+    utils::printer(&t!("unset_done", scope), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle delete command
+///
+/// Each group is checked on its own and reported; the groups that can be
+/// deleted are, and the config file is saved once at the end. A group
+/// stays while a group inheriting from it stays, so no `inherit` is left
+/// pointing to a deleted group.
+fn handle_delete(
+    config: &mut Config,
+    group_names: Vec<String>,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing delete command, target groups: {:?}", group_names);
+    let mut names: Vec<String> = Vec::new();
+    for group_name in &group_names {
+        let group_name = config.normalize_group_name(group_name);
+        if !names.contains(&group_name) {
+            names.push(group_name);
+        }
     }
 
-    if config.groups.remove(&group_name).is_some() {
+    let mut deleted = Vec::new();
+    let mut failed = Vec::new();
+    for group_name in &names {
+        if !config.groups.contains_key(group_name) && !config.is_system_group(group_name) {
+            log::warn!("Group not found: {}", group_name);
+            failed.push(unknown_group(config, group_name));
+            continue;
+        }
+        if let Err(e) = check_deletable(config, group_name, force) {
+            failed.push(e);
+            continue;
+        }
+        deleted.push(group_name.clone());
+    }
+    for (group_name, children) in config.retain_uninherited(&mut deleted) {
+        failed.push(inherited_group(&group_name, &children));
+    }
+    if !deleted.is_empty() {
+        for group_name in &deleted {
+            config.remove_group(group_name);
+        }
         config.save()?;
-        log::info!("Successfully deleted group: {}", group_name);
-        utils::printer(
-            &format!("Successfully deleted {} group", group_name),
-            "green",
-        );
+        log::info!("Successfully deleted groups: {:?}", deleted);
+        for group_name in &deleted {
+            utils::printer(&t!("delete_success", group_name), "green");
+        }
+        println!();
+    }
+
+    match failed.len() {
+        0 => Ok(()),
+        1 if names.len() == 1 => Err(failed.remove(0)),
+        count => {
+            utils::printer(&t!("delete_partial", count, names.len()), "red");
+            println!();
+            Err(t!("delete_partial", count, names.len()).into())
+        }
+    }
+}
+
+/// Fail when `group_name` cannot be deleted: reserved, read-only or locked
+/// without `force`
+///
+/// Groups inheriting from it are checked on the whole selection with
+/// [`Config::retain_uninherited`].
+fn check_deletable(
+    config: &Config,
+    group_name: &str,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if validate::is_reserved_group_name(group_name) {
+        log::warn!("Attempting to delete reserved group '{}'", group_name);
+        utils::printer(&t!("cannot_delete", group_name), "red");
+        println!();
+        return Err(t!("cannot_delete", group_name).into());
+    }
+
+    if config.is_system_group(group_name) {
+        log::warn!("Attempting to delete system group: {}", group_name);
+        utils::printer(&t!("system_group_delete", group_name), "red");
+        println!();
+        return Err(t!("system_group_readonly", group_name).into());
+    }
+
+    check_unlocked(config, group_name, force)
+}
+
+/// Fail when the group is locked and `force` is not given, pointing to
+/// `--force`
+fn check_unlocked(
+    config: &Config,
+    group_name: &str,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    config.check_unlocked(group_name, force).map_err(locked)
+}
+
+/// Report a change refused because a group is locked
+fn locked(e: anyhow::Error) -> Box<dyn std::error::Error> {
+    utils::printer(&e.to_string(), "red");
+    utils::printer(&t!("use_force_locked"), "yellow");
+    println!();
+    e.into()
+}
+
+/// Report that `group_name` is kept because `children` inherit from it
+fn inherited_group(group_name: &str, children: &[String]) -> Box<dyn std::error::Error> {
+    log::warn!("Attempting to delete inherited group: {}", group_name);
+    utils::printer(
+        &t!("group_inherited", group_name, children.join(", ")),
+        "red",
+    );
+    println!();
+    t!("group_inherited", group_name, children.join(", ")).into()
+}
+
+/// Handle delete --interactive command
+///
+/// Lists the groups of the config file with their identities, asks for the
+/// numbers to delete and confirms the whole selection once.
+fn handle_delete_interactive(
+    config: &mut Config,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing interactive delete command");
+    let mut names: Vec<String> = config.groups.keys().cloned().collect();
+    names.sort();
+    if names.is_empty() {
+        utils::printer(&t!("no_groups_to_delete"), "yellow");
+        println!();
+        return Ok(());
+    }
+    if utils::is_non_interactive() {
+        return Err(utils::input_required("delete --interactive").into());
+    }
+
+    let width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let print_list = |selected: &[usize]| {
+        println!();
+        for (i, group_name) in names.iter().enumerate() {
+            let user = &config.groups[group_name];
+            let mark = if selected.contains(&i) { "x" } else { " " };
+            let locked = if user.locked {
+                format!(" {}", t!("locked_marker"))
+            } else {
+                String::new()
+            };
+            utils::write_stdout(&format!(
+                "  [{}] {:>2}) {:<width$}  {} <{}>{}\n",
+                mark,
+                i + 1,
+                group_name,
+                user.name,
+                user.email,
+                locked
+            ));
+        }
+        println!();
+    };
+    // Each answer toggles the groups it names; an empty one ends the selection
+    let mut selected: Vec<usize> = Vec::new();
+    loop {
+        print_list(&selected);
+        let Some(answer) = utils::prompt(&t!("select_groups_to_delete")) else {
+            break;
+        };
+        match utils::parse_selection(&answer, names.len()) {
+            Ok(toggled) => {
+                for i in toggled {
+                    match selected.iter().position(|&s| s == i) {
+                        Some(pos) => {
+                            selected.remove(pos);
+                        }
+                        None => selected.push(i),
+                    }
+                }
+            }
+            Err(reason) => utils::printer(&reason, "red"),
+        }
+    }
+    selected.sort_unstable();
+    if selected.is_empty() {
+        utils::printer(&t!("skipped"), "white");
+        println!();
+        return Ok(());
+    }
+    let chosen: Vec<String> = selected.iter().map(|&i| names[i].clone()).collect();
+    for group_name in &chosen {
+        check_deletable(config, group_name, force)?;
+    }
+    let mut deletable = chosen.clone();
+    if let Some((group_name, children)) = config.retain_uninherited(&mut deletable).first() {
+        return Err(inherited_group(group_name, children));
+    }
+
+    if !utils::confirm(&t!("delete_selected_confirm", chosen.len())) {
+        utils::printer(&t!("skipped"), "white");
         println!();
-        Ok(())
+        return Ok(());
+    }
+    for group_name in &chosen {
+        config.remove_group(group_name);
+    }
+    config.save()?;
+    log::info!("Deleted groups: {:?}", chosen);
+    utils::printer(
+        &t!("deleted_groups", chosen.len(), chosen.join(", ")),
+        "green",
+    );
+    println!();
+    Ok(())
+}
+
+/// Handle edit command
+fn handle_edit() -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing edit command");
+    if utils::is_non_interactive() {
+        return Err(utils::input_required("edit").into());
+    }
+
+    let config_path = utils::get_config_path()?;
+    let original = if config_path.exists() {
+        std::fs::read_to_string(&config_path)?
     } else {
-        log::warn!("Group not found: {}", group_name);
-        utils::printer(&format!("{} group not found", group_name), "red");
+        "{\n  \"groups\": {}\n}\n".to_string()
+    };
+
+    // Keep a .jsonc extension so editors pick JSON highlighting
+    let mut edit_path = config_path.clone().into_os_string();
+    edit_path.push(".edit.jsonc");
+    let edit_path = std::path::PathBuf::from(edit_path);
+    if let Some(parent) = edit_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&edit_path, &original)?;
+
+    let result = edit_until_valid(&edit_path);
+    let _ = std::fs::remove_file(&edit_path);
+    let Some(content) = result? else {
+        utils::printer(&t!("changes_discarded"), "yellow");
+        println!();
+        return Ok(());
+    };
+
+    if content == original {
+        utils::printer(&t!("no_changes"), "white");
+        println!();
+        return Ok(());
+    }
+
+    gum_rs::config::write_config_content(&config_path, &content)?;
+    log::info!("Saved edited configuration to {}", config_path.display());
+    utils::printer(&t!("saved", config_path.display()), "green");
+    println!();
+    Ok(())
+}
+
+/// Run the editor on `path` until the content validates or the user gives up
+///
+/// Returns the valid content, or `None` when the user chose to discard it.
+fn edit_until_valid(path: &std::path::Path) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    loop {
+        utils::open_editor(path)?;
+        let content = std::fs::read_to_string(path)?;
+        let issues = validate::lint(&content);
+
+        if !issues.iter().any(|issue| issue.severity == Severity::Error) {
+            for issue in &issues {
+                utils::printer_no_newline(&issue.to_string(), "yellow");
+                println!();
+            }
+            return Ok(Some(content));
+        }
+
         println!();
-        Err(format!("{} group not found", group_name).into())
+        for issue in &issues {
+            let color = match issue.severity {
+                Severity::Error => "red",
+                Severity::Warning => "yellow",
+            };
+            utils::printer_no_newline(&issue.to_string(), color);
+            println!();
+        }
+        let answer = utils::prompt(&t!("edit_invalid"));
+        match answer.as_deref().map(str::to_lowercase).as_deref() {
+            Some("r") | Some("reopen") => continue,
+            _ => return Ok(None),
+        }
     }
 }
-fn print_config_table(all_config: &HashMap<String, UserConfig>) {
-    let mut max_group = 10;
-    let mut max_name = 4;
-    let mut max_email = 5;
 
-    for (group_name, user) in all_config {
-        max_group = max_group.max(group_name.len());
-        max_name = max_name.max(user.name.len());
-        max_email = max_email.max(user.email.len());
+/// Handle dedupe command
+fn handle_dedupe(config: &mut Config, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing dedupe command");
+
+    let sets = config.duplicate_identities();
+    if sets.is_empty() {
+        utils::printer(&t!("no_duplicates"), "green");
+        println!();
+        return Ok(());
+    }
+
+    if utils::is_non_interactive() {
+        return Err(utils::input_required("dedupe").into());
     }
 
-    println!(
-        "┌{0:─<1$}┬{0:─<2$}┬{0:─<3$}┐",
-        "─",
-        max_group + 2,
-        max_name + 2,
-        max_email + 2
+    let mut removed = Vec::new();
+    let mut merges = Vec::new();
+    for set in sets {
+        let user = config.groups[&set[0]].clone();
+        utils::printer(&t!("identity_stored_in", user.name, user.email), "yellow");
+        for (i, group_name) in set.iter().enumerate() {
+            let group = config
+                .resolve_group(group_name)
+                .unwrap_or_else(|_| config.groups[group_name].clone());
+            utils::write_stdout(&format!(
+                "  {}) {}  {} <{}>\n",
+                i + 1,
+                group_name,
+                group.name,
+                group.email
+            ));
+        }
+
+        let answer = utils::prompt(&t!("keep_which", set.len()));
+        let Some(keep) = answer
+            .and_then(|a| a.parse::<usize>().ok())
+            .filter(|n| (1..=set.len()).contains(n))
+        else {
+            utils::printer(&t!("skipped"), "white");
+            continue;
+        };
+
+        // Aliases, rules and inheriting groups now point to the survivor
+        let survivor = set[keep - 1].clone();
+        let merged: Vec<String> = set.into_iter().filter(|name| *name != survivor).collect();
+        if let Err(e) = config.merge_groups(&survivor, &merged, force) {
+            locked(e);
+            continue;
+        }
+        removed.extend(merged.iter().cloned());
+        log::info!("Kept group {}", survivor);
+        merges.push((survivor, merged));
+    }
+
+    if removed.is_empty() {
+        println!();
+        return Ok(());
+    }
+
+    config.save()?;
+    utils::printer(
+        &t!("deleted_groups", removed.len(), removed.join(", ")),
+        "green",
     );
-    println!(
-        "│ {:<width_g$} │ {:<width_n$} │ {:<width_e$} │",
-        "group-name",
-        "name",
-        "email",
-        width_g = max_group,
-        width_n = max_name,
-        width_e = max_email
+    // Repositories recorded with a merged group now list the survivor
+    for (survivor, merged) in merges {
+        match history::retarget(&merged, &survivor) {
+            Ok(0) => {}
+            Ok(count) => utils::printer(&t!("history_retargeted", count, survivor), "green"),
+            Err(e) => log::warn!("Cannot update the history: {}", e),
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// Seconds a tmux segment is cached when `--cache-ttl` is not given
+const TMUX_DEFAULT_CACHE_TTL: u64 = 5;
+
+/// Handle prompt command
+fn handle_prompt(
+    symbol: Option<String>,
+    no_color: bool,
+    tmux: bool,
+    path: Option<std::path::PathBuf>,
+    cache_ttl: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let style = if tmux {
+        PromptStyle::Tmux
+    } else if no_color || term::is_plain() {
+        PromptStyle::Plain
+    } else {
+        PromptStyle::Ansi
+    };
+    let cache_ttl = cache_ttl
+        .or(tmux.then_some(TMUX_DEFAULT_CACHE_TTL))
+        .filter(|ttl| *ttl > 0)
+        .map(std::time::Duration::from_secs);
+    let options = PromptOptions {
+        symbol: symbol.unwrap_or_else(|| prompt::DEFAULT_SYMBOL.to_string()),
+        style,
+        dir: path,
+        cache_ttl,
+    };
+    let segment = prompt::render(&options)?;
+    if !segment.is_empty() {
+        println!("{}", segment);
+    }
+    Ok(())
+}
+
+/// Handle serve command
+fn handle_serve(
+    mcp: bool,
+    addr: Option<SocketAddr>,
+    token_file: Option<PathBuf>,
+    allow_remote: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing serve command (mcp: {}, addr: {:?})", mcp, addr);
+    if mcp {
+        McpServer::new().serve()?;
+    } else if let Some(addr) = addr {
+        let token_file = match token_file {
+            Some(path) => path,
+            None => http::token_path()?,
+        };
+        let token = http::load_or_create_token(&token_file)?;
+        let server = http::HttpServer::bind(addr, token, allow_remote)?;
+        eprintln!(
+            "{}",
+            t!(
+                "serve_listening",
+                server.local_addr()?,
+                token_file.display()
+            )
+        );
+        server.serve()?;
+    }
+    Ok(())
+}
+
+/// Handle exec command
+fn handle_exec(
+    config: &Config,
+    group_name: Option<String>,
+    command: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let env_name = utils::env_value(utils::NAME_ENV);
+    let env_email = utils::env_value(utils::EMAIL_ENV);
+    let group_name = group_name.or_else(|| utils::env_value(utils::GROUP_ENV));
+    let (group_name, user) = match group_name {
+        Some(group_name) => {
+            log::info!("Executing exec command as group {}", group_name);
+            let group_name = config.normalize_group_name(&group_name);
+            if config.get_group(&group_name).is_none() {
+                return Err(unknown_group(config, &group_name));
+            }
+            let mut user = config.resolve_group(&group_name)?;
+            override_identity(&mut user, &group_name, env_name, env_email);
+            (group_name, user)
+        }
+        // GUM_NAME and GUM_EMAIL alone describe an identity outside any group
+        None => match (env_name, env_email) {
+            (Some(name), Some(email)) => {
+                log::info!(
+                    "Executing exec command with the identity from {} and {}",
+                    utils::NAME_ENV,
+                    utils::EMAIL_ENV
+                );
+                let user = UserConfig {
+                    name,
+                    email,
+                    ..UserConfig::default()
+                };
+                (utils::NAME_ENV.to_string(), user)
+            }
+            _ => {
+                utils::printer(&t!("exec_no_group"), "red");
+                println!();
+                return Err(t!("exec_no_group").into());
+            }
+        },
+    };
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| t!("missing_subcommand"))?;
+
+    let existing = std::env::var("GIT_CONFIG_COUNT")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0);
+    let mut child = std::process::Command::new(program);
+    child
+        .args(args)
+        .envs(export::identity_environment(&user))
+        .envs(export::config_environment(&user, existing));
+    log::debug!("Running {} as {}", program, group_name);
+    let status = utils::interactive(&mut child).map_err(|e| t!("exec_failed", program, e))?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Handle shell command
+fn handle_shell(
+    config: &Config,
+    group_name: String,
+    print: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing shell command as group {}", group_name);
+    let group_name = config.normalize_group_name(&group_name);
+    if config.get_group(&group_name).is_none() {
+        return Err(unknown_group(config, &group_name));
+    }
+    let user = config.resolve_group(&group_name)?;
+    // A nested session numbers its settings after the outer ones
+    let existing = std::env::var("GIT_CONFIG_COUNT")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0);
+    if print {
+        print!("{}", export::shell_exports(&group_name, &user, existing));
+        return Ok(());
+    }
+
+    let program = std::env::var_os("SHELL")
+        .or_else(|| cfg!(windows).then(|| std::env::var_os("COMSPEC")).flatten())
+        .unwrap_or_else(|| if cfg!(windows) { "cmd.exe" } else { "/bin/sh" }.into());
+    let mut child = std::process::Command::new(&program);
+    child
+        .envs(export::identity_environment(&user))
+        .envs(export::config_environment(&user, existing))
+        .env(export::SHELL_GROUP_ENV, &group_name);
+    utils::printer(
+        &t!("shell_started", group_name, user.name, user.email),
+        "green",
     );
-    println!(
-        "├{0:─<1$}┼{0:─<2$}┼{0:─<3$}┤",
-        "─",
-        max_group + 2,
-        max_name + 2,
-        max_email + 2
+    log::debug!("Running {:?} as {}", program, group_name);
+    let status = utils::interactive(&mut child)
+        .map_err(|e| t!("exec_failed", program.to_string_lossy(), e))?;
+    utils::printer(&t!("shell_exited", group_name), "yellow");
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Handle watch command
+///
+/// Repositories whose remote resolves to no group yet are checked again on
+/// later rounds, up to [`watch::RESOLVE_GRACE`] after they appeared.
+fn handle_watch(
+    dirs: Vec<PathBuf>,
+    notify: bool,
+    interval: u64,
+    depth: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!(
+        "Executing watch command for {:?} (notify: {})",
+        dirs,
+        notify
     );
+    for dir in &dirs {
+        if !dir.is_dir() {
+            utils::printer(&t!("watch_not_directory", dir.display()), "red");
+            println!();
+            return Err(t!("watch_not_directory", dir.display()).into());
+        }
+    }
+    let mut watcher = watch::Watcher::new(dirs, depth);
+    utils::printer(&t!("watch_started"), "yellow");
+    println!();
 
-    for (group_name, user) in all_config {
-        println!(
-            "│ {:<width_g$} │ {:<width_n$} │ {:<width_e$} │",
-            group_name,
+    let mut pending: Vec<(PathBuf, Instant)> = Vec::new();
+    loop {
+        let now = Instant::now();
+        pending.extend(watcher.poll().into_iter().map(|repo| (repo, now)));
+        pending.retain(|(repo, since)| {
+            !watch_repository(repo, since.elapsed() >= watch::RESOLVE_GRACE, notify)
+        });
+        std::thread::sleep(Duration::from_secs(interval.max(1)));
+    }
+}
+
+/// Apply (or report) the resolved group of a new repository
+///
+/// Returns whether the repository is settled; `false` to check it again.
+fn watch_repository(repo: &Path, give_up: bool, notify: bool) -> bool {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            log::warn!("Cannot load the configuration: {}", e);
+            return false;
+        }
+    };
+    if let Ok(user) = gum_rs::config::get_git_user_in(false, Some(repo)) {
+        log::info!(
+            "{} already uses {} <{}>",
+            repo.display(),
             user.name,
-            user.email,
-            width_g = max_group,
-            width_n = max_name,
-            width_e = max_email
+            user.email
+        );
+        return true;
+    }
+    let Some(resolution) = resolve::resolve(&config, Some(repo)) else {
+        if give_up {
+            utils::printer(&t!("watch_unresolved", repo.display()), "yellow");
+        }
+        return give_up;
+    };
+    if notify {
+        utils::printer(
+            &t!(
+                "watch_resolved",
+                repo.display(),
+                resolution.group,
+                resolution.reason
+            ),
+            "yellow",
         );
+        return true;
+    }
+    let args = serde_json::json!({"group": resolution.group, "path": repo});
+    match service::apply_profile(&args) {
+        Ok(_) => {
+            if let Some(ref rule) = resolution.rule {
+                rules::record_hit(rule);
+            }
+            utils::printer(
+                &t!(
+                    "watch_applied",
+                    repo.display(),
+                    resolution.group,
+                    resolution.reason
+                ),
+                "green",
+            )
+        }
+        Err(e) => utils::printer(&t!("watch_apply_failed", repo.display(), e), "red"),
+    }
+    true
+}
+
+/// Handle external subcommand
+///
+/// Runs `gum-<name>` from PATH with the remaining arguments and exits with
+/// its exit code.
+fn handle_external(config: &Config, args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let (subcommand, rest) = args.split_first().ok_or_else(|| t!("missing_subcommand"))?;
+    log::info!("Executing external subcommand: {}", subcommand);
+
+    let Some(program) = plugin::find_plugin(subcommand) else {
+        let candidates = gum_rs::cli::commands_starting_with(subcommand);
+        if !candidates.is_empty() {
+            return Err(t!("ambiguous_subcommand", subcommand, candidates.join(", ")).into());
+        }
+        return Err(t!(
+            "unknown_subcommand",
+            subcommand,
+            format!("{}{}", plugin::PLUGIN_PREFIX, subcommand)
+        )
+        .into());
+    };
+    log::debug!("Running plugin {}", program.display());
+
+    let mut command = std::process::Command::new(&program);
+    command
+        .args(rest)
+        .env(utils::CONFIG_ENV, utils::get_config_path()?)
+        .env("GUM_VERSION", env!("CARGO_PKG_VERSION"));
+    if let Some(name) = workspace::active() {
+        command.env(workspace::WORKSPACE_ENV, name);
+    }
+    if let Ok(exe) = std::env::current_exe() {
+        command.env("GUM_EXE", exe);
+    }
+    if let Ok(using) = config.get_using_git_user() {
+        command
+            .env("GUM_CURRENT_NAME", &using.name)
+            .env("GUM_CURRENT_EMAIL", &using.email);
+        if let Some(group_name) = config.find_identity(using, "").first() {
+            command.env("GUM_CURRENT_GROUP", group_name);
+        }
+    }
+
+    let status =
+        utils::interactive(&mut command).map_err(|e| t!("plugin_failed", program.display(), e))?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Handle ssh setup command
+fn handle_ssh_setup(
+    config: &mut Config,
+    group_name: String,
+    key: String,
+    host: String,
+    alias: Option<String>,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing ssh setup command, target group: {}", group_name);
+    let group_name = config.normalize_group_name(&group_name);
+    if !config.groups.contains_key(&group_name) {
+        let key = if config.is_system_group(&group_name) {
+            "system_group_readonly"
+        } else {
+            "group_not_found"
+        };
+        utils::printer(&t!(key, group_name), "red");
+        println!();
+        return Err(t!(key, group_name).into());
+    }
+    check_unlocked(config, &group_name, force)?;
+    if !std::path::Path::new(&utils::expand_home(&key)).exists() {
+        utils::printer(&t!("ssh_key_missing", key), "yellow");
+    }
+
+    let alias = alias.unwrap_or_else(|| ssh::default_alias(&host, &group_name));
+    let path = ssh::setup_host(&alias, &host, &key)?;
+    if let Some(group) = config.groups.get_mut(&group_name) {
+        group.ssh_alias = Some(alias.clone());
+        group.ssh_key = Some(key);
+    }
+    config.save()?;
+    utils::printer(
+        &t!("ssh_host_written", alias, path.display(), host),
+        "green",
+    );
+    println!();
+    Ok(())
+}
+
+/// Handle ssh keygen command
+fn handle_ssh_keygen(
+    config: &mut Config,
+    group_name: String,
+    path: Option<String>,
+    print: bool,
+    signing: bool,
+    no_passphrase: bool,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing ssh keygen command, target group: {}", group_name);
+    let group_name = config.normalize_group_name(&group_name);
+    if !config.groups.contains_key(&group_name) {
+        let key = if config.is_system_group(&group_name) {
+            "system_group_readonly"
+        } else {
+            "group_not_found"
+        };
+        utils::printer(&t!(key, group_name), "red");
+        println!();
+        return Err(t!(key, group_name).into());
+    }
+    check_unlocked(config, &group_name, force)?;
+
+    let key = path.unwrap_or_else(|| ssh::default_key_path(&group_name));
+    let comment = config.resolve_group(&group_name)?.email;
+    let public_key = match ssh::keygen(&key, &comment, no_passphrase) {
+        Ok(public_key) => public_key,
+        Err(e) => {
+            utils::printer(&e.to_string(), "red");
+            println!();
+            return Err(e.into());
+        }
+    };
+
+    if let Some(group) = config.groups.get_mut(&group_name) {
+        group.ssh_key = Some(key.clone());
+        if signing {
+            group.signing_key = Some(format!("{}.pub", key));
+            group.signing_format = Some("ssh".to_string());
+        }
+    }
+    config.save()?;
+    utils::printer(&t!("ssh_key_generated", key, group_name), "green");
+    if print {
+        println!("{}", public_key);
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle undo command
+fn handle_undo(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing undo command");
+    let entries = history::load()?;
+    let Some(target) = history::undo_target(&entries) else {
+        utils::printer(&t!("nothing_to_undo"), "yellow");
+        println!();
+        return Ok(());
+    };
+
+    let snapshot = &target.before;
+    // Keys beyond the managed ones, e.g. a workstyle applied with the use
+    let managed = undo::managed_keys();
+    let extra_keys: Vec<String> = snapshot
+        .values
+        .iter()
+        .map(|(key, _)| key.clone())
+        .filter(|key| !managed.contains(&key.as_str()))
+        .collect();
+    let current = undo::capture(snapshot.global, snapshot.repo.as_deref(), &extra_keys)?;
+    undo::restore(snapshot)?;
+    let restored = snapshot.identity();
+    let group = restored
+        .as_ref()
+        .and_then(|user| config.match_groups(user).into_iter().next());
+    history::record(
+        history::Action::Undo,
+        current,
+        restored.as_ref(),
+        group.clone(),
+    );
+
+    match restored {
+        Some(user) => {
+            let who = match group {
+                Some(group) => format!("{} <{}> ({})", user.name, user.email, group),
+                None => format!("{} <{}>", user.name, user.email),
+            };
+            utils::printer(&t!("undo_restored", target.scope(), who), "green")
+        }
+        None => utils::printer(&t!("undo_unset", target.scope()), "green"),
     }
+    println!();
+    Ok(())
+}
 
-    println!(
-        "└{0:─<1$}┴{0:─<2$}┴{0:─<3$}┘",
-        "─",
-        max_group + 2,
-        max_name + 2,
-        max_email + 2
+/// Handle prune command
+fn handle_prune(
+    config: &mut Config,
+    months: u32,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing prune command (months: {})", months);
+    let entries = history::load()?;
+    let now = utils::now();
+    let cutoff = now - i64::from(months) * prune::MONTH_SECONDS;
+    let in_use = prune::groups_in_use(config, &entries);
+    let candidates = prune::candidates(
+        config,
+        &prune::last_used(&entries),
+        &in_use,
+        cutoff,
+        now,
+        force,
     );
+    if candidates.is_empty() {
+        utils::printer(&t!("prune_nothing", months), "green");
+        println!();
+        return Ok(());
+    }
+
+    utils::printer(&t!("prune_candidates", candidates.len(), months), "yellow");
+    for candidate in &candidates {
+        let last_used = match candidate.last_used {
+            Some(timestamp) => utils::format_timestamp(timestamp),
+            None => t!("prune_never"),
+        };
+        let reason = match candidate.expired {
+            Some(ref date) => t!("prune_expired", date),
+            None => t!("prune_last_used", last_used),
+        };
+        println!("  {}  ({})", candidate.group, reason);
+    }
+    println!();
+
+    if utils::is_non_interactive() {
+        return Err(utils::input_required("prune").into());
+    }
+
+    let mut removed = Vec::new();
+    for candidate in &candidates {
+        if utils::confirm(&t!("prune_confirm", candidate.group))
+            && config.remove_group(&candidate.group).is_some()
+        {
+            removed.push(candidate.group.as_str());
+        }
+    }
+    if removed.is_empty() {
+        utils::printer(&t!("prune_kept"), "yellow");
+        println!();
+        return Ok(());
+    }
+    config.save()?;
+    log::info!("Pruned groups: {:?}", removed);
+    utils::printer(&t!("pruned", removed.join(", ")), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle batch command
+fn handle_batch(config: &mut Config, input: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing batch command from {}", input.display());
+    let content = if input.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(&input)?
+    };
+    let result = batch::parse(&content).and_then(|operations| {
+        batch::apply(config, &operations)?;
+        Ok(operations)
+    });
+    let operations = match result {
+        Ok(operations) => operations,
+        Err(e) => {
+            utils::printer(&t!("batch_failed", e), "red");
+            println!();
+            return Err(t!("batch_failed", e).into());
+        }
+    };
+    config.save()?;
+    println!();
+    for operation in &operations {
+        utils::printer_no_newline(&operation.describe(), "green");
+        println!();
+    }
+    utils::printer(&t!("batch_applied", operations.len()), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle snapshot command
+fn handle_snapshot(
+    config: &mut Config,
+    name: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing snapshot command");
+    let prefix = name.unwrap_or_else(|| {
+        let stamp = utils::format_timestamp(utils::now())
+            .replace(['-', ':'], "")
+            .replace(' ', "-");
+        format!("snapshot-{}", stamp)
+    });
+
+    let mut scopes = vec![("global", "--global")];
+    if utils::is_git_repository() {
+        scopes.push(("local", "--local"));
+    }
+    let mut snapshot = Vec::new();
+    for (label, scope) in scopes {
+        let Some(user) = gum_rs::config::git_scope_as_group(scope, None) else {
+            log::debug!("No {} identity to snapshot", label);
+            continue;
+        };
+        let group_name = format!("{}-{}", prefix, label);
+        if let Err(reason) = config.check_new_group_name(&group_name) {
+            utils::printer(&t!("invalid_group_name", reason), "red");
+            println!();
+            return Err(t!("invalid_group_name", reason).into());
+        }
+        if config.get_group(&group_name).is_some() {
+            utils::printer(&t!("snapshot_exists", group_name), "red");
+            println!();
+            return Err(t!("snapshot_exists", group_name).into());
+        }
+        snapshot.push((group_name, user));
+    }
+    if snapshot.is_empty() {
+        utils::printer(&t!("snapshot_empty"), "yellow");
+        println!();
+        return Ok(());
+    }
+
+    for (group_name, user) in &snapshot {
+        config.groups.insert(group_name.clone(), user.clone());
+    }
+    config.save()?;
+    println!();
+    for (group_name, user) in &snapshot {
+        log::info!("Saved snapshot group {}", group_name);
+        utils::printer_no_newline(
+            &t!("snapshot_saved", group_name, user.name, user.email),
+            "green",
+        );
+        println!();
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle adopt command
+fn handle_adopt(
+    config: &mut Config,
+    group_name: Option<String>,
+    rule: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing adopt command");
+    if !utils::is_git_repository() {
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    }
+    let Some(user) = gum_rs::config::git_scope_as_group("--local", None) else {
+        utils::printer(&t!("adopt_no_identity"), "red");
+        println!();
+        return Err(t!("adopt_no_identity").into());
+    };
+    if let Err(reason) = validate::check_identity(
+        &user.name,
+        &user.email,
+        &config.settings.allowed_email_domains,
+    ) {
+        utils::printer(&t!("invalid_identity", reason), "red");
+        println!();
+        return Err(t!("invalid_identity", reason).into());
+    }
+
+    let matching = config.match_groups(&user);
+    let group_name = match group_name {
+        Some(group_name) => config.normalize_group_name(&group_name),
+        None => matching
+            .first()
+            .cloned()
+            .unwrap_or_else(|| config.suggest_group_name(&user)),
+    };
+    let created = match config.get_group(&group_name) {
+        Some(_) if matching.contains(&group_name) => false,
+        Some(_) => {
+            utils::printer(&t!("adopt_group_differs", group_name), "red");
+            println!();
+            return Err(t!("adopt_group_differs", group_name).into());
+        }
+        None => {
+            if let Err(reason) = config.check_new_group_name(&group_name) {
+                utils::printer(&t!("invalid_group_name", reason), "red");
+                println!();
+                return Err(t!("invalid_group_name", reason).into());
+            }
+            config.groups.insert(group_name.clone(), user.clone());
+            true
+        }
+    };
+
+    let pattern = if rule {
+        resolve::suggest_rule(config, None)
+    } else {
+        None
+    };
+    if let Some(ref pattern) = pattern {
+        config.hosts.insert(pattern.clone(), group_name.clone());
+    }
+    if created || pattern.is_some() {
+        config.save()?;
+    }
+
+    match undo::capture(false, None, &[]) {
+        Ok(before) => history::record(
+            history::Action::Adopt,
+            before,
+            Some(&user),
+            Some(group_name.clone()),
+        ),
+        Err(e) => log::warn!("Cannot record the adopted repository: {}", e),
+    }
+
+    let message = if created {
+        "adopt_created"
+    } else {
+        "adopt_matched"
+    };
+    utils::printer(&t!(message, group_name, user.name, user.email), "green");
+    if let Some(pattern) = pattern {
+        utils::printer(&t!("rule_added", pattern, group_name), "green");
+    } else if rule {
+        utils::printer(&t!("adopt_no_rule"), "yellow");
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle migrate-domain command
+fn handle_migrate_domain(
+    config: &mut Config,
+    old: &str,
+    new: &str,
+    apply: bool,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing migrate-domain command: {} -> {}", old, new);
+    let changes = config.migrate_domain(old, new, force).map_err(locked)?;
+    if changes.is_empty() {
+        utils::printer(&t!("no_domain_emails", old), "yellow");
+        println!();
+        return Ok(());
+    }
+    for (_, _, email) in &changes {
+        if let Err(reason) = validate::check_email(email, &config.settings.allowed_email_domains) {
+            log::warn!("Rejected migrated email: {}", reason);
+            utils::printer(&t!("invalid_email", reason), "red");
+            println!();
+            return Err(t!("invalid_email", reason).into());
+        }
+    }
+    config.save()?;
+
+    utils::printer(&t!("domain_migrated", changes.len(), old, new), "green");
+    for (group_name, from, to) in &changes {
+        utils::write_stdout(&format!("  {}  {} -> {}\n", group_name, from, to));
+    }
+
+    let mut groups: Vec<String> = changes.into_iter().map(|(group, _, _)| group).collect();
+    groups.dedup();
+    let entries = history::load().unwrap_or_else(|e| {
+        log::warn!("Cannot read the history: {}", e);
+        Vec::new()
+    });
+    let repos = history::repos_using(&entries, &groups);
+    if repos.is_empty() {
+        utils::printer(&t!("no_affected_repos"), "white");
+        println!();
+        return Ok(());
+    }
+    utils::printer(&t!("affected_repos", repos.len()), "yellow");
+    for (repo, group_name) in &repos {
+        println!("  {}  ({})", repo.display(), group_name);
+    }
+    if !apply {
+        utils::printer(&t!("migrate_apply_hint"), "white");
+        println!();
+        return Ok(());
+    }
+
+    for (repo, group_name) in &repos {
+        if repo::detect(Some(repo)).is_none() {
+            utils::printer(&t!("repo_gone", repo.display()), "yellow");
+            continue;
+        }
+        let user = reapply_group(config, group_name, repo, old, new)?;
+        utils::printer(
+            &t!("repo_reapplied", repo.display(), user.name, user.email),
+            "green",
+        );
+    }
+    println!();
+    Ok(())
+}
+
+/// Apply `group_name` to the repository at `dir` after a domain migration
+///
+/// A repository that used an alternate email of the group keeps using it.
+fn reapply_group(
+    config: &Config,
+    group_name: &str,
+    dir: &Path,
+    old: &str,
+    new: &str,
+) -> Result<UserConfig, Box<dyn std::error::Error>> {
+    let mut user = config.resolve_group(group_name)?;
+    let current = gum_rs::config::git_scope_as_group("--local", Some(dir))
+        .and_then(|current| gum_rs::config::migrate_email(&current.email, old, new));
+    if let Some(variant) = user.email_variants.values().find(|email| {
+        current
+            .as_ref()
+            .is_some_and(|c| c.eq_ignore_ascii_case(email))
+    }) {
+        user.email = variant.clone();
+    }
+
+    let before = undo::capture(false, Some(dir), &config.url_rewrite_keys());
+    gum_rs::config::set_git_user_in(&user, false, Some(dir))?;
+    config.apply_group_settings(&user, false, Some(dir))?;
+    match before {
+        Ok(before) => history::record(
+            history::Action::Use,
+            before,
+            Some(&user),
+            Some(group_name.to_string()),
+        ),
+        Err(e) => log::warn!("Cannot capture the previous identity: {}", e),
+    }
+    Ok(user)
+}
+
+/// Handle history command
+fn handle_history(repo: Option<PathBuf>, limit: usize) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing history command");
+    let repo = match repo {
+        Some(dir) => Some(
+            repo::detect(Some(&dir))
+                .ok_or_else(|| t!("not_git_repository"))?
+                .root()
+                .to_path_buf(),
+        ),
+        None => None,
+    };
+    let entries = history::filter(history::load()?, repo.as_deref());
+    if entries.is_empty() {
+        utils::printer(&t!("no_history"), "yellow");
+        println!();
+        return Ok(());
+    }
+
+    let none = t!("history_none");
+    for entry in entries.iter().rev().take(limit).rev() {
+        let action = match entry.action {
+            history::Action::Use => "use  ",
+            history::Action::Undo => "undo ",
+            history::Action::Unset => "unset",
+            history::Action::Adopt => "adopt",
+        };
+        let to = match (&entry.to, &entry.group) {
+            (Some(to), Some(group)) => format!("{} ({})", to, group),
+            (Some(to), None) => to.clone(),
+            (None, _) => none.clone(),
+        };
+        let line = format!(
+            "{}  {}  {}  {} -> {}",
+            utils::format_timestamp(entry.timestamp),
+            action,
+            entry.scope(),
+            entry.from.as_deref().unwrap_or(&none),
+            to
+        );
+        println!("{}", utils::redact(&line));
+    }
+    Ok(())
+}
+
+/// Handle gc command
+fn handle_gc(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing gc command");
+    let retention = &config.settings.retention;
+    let versions = versions::trim_to(&utils::get_config_path()?, retention.versions())?;
+    let history_days = retention.history_days();
+    let history = history::trim(
+        utils::now().saturating_sub(
+            i64::try_from(history_days)
+                .unwrap_or(i64::MAX)
+                .saturating_mul(86_400),
+        ),
+    )?;
+    let journal = journal::rotate(retention.journal_kib().saturating_mul(1024))?;
+    let cache_days = retention.cache_days();
+    let cache = prompt::clean_cache(Duration::from_secs(cache_days.saturating_mul(86_400)))?;
+
+    if versions == 0 && history == 0 && journal.is_none() && cache == 0 {
+        utils::printer(&t!("gc_nothing"), "green");
+        println!();
+        return Ok(());
+    }
+    let header = if utils::is_dry_run() {
+        t!("gc_would")
+    } else {
+        t!("gc_done")
+    };
+    utils::printer(&header, "green");
+    if versions > 0 {
+        println!("  {}", t!("gc_versions", versions));
+    }
+    if history > 0 {
+        println!("  {}", t!("gc_history", history, history_days));
+    }
+    if let Some(rotated) = journal {
+        println!("  {}", t!("gc_journal", rotated.display()));
+    }
+    if cache > 0 {
+        println!("  {}", t!("gc_cache", cache, cache_days));
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle log command
+fn handle_log(limit: usize, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing log command");
+    let changes = journal::load()?;
+    let changes = &changes[changes.len().saturating_sub(limit)..];
+    if json {
+        for change in changes {
+            println!("{}", serde_json::to_string(change)?);
+        }
+        return Ok(());
+    }
+    if changes.is_empty() {
+        utils::printer(&t!("no_journal"), "yellow");
+        println!();
+        return Ok(());
+    }
+
+    let none = t!("history_none");
+    for change in changes {
+        let time = utils::format_timestamp(change.timestamp);
+        match change.kind {
+            journal::ChangeKind::Git => utils::write_stdout(&format!(
+                "{}  git   {}  {}: {} -> {}\n",
+                time,
+                change.target,
+                change.key.as_deref().unwrap_or_default(),
+                change.old.as_deref().unwrap_or(&none),
+                change.new.as_deref().unwrap_or(&none)
+            )),
+            journal::ChangeKind::File => {
+                utils::write_stdout(&format!("{}  file  {}\n", time, change.target));
+                for (sign, lines) in [('-', &change.old), ('+', &change.new)] {
+                    for line in lines.iter().flat_map(|lines| lines.lines()) {
+                        utils::write_stdout(&format!("    {}{}\n", sign, line));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle which command
+fn handle_which(config: &Config, scope: Option<Scope>) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing which command (scope: {:?})", scope);
+    let user = match scope {
+        Some(Scope::Global) => gum_rs::config::get_git_user_in(true, None),
+        Some(Scope::Local) => gum_rs::config::get_git_user_in(false, None),
+        None => gum_rs::config::get_effective_git_user(None),
+    };
+    let groups = user
+        .map(|user| config.match_groups(&user))
+        .unwrap_or_default();
+    if groups.is_empty() {
+        utils::printer(&t!("no_matching_group"), "red");
+        println!();
+        return Err(t!("no_matching_group").into());
+    }
+    for group in groups {
+        println!("{}", group);
+    }
+    Ok(())
+}
+
+/// Handle get command
+fn handle_get(
+    config: &Config,
+    group_name: Option<String>,
+    field: Option<String>,
+    current: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (label, user, field) = match (current, group_name, field) {
+        (Some(field), _, _) => {
+            log::info!("Executing get command for the current identity: {}", field);
+            let identity = gum_rs::config::get_effective_git_user(None)
+                .map_err(|_| t!("currently_using_none"))?;
+            let mut user = config
+                .match_groups(&identity)
+                .first()
+                .and_then(|group_name| config.resolve_group(group_name).ok())
+                .unwrap_or_default();
+            user.name = identity.name;
+            user.email = identity.email;
+            ("current".to_string(), user, field)
+        }
+        (None, Some(group_name), Some(field)) => {
+            log::info!("Executing get command: {} {}", group_name, field);
+            let group_name = config.normalize_group_name(&group_name);
+            let Some(user) = config.get_all_config_info().remove(&group_name) else {
+                return Err(t!("group_not_found", group_name).into());
+            };
+            (group_name, user, field)
+        }
+        _ => unreachable!("clap requires a group and field without --current"),
+    };
+    match output::field(&user, &field) {
+        Ok(Some(value)) => {
+            println!("{}", value);
+            Ok(())
+        }
+        Ok(None) => Err(t!("field_not_set", field, label).into()),
+        Err(reason) => Err(reason.into()),
+    }
+}
+
+/// Handle diff command
+fn handle_diff(
+    config: &Config,
+    group_name: String,
+    global: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing diff command, target group: {}", group_name);
+    let group_name = config.normalize_group_name(&group_name);
+    let Some(user) = config.get_all_config_info().remove(&group_name) else {
+        return Err(unknown_group(config, &group_name));
+    };
+    if !global && !utils::is_git_repository() {
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    }
+
+    let plan = config.plan_use(&user, global, None);
+    let scope = if global { "global" } else { "local" };
+    utils::printer(&format!("{} ({})", group_name, scope), "green");
+    for change in &plan {
+        match (&change.current, &change.planned) {
+            (None, None) => {}
+            (Some(value), _) if !change.changes() => {
+                utils::write_stdout(&format!("  {:<16} {}\n", change.key, value))
+            }
+            (current, planned) => {
+                if let Some(current) = current {
+                    utils::printer_no_newline(&format!("- {:<16} {}", change.key, current), "red");
+                    println!();
+                }
+                if let Some(planned) = planned {
+                    utils::printer_no_newline(
+                        &format!("+ {:<16} {}", change.key, planned),
+                        "green",
+                    );
+                    println!();
+                }
+            }
+        }
+    }
+    if !plan.iter().any(|change| change.changes()) {
+        utils::printer(&t!("no_differences", group_name), "yellow");
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle verify-signing command
+fn handle_verify_signing(
+    config: &Config,
+    group_name: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing verify-signing command: {:?}", group_name);
+    let group_name = match group_name {
+        Some(group_name) => config.normalize_group_name(&group_name),
+        None => {
+            let current = config.get_using_git_user().ok();
+            match current.and_then(|user| config.match_groups(user).into_iter().next()) {
+                Some(group_name) => group_name,
+                None => {
+                    utils::printer(&t!("no_matching_group"), "red");
+                    println!();
+                    return Err(t!("no_matching_group").into());
+                }
+            }
+        }
+    };
+    let Some(user) = config.get_all_config_info().remove(&group_name) else {
+        return Err(unknown_group(config, &group_name));
+    };
+    let Some(ref key) = user.signing_key else {
+        utils::printer(&t!("no_signing_key", group_name), "red");
+        println!();
+        return Err(t!("no_signing_key", group_name).into());
+    };
+
+    let format = user.signing_format.as_deref().unwrap_or("openpgp");
+    match signing::verify(key, Some(format)) {
+        Ok(()) => {
+            utils::printer(&t!("signing_ok", group_name, key, format), "green");
+            println!();
+            Ok(())
+        }
+        Err(e) => {
+            utils::printer(&t!("signing_failed", group_name, e), "red");
+            println!();
+            Err(t!("signing_failed", group_name, e).into())
+        }
+    }
+}
+
+/// Read an API token from a file, or from stdin for `-`
+fn read_token(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let content = if path.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(path)?
+    };
+    let token = content.trim();
+    if token.is_empty() {
+        let source = if path.as_os_str() == "-" {
+            "stdin".to_string()
+        } else {
+            path.display().to_string()
+        };
+        utils::printer(&t!("token_empty", source), "red");
+        println!();
+        return Err(t!("token_empty", source).into());
+    }
+    Ok(token.to_string())
+}
+
+/// Handle import command
+fn handle_import(config: &mut Config, args: ImportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing import command");
+    let token = match args.token_file {
+        Some(ref path) => Some(read_token(path)?),
+        None => None,
+    };
+    let identities = match args.from {
+        Some(tool) => import_from_tool(tool, args.file),
+        None if args.from_gitlab => {
+            import::from_gitlab(args.host.as_deref(), token.as_deref()).map(|i| vec![i])
+        }
+        None => import::from_gh(token.as_deref()),
+    };
+    let identities = match identities {
+        Ok(identities) => identities,
+        Err(e) => {
+            utils::printer(&t!("import_failed", e), "red");
+            println!();
+            return Err(t!("import_failed", e).into());
+        }
+    };
+
+    let mut imported = 0;
+    for identity in identities {
+        let group_name = config.normalize_group_name(&identity.group);
+        let existing = config.find_identity(&identity.user, "");
+        if !existing.is_empty() {
+            utils::printer(
+                &t!(
+                    "import_identity_exists",
+                    identity.source,
+                    existing.join(", ")
+                ),
+                "yellow",
+            );
+            continue;
+        }
+        if config.get_all_config_info().contains_key(&group_name)
+            || validate::is_reserved_group_name(&group_name)
+            || config.check_new_group_name(&group_name).is_err()
+        {
+            utils::printer(
+                &t!("import_group_taken", identity.source, group_name),
+                "yellow",
+            );
+            continue;
+        }
+        log::debug!("Importing {} as group {}", identity.source, group_name);
+        utils::printer(
+            &t!(
+                "imported",
+                identity.source,
+                group_name,
+                identity.user.name,
+                identity.user.email
+            ),
+            "green",
+        );
+        config.groups.insert(group_name, identity.user);
+        imported += 1;
+    }
+
+    if imported > 0 {
+        config.save()?;
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle export command
+fn handle_export(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing export command");
+    let mut groups: Vec<(String, UserConfig)> = config
+        .get_all_config_info()
+        .into_iter()
+        .filter(|(name, _)| name != "global")
+        .collect();
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    log::debug!("Exporting {} groups as shell aliases", groups.len());
+    print!(
+        "{}",
+        export::shell_aliases(groups.iter().map(|(name, user)| (name.as_str(), user)))
+    );
+    Ok(())
+}
+
+/// Read the profiles of another identity switcher
+fn import_from_tool(
+    tool: ImportTool,
+    file: Option<PathBuf>,
+) -> anyhow::Result<Vec<import::ImportedIdentity>> {
+    log::debug!("Importing {:?} profiles", tool);
+    let path = |default: fn() -> Option<PathBuf>| {
+        file.clone()
+            .or_else(default)
+            .ok_or_else(|| anyhow::anyhow!(t!("import_no_home")))
+    };
+    let identities = match tool {
+        ImportTool::GitUserSwitch => {
+            interop::from_git_user_switch(&path(interop::git_user_switch_path)?)?
+        }
+        ImportTool::Gitprofile => interop::from_gitprofile(&path(interop::gitprofile_path)?)?,
+        ImportTool::GitIdentity => interop::from_git_identity(file.as_deref())?,
+    };
+    if identities.is_empty() {
+        anyhow::bail!(t!("import_no_profiles"));
+    }
+    Ok(identities)
+}
+
+/// Handle show command
+fn handle_show(
+    config: &Config,
+    group_name: String,
+    template: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing show command, target group: {}", group_name);
+    let group_name = config.normalize_group_name(&group_name);
+    let template = template.as_deref().map(parse_output_template).transpose()?;
+    if config.get_group(&group_name).is_none() {
+        return Err(unknown_group(config, &group_name));
+    }
+    if let Some(template) = template {
+        let user = config.resolve_group(&group_name)?;
+        println!("{}", template.render(&group_name, &user));
+        return Ok(());
+    }
+
+    let chain = config.inheritance_chain(&group_name)?;
+    // Group in the chain a field value comes from
+    let source = |has: &dyn Fn(&UserConfig) -> bool| {
+        chain
+            .iter()
+            .find(|(_, group)| has(group))
+            .map(|(name, _)| name.as_str())
+    };
+    let field = |label: &str, value: &str, from: Option<&str>| match from {
+        Some(from) if from != group_name => {
+            println!(
+                "  {:<16} {}  ({})",
+                label,
+                utils::redact(value),
+                t!("inherited_from", from)
+            )
+        }
+        _ => println!("  {:<16} {}", label, utils::redact(value)),
+    };
+    let resolved = config.resolve_group(&group_name)?;
+
+    utils::printer(&group_name, "green");
+    field("name", &resolved.name, source(&|g| !g.name.is_empty()));
+    field("email", &resolved.email, source(&|g| !g.email.is_empty()));
+    for (variant, email) in &resolved.email_variants {
+        field(
+            &format!("email ({})", variant),
+            email,
+            source(&|g| g.email_variants.contains_key(variant)),
+        );
+    }
+    if let Some(ref name) = resolved.committer_name {
+        field(
+            "committer name",
+            name,
+            source(&|g| g.committer_name.is_some()),
+        );
+    }
+    if let Some(ref email) = resolved.committer_email {
+        field(
+            "committer email",
+            email,
+            source(&|g| g.committer_email.is_some()),
+        );
+    }
+    for (key, value) in &resolved.workstyle {
+        field(
+            &format!("workstyle {}", key),
+            value,
+            source(&|g| g.workstyle.contains_key(key)),
+        );
+    }
+    for (prefix, replacement) in &resolved.url_rewrites {
+        field(
+            &format!("rewrite {}", prefix),
+            replacement,
+            source(&|g| g.url_rewrites.contains_key(prefix)),
+        );
+    }
+    if let Some(ref template) = resolved.commit_template {
+        field(
+            "commit_template",
+            template,
+            source(&|g| g.commit_template.is_some()),
+        );
+    }
+    let network = [
+        (
+            "http.proxy",
+            &resolved.http_proxy,
+            source(&|g| g.http_proxy.is_some()),
+        ),
+        (
+            "http.sslCAInfo",
+            &resolved.ssl_ca_info,
+            source(&|g| g.ssl_ca_info.is_some()),
+        ),
+        (
+            "credential.helper",
+            &resolved.credential_helper,
+            source(&|g| g.credential_helper.is_some()),
+        ),
+        (
+            "sendemail.from",
+            &resolved.sendemail_from,
+            source(&|g| g.sendemail_from.is_some()),
+        ),
+        (
+            "sendemail.smtpServer",
+            &resolved.smtp_server,
+            source(&|g| g.smtp_server.is_some()),
+        ),
+        (
+            "sendemail.smtpServerPort",
+            &resolved.smtp_server_port,
+            source(&|g| g.smtp_server_port.is_some()),
+        ),
+        (
+            "sendemail.smtpUser",
+            &resolved.smtp_user,
+            source(&|g| g.smtp_user.is_some()),
+        ),
+        (
+            "sendemail.smtpEncryption",
+            &resolved.smtp_encryption,
+            source(&|g| g.smtp_encryption.is_some()),
+        ),
+    ];
+    for (label, value, from) in network {
+        if let Some(value) = value {
+            field(label, value, from);
+        }
+    }
+    for template in &resolved.trailers {
+        field("trailer", template, source(&|g| !g.trailers.is_empty()));
+    }
+    if !resolved.tags.is_empty() {
+        field("tags", &resolved.tags.join(", "), None);
+    }
+    if let Some(ref text) = resolved.description {
+        field("description", text, None);
+    }
+    if let Some(signoff) = resolved.signoff {
+        field(
+            "signoff",
+            &signoff.to_string(),
+            source(&|g| g.signoff.is_some()),
+        );
+    }
+    if resolved.locked {
+        field("locked", "true", None);
+    }
+    if let Some(ref date) = resolved.expires {
+        let text = if resolved.is_expired(utils::now()) {
+            format!("{} {}", date, t!("expired_marker"))
+        } else {
+            date.clone()
+        };
+        field("expires", &text, None);
+    }
+    if let Some(ref alias) = resolved.ssh_alias {
+        field("ssh_alias", alias, None);
+    }
+    if let Some(ref key) = resolved.ssh_key {
+        field("ssh_key", key, None);
+    }
+    if let Some(ref key) = resolved.signing_key {
+        let format = resolved.signing_format.as_deref().unwrap_or("openpgp");
+        field("signing_key", &format!("{} ({})", key, format), None);
+    }
+    if chain.len() > 1 {
+        let parents: Vec<&str> = chain[1..].iter().map(|(name, _)| name.as_str()).collect();
+        field("inherits", &parents.join(" -> "), None);
+    }
+    let aliases = config.aliases_of(&group_name);
+    if !aliases.is_empty() {
+        field("aliases", &aliases.join(", "), None);
+    }
+    if config.is_system_group(&group_name) {
+        field("source", "system", None);
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle alias add command
+fn handle_alias_add(
+    config: &mut Config,
+    alias: String,
+    group_name: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing alias add command: {} -> {}", alias, group_name);
+    let alias = alias.trim().to_string();
+    let group_name = config.normalize_group_name(&group_name);
+
+    if config.get_group(&group_name).is_none() {
+        return Err(unknown_group(config, &group_name));
+    }
+    if config.get_group(&alias).is_some() {
+        utils::printer(&t!("alias_is_group", alias), "red");
+        println!();
+        return Err(t!("alias_is_group", alias).into());
+    }
+    if validate::is_reserved_group_name(&alias) {
+        utils::printer(&t!("reserved_group_name", alias), "red");
+        println!();
+        return Err(t!("reserved_group_name", alias).into());
+    }
+    if let Err(reason) = config.check_new_group_name(&alias) {
+        utils::printer(&t!("invalid_group_name", reason), "red");
+        println!();
+        return Err(t!("invalid_group_name", reason).into());
+    }
+
+    config.aliases.insert(alias.clone(), group_name.clone());
+    config.save()?;
+    utils::printer(&t!("alias_added", alias, group_name), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle rule add command
+fn handle_rule_add(
+    config: &mut Config,
+    pattern: String,
+    group_name: String,
+    branch: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!(
+        "Executing rule add command: {} -> {} (branch: {})",
+        pattern,
+        group_name,
+        branch
+    );
+    // Branch names are case-sensitive, hosts are not
+    let pattern = if branch {
+        pattern.trim().to_string()
+    } else {
+        pattern.trim().trim_end_matches('/').to_lowercase()
+    };
+    let group_name = config.normalize_group_name(&group_name);
+
+    if config.get_group(&group_name).is_none() {
+        return Err(unknown_group(config, &group_name));
+    }
+    if pattern.is_empty() {
+        utils::printer(&t!("rule_empty"), "red");
+        println!();
+        return Err(t!("rule_empty").into());
+    }
+    if branch && pattern.contains(char::is_whitespace) {
+        utils::printer(&t!("branch_rule_whitespace", pattern), "red");
+        println!();
+        return Err(t!("branch_rule_whitespace", pattern).into());
+    }
+
+    if branch {
+        config.branches.insert(pattern.clone(), group_name.clone());
+    } else {
+        config.hosts.insert(pattern.clone(), group_name.clone());
+    }
+    config.save()?;
+    if branch {
+        utils::printer(&t!("branch_rule_added", pattern, group_name), "green");
+    } else {
+        utils::printer(&t!("rule_added", pattern, group_name), "green");
+    }
+    println!();
+    Ok(())
+}
+
+/// Recompile the branch rules and push policies read by gum's pre-commit
+/// and pre-push hooks
+///
+/// Saving the configuration does this already; system groups and profile
+/// files change without gum, so `use` refreshes them too.
+fn refresh_hook_rules(config: &Config) {
+    if let Err(e) = hooks::write_shared_rules(config) {
+        log::warn!("Cannot update the rules read by the git hooks: {}", e);
+    }
+}
+
+/// Handle rule remove command
+fn handle_rule_remove(config: &mut Config, rule: String) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing rule remove command: {}", rule);
+    let Some(rule) = rules::find(config, &rule) else {
+        utils::printer(&t!("rule_not_found", rule), "red");
+        println!();
+        return Err(t!("rule_not_found", rule).into());
+    };
+    if rule.kind == rules::RuleKind::Branch {
+        config.branches.remove(&rule.pattern);
+    } else {
+        config.hosts.remove(&rule.pattern);
+    }
+    config.save()?;
+    if rule.kind != rules::RuleKind::Branch && !utils::is_dry_run() {
+        rules::forget_hits(&rule.pattern);
+    }
+    utils::printer(&t!("rule_removed", rule.pattern, rule.group), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle rule list command
+fn handle_rule_list(
+    config: &Config,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing rule list command");
+    let hits = rules::load_hits().unwrap_or_else(|e| {
+        log::warn!("Cannot read rule hits: {}", e);
+        HashMap::new()
+    });
+    let rules = rules::list(config, &hits);
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&rules)?);
+        return Ok(());
+    }
+    if rules.is_empty() {
+        utils::printer(&t!("no_rules"), "yellow");
+        println!();
+        return Ok(());
+    }
+    let mut table = Table::new(&["#", "pattern", "type", "group", "hits"]);
+    for rule in rules {
+        table.push(vec![
+            rule.priority.to_string(),
+            rule.pattern,
+            rule.kind.name().to_string(),
+            rule.group,
+            rule.hits.to_string(),
+        ]);
+    }
+    print!("{}", table.render_to_fit(term::width()));
+    Ok(())
+}
+
+/// Handle policy add command
+fn handle_policy_add(
+    config: &mut Config,
+    email: String,
+    remote: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing policy add command: {} -> {}", email, remote);
+    let policy = gum_rs::policy::Policy {
+        email: email.trim().to_lowercase(),
+        remote: remote.trim().to_string(),
+    };
+    // Tabs and newlines would break the file compiled for the pre-push hook
+    for pattern in [&policy.email, &policy.remote] {
+        if pattern.is_empty() || pattern.contains(char::is_whitespace) {
+            utils::printer(&t!("policy_invalid", pattern), "red");
+            println!();
+            return Err(t!("policy_invalid", pattern).into());
+        }
+    }
+    if config.policies.contains(&policy) {
+        utils::printer(&t!("policy_exists"), "yellow");
+        println!();
+        return Ok(());
+    }
+
+    config.policies.push(policy.clone());
+    config.save()?;
+    utils::printer(&t!("policy_added", policy.email, policy.remote), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle policy remove command
+fn handle_policy_remove(
+    config: &mut Config,
+    number: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing policy remove command: {}", number);
+    if number == 0 || number > config.policies.len() {
+        utils::printer(&t!("policy_not_found", number), "red");
+        println!();
+        return Err(t!("policy_not_found", number).into());
+    }
+    let policy = config.policies.remove(number - 1);
+    config.save()?;
+    utils::printer(&t!("policy_removed", policy.email, policy.remote), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle policy list command
+fn handle_policy_list(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing policy list command");
+    if config.policies.is_empty() {
+        utils::printer(&t!("no_policies"), "yellow");
+        println!();
+        return Ok(());
+    }
+    let mut table = Table::new(&["#", "email", "remote"]);
+    for (i, policy) in config.policies.iter().enumerate() {
+        table.push(vec![
+            (i + 1).to_string(),
+            policy.email.clone(),
+            policy.remote.clone(),
+        ]);
+    }
+    print!("{}", table.render_to_fit(term::width()));
+    Ok(())
+}
+
+/// Handle check command
+fn handle_check(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing check command");
+    if !utils::is_git_repository() {
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    }
+    let email = gum_rs::config::get_effective_git_user(None)
+        .map(|user| user.email)
+        .unwrap_or_default();
+    if email.is_empty() {
+        utils::printer(&t!("check_no_identity"), "red");
+        println!();
+        return Err(t!("check_no_identity").into());
+    }
+
+    let mut problems = 0;
+    let remotes = resolve::remote_urls(None);
+    for violation in gum_rs::policy::violations(config, &email, &remotes) {
+        let policy = &config.policies[violation.policy - 1];
+        utils::printer(
+            &t!(
+                "check_policy_violation",
+                email,
+                violation.remote,
+                violation.url,
+                policy.email,
+                policy.remote
+            ),
+            "red",
+        );
+        problems += 1;
+    }
+    if let Some(branch) = repo::current_branch(None)
+        && let Some(rule) = rules::branch_rule(config, &branch)
+        && let Ok(user) = config.resolve_group(&rule.group)
+        && !user.email.eq_ignore_ascii_case(&email)
+    {
+        utils::printer(
+            &t!(
+                "check_branch_violation",
+                branch,
+                rule.group,
+                user.email,
+                email
+            ),
+            "red",
+        );
+        problems += 1;
+    }
+
+    if problems > 0 {
+        println!();
+        return Err(t!("check_failed", problems).into());
+    }
+    utils::printer(&t!("check_passed", email, remotes.len()), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle alias remove command
+fn handle_alias_remove(
+    config: &mut Config,
+    alias: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing alias remove command: {}", alias);
+    let alias = alias.trim();
+    if config.aliases.remove(alias).is_none() {
+        utils::printer(&t!("alias_not_found", alias), "red");
+        println!();
+        return Err(t!("alias_not_found", alias).into());
+    }
+    config.save()?;
+    utils::printer(&t!("alias_removed", alias), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle alias list command
+fn handle_alias_list(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing alias list command");
+    if config.aliases.is_empty() {
+        utils::printer(&t!("no_aliases"), "yellow");
+        println!();
+        return Ok(());
+    }
+
+    let mut aliases: Vec<_> = config.aliases.iter().collect();
+    aliases.sort();
+    let width = aliases
+        .iter()
+        .map(|(alias, _)| alias.len())
+        .max()
+        .unwrap_or(0);
+    println!();
+    for (alias, group_name) in aliases {
+        println!("  {:<width$}  ->  {}", alias, group_name, width = width);
+    }
+    println!();
+    Ok(())
+}
+
+/// Fail unless the workspace `name` exists, with a valid name
+fn check_workspace(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Err(reason) = workspace::validate_name(name) {
+        utils::printer(&reason, "red");
+        println!();
+        return Err(reason.into());
+    }
+    if !workspace::exists(name)? {
+        utils::printer(&t!("workspace_not_found", name), "red");
+        println!();
+        return Err(t!("workspace_not_found", name).into());
+    }
+    Ok(())
+}
+
+/// Handle workspace list command
+fn handle_workspace_list() -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing workspace list command");
+    let active = workspace::active();
+    let names = std::iter::once(workspace::DEFAULT_WORKSPACE.to_string())
+        .chain(workspace::list()?)
+        .collect::<Vec<_>>();
+    println!();
+    for name in names {
+        let current = active.as_deref().unwrap_or(workspace::DEFAULT_WORKSPACE) == name;
+        let marker = if current { "*" } else { " " };
+        println!("{} {}", marker, name);
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle workspace new command
+fn handle_workspace_new(name: String) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing workspace new command: {}", name);
+    if let Err(reason) = workspace::validate_name(&name) {
+        utils::printer(&reason, "red");
+        println!();
+        return Err(reason.into());
+    }
+    if workspace::exists(&name)? {
+        utils::printer(&t!("workspace_exists", name), "red");
+        println!();
+        return Err(t!("workspace_exists", name).into());
+    }
+    let dir = workspace::create(&name)?;
+    utils::printer(&t!("workspace_created", name, dir.display()), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle workspace use command
+fn handle_workspace_use(name: String) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing workspace use command: {}", name);
+    check_workspace(&name)?;
+    workspace::remember(&name)?;
+    // The hooks now enforce the rules of this workspace
+    let base = utils::get_config_base_dir()?;
+    let dir = if name == workspace::DEFAULT_WORKSPACE {
+        base
+    } else {
+        workspace::dir(&base, &name)
+    };
+    match Config::builder()
+        .config_path(dir.join(utils::CONFIG_FILE_NAME))
+        .build()
+    {
+        Ok(config) => refresh_hook_rules(&config),
+        Err(e) => log::warn!("Cannot load the configuration of workspace {}: {}", name, e),
+    }
+    if std::env::var_os(workspace::WORKSPACE_ENV).is_some() {
+        utils::printer(
+            &t!("workspace_env_wins", workspace::WORKSPACE_ENV),
+            "yellow",
+        );
+    }
+    utils::printer(&t!("workspace_used", name), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle template install command
+fn handle_template_install(
+    dir: Option<std::path::PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing template install command");
+    let template_dir = hooks::install_template(dir)?;
+    utils::printer(&t!("template_installed", template_dir.display()), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle pair start command
+fn handle_pair_start(
+    config: &mut Config,
+    groups: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing pair start command: {:?}", groups);
+    if !utils::is_git_repository() {
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    }
+    let mut names: Vec<String> = Vec::new();
+    let mut users = Vec::new();
+    for group_name in &groups {
+        let group_name = config.normalize_group_name(group_name);
+        if config.get_group(&group_name).is_none() {
+            return Err(unknown_group(config, &group_name));
+        }
+        if names.contains(&group_name) {
+            utils::printer(&t!("pair_duplicate", group_name), "red");
+            println!();
+            return Err(t!("pair_duplicate", group_name).into());
+        }
+        users.push(config.resolve_group(&group_name)?);
+        names.push(group_name);
+    }
+
+    // The hook goes first, so a foreign commit-msg hook stops everything
+    hooks::install_repository_hook(hooks::HookKind::CommitMsg, None)?;
+    apply_use(config, Some(&names[0]), &users[0], false, false, None)?;
+    pair::start(&users[1..], None)?;
+    utils::printer(
+        &t!("pair_started", names[0], names[1..].join(", ")),
+        "green",
+    );
+    println!();
+    Ok(())
+}
+
+/// Handle pair stop command
+fn handle_pair_stop() -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing pair stop command");
+    if !utils::is_git_repository() {
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    }
+    let removed = pair::stop(None)?;
+    if removed.is_empty() {
+        utils::printer(&t!("pair_not_started"), "yellow");
+    } else {
+        utils::printer(&t!("pair_stopped", removed.len()), "green");
+    }
+    println!();
+    Ok(())
+}
+
+/// Handle audit command
+fn handle_audit(
+    config: &Config,
+    all_repos: bool,
+    dirs: Vec<PathBuf>,
+    depth: usize,
+    max_count: usize,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing audit command (all repos: {})", all_repos);
+    let repositories = if all_repos {
+        let roots = if dirs.is_empty() {
+            vec![std::env::current_dir()?]
+        } else {
+            dirs
+        };
+        let mut found: Vec<PathBuf> = roots
+            .iter()
+            .flat_map(|root| watch::find_repositories(root, depth))
+            .collect();
+        found.sort();
+        found.dedup();
+        found
+    } else {
+        let Some(repository) = repo::detect(None) else {
+            utils::printer(&t!("not_git_repository"), "red");
+            println!();
+            return Err(t!("not_git_repository").into());
+        };
+        vec![repository.root().to_path_buf()]
+    };
+
+    let mut report = audit::Report::default();
+    for path in repositories {
+        match audit::audit_repository(config, &path, max_count) {
+            Ok(repository) => report.push(repository),
+            Err(e) => {
+                log::warn!("Skipping {}: {}", path.display(), e);
+                eprintln!("{}", t!("audit_skipped", path.display(), e));
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for repository in &report.repositories {
+            let path = repository.path.display();
+            let Some(ref group) = repository.group else {
+                utils::printer(&t!("audit_unresolved", path), "yellow");
+                println!();
+                continue;
+            };
+            if repository.wrong.is_empty() {
+                utils::printer(&t!("audit_clean", path, group, repository.checked), "green");
+                println!();
+                continue;
+            }
+            utils::printer(
+                &t!("audit_wrong", path, group, repository.wrong.len()),
+                "red",
+            );
+            println!();
+            for commit in &repository.wrong {
+                utils::write_stdout(&format!(
+                    "  {:.10}  {} <{}> ({})  {}\n",
+                    commit.commit,
+                    commit.name,
+                    commit.email,
+                    commit.groups.join(", "),
+                    commit.subject
+                ));
+            }
+        }
+    }
+
+    if report.wrong_commits > 0 {
+        return Err(t!("audit_found", report.wrong_commits).into());
+    }
+    Ok(())
+}
+
+/// State file recording that onboarding was offered
+const ONBOARDING_FILE: &str = "onboarding-offered";
+
+/// Offer `gum onboard` on the first run without a config file
+///
+/// Only asked once, for a bare `gum`, `list` or `use` on a terminal. The
+/// command typed still runs once onboarding is done.
+fn offer_onboarding(command: Option<&Commands>) -> bool {
+    let first_command = match command {
+        None => true,
+        Some(Commands::List { template, .. }) => template.is_none(),
+        Some(Commands::Use { .. }) => true,
+        Some(_) => false,
+    };
+    if !first_command || utils::is_non_interactive() || utils::is_dry_run() {
+        return false;
+    }
+    if utils::get_config_path().is_ok_and(|path| path.exists()) {
+        return false;
+    }
+    let Ok(marker) = utils::get_state_dir().map(|dir| dir.join(ONBOARDING_FILE)) else {
+        return false;
+    };
+    if marker.exists() {
+        return false;
+    }
+    let written = marker
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&marker, ""));
+    if let Err(e) = written {
+        log::debug!("Cannot record the onboarding offer: {}", e);
+    }
+    utils::confirm(&t!("onboard_offer"))
+}
+
+/// Handle onboard command
+fn handle_onboard(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing onboard command");
+    if utils::is_non_interactive() {
+        return Err(utils::input_required("onboard").into());
+    }
+    utils::printer(&t!("onboard_welcome"), "cyan");
+
+    // The global identity becomes the first group
+    let mut created = false;
+    match config.global_user.clone() {
+        Some(user) => match config.match_groups(&user).first() {
+            Some(group) => utils::printer(&t!("onboard_global_known", group), "green"),
+            None => {
+                if let Err(reason) =
+                    validate::check_email(&user.email, &config.settings.allowed_email_domains)
+                {
+                    utils::printer(&t!("invalid_email", reason), "red");
+                } else if utils::confirm(&t!("onboard_import_global", user.name, user.email))
+                    && let Some(group_name) =
+                        ask_new_group_name(config, &config.suggest_group_name(&user))
+                {
+                    config.groups.insert(group_name, user);
+                    created = true;
+                }
+            }
+        },
+        None => utils::printer(&t!("onboard_no_global"), "yellow"),
+    }
+
+    if utils::confirm(&t!("onboard_create_work"))
+        && let Some(group_name) = ask_new_group_name(config, "work")
+    {
+        let default_name = config.global_user.as_ref().map(|user| user.name.clone());
+        let (name, email) = prompt_user_fields(Some(&UserConfig {
+            name: default_name.unwrap_or_default(),
+            ..UserConfig::default()
+        }));
+        let name = name.or_else(|| config.global_user.as_ref().map(|user| user.name.clone()));
+        match (name, email) {
+            (Some(name), Some(email)) => {
+                match validate::check_email(&email, &config.settings.allowed_email_domains) {
+                    Ok(()) => {
+                        config.groups.insert(
+                            group_name,
+                            UserConfig {
+                                name,
+                                email,
+                                ..UserConfig::default()
+                            },
+                        );
+                        created = true;
+                    }
+                    Err(reason) => utils::printer(&t!("invalid_email", reason), "red"),
+                }
+            }
+            _ => utils::printer(&t!("skipped"), "yellow"),
+        }
+    }
+    if created {
+        config.save()?;
+    }
+
+    // The group used outside repositories with their own identity
+    let mut names: Vec<String> = config.groups.keys().cloned().collect();
+    names.sort();
+    if !names.is_empty() {
+        for (i, name) in names.iter().enumerate() {
+            println!("  {}. {}", i + 1, name);
+        }
+        let choice = utils::prompt(&t!("onboard_default"))
+            .and_then(|answer| answer.parse::<usize>().ok())
+            .and_then(|number| names.get(number.checked_sub(1)?));
+        if let Some(group_name) = choice.cloned() {
+            handle_use(config, Some(group_name), vec![true], None, false, None)?;
+        }
+    }
+
+    if let Some(shell) = completions::Shell::from_env()
+        && utils::confirm(&t!("onboard_completions", shell))
+    {
+        match completions::install(&Cli::command(), shell)? {
+            Some(path) => utils::printer(&t!("completions_installed", path.display()), "green"),
+            None => utils::printer(&t!("completions_eval_hint", shell), "yellow"),
+        }
+    }
+
+    if utils::confirm(&t!("onboard_template")) {
+        handle_template_install(None)?;
+    }
+    utils::printer(&t!("onboard_done"), "green");
+    println!();
+    Ok(())
+}
+
+/// Ask for the name of a new group until it is valid, `suggested` when
+/// nothing is entered; `None` when `suggested` cannot be used either
+fn ask_new_group_name(config: &Config, suggested: &str) -> Option<String> {
+    loop {
+        let answer = utils::prompt(&format!("{} [{}]:", t!("onboard_group_name"), suggested));
+        let group_name = answer.as_deref().map_or_else(
+            || suggested.to_string(),
+            |name| config.normalize_group_name(name),
+        );
+        match config.check_new_group_name(&group_name) {
+            Ok(()) if config.get_group(&group_name).is_none() => return Some(group_name),
+            Ok(()) => utils::printer(&t!("onboard_group_exists", group_name), "red"),
+            Err(reason) => utils::printer(&t!("invalid_group_name", reason), "red"),
+        }
+        answer.as_ref()?;
+    }
+}
+
+/// Handle infer command
+fn handle_infer(
+    config: &mut Config,
+    max_count: usize,
+    apply: bool,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing infer command (apply: {})", apply);
+    let Some(repository) = repo::detect(None) else {
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    };
+    let inference = infer::infer(config, repository.root(), max_count)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&inference)?);
+        return Ok(());
+    }
+
+    if inference.candidates.is_empty() {
+        utils::printer(&t!("infer_nothing", inference.checked), "yellow");
+        println!();
+        return Ok(());
+    }
+    let width = inference
+        .candidates
+        .iter()
+        .map(|candidate| candidate.group.chars().count())
+        .max()
+        .unwrap_or(0);
+    for candidate in &inference.candidates {
+        println!(
+            "  {:<width$}  {}",
+            candidate.group,
+            candidate.reasons.join("; "),
+            width = width
+        );
+    }
+    let Some(suggestion) = inference.suggestion() else {
+        utils::printer(&t!("infer_tied"), "yellow");
+        println!();
+        return Ok(());
+    };
+    let group = suggestion.group.clone();
+    if !apply {
+        utils::printer(&t!("infer_suggested", group), "green");
+        println!();
+        return Ok(());
+    }
+    handle_use(config, Some(group), vec![false], None, false, None)
+}
+
+/// Handle verify-commits command
+fn handle_verify_commits(
+    config: &Config,
+    since: Option<String>,
+    max_count: usize,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing verify-commits command (since: {:?})", since);
+    let Some(repository) = repo::detect(None) else {
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    };
+    let report = verify::verify_repository(config, repository.root(), since.as_deref(), max_count)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if report.flagged.is_empty() {
+        utils::printer(&t!("verify_clean", report.checked), "green");
+        println!();
+    } else {
+        utils::printer(
+            &t!("verify_flagged", report.flagged.len(), report.checked),
+            "red",
+        );
+        println!();
+        for commit in &report.flagged {
+            let problem = match commit.problem {
+                verify::Problem::Unsigned => t!("verify_unsigned"),
+                verify::Problem::Invalid => t!("verify_invalid"),
+                verify::Problem::Unverifiable => t!("verify_unverifiable"),
+                verify::Problem::Expired => t!("verify_expired"),
+                verify::Problem::Untrusted => t!("verify_untrusted"),
+                verify::Problem::WrongKey => {
+                    t!(
+                        "verify_wrong_key",
+                        commit.key.as_deref().unwrap_or_default()
+                    )
+                }
+            };
+            utils::write_stdout(&format!(
+                "  {:.10}  {} <{}> ({})  {}  {}\n",
+                commit.commit,
+                commit.name,
+                commit.email,
+                commit.groups.join(", "),
+                problem,
+                commit.subject
+            ));
+        }
+    }
+
+    if !report.flagged.is_empty() {
+        return Err(t!("verify_found", report.flagged.len()).into());
+    }
+    Ok(())
+}
+
+/// Handle hook install command
+fn handle_hook_install(
+    config: &Config,
+    hook: hooks::HookKind,
+    template: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!(
+        "Executing hook install command: {} (template: {})",
+        hook.file_name(),
+        template
+    );
+    if hook.uses_compiled_rules() {
+        hooks::write_shared_rules(config)?;
+    }
+    if template {
+        let template_dir = hooks::install_template_hook(
+            None,
+            hook.file_name(),
+            &hook.script(&hooks::rules_dir()?),
+        )?;
+        utils::printer(
+            &t!(
+                "hook_installed_template",
+                hook.file_name(),
+                template_dir.display()
+            ),
+            "green",
+        );
+        println!();
+        return Ok(());
+    }
+    if !utils::is_git_repository() {
+        utils::printer(&t!("not_git_repository"), "red");
+        println!();
+        return Err(t!("not_git_repository").into());
+    }
+    let path = hooks::install_repository_hook(hook, None)?;
+    utils::printer(&t!("hook_installed", path.display()), "green");
+    println!();
+    Ok(())
+}
+
+/// Handle self-update command
+fn handle_self_update(check: bool) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing self-update command (check: {})", check);
+
+    let release = update::latest_release()?;
+    if !update::is_newer(&release.version, update::CURRENT_VERSION) {
+        utils::printer(&t!("up_to_date", update::CURRENT_VERSION), "green");
+        println!();
+        return Ok(());
+    }
+    utils::printer(
+        &t!("update_available", release.version, update::CURRENT_VERSION),
+        "yellow",
+    );
+    if check {
+        println!();
+        return Ok(());
+    }
+
+    let exe = std::env::current_exe()?;
+    if let Some((manager, command)) = update::package_manager(&exe) {
+        utils::printer(&t!("update_managed", manager, command), "red");
+        println!();
+        return Err(t!("update_managed", manager, command).into());
+    }
+
+    let exe = update::install(&release)?;
+    utils::printer(
+        &t!("update_success", exe.display(), release.version),
+        "green",
+    );
+    println!();
+    Ok(())
+}
+
+/// Handle config rollback command
+fn handle_config_rollback(steps: u16) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing config rollback command (steps: {})", steps);
+    let version = match gum_rs::config::rollback(usize::from(steps)) {
+        Ok(version) => version,
+        Err(e) => {
+            utils::printer(&t!("rollback_failed", e), "red");
+            println!();
+            return Err(t!("rollback_failed", e).into());
+        }
+    };
+    utils::printer(
+        &t!(
+            "rolled_back",
+            utils::format_timestamp(version.timestamp),
+            version.hash
+        ),
+        "green",
+    );
+    println!();
+    Ok(())
+}
+
+/// Handle config lint command
+fn handle_config_lint() -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Executing config lint command");
+
+    let config_path = utils::get_config_path()?;
+    if !config_path.exists() {
+        utils::printer(&t!("no_config_file", config_path.display()), "yellow");
+        println!();
+        return Ok(());
+    }
+
+    let content = std::fs::read_to_string(&config_path)?;
+    let issues = validate::lint(&content);
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+
+    println!();
+    for issue in &issues {
+        let color = match issue.severity {
+            Severity::Error => "red",
+            Severity::Warning => "yellow",
+        };
+        utils::printer_no_newline(&format!("{}:{}", config_path.display(), issue), color);
+        println!();
+    }
+
+    if issues.is_empty() {
+        utils::printer(&t!("config_valid", config_path.display()), "green");
+        println!();
+        return Ok(());
+    }
+
+    utils::printer(
+        &t!("lint_summary", errors, issues.len() - errors),
+        if errors > 0 { "red" } else { "yellow" },
+    );
+    println!();
+
+    if errors > 0 {
+        return Err(t!("config_invalid", config_path.display()).into());
+    }
+    Ok(())
+}
+
+/// Empty table of groups: name and email, plus the aliases, signing key,
+/// tags, description, last use, rule count and problems when `details` are
+/// given (`list --long`)
+fn group_table(details: Option<&ListDetails>) -> Table {
+    match details {
+        Some(_) => Table::new(&[
+            "group-name",
+            "name",
+            "email",
+            "aliases",
+            "signing-key",
+            "tags",
+            "description",
+            "last-used",
+            "rules",
+            "health",
+        ]),
+        None => Table::new(&["group-name", "name", "email"]),
+    }
+}
+
+/// What `list --long` shows besides the group fields, gathered once per
+/// listing
+struct ListDetails {
+    /// When each group was last applied
+    last_used: HashMap<String, i64>,
+    /// What the groups' health is checked against
+    env: health::Environment,
+}
+
+impl ListDetails {
+    fn gather(config: &Config, all_config: &HashMap<String, UserConfig>) -> Self {
+        let entries = history::load().unwrap_or_else(|e| {
+            log::warn!("Cannot read history: {}", e);
+            Vec::new()
+        });
+        let names: Vec<String> = all_config.keys().cloned().collect();
+        let mut repos: HashMap<String, usize> = HashMap::new();
+        for (_, group_name) in history::repos_using(&entries, &names) {
+            *repos.entry(group_name).or_default() += 1;
+        }
+        ListDetails {
+            last_used: prune::last_used(&entries),
+            env: health::Environment::detect(config, repos),
+        }
+    }
+}
+
+/// Add a row per group, sorted by name, to a table made by [`group_table`]
+/// with the same `details`
+fn push_groups<'a>(
+    table: &mut Table,
+    config: &Config,
+    active: &HashMap<String, Active>,
+    groups: impl IntoIterator<Item = (&'a String, &'a UserConfig)>,
+    details: Option<&ListDetails>,
+) {
+    let mut groups: Vec<(&String, &UserConfig)> = groups.into_iter().collect();
+    groups.sort_by_key(|(group_name, _)| *group_name);
+    for (group_name, user) in groups {
+        let mut row = vec![
+            group_cell(group_name, user, active),
+            user.name.clone(),
+            user.email.clone(),
+        ];
+        if let Some(details) = details {
+            let rules = config
+                .hosts
+                .values()
+                .chain(config.branches.values())
+                .filter(|g| *g == group_name)
+                .count();
+            row.extend([
+                config.aliases_of(group_name).join(", "),
+                user.signing_key.clone().unwrap_or_default(),
+                user.tags.join(", "),
+                user.description.clone().unwrap_or_default(),
+                details
+                    .last_used
+                    .get(group_name)
+                    .map(|&timestamp| utils::format_timestamp(timestamp))
+                    .unwrap_or_default(),
+                rules.to_string(),
+                health_cell(&health::check(config, group_name, user, &details.env)),
+            ]);
+        }
+        table.push(row);
+    }
+}
+
+/// How a listed group relates to the identity git uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Active {
+    /// Holds the repository's local identity, which git uses
+    Local,
+    /// Holds the global identity, which git uses
+    Global,
+    /// Holds the global identity, which a local one overrides
+    Overridden,
+}
+
+/// Groups holding the local and global identities
+fn active_groups(config: &Config) -> HashMap<String, Active> {
+    let mut active = HashMap::new();
+    if let Some(ref local) = config.project_user {
+        for group_name in config.match_groups(local) {
+            active.insert(group_name, Active::Local);
+        }
+    }
+    if let Some(ref global) = config.global_user {
+        let state = if config.project_user.is_some() {
+            Active::Overridden
+        } else {
+            Active::Global
+        };
+        // The `global` row of the listing is the global identity itself
+        for group_name in config
+            .match_groups(global)
+            .into_iter()
+            .chain(["global".into()])
+        {
+            active.entry(group_name).or_insert(state);
+        }
+    }
+    active
+}
+
+/// Group name as listed: marked when it holds the identity in use, and
+/// flagged when the group has expired
+fn group_cell(group_name: &str, user: &UserConfig, active: &HashMap<String, Active>) -> String {
+    let plain = term::is_plain();
+    let (symbol, scope, in_use) = match active.get(group_name) {
+        Some(Active::Local) => (if plain { "*" } else { "●" }, t!("active_local"), true),
+        Some(Active::Global) => (if plain { "*" } else { "●" }, t!("active_global"), true),
+        Some(Active::Overridden) => (
+            if plain { "-" } else { "○" },
+            t!("active_overridden"),
+            false,
+        ),
+        None => (" ", String::new(), false),
+    };
+    // Names stay aligned when some group carries a symbol
+    let mut cell = if active.is_empty() {
+        group_name.to_string()
+    } else {
+        format!("{} {}", symbol, group_name)
+    };
+    if !scope.is_empty() && group_name != "global" {
+        cell = format!("{} {}", cell, scope);
+    }
+    if user.is_expired(utils::now()) {
+        cell = format!("{} {}", cell, t!("expired_marker"));
+    }
+    if in_use {
+        term::paint(&cell, "green")
+    } else {
+        cell
+    }
+}
+
+/// Problems of a group as listed, red, or `ok`
+fn health_cell(problems: &[health::Problem]) -> String {
+    if problems.is_empty() {
+        return t!("health_ok");
+    }
+    let problems: Vec<String> = problems
+        .iter()
+        .map(|problem| match problem {
+            health::Problem::GpgKeyMissing(key) => t!("health_gpg_key_missing", key),
+            health::Problem::SshKeyMissing(key) => t!("health_ssh_key_missing", key),
+            health::Problem::SshKeyNotLoaded(key) => t!("health_ssh_key_not_loaded", key),
+            health::Problem::InvalidEmail(reason) => reason.clone(),
+            health::Problem::UnusedRules(rules) => t!("health_unused_rules", rules),
+            health::Problem::Expired(date) => t!("health_expired", date),
+        })
+        .collect();
+    term::paint(&problems.join("; "), "red")
 }
@@ -0,0 +1,159 @@
+//! # MCP Server Module
+//!
+//! Exposes gum to AI coding agents over the Model Context Protocol (stdio
+//! transport), so an agent can look up and apply the right git identity
+//! before committing on a user's behalf.
+//!
+//! ## Tools
+//! - `list_profiles`: All configuration groups and the effective identity
+//! - `resolve_profile_for_repo`: Identity configured for a repository and the group it matches
+//! - `apply_profile`: Apply a group to a repository (or globally)
+
+use crate::rpc::{self, Handler, RpcError};
+use crate::service;
+use serde_json::{Value, json};
+use std::path::Path;
+
+/// Protocol revisions this server can speak, newest first
+const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// MCP request handler
+#[derive(Default)]
+pub struct McpServer;
+
+impl McpServer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Serve MCP requests on stdin/stdout until stdin is closed
+    pub fn serve(&mut self) -> anyhow::Result<()> {
+        rpc::serve_stdio(self)
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let version = SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .find(|v| **v == requested)
+            .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0]);
+
+        json!({
+            "protocolVersion": version,
+            "capabilities": {"tools": {}},
+            "serverInfo": {"name": "gum", "version": env!("CARGO_PKG_VERSION")},
+            "instructions": "Use resolve_profile_for_repo before committing to check which git identity a repository uses, and apply_profile to switch it.",
+        })
+    }
+
+    fn list_tools(&self) -> Value {
+        json!({
+            "tools": [
+                {
+                    "name": "list_profiles",
+                    "description": "List all gum configuration groups (git identities) and the currently effective identity.",
+                    "inputSchema": {"type": "object", "properties": {}},
+                },
+                {
+                    "name": "resolve_profile_for_repo",
+                    "description": "Show the git identity configured for a repository (local, else global) and the gum group it matches.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "path": {"type": "string", "description": "Path inside the repository"},
+                        },
+                        "required": ["path"],
+                    },
+                },
+                {
+                    "name": "apply_profile",
+                    "description": "Apply a gum group as the git identity of a repository, or globally.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "group": {"type": "string", "description": "Group name"},
+                            "path": {"type": "string", "description": "Path inside the repository (required unless global)"},
+                            "global": {"type": "boolean", "description": "Set the global identity instead"},
+                            "email_variant": {"type": "string", "description": "Alternate email of the group to use, e.g. noreply"},
+                        },
+                        "required": ["group"],
+                    },
+                },
+            ]
+        })
+    }
+
+    fn call_tool(&self, params: &Value) -> Result<Value, RpcError> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::invalid_params("Missing tool name"))?;
+        let args = params.get("arguments").cloned().unwrap_or(json!({}));
+        log::info!("MCP tool call: {}", name);
+
+        let result = match name {
+            "list_profiles" => service::list_profiles(),
+            "resolve_profile_for_repo" => service::string_arg(&args, "path")
+                .and_then(|path| service::resolve_identity(Path::new(path))),
+            "apply_profile" => service::apply_profile(&args),
+            _ => return Err(RpcError::invalid_params(format!("Unknown tool: {}", name))),
+        };
+
+        Ok(match result {
+            Ok(value) => json!({
+                "content": [{"type": "text", "text": value.to_string()}],
+                "structuredContent": value,
+                "isError": false,
+            }),
+            Err(e) => json!({
+                "content": [{"type": "text", "text": e.to_string()}],
+                "isError": true,
+            }),
+        })
+    }
+}
+
+impl Handler for McpServer {
+    fn handle(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools()),
+            "tools/call" => self.call_tool(&params),
+            m if m.starts_with("notifications/") => Ok(Value::Null),
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initialize_and_list_tools() {
+        let mut server = McpServer::new();
+        let result = server
+            .handle("initialize", json!({"protocolVersion": "2024-11-05"}))
+            .unwrap();
+        assert_eq!(result["protocolVersion"], "2024-11-05");
+        assert_eq!(result["serverInfo"]["name"], "gum");
+
+        let tools = server.handle("tools/list", Value::Null).unwrap();
+        let names: Vec<&str> = tools["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            ["list_profiles", "resolve_profile_for_repo", "apply_profile"]
+        );
+
+        assert!(server.handle("resources/list", Value::Null).is_err());
+    }
+}
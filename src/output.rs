@@ -0,0 +1,185 @@
+//! # Output Template Module
+//!
+//! User-defined output for `list --template` and `show --template`, so
+//! scripts can print exactly the fields they need:
+//!
+//! ```text
+//! gum list --template '{group}\t{email}'
+//! gum show work --template '{name} <{email}>'
+//! ```
+//!
+//! `{field}` is replaced by a group field (the keys of the configuration
+//! file, plus `group` for the group name); `{{` and `}}` give literal braces,
+//! and `\t`, `\n` and `\\` a tab, a newline and a backslash. Unset fields are
+//! empty, lists are joined with commas and maps are written as `key=value`
+//! pairs.
+
+use crate::config::UserConfig;
+use crate::validate;
+use serde_json::Value;
+
+/// Placeholder for the group name
+const GROUP_FIELD: &str = "group";
+
+/// A parsed output template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field(String),
+}
+
+impl OutputTemplate {
+    /// Parse a template, rejecting unknown fields and unbalanced braces
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.peek() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('\\') => text.push('\\'),
+                    _ => {
+                        text.push('\\');
+                        continue;
+                    }
+                },
+                '{' if chars.peek() == Some(&'{') => text.push('{'),
+                '}' if chars.peek() == Some(&'}') => text.push('}'),
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => return Err(format!("unclosed \"{{{}\"", field)),
+                        }
+                    }
+                    let field = field.trim().to_string();
+                    if field != GROUP_FIELD && !validate::GROUP_KEYS.contains(&field.as_str()) {
+                        return Err(format!("unknown field \"{}\"", field));
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field));
+                    continue;
+                }
+                '}' => return Err("unmatched \"}\" (write \"}}\" for a brace)".to_string()),
+                c => {
+                    text.push(c);
+                    continue;
+                }
+            }
+            // Second character of an escape or doubled brace
+            chars.next();
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Fill the template with a group's fields
+    pub fn render(&self, group_name: &str, user: &UserConfig) -> String {
+        let fields = serde_json::to_value(user).unwrap_or_default();
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Field(field) if field == GROUP_FIELD => group_name.to_string(),
+                Part::Field(field) => field_text(&fields[field.as_str()]),
+            })
+            .collect()
+    }
+}
+
+/// Text of one field of a group, `None` when the field is unset or empty
+///
+/// Fails for names that are not group fields.
+pub fn field(user: &UserConfig, field: &str) -> Result<Option<String>, String> {
+    if !validate::GROUP_KEYS.contains(&field) {
+        return Err(format!("unknown field \"{}\"", field));
+    }
+    let fields = serde_json::to_value(user).unwrap_or_default();
+    Ok(Some(field_text(&fields[field])).filter(|text| !text.is_empty()))
+}
+
+/// Text of a field value
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(field_text).collect::<Vec<_>>().join(","),
+        Value::Object(entries) => entries
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, field_text(value)))
+            .collect::<Vec<_>>()
+            .join(","),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let user = UserConfig {
+            name: "Li Si".to_string(),
+            email: "li@acme.com".to_string(),
+            trailers: vec!["A: b".to_string(), "C: d".to_string()],
+            signoff: Some(true),
+            ..UserConfig::default()
+        };
+        let template =
+            OutputTemplate::parse(r"{group}\t{name} <{email}> {{{signing_key}}}").unwrap();
+        assert_eq!(
+            template.render("work", &user),
+            "work\tLi Si <li@acme.com> {}"
+        );
+        let template = OutputTemplate::parse("{ trailers }|{signoff}").unwrap();
+        assert_eq!(template.render("work", &user), "A: b,C: d|true");
+    }
+
+    #[test]
+    fn test_field() {
+        let user = UserConfig {
+            email: "li@acme.com".to_string(),
+            ..UserConfig::default()
+        };
+        assert_eq!(
+            field(&user, "email").unwrap().as_deref(),
+            Some("li@acme.com")
+        );
+        assert_eq!(field(&user, "name").unwrap(), None);
+        assert_eq!(field(&user, "ssh_key").unwrap(), None);
+        assert!(field(&user, "mail").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            OutputTemplate::parse("{mail}").unwrap_err(),
+            "unknown field \"mail\""
+        );
+        assert_eq!(
+            OutputTemplate::parse("{name").unwrap_err(),
+            "unclosed \"{name\""
+        );
+        assert!(OutputTemplate::parse("name}").is_err());
+        assert_eq!(
+            OutputTemplate::parse(r"C:\path")
+                .unwrap()
+                .render("g", &UserConfig::default()),
+            r"C:\path"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! # Pager Module
+//!
+//! Sends long listings through the user's pager the way git does: the pager
+//! is taken from `GIT_PAGER`, `core.pager` or `PAGER` (default `less`), and
+//! `less` is started with `LESS=FRX` unless the user set `LESS`, so output
+//! that fits on one screen is printed directly.
+//!
+//! Paging only happens when stdout is a terminal, and only on Unix, where
+//! stdout is redirected into the pager's stdin for as long as the
+//! [`Pager`] guard lives.
+
+use crate::config;
+use crate::term;
+use crate::utils;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Child, Command, Stdio};
+
+/// A running pager that receives stdout until dropped
+pub struct Pager {
+    child: Child,
+    /// Duplicate of the original stdout, restored on drop
+    #[cfg(unix)]
+    saved_stdout: i32,
+}
+
+/// Start the pager when stdout is a terminal and a pager is configured
+///
+/// Returns `None` (and output goes straight to the terminal) when paging is
+/// disabled, not supported on this platform or the pager cannot be started.
+pub fn start() -> Option<Pager> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    let pager = pager_command(
+        env::var("GIT_PAGER").ok(),
+        config::get_git_config(None, "core.pager", None),
+        env::var("PAGER").ok(),
+    )?;
+    // Decide on colors and width while stdout is still the terminal
+    term::colors_enabled();
+    term::width();
+    spawn(&pager)
+        .inspect_err(|e| log::warn!("Cannot start pager '{}': {}", pager, e))
+        .ok()
+}
+
+/// Pick the pager command; `None` when paging is turned off
+fn pager_command(
+    git_pager: Option<String>,
+    core_pager: Option<String>,
+    pager: Option<String>,
+) -> Option<String> {
+    let command = git_pager
+        .or(core_pager)
+        .or(pager)
+        .unwrap_or_else(|| "less".to_string());
+    let command = command.trim();
+    (!command.is_empty() && command != "cat").then(|| command.to_string())
+}
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn dup(fd: i32) -> i32;
+    fn dup2(old: i32, new: i32) -> i32;
+    fn close(fd: i32) -> i32;
+}
+
+#[cfg(unix)]
+fn spawn(pager: &str) -> io::Result<Pager> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut command = Command::new("sh");
+    command.args(["-c", pager]).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    if env::var_os("LV").is_none() {
+        command.env("LV", "-c");
+    }
+    log::debug!("Starting pager: {}", utils::command_line(&command));
+    let mut child = command.spawn()?;
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::other("pager has no stdin"))?;
+
+    io::stdout().flush()?;
+    // SAFETY: only file descriptors owned by this process are duplicated;
+    // fd 1 keeps the pipe open after `stdin` is dropped below.
+    let saved_stdout = unsafe {
+        let saved = dup(1);
+        if saved < 0 || dup2(stdin.as_raw_fd(), 1) < 0 {
+            if saved >= 0 {
+                close(saved);
+            }
+            drop(stdin);
+            let _ = child.wait();
+            return Err(io::Error::last_os_error());
+        }
+        saved
+    };
+    drop(stdin);
+    Ok(Pager {
+        child,
+        saved_stdout,
+    })
+}
+
+#[cfg(not(unix))]
+fn spawn(_pager: &str) -> io::Result<Pager> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "paging is not supported on this platform",
+    ))
+}
+
+impl Drop for Pager {
+    /// Put stdout back, which closes the pipe, and wait for the user to
+    /// leave the pager
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        #[cfg(unix)]
+        // SAFETY: `saved_stdout` was returned by `dup` and is closed once
+        unsafe {
+            dup2(self.saved_stdout, 1);
+            close(self.saved_stdout);
+        }
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pager_command() {
+        assert_eq!(pager_command(None, None, None).as_deref(), Some("less"));
+        assert_eq!(
+            pager_command(Some("delta".into()), Some("most".into()), None).as_deref(),
+            Some("delta")
+        );
+        assert_eq!(
+            pager_command(None, Some("most".into()), Some("more".into())).as_deref(),
+            Some("most")
+        );
+        assert_eq!(pager_command(Some("cat".into()), None, None), None);
+        assert_eq!(pager_command(None, None, Some(String::new())), None);
+    }
+}
@@ -0,0 +1,82 @@
+//! # Pair Module
+//!
+//! Pair and mob programming: one group commits while the others are
+//! credited with `Co-authored-by` trailers. The trailers are kept in the
+//! repository's `gum.coauthors` setting, which gum's commit-msg hook appends
+//! to every commit message until the pairing is stopped.
+
+use crate::config::{self, UserConfig};
+use std::path::Path;
+
+/// Git setting holding the co-author trailers, one per line
+pub const COAUTHORS_KEY: &str = "gum.coauthors";
+
+/// `Co-authored-by` trailer crediting a group
+pub fn coauthor_trailer(user: &UserConfig) -> String {
+    format!("Co-authored-by: {} <{}>", user.name, user.email)
+}
+
+/// Co-author trailers of the repository at `dir`
+pub fn coauthors(dir: Option<&Path>) -> Vec<String> {
+    config::get_git_config(Some("--local"), COAUTHORS_KEY, dir)
+        .map(|value| value.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Credit `coauthors` in the commits of the repository at `dir`
+pub fn start(coauthors: &[UserConfig], dir: Option<&Path>) -> anyhow::Result<()> {
+    let trailers: Vec<String> = coauthors.iter().map(coauthor_trailer).collect();
+    config::apply_managed_setting(COAUTHORS_KEY, Some(&trailers.join("\n")), &[], false, dir)
+}
+
+/// Stop crediting co-authors; returns the trailers removed
+pub fn stop(dir: Option<&Path>) -> anyhow::Result<Vec<String>> {
+    let Some(current) = config::get_git_config(Some("--local"), COAUTHORS_KEY, dir) else {
+        return Ok(Vec::new());
+    };
+    config::apply_managed_setting(
+        COAUTHORS_KEY,
+        None,
+        std::slice::from_ref(&current),
+        false,
+        dir,
+    )?;
+    Ok(current.lines().map(str::to_string).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_start_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir.path())
+            .args(["init", "-q"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let coauthor = |name: &str, email: &str| UserConfig {
+            name: name.to_string(),
+            email: email.to_string(),
+            ..UserConfig::default()
+        };
+        let team = [
+            coauthor("Wang Er", "wanger@acme.com"),
+            coauthor("Zhang San", "zhangsan@acme.com"),
+        ];
+        start(&team, Some(dir.path())).unwrap();
+        let expected = [
+            "Co-authored-by: Wang Er <wanger@acme.com>",
+            "Co-authored-by: Zhang San <zhangsan@acme.com>",
+        ];
+        assert_eq!(coauthors(Some(dir.path())), expected);
+        assert_eq!(stop(Some(dir.path())).unwrap(), expected);
+        assert!(coauthors(Some(dir.path())).is_empty());
+        assert!(stop(Some(dir.path())).unwrap().is_empty());
+    }
+}
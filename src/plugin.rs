@@ -0,0 +1,91 @@
+//! # Plugin Module
+//!
+//! Supports external subcommands in the style of git and cargo: running
+//! `gum foo args...` with no built-in `foo` command executes `gum-foo args...`
+//! found on `PATH`.
+//!
+//! ## Environment passed to plugins
+//! - `GUM_CONFIG`: Resolved configuration file path
+//! - `GUM_WORKSPACE`: Active workspace (if any)
+//! - `GUM_EXE`: Path of the running gum executable
+//! - `GUM_VERSION`: Version of the running gum executable
+//! - `GUM_CURRENT_GROUP`: Group matching the effective git identity (if any)
+//! - `GUM_CURRENT_NAME` / `GUM_CURRENT_EMAIL`: Effective git identity (if any)
+
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Prefix of plugin executable names
+pub const PLUGIN_PREFIX: &str = "gum-";
+
+/// Find the executable implementing a plugin subcommand on `PATH`
+pub fn find_plugin(subcommand: &str) -> Option<PathBuf> {
+    let path_var = env::var_os("PATH")?;
+    find_plugin_in(subcommand, &path_var)
+}
+
+/// Find the executable implementing a plugin subcommand in a PATH-style list
+///
+/// On Windows every extension listed in `PATHEXT` is tried as well.
+fn find_plugin_in(subcommand: &str, path_var: &OsStr) -> Option<PathBuf> {
+    if subcommand.is_empty() || subcommand.contains(['/', '\\']) {
+        return None;
+    }
+    let file_name = format!("{}{}", PLUGIN_PREFIX, subcommand);
+    log::debug!("Searching PATH for plugin {}", file_name);
+
+    env::split_paths(path_var)
+        .flat_map(|dir| candidates(&dir, &file_name))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Candidate file paths for a plugin inside one directory
+fn candidates(dir: &Path, file_name: &str) -> Vec<PathBuf> {
+    let mut paths = vec![dir.join(file_name)];
+    if cfg!(windows) {
+        let exts = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        paths.extend(
+            exts.split(';')
+                .filter(|ext| !ext.is_empty())
+                .map(|ext| dir.join(format!("{}{}", file_name, ext.to_lowercase()))),
+        );
+    }
+    paths
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_plugin_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = dir.path().join("gum-hello");
+        fs::write(&plugin, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let path_var = env::join_paths([dir.path()]).unwrap();
+        assert_eq!(find_plugin_in("hello", &path_var), Some(plugin));
+        assert_eq!(find_plugin_in("missing", &path_var), None);
+        assert_eq!(find_plugin_in("../hello", &path_var), None);
+    }
+}
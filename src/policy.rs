@@ -0,0 +1,153 @@
+//! # Policy Module
+//!
+//! Push policies forbid identities to reach some remotes, e.g. personal
+//! addresses on the corporate GitLab, however the local configuration drifts:
+//!
+//! ```jsonc
+//! "policies": [
+//!   { "email": "*@gmail.com", "remote": "*gitlab.acme.com*" }
+//! ]
+//! ```
+//!
+//! Both patterns are shell globs (`*` and `?`) matched case-insensitively
+//! against the whole email and remote URL. gum's pre-push hook enforces them
+//! on the pushed commits and `gum check` on the current identity.
+
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+
+/// Never allow emails matching `email` to push to remotes matching `remote`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    /// Glob of the forbidden emails
+    pub email: String,
+    /// Glob of the protected remote URLs
+    pub remote: String,
+}
+
+impl Policy {
+    /// Whether the policy forbids `email` on the remote at `url`
+    pub fn forbids(&self, email: &str, url: &str) -> bool {
+        glob_match(&self.email.to_lowercase(), &email.to_lowercase())
+            && glob_match(&self.remote.to_lowercase(), &url.to_lowercase())
+    }
+}
+
+/// A remote the current identity must not push to
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Position of the policy in the configuration, from 1
+    pub policy: usize,
+    pub email: String,
+    pub remote: String,
+    pub url: String,
+}
+
+/// Remotes, given as names and URLs, that policies forbid `email` to push to
+pub fn violations(config: &Config, email: &str, remotes: &[(String, String)]) -> Vec<Violation> {
+    let mut found = Vec::new();
+    for (i, policy) in config.policies.iter().enumerate() {
+        for (name, url) in remotes {
+            if policy.forbids(email, url) {
+                found.push(Violation {
+                    policy: i + 1,
+                    email: email.to_string(),
+                    remote: name.clone(),
+                    url: url.clone(),
+                });
+            }
+        }
+    }
+    found
+}
+
+/// Policies as read by the pre-push hook, one lowercased
+/// `email<TAB>remote` line per policy
+pub fn compile(config: &Config) -> String {
+    config
+        .policies
+        .iter()
+        .map(|policy| {
+            format!(
+                "{}\t{}\n",
+                policy.email.to_lowercase(),
+                policy.remote.to_lowercase()
+            )
+        })
+        .collect()
+}
+
+/// Shell-style glob match of the whole `text`: `*` matches any run of
+/// characters (including `/`), `?` any single character
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*@gmail.com", "li@gmail.com"));
+        assert!(!glob_match("*@gmail.com", "li@gmail.com.cn"));
+        assert!(glob_match(
+            "*gitlab.acme.com*",
+            "git@gitlab.acme.com:team/app.git"
+        ));
+        assert!(glob_match("release/*", "release/1.0/rc"));
+        assert!(glob_match("v?.*", "v1.2"));
+        assert!(!glob_match("v?.*", "v10.2"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("a*b", "acbd"));
+    }
+
+    #[test]
+    fn test_violations() {
+        let mut config = Config::new();
+        config.policies.push(Policy {
+            email: "*@Gmail.com".to_string(),
+            remote: "*gitlab.acme.com*".to_string(),
+        });
+        let remotes = [
+            (
+                "origin".to_string(),
+                "git@gitlab.acme.com:team/app.git".to_string(),
+            ),
+            (
+                "github".to_string(),
+                "https://github.com/li/app.git".to_string(),
+            ),
+        ];
+        let found = violations(&config, "Li@gmail.com", &remotes);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].remote, "origin");
+        assert!(violations(&config, "li@acme.com", &remotes).is_empty());
+        assert_eq!(compile(&config), "*@gmail.com\t*gitlab.acme.com*\n");
+    }
+}
@@ -0,0 +1,331 @@
+//! # Prompt Segment Module
+//!
+//! Renders the one-line identity segment shown by `gum prompt` in shell
+//! prompts (Starship custom modules, PS1, etc) and the tmux status line. It
+//! runs on every prompt, so it skips the full configuration load: the effective
+//! identity comes from the identity cache (one git call only after a git
+//! config file changed) and the config files are read plainly. Inside a
+//! `gum shell` session the author identity of the environment wins, as it
+//! does for git.
+//!
+//! The segment is green when the identity belongs to a group and no `hosts`
+//! rule or SSH host alias of the repository's remotes asks for another one,
+//! and red otherwise: the group's name when a rule disagrees, the identity
+//! itself when no group holds it.
+//!
+//! Status lines refresh on a timer, so rendered segments can additionally be
+//! cached per directory for a few seconds (`cache_ttl`).
+
+use crate::config::{Config, UserConfig};
+use crate::identity_cache;
+use crate::resolve;
+use crate::timings;
+use crate::utils;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Default symbol shown before the group name (Nerd Font "user" glyph)
+pub const DEFAULT_SYMBOL: &str = "\u{f007}";
+
+/// Output flavor of the segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PromptStyle {
+    /// No colors
+    Plain,
+    /// ANSI escape sequences
+    Ansi,
+    /// tmux format strings (`#[fg=green]`)
+    Tmux,
+}
+
+/// Prompt segment options
+#[derive(Debug, Clone)]
+pub struct PromptOptions {
+    /// Symbol printed before the group name (may be empty)
+    pub symbol: String,
+    /// Output flavor
+    pub style: PromptStyle,
+    /// Directory to resolve the identity for (default: working directory)
+    pub dir: Option<PathBuf>,
+    /// Reuse a segment rendered for the same directory within this duration
+    pub cache_ttl: Option<Duration>,
+}
+
+impl Default for PromptOptions {
+    fn default() -> Self {
+        Self {
+            symbol: DEFAULT_SYMBOL.to_string(),
+            style: PromptStyle::Ansi,
+            dir: None,
+            cache_ttl: None,
+        }
+    }
+}
+
+/// Render the prompt segment
+///
+/// Returns an empty string when no git identity is configured at all.
+pub fn render(options: &PromptOptions) -> anyhow::Result<String> {
+    let cache_file = match options.cache_ttl {
+        Some(ttl) => {
+            let file = cache_file(options)?;
+            if let Some(segment) = read_cache(&file, ttl) {
+                log::debug!("Using cached prompt segment from {}", file.display());
+                return Ok(segment);
+            }
+            Some(file)
+        }
+        None => None,
+    };
+
+    let segment = render_uncached(options)?;
+
+    if let Some(file) = cache_file {
+        let written = file
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&file, &segment));
+        if let Err(e) = written {
+            log::debug!("Failed to write prompt cache {}: {}", file.display(), e);
+        }
+    }
+    Ok(segment)
+}
+
+fn render_uncached(options: &PromptOptions) -> anyhow::Result<String> {
+    // Read first, as it sets the git program and timeout
+    let config = Config::load_files_only()?;
+    let snapshot = identity_cache::lookup(options.dir.as_deref());
+    let user = match (environment_user(), &snapshot) {
+        (Some(user), _) => user,
+        (None, Ok(snapshot)) => snapshot.user.clone(),
+        (None, Err(_)) => return Ok(String::new()),
+    };
+    let remotes = snapshot
+        .map(|snapshot| snapshot.remotes)
+        .unwrap_or_default();
+    let expected = resolve::resolve_remotes(&config, &remotes).map(|r| r.group);
+    let status = status(config.match_groups(&user), expected);
+    Ok(timings::time(
+        || "render prompt segment".to_string(),
+        || format_segment(options, &status, &user),
+    ))
+}
+
+/// How the effective identity relates to the groups and rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Status {
+    /// The identity of this group, which the rules agree with
+    Matched(String),
+    /// The identity of this group, while a rule asks for another
+    Mismatched(String),
+    /// No group holds the identity
+    Unmanaged,
+}
+
+/// Status of an identity held by `groups` (sorted) in a repository whose
+/// rules ask for `expected`
+fn status(groups: Vec<String>, expected: Option<String>) -> Status {
+    let Some(first) = groups.first().cloned() else {
+        return Status::Unmanaged;
+    };
+    match expected {
+        Some(expected) if groups.contains(&expected) => Status::Matched(expected),
+        Some(_) => Status::Mismatched(first),
+        None => Status::Matched(first),
+    }
+}
+
+/// Author identity set in the environment, e.g. by `gum shell`
+fn environment_user() -> Option<UserConfig> {
+    let email = utils::env_value("GIT_AUTHOR_EMAIL")?;
+    Some(UserConfig {
+        name: utils::env_value("GIT_AUTHOR_NAME").unwrap_or_default(),
+        email,
+        ..UserConfig::default()
+    })
+}
+
+/// Format the segment for an identity and its status
+///
+/// A matched group is shown in green. A group the rules disagree with is
+/// shown in red, and so is an identity no group holds, by its email (or
+/// name), since it was not set through gum.
+fn format_segment(options: &PromptOptions, status: &Status, user: &UserConfig) -> String {
+    let (text, matched) = match status {
+        Status::Matched(group) => (group.as_str(), true),
+        Status::Mismatched(group) => (group.as_str(), false),
+        Status::Unmanaged if !user.email.is_empty() => (user.email.as_str(), false),
+        Status::Unmanaged => (user.name.as_str(), false),
+    };
+    let text = if options.symbol.is_empty() {
+        text.to_string()
+    } else {
+        format!("{} {}", options.symbol, text)
+    };
+
+    match (options.style, matched) {
+        (PromptStyle::Plain, _) => text,
+        (PromptStyle::Ansi, true) => format!("\x1b[32m{}\x1b[0m", text),
+        (PromptStyle::Ansi, false) => format!("\x1b[31m{}\x1b[0m", text),
+        // "#" starts a tmux format, so literal ones must be doubled
+        (PromptStyle::Tmux, true) => format!("#[fg=green]{}#[default]", text.replace('#', "##")),
+        (PromptStyle::Tmux, false) => format!("#[fg=red]{}#[default]", text.replace('#', "##")),
+    }
+}
+
+/// Cache file for a directory and output flavor
+fn cache_file(options: &PromptOptions) -> anyhow::Result<PathBuf> {
+    let dir = match options.dir {
+        Some(ref dir) => dir.clone(),
+        None => std::env::current_dir()?,
+    };
+    let dir = dir.canonicalize().unwrap_or(dir);
+
+    let mut hasher = DefaultHasher::new();
+    dir.hash(&mut hasher);
+    options.style.hash(&mut hasher);
+    options.symbol.hash(&mut hasher);
+    Ok(utils::get_cache_dir()?
+        .join("prompt")
+        .join(format!("{:016x}", hasher.finish())))
+}
+
+/// Remove the cached segments and identities not written for `max_age`,
+/// returning how many there were
+///
+/// Under `--dry-run` nothing is removed.
+pub fn clean_cache(max_age: Duration) -> anyhow::Result<usize> {
+    let mut removed = 0;
+    for name in ["prompt", identity_cache::CACHE_DIR] {
+        let dir = utils::get_cache_dir()?.join(name);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            let stale = path
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age > max_age);
+            if stale {
+                if !utils::is_dry_run() {
+                    fs::remove_file(&path)?;
+                }
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// Read a cached segment if it is younger than `ttl`
+fn read_cache(file: &Path, ttl: Duration) -> Option<String> {
+    let modified = file.metadata().ok()?.modified().ok()?;
+    let age = SystemTime::now().duration_since(modified).ok()?;
+    if age > ttl {
+        return None;
+    }
+    fs::read_to_string(file).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user() -> UserConfig {
+        UserConfig {
+            name: "li si".to_string(),
+            email: "lisi@gmail.com".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn matched() -> Status {
+        Status::Matched("work".to_string())
+    }
+
+    #[test]
+    fn test_status() {
+        let groups = || vec!["oss".to_string(), "work".to_string()];
+        assert_eq!(status(groups(), None), Status::Matched("oss".to_string()));
+        assert_eq!(
+            status(groups(), Some("work".to_string())),
+            Status::Matched("work".to_string())
+        );
+        assert_eq!(
+            status(groups(), Some("corp".to_string())),
+            Status::Mismatched("oss".to_string())
+        );
+        assert_eq!(
+            status(Vec::new(), Some("corp".to_string())),
+            Status::Unmanaged
+        );
+    }
+
+    #[test]
+    fn test_format_segment() {
+        let plain = PromptOptions {
+            symbol: "@".to_string(),
+            style: PromptStyle::Plain,
+            ..PromptOptions::default()
+        };
+        assert_eq!(format_segment(&plain, &matched(), &user()), "@ work");
+        assert_eq!(
+            format_segment(&plain, &Status::Unmanaged, &user()),
+            "@ lisi@gmail.com"
+        );
+
+        let ansi = PromptOptions {
+            symbol: String::new(),
+            ..PromptOptions::default()
+        };
+        assert_eq!(
+            format_segment(&ansi, &matched(), &user()),
+            "\x1b[32mwork\x1b[0m"
+        );
+        assert_eq!(
+            format_segment(&ansi, &Status::Mismatched("work".to_string()), &user()),
+            "\x1b[31mwork\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_format_segment_tmux() {
+        let tmux = PromptOptions {
+            symbol: "#".to_string(),
+            style: PromptStyle::Tmux,
+            ..PromptOptions::default()
+        };
+        assert_eq!(
+            format_segment(&tmux, &matched(), &user()),
+            "#[fg=green]## work#[default]"
+        );
+        assert_eq!(
+            format_segment(&tmux, &Status::Unmanaged, &user()),
+            "#[fg=red]## lisi@gmail.com#[default]"
+        );
+    }
+
+    #[test]
+    fn test_read_cache_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("segment");
+        assert!(read_cache(&file, Duration::from_secs(5)).is_none());
+
+        fs::write(&file, "work").unwrap();
+        assert_eq!(
+            read_cache(&file, Duration::from_secs(5)).as_deref(),
+            Some("work")
+        );
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(read_cache(&file, Duration::from_millis(1)).is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! # Prune Module
+//!
+//! Finds groups that have not been applied for a long time, using the
+//! history of identity changes as the record of where and when each group
+//! was used. A group is kept when it is still the identity of the global
+//! scope or of a repository it was applied to, or when other groups
+//! inherit from it. A group the history never saw applied is kept too: it
+//! may have been created after the history began, so it is not known to be
+//! old.
+//!
+//! Groups past their `expires` date are offered however recently they were
+//! used, unless other groups inherit from them. Locked groups are only
+//! offered with `--force`.
+
+use crate::config::{self, Config};
+use crate::history::{Action, Entry};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+
+/// Seconds in the 30-day months used for `--months`
+pub const MONTH_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// A group that may be pruned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub group: String,
+    /// When the group was last applied, `None` if never (since the history
+    /// began), which only an expired group can be
+    pub last_used: Option<i64>,
+    /// The expiry date, when the group has expired
+    pub expired: Option<String>,
+}
+
+/// When each group was last applied by `use`
+pub fn last_used(entries: &[Entry]) -> HashMap<String, i64> {
+    let mut last = HashMap::new();
+    for entry in entries {
+        if entry.action == Action::Use
+            && let Some(ref group) = entry.group
+        {
+            last.insert(group.clone(), entry.timestamp);
+        }
+    }
+    last
+}
+
+/// Groups whose identity is currently configured globally or in one of the
+/// repositories recorded in the history
+pub fn groups_in_use(config: &Config, entries: &[Entry]) -> BTreeSet<String> {
+    let mut in_use = BTreeSet::new();
+    if let Some(ref global) = config.global_user {
+        in_use.extend(config.find_identity(global, ""));
+    }
+    let repos: BTreeSet<&PathBuf> = entries
+        .iter()
+        .filter_map(|entry| entry.before.repo.as_ref())
+        .collect();
+    for repo in repos.into_iter().filter(|repo| repo.exists()) {
+        if let Ok(user) = config::get_effective_git_user(Some(repo)) {
+            in_use.extend(config.find_identity(&user, ""));
+        }
+    }
+    in_use
+}
+
+/// Groups of the user configuration not applied since `cutoff` or expired
+/// at `now`, oldest first
+pub fn candidates(
+    config: &Config,
+    last_used: &HashMap<String, i64>,
+    in_use: &BTreeSet<String>,
+    cutoff: i64,
+    now: i64,
+    force: bool,
+) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = config
+        .groups
+        .iter()
+        .filter(|(group, _)| config.children_of(group).is_empty())
+        .filter(|(group, _)| config.check_unlocked(group, force).is_ok())
+        .map(|(group, user)| Candidate {
+            group: group.clone(),
+            last_used: last_used.get(group).copied(),
+            expired: user.is_expired(now).then(|| user.expires.clone()).flatten(),
+        })
+        .filter(|candidate| {
+            candidate.expired.is_some()
+                || (!in_use.contains(&candidate.group)
+                    && candidate.last_used.is_some_and(|time| time < cutoff))
+        })
+        .collect();
+    candidates.sort_by(|a, b| (a.last_used, &a.group).cmp(&(b.last_used, &b.group)));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::UserConfig;
+    use crate::undo::Snapshot;
+
+    fn group(email: &str, inherit: Option<&str>) -> UserConfig {
+        UserConfig {
+            name: "Li Si".to_string(),
+            email: email.to_string(),
+            inherit: inherit.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn used(group: &str, timestamp: i64) -> Entry {
+        Entry {
+            timestamp,
+            action: Action::Use,
+            group: Some(group.to_string()),
+            from: None,
+            to: None,
+            before: Snapshot {
+                global: true,
+                repo: None,
+                values: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut config = Config::new();
+        for (name, email, inherit) in [
+            ("old", "old@example.com", None),
+            ("recent", "recent@example.com", None),
+            ("never", "never@example.com", None),
+            ("base", "base@example.com", None),
+            ("child", "child@example.com", Some("base")),
+            ("current", "current@example.com", None),
+            ("contractor", "li@client.example", None),
+        ] {
+            config
+                .groups
+                .insert(name.to_string(), group(email, inherit));
+        }
+        config.groups.get_mut("contractor").unwrap().expires = Some("1970-01-01".to_string());
+        let entries = vec![
+            used("base", 100),
+            used("child", 100),
+            used("old", 100),
+            used("recent", 900),
+            used("old", 200),
+            used("contractor", 800),
+        ];
+        let last = last_used(&entries);
+        assert_eq!(last["old"], 200);
+
+        let in_use = BTreeSet::from(["current".to_string(), "contractor".to_string()]);
+        let groups: Vec<(String, Option<i64>)> =
+            candidates(&config, &last, &in_use, 500, 86_400, false)
+                .into_iter()
+                .map(|c| (c.group, c.last_used))
+                .collect();
+        assert_eq!(
+            groups,
+            [
+                ("child".to_string(), Some(100)),
+                ("old".to_string(), Some(200)),
+                ("contractor".to_string(), Some(800)),
+            ]
+        );
+        let expired = candidates(&config, &last, &in_use, 500, 86_399, false);
+        assert!(expired.iter().all(|c| c.group != "contractor"));
+
+        config.groups.get_mut("old").unwrap().locked = true;
+        let offered = |force| {
+            candidates(&config, &last, &in_use, 500, 86_400, force)
+                .iter()
+                .any(|c| c.group == "old")
+        };
+        assert!(!offered(false));
+        assert!(offered(true));
+    }
+}
@@ -0,0 +1,156 @@
+//! # Repository Module
+//!
+//! Finds the git repository containing a directory by asking git itself, so
+//! linked worktrees, `.git` files (submodules, `git init --separate-git-dir`),
+//! bare repositories and the `GIT_DIR` / `GIT_WORK_TREE` overrides are all
+//! handled the way git handles them.
+
+use crate::utils::{self, git_command};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A git repository as seen from one directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    /// Top of the work tree, `None` for bare repositories and when the
+    /// directory is inside the git directory
+    pub work_tree: Option<PathBuf>,
+    /// Git directory of this work tree; for a linked worktree this is
+    /// `<common dir>/worktrees/<name>`
+    pub git_dir: PathBuf,
+    /// Git directory shared by all worktrees, holding config and hooks
+    pub common_dir: PathBuf,
+    /// Whether the repository is bare
+    pub bare: bool,
+}
+
+impl Repository {
+    /// Whether this is a worktree added with `git worktree add`
+    pub fn is_linked_worktree(&self) -> bool {
+        self.git_dir != self.common_dir
+    }
+
+    /// Top of the work tree, or the git directory when there is none
+    pub fn root(&self) -> &Path {
+        self.work_tree.as_deref().unwrap_or(&self.git_dir)
+    }
+}
+
+/// Repository containing `dir` (the current directory when `None`)
+pub fn detect(dir: Option<&Path>) -> Option<Repository> {
+    let output = utils::output(git_command(dir).args([
+        "rev-parse",
+        "--is-bare-repository",
+        "--is-inside-work-tree",
+        "--absolute-git-dir",
+        "--git-common-dir",
+    ]))
+    .ok()?;
+    if !output.status.success() {
+        log::debug!("Not inside a git repository");
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    let [bare, inside_work_tree, git_dir, common_dir] = lines[..] else {
+        log::warn!("Unexpected git rev-parse output: {:?}", stdout);
+        return None;
+    };
+
+    // The common dir is printed relative to the directory git ran in
+    let base = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => env::current_dir().ok()?,
+    };
+    let work_tree = if inside_work_tree == "true" {
+        let output = utils::output(git_command(dir).args(["rev-parse", "--show-toplevel"])).ok()?;
+        output.status.success().then(|| {
+            normalize(Path::new(
+                String::from_utf8_lossy(&output.stdout).trim_end(),
+            ))
+        })
+    } else {
+        None
+    };
+    let repository = Repository {
+        work_tree,
+        git_dir: normalize(Path::new(git_dir)),
+        common_dir: normalize(&base.join(common_dir)),
+        bare: bare == "true",
+    };
+    log::debug!("Detected repository: {:?}", repository);
+    Some(repository)
+}
+
+/// Resolve `.` and `..` and symlinks so paths can be compared
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Branch checked out in `dir` (the current directory when `None`), `None`
+/// on a detached HEAD
+pub fn current_branch(dir: Option<&Path>) -> Option<String> {
+    let output =
+        utils::output(git_command(dir).args(["symbolic-ref", "--short", "-q", "HEAD"])).ok()?;
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !branch.is_empty()).then_some(branch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=gum", "-c", "user.email=gum@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_detect_work_tree_and_worktree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp = normalize(tmp.path());
+        let main = tmp.join("main");
+        fs::create_dir_all(main.join("sub")).unwrap();
+        git(&main, &["init", "-q"]);
+        git(&main, &["commit", "-q", "--allow-empty", "-m", "init"]);
+
+        let repo = detect(Some(&main.join("sub"))).unwrap();
+        assert_eq!(repo.work_tree.as_deref(), Some(main.as_path()));
+        assert_eq!(repo.git_dir, main.join(".git"));
+        assert!(!repo.is_linked_worktree());
+        assert!(!repo.bare);
+
+        let linked = tmp.join("linked");
+        git(&main, &["worktree", "add", "-q", linked.to_str().unwrap()]);
+        let repo = detect(Some(&linked)).unwrap();
+        assert_eq!(repo.root(), linked);
+        assert_eq!(repo.common_dir, main.join(".git"));
+        assert!(repo.is_linked_worktree());
+    }
+
+    #[test]
+    fn test_detect_bare_and_outside() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp = normalize(tmp.path());
+        let bare = tmp.join("app.git");
+        fs::create_dir_all(&bare).unwrap();
+        git(&bare, &["init", "-q", "--bare"]);
+
+        let repo = detect(Some(&bare)).unwrap();
+        assert!(repo.bare);
+        assert_eq!(repo.work_tree, None);
+        assert_eq!(repo.root(), bare);
+
+        let outside = tmp.join("plain");
+        fs::create_dir_all(&outside).unwrap();
+        assert_eq!(detect(Some(&outside)), None);
+    }
+}
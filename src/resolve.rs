@@ -0,0 +1,357 @@
+//! # Group Resolution Module
+//!
+//! Works out which group a repository should use from its remotes. The
+//! `hosts` section of the config file maps git hosts to default groups:
+//!
+//! ```jsonc
+//! "hosts": { "github.com": "personal", "*.acme.com": "work", "github.com/acme": "work" }
+//! ```
+//!
+//! A `host/owner` entry covers the repositories of one organization or user
+//! on a shared host and wins over the host's own entry.
+//!
+//! A remote using a group's SSH host alias (`git@github-work:acme/app.git`,
+//! see `gum ssh setup`) resolves to that group before `hosts` is consulted.
+//! The `origin` remote is consulted first, then the other remotes in name
+//! order.
+
+use crate::config::Config;
+use crate::utils;
+use std::path::Path;
+
+/// A group chosen for a repository and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    pub group: String,
+    /// Human readable reason, e.g. `host github.com (remote origin)`
+    pub reason: String,
+    /// `hosts` entry that matched, `None` for an SSH host alias
+    pub rule: Option<String>,
+}
+
+/// Resolve the group for the repository at `dir` (`None`: current directory)
+pub fn resolve(config: &Config, dir: Option<&Path>) -> Option<Resolution> {
+    let remotes = remote_urls(dir);
+    log::debug!("Resolving group from {} remote(s)", remotes.len());
+    resolve_remotes(config, &remotes)
+}
+
+/// Resolve the group for a list of `(remote name, url)` pairs
+pub fn resolve_remotes(config: &Config, remotes: &[(String, String)]) -> Option<Resolution> {
+    remotes
+        .iter()
+        .find_map(|(remote, url)| resolve_remote(config, remote, url))
+}
+
+/// A remote of a repository and the group it alone would resolve to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteMatch {
+    pub remote: String,
+    pub url: String,
+    pub resolution: Option<Resolution>,
+    /// Whether this remote decides the repository's group, being the first
+    /// that resolves
+    pub chosen: bool,
+}
+
+/// Each remote with the group it resolves to, in the order consulted
+pub fn remote_matches(config: &Config, remotes: &[(String, String)]) -> Vec<RemoteMatch> {
+    let mut chosen = false;
+    remotes
+        .iter()
+        .map(|(remote, url)| {
+            let resolution = resolve_remote(config, remote, url);
+            let decides = resolution.is_some() && !chosen;
+            chosen |= decides;
+            RemoteMatch {
+                remote: remote.clone(),
+                url: url.clone(),
+                resolution,
+                chosen: decides,
+            }
+        })
+        .collect()
+}
+
+/// Groups the remotes resolve to, when they do not all agree
+pub fn conflicting_groups(matches: &[RemoteMatch]) -> Vec<&str> {
+    let mut groups: Vec<&str> = matches
+        .iter()
+        .filter_map(|m| Some(m.resolution.as_ref()?.group.as_str()))
+        .collect();
+    groups.sort();
+    groups.dedup();
+    if groups.len() > 1 { groups } else { Vec::new() }
+}
+
+/// Group a single remote resolves to
+fn resolve_remote(config: &Config, remote: &str, url: &str) -> Option<Resolution> {
+    let host = parse_host(url)?;
+    if let Some(group) = ssh_alias_group(config, &host) {
+        return Some(Resolution {
+            group: group.to_string(),
+            reason: format!("SSH alias {} (remote {})", host, remote),
+            rule: None,
+        });
+    }
+    if let Some(owner) = parse_owner(url) {
+        let pattern = format!("{}/{}", host, owner);
+        if let Some(group) = config.hosts.get(&pattern) {
+            return Some(Resolution {
+                group: group.to_string(),
+                reason: format!("owner {} (remote {})", pattern, remote),
+                rule: Some(pattern),
+            });
+        }
+    }
+    let (pattern, group) = host_group(config, &host)?;
+    Some(Resolution {
+        group: group.to_string(),
+        reason: format!("host {} (remote {})", host, remote),
+        rule: Some(pattern.to_string()),
+    })
+}
+
+/// Rule worth suggesting for a repository no rule resolves
+///
+/// Returns `host/owner` of the first remote that has an owner, e.g.
+/// `github.com/acme`; `None` when the remotes already resolve to a group.
+pub fn suggest_rule(config: &Config, dir: Option<&Path>) -> Option<String> {
+    let remotes = remote_urls(dir);
+    if resolve_remotes(config, &remotes).is_some() {
+        return None;
+    }
+    remotes
+        .iter()
+        .find_map(|(_, url)| Some(format!("{}/{}", parse_host(url)?, parse_owner(url)?)))
+}
+
+/// Group whose SSH host alias is `host`
+fn ssh_alias_group<'a>(config: &'a Config, host: &str) -> Option<&'a str> {
+    config
+        .groups
+        .iter()
+        .find(|(_, user)| {
+            user.ssh_alias
+                .as_deref()
+                .is_some_and(|alias| alias.eq_ignore_ascii_case(host))
+        })
+        .map(|(name, _)| name.as_str())
+}
+
+/// `hosts` entry and group mapped to a host
+///
+/// Exact entries win over `*.domain` wildcards, which match any subdomain.
+fn host_group<'a>(config: &'a Config, host: &str) -> Option<(&'a str, &'a str)> {
+    if let Some((pattern, group)) = config.hosts.get_key_value(host) {
+        return Some((pattern, group));
+    }
+    config
+        .hosts
+        .iter()
+        .filter_map(|(pattern, group)| Some((pattern, pattern.strip_prefix("*.")?, group)))
+        .filter(|(_, domain, _)| host.ends_with(&format!(".{}", domain)))
+        // The most specific wildcard wins
+        .max_by_key(|(_, domain, _)| domain.len())
+        .map(|(pattern, _, group)| (pattern.as_str(), group.as_str()))
+}
+
+/// Extract the host from a git remote URL
+///
+/// Handles `https://host/path`, `ssh://user@host:port/path` and the scp-like
+/// `user@host:path` form. Returns `None` for local paths.
+pub fn parse_host(url: &str) -> Option<String> {
+    let authority = match url.split_once("://") {
+        Some((scheme, rest)) if scheme != "file" => rest.split('/').next()?,
+        Some(_) => return None,
+        None => {
+            // scp-like syntax needs a colon before the first slash
+            let (authority, _) = url.split_once(':')?;
+            if authority.contains('/') || authority.len() == 1 {
+                // A path, or a Windows drive letter
+                return None;
+            }
+            authority
+        }
+    };
+    let host = authority.rsplit('@').next()?;
+    let host = match host.strip_prefix('[') {
+        // IPv6 literal
+        Some(rest) => rest.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+/// Extract the owner (organization or user) from a git remote URL
+///
+/// The owner is the first path segment, lowercased: `acme` in
+/// `git@github.com:acme/app.git`. Returns `None` for local paths and URLs
+/// with a single path segment.
+pub fn parse_owner(url: &str) -> Option<String> {
+    parse_host(url)?;
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => url.split_once(':')?.1,
+    };
+    let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+    let owner = segments.next()?;
+    segments.next()?;
+    Some(owner.trim_start_matches('~').to_lowercase())
+}
+
+/// Remote names and URLs of a repository, `origin` first
+pub fn remote_urls(dir: Option<&Path>) -> Vec<(String, String)> {
+    let mut command = utils::git();
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    let Ok(output) = utils::output(command.args(["config", "--get-regexp", r"^remote\..*\.url$"]))
+    else {
+        return Vec::new();
+    };
+
+    let mut remotes: Vec<(String, String)> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (key, url) = line.split_once(' ')?;
+            let name = key.strip_prefix("remote.")?.strip_suffix(".url")?;
+            Some((name.to_string(), url.to_string()))
+        })
+        .collect();
+    remotes.sort_by_key(|(name, _)| (name != "origin", name.clone()));
+    remotes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::UserConfig;
+
+    #[test]
+    fn test_parse_host() {
+        assert_eq!(
+            parse_host("https://github.com/slow-groovin/gum-rs.git").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            parse_host("git@GitLab.acme.com:team/app.git").as_deref(),
+            Some("gitlab.acme.com")
+        );
+        assert_eq!(
+            parse_host("ssh://git@bitbucket.org:7999/team/app.git").as_deref(),
+            Some("bitbucket.org")
+        );
+        assert_eq!(parse_host("/srv/git/app.git"), None);
+        assert_eq!(parse_host("C:/repos/app"), None);
+        assert_eq!(parse_host("file:///srv/git/app.git"), None);
+    }
+
+    #[test]
+    fn test_parse_owner() {
+        assert_eq!(
+            parse_owner("https://github.com/Slow-Groovin/gum-rs.git").as_deref(),
+            Some("slow-groovin")
+        );
+        assert_eq!(
+            parse_owner("git@github.com:acme/app.git").as_deref(),
+            Some("acme")
+        );
+        assert_eq!(
+            parse_owner("ssh://git@gitlab.acme.com:2222/team/sub/app.git").as_deref(),
+            Some("team")
+        );
+        assert_eq!(parse_owner("https://example.com/app.git"), None);
+        assert_eq!(parse_owner("/srv/git/app.git"), None);
+    }
+
+    #[test]
+    fn test_resolve_remotes() {
+        let mut config = Config::new();
+        config
+            .hosts
+            .insert("github.com".to_string(), "personal".to_string());
+        config
+            .hosts
+            .insert("*.acme.com".to_string(), "work".to_string());
+
+        let remotes = vec![
+            ("origin".to_string(), "/local/mirror".to_string()),
+            (
+                "upstream".to_string(),
+                "git@gitlab.acme.com:team/app.git".to_string(),
+            ),
+        ];
+        let resolution = resolve_remotes(&config, &remotes).unwrap();
+        assert_eq!(resolution.group, "work");
+        assert_eq!(resolution.reason, "host gitlab.acme.com (remote upstream)");
+        assert_eq!(resolution.rule.as_deref(), Some("*.acme.com"));
+
+        let remotes = vec![(
+            "origin".to_string(),
+            "https://bitbucket.org/a/b".to_string(),
+        )];
+        assert_eq!(resolve_remotes(&config, &remotes), None);
+
+        // An owner rule wins over its host
+        config
+            .hosts
+            .insert("github.com/acme".to_string(), "work".to_string());
+        let remotes = vec![(
+            "origin".to_string(),
+            "git@github.com:Acme/app.git".to_string(),
+        )];
+        let resolution = resolve_remotes(&config, &remotes).unwrap();
+        assert_eq!(resolution.group, "work");
+        assert_eq!(resolution.reason, "owner github.com/acme (remote origin)");
+
+        config.groups.insert(
+            "oss".to_string(),
+            UserConfig {
+                ssh_alias: Some("github-oss".to_string()),
+                ..UserConfig::default()
+            },
+        );
+        let remotes = vec![(
+            "origin".to_string(),
+            "git@github-oss:acme/app.git".to_string(),
+        )];
+        assert_eq!(resolve_remotes(&config, &remotes).unwrap().group, "oss");
+    }
+
+    #[test]
+    fn test_remote_matches() {
+        let mut config = Config::new();
+        config
+            .hosts
+            .insert("github.com".to_string(), "personal".to_string());
+        config
+            .hosts
+            .insert("github.com/acme".to_string(), "work".to_string());
+
+        // A fork: origin is the user's copy, upstream the organization's
+        let remotes = vec![
+            (
+                "origin".to_string(),
+                "git@github.com:li/app.git".to_string(),
+            ),
+            (
+                "upstream".to_string(),
+                "https://github.com/acme/app.git".to_string(),
+            ),
+            ("mirror".to_string(), "/srv/git/app.git".to_string()),
+        ];
+        let matches = remote_matches(&config, &remotes);
+        let groups: Vec<Option<&str>> = matches
+            .iter()
+            .map(|m| m.resolution.as_ref().map(|r| r.group.as_str()))
+            .collect();
+        assert_eq!(groups, [Some("personal"), Some("work"), None]);
+        assert_eq!(
+            matches.iter().map(|m| m.chosen).collect::<Vec<_>>(),
+            [true, false, false]
+        );
+        assert_eq!(conflicting_groups(&matches), ["personal", "work"]);
+        assert!(conflicting_groups(&matches[..1]).is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+//! # JSON-RPC Module
+//!
+//! Minimal JSON-RPC 2.0 transport over stdin/stdout, one message per line.
+//! Long-running modes (the MCP server and editor integrations) implement the
+//! `Handler` trait and let `serve_stdio` do the framing and error replies.
+
+use serde_json::{Value, json};
+use std::io::{self, BufRead, Write};
+
+/// Invalid JSON was received
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON sent is not a valid request object
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters
+pub const INVALID_PARAMS: i64 = -32602;
+/// Internal error while handling the request
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// Error returned by a method handler
+#[derive(Debug, Clone)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Error for a method the handler does not know
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(METHOD_NOT_FOUND, format!("Method not found: {}", method))
+    }
+
+    /// Error for missing or malformed parameters
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(INTERNAL_ERROR, e.to_string())
+    }
+}
+
+/// Method dispatcher for a JSON-RPC session
+pub trait Handler {
+    /// Handle a request or notification
+    ///
+    /// The return value of notifications is discarded.
+    fn handle(&mut self, method: &str, params: Value) -> Result<Value, RpcError>;
+}
+
+/// Serve JSON-RPC requests from stdin until it is closed
+///
+/// Responses are written to stdout, one per line. Diagnostics must go to
+/// stderr (the logger already does) so they do not corrupt the stream.
+pub fn serve_stdio(handler: &mut impl Handler) -> anyhow::Result<()> {
+    log::debug!("Serving JSON-RPC on stdio");
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(handler, &line) {
+            writeln!(stdout, "{}", response)?;
+            stdout.flush()?;
+        }
+    }
+
+    log::debug!("stdin closed, stopping JSON-RPC server");
+    Ok(())
+}
+
+/// Handle one incoming line and produce the response line, if any
+///
+/// Notifications (requests without an `id`) produce no response.
+/// Batches are not supported.
+pub fn handle_message(handler: &mut impl Handler, line: &str) -> Option<String> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ));
+        }
+    };
+
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        return Some(error_response(
+            id.unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, "Missing method"),
+        ));
+    };
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    log::debug!("JSON-RPC call: {}", method);
+
+    let result = handler.handle(method, params);
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string(),
+        Err(e) => error_response(id, e),
+    })
+}
+
+fn error_response(id: Value, error: RpcError) -> String {
+    log::debug!("JSON-RPC error {}: {}", error.code, error.message);
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": error.code, "message": error.message},
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl Handler for Echo {
+        fn handle(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+            match method {
+                "echo" => Ok(params),
+                _ => Err(RpcError::method_not_found(method)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_handle_message() {
+        let response = handle_message(
+            &mut Echo,
+            r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":[1]}"#,
+        );
+        let response: Value = serde_json::from_str(&response.unwrap()).unwrap();
+        assert_eq!(response["result"], json!([1]));
+
+        let response = handle_message(&mut Echo, r#"{"jsonrpc":"2.0","id":"a","method":"nope"}"#);
+        let response: Value = serde_json::from_str(&response.unwrap()).unwrap();
+        assert_eq!(response["id"], "a");
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        assert!(handle_message(&mut Echo, r#"{"jsonrpc":"2.0","method":"echo"}"#).is_none());
+
+        let response = handle_message(&mut Echo, "not json").unwrap();
+        assert!(response.contains(&PARSE_ERROR.to_string()));
+    }
+}
@@ -0,0 +1,287 @@
+//! # Rules Module
+//!
+//! Lists the `hosts` rules in the order `resolve` consults them and counts
+//! how often each rule picked the group that was applied. The counts are
+//! kept in `rule-hits.json` inside the state directory.
+//!
+//! Also compiles the `branches` rules into the file gum's pre-commit and
+//! pre-push hooks read, since git configuration cannot vary by branch.
+
+use crate::config::Config;
+use crate::policy;
+use crate::utils;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// File holding the hit counts, inside the state directory
+const HITS_FILE: &str = "rule-hits.json";
+
+/// Kind of a rule pattern
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    /// `host/owner`: one organization or user on a host
+    Owner,
+    /// An exact host
+    Host,
+    /// `*.domain`: any subdomain
+    Wildcard,
+    /// A branch name or glob, e.g. `release/*`, from `branches`
+    Branch,
+}
+
+impl RuleKind {
+    /// Kind of a pattern
+    pub fn of(pattern: &str) -> Self {
+        if pattern.contains('/') {
+            RuleKind::Owner
+        } else if pattern.starts_with("*.") {
+            RuleKind::Wildcard
+        } else {
+            RuleKind::Host
+        }
+    }
+
+    /// Lowercase name, e.g. `owner`
+    pub fn name(self) -> &'static str {
+        match self {
+            RuleKind::Owner => "owner",
+            RuleKind::Host => "host",
+            RuleKind::Wildcard => "wildcard",
+            RuleKind::Branch => "branch",
+        }
+    }
+}
+
+/// A `hosts` or `branches` rule
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// Position in precedence order, from 1; also used by `rule remove`
+    pub priority: usize,
+    pub pattern: String,
+    pub kind: RuleKind,
+    pub group: String,
+    /// How often the rule picked the group applied by `use` or `watch`
+    pub hits: u64,
+}
+
+/// The rules in the order they are consulted
+///
+/// Owner rules come first, then exact hosts, then wildcards from the most
+/// specific domain to the least; ties are sorted by pattern. Branch rules
+/// follow, longest pattern first, in the order the hooks try them.
+pub fn list(config: &Config, hits: &HashMap<String, u64>) -> Vec<Rule> {
+    let mut rules: Vec<(RuleKind, &String, &String)> = config
+        .hosts
+        .iter()
+        .map(|(pattern, group)| (RuleKind::of(pattern), pattern, group))
+        .chain(
+            config
+                .branches
+                .iter()
+                .map(|(pattern, group)| (RuleKind::Branch, pattern, group)),
+        )
+        .collect();
+    rules.sort_by_key(|(kind, pattern, _)| {
+        (*kind, std::cmp::Reverse(pattern.len()), pattern.to_string())
+    });
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(i, (kind, pattern, group))| Rule {
+            priority: i + 1,
+            pattern: pattern.clone(),
+            kind,
+            group: group.clone(),
+            hits: match kind {
+                RuleKind::Branch => 0,
+                _ => hits.get(pattern).copied().unwrap_or_default(),
+            },
+        })
+        .collect()
+}
+
+/// Rule given by priority (`2`) or pattern
+///
+/// Host patterns are matched case-insensitively, branch patterns exactly.
+pub fn find(config: &Config, target: &str) -> Option<Rule> {
+    let target = target.trim();
+    let host = target.trim_end_matches('/').to_lowercase();
+    let rules = list(config, &HashMap::new());
+    if let Some(rule) = rules.iter().find(|rule| match rule.kind {
+        RuleKind::Branch => rule.pattern == target,
+        _ => rule.pattern == host,
+    }) {
+        return Some(rule.clone());
+    }
+    let priority: usize = target.parse().ok()?;
+    rules.into_iter().find(|rule| rule.priority == priority)
+}
+
+/// First branch rule matching `branch`, the one the hooks enforce
+pub fn branch_rule(config: &Config, branch: &str) -> Option<Rule> {
+    list(config, &HashMap::new())
+        .into_iter()
+        .find(|rule| rule.kind == RuleKind::Branch && policy::glob_match(&rule.pattern, branch))
+}
+
+/// Branch rules as read by the hooks, one `pattern<TAB>group<TAB>email`
+/// line per rule in the order they are tried
+///
+/// The email is the lowercased email of the group; rules whose group cannot
+/// be resolved or has no email are left out with a warning.
+pub fn compile_branch_rules(config: &Config) -> String {
+    list(config, &HashMap::new())
+        .into_iter()
+        .filter(|rule| rule.kind == RuleKind::Branch)
+        .filter_map(|rule| {
+            let email = match config.resolve_group(&rule.group) {
+                Ok(user) if !user.email.is_empty() => user.email.to_lowercase(),
+                Ok(_) => {
+                    log::warn!("Skipping branch rule {}: group has no email", rule.pattern);
+                    return None;
+                }
+                Err(e) => {
+                    log::warn!("Skipping branch rule {}: {}", rule.pattern, e);
+                    return None;
+                }
+            };
+            Some(format!("{}\t{}\t{}\n", rule.pattern, rule.group, email))
+        })
+        .collect()
+}
+
+/// Hit counts per pattern
+pub fn load_hits() -> anyhow::Result<HashMap<String, u64>> {
+    match fs::read_to_string(hits_path()?) {
+        Ok(content) => Ok(serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable {}: {}", HITS_FILE, e);
+            HashMap::new()
+        })),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Count a use of a rule, logging instead of failing since the group was
+/// already applied
+///
+/// Nothing is counted under `--dry-run`.
+pub fn record_hit(pattern: &str) {
+    if utils::is_dry_run() {
+        return;
+    }
+    let result = load_hits().and_then(|mut hits| {
+        *hits.entry(pattern.to_string()).or_default() += 1;
+        save_hits(&hits)
+    });
+    if let Err(e) = result {
+        log::warn!("Cannot count rule hit: {}", e);
+    }
+}
+
+/// Forget the hit count of a removed rule
+pub fn forget_hits(pattern: &str) {
+    let result = load_hits().and_then(|mut hits| {
+        if hits.remove(pattern).is_some() {
+            save_hits(&hits)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        log::warn!("Cannot update rule hits: {}", e);
+    }
+}
+
+fn save_hits(hits: &HashMap<String, u64>) -> anyhow::Result<()> {
+    let path = hits_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(hits)?)?;
+    Ok(())
+}
+
+fn hits_path() -> anyhow::Result<PathBuf> {
+    Ok(utils::get_state_dir()?.join(HITS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::UserConfig;
+
+    #[test]
+    fn test_list() {
+        let mut config = Config::new();
+        for (pattern, group) in [
+            ("*.com", "fallback"),
+            ("github.com", "personal"),
+            ("*.acme.com", "work"),
+            ("github.com/acme", "work"),
+        ] {
+            config.hosts.insert(pattern.to_string(), group.to_string());
+        }
+        let hits = HashMap::from([("github.com".to_string(), 3)]);
+        let rules = list(&config, &hits);
+        let patterns: Vec<&str> = rules.iter().map(|rule| rule.pattern.as_str()).collect();
+        assert_eq!(
+            patterns,
+            ["github.com/acme", "github.com", "*.acme.com", "*.com"]
+        );
+        assert_eq!(rules[1].kind, RuleKind::Host);
+        assert_eq!(rules[1].hits, 3);
+        assert_eq!(rules[3].priority, 4);
+
+        assert_eq!(find(&config, "2").unwrap().pattern, "github.com");
+        assert_eq!(
+            find(&config, "GitHub.com/Acme/").unwrap().pattern,
+            "github.com/acme"
+        );
+        assert_eq!(find(&config, "9"), None);
+    }
+
+    #[test]
+    fn test_branch_rules() {
+        let mut config = Config::new();
+        config.groups.insert(
+            "bot".to_string(),
+            UserConfig {
+                name: "Release Bot".to_string(),
+                email: "Bot@Acme.com".to_string(),
+                ..UserConfig::default()
+            },
+        );
+        config
+            .groups
+            .insert("empty".to_string(), UserConfig::default());
+        config
+            .hosts
+            .insert("github.com".to_string(), "bot".to_string());
+        for (pattern, group) in [("*", "empty"), ("release/*", "bot"), ("Main", "bot")] {
+            config
+                .branches
+                .insert(pattern.to_string(), group.to_string());
+        }
+        let rules = list(&config, &HashMap::new());
+        let patterns: Vec<&str> = rules.iter().map(|rule| rule.pattern.as_str()).collect();
+        assert_eq!(patterns, ["github.com", "release/*", "Main", "*"]);
+        assert_eq!(rules[1].kind, RuleKind::Branch);
+
+        assert_eq!(find(&config, "Main").unwrap().kind, RuleKind::Branch);
+        assert_eq!(find(&config, "main"), None);
+        assert_eq!(find(&config, "2").unwrap().pattern, "release/*");
+        assert_eq!(
+            branch_rule(&config, "release/1.0").unwrap().group,
+            "bot".to_string()
+        );
+        assert_eq!(branch_rule(&config, "dev").unwrap().pattern, "*");
+
+        assert_eq!(
+            compile_branch_rules(&config),
+            "release/*\tbot\tbot@acme.com\nMain\tbot\tbot@acme.com\n"
+        );
+    }
+}
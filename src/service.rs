@@ -0,0 +1,260 @@
+//! # Service Module
+//!
+//! Operations shared by gum's long-running modes (the MCP server, the
+//! editor JSON-RPC mode and the HTTP API). Each operation loads the
+//! configuration afresh, so edits made by other gum processes are picked up,
+//! and returns JSON.
+
+use crate::batch::{self, Operation};
+use crate::config::{self, Config};
+use crate::history;
+use crate::repo;
+use crate::resolve;
+use crate::rules;
+use crate::undo;
+use serde_json::{Value, json};
+use std::path::Path;
+
+/// List all groups and the effective identity of the current directory
+pub fn list_profiles() -> anyhow::Result<Value> {
+    let config = load_config()?;
+    let mut names: Vec<&String> = config
+        .groups
+        .keys()
+        .chain(config.system_groups.keys())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    let profiles: Vec<Value> = names
+        .into_iter()
+        .filter_map(|name| {
+            let user = config.get_group(name)?;
+            Some(json!({
+                "group": name,
+                "name": user.name,
+                "email": user.email,
+                "source": if config.is_system_group(name) { "system" } else { "user" },
+            }))
+        })
+        .collect();
+
+    let current = config
+        .get_using_git_user()
+        .ok()
+        .map(|user| json!({"name": user.name, "email": user.email}));
+    Ok(json!({"profiles": profiles, "current": current}))
+}
+
+/// Resolve the identity configured for the repository at `dir`
+///
+/// Looks at the local identity first, then the global one, and reports the
+/// group holding that identity, if any, along with the group suggested by
+/// the `hosts` mapping for the repository's remotes.
+pub fn resolve_identity(dir: &Path) -> anyhow::Result<Value> {
+    if !is_git_repository_at(dir) {
+        return Err(anyhow::anyhow!(
+            "{} is not inside a git repository",
+            dir.display()
+        ));
+    }
+
+    let config = load_config()?;
+    let (scope, user) = match config::get_git_user_in(false, Some(dir)) {
+        Ok(user) => ("local", Some(user)),
+        Err(_) => match config::get_git_user_in(true, Some(dir)) {
+            Ok(user) => ("global", Some(user)),
+            Err(_) => ("none", None),
+        },
+    };
+
+    let group = user
+        .as_ref()
+        .and_then(|user| config.match_groups(user).into_iter().next());
+    Ok(json!({
+        "path": dir,
+        "scope": scope,
+        "name": user.as_ref().map(|u| u.name.clone()),
+        "email": user.as_ref().map(|u| u.email.clone()),
+        "group": group,
+        "suggested_group": resolve::resolve(&config, Some(dir)).map(|r| r.group),
+    }))
+}
+
+/// Apply a group
+///
+/// Arguments: `group` (required), `path` (required unless `global`), `global`.
+pub fn apply_profile(args: &Value) -> anyhow::Result<Value> {
+    let config = load_config()?;
+    let group_name = config.normalize_group_name(string_arg(args, "group")?);
+    let global = args.get("global").and_then(Value::as_bool).unwrap_or(false);
+
+    let all_config = config.get_all_config_info();
+    let user = all_config
+        .get(&group_name)
+        .ok_or_else(|| anyhow::anyhow!("{} is an invalid group name", group_name))?;
+
+    if global && config.settings.use_config_only {
+        return Err(anyhow::anyhow!(
+            "use_config_only is enabled, groups can only be applied to repositories"
+        ));
+    }
+
+    if config.get_group(&group_name).is_some() {
+        config.inheritance_chain(&group_name)?;
+    }
+    let variant_user;
+    let user = match args.get("email_variant").and_then(Value::as_str) {
+        Some(variant) => {
+            variant_user = user.with_email_variant(variant)?;
+            &variant_user
+        }
+        None => user,
+    };
+
+    let path = args.get("path").and_then(Value::as_str);
+    if !global {
+        let path = path.ok_or_else(|| anyhow::anyhow!("path is required unless global is true"))?;
+        if !is_git_repository_at(Path::new(path)) {
+            return Err(anyhow::anyhow!("{} is not inside a git repository", path));
+        }
+    }
+
+    let before = undo::capture(global, path.map(Path::new), &config.url_rewrite_keys());
+    config::set_git_user_in(user, global, path.map(Path::new))?;
+    config.apply_group_settings(user, global, path.map(Path::new))?;
+    match before {
+        Ok(before) => history::record(
+            history::Action::Use,
+            before,
+            Some(user),
+            Some(group_name.clone()),
+        ),
+        Err(e) => log::warn!("Cannot capture the previous identity: {}", e),
+    }
+    let removed_global = if config.settings.use_config_only {
+        config::enforce_use_config_only()?
+    } else {
+        None
+    };
+    if let Some(ref removed) = removed_global {
+        log::warn!(
+            "Removed global identity {} <{}> (use_config_only)",
+            removed.name,
+            removed.email
+        );
+    }
+    log::info!("Applied group {} (global: {})", group_name, global);
+    Ok(json!({
+        "group": group_name,
+        "scope": if global { "global" } else { "local" },
+        "path": path,
+        "name": user.name,
+        "email": user.email,
+        "removed_global": removed_global.map(|removed| json!({
+            "name": removed.name,
+            "email": removed.email,
+        })),
+    }))
+}
+
+/// Apply batch operations to the config file, all or nothing
+pub fn apply_operations(operations: &[Operation]) -> anyhow::Result<Value> {
+    let mut config = load_config()?;
+    batch::apply(&mut config, operations)?;
+    config.save().map_err(|e| anyhow::anyhow!("{}", e))?;
+    let applied: Vec<String> = operations.iter().map(Operation::describe).collect();
+    log::info!("Applied operations: {:?}", applied);
+    Ok(json!({"applied": applied}))
+}
+
+/// Rules of the `hosts` and `branches` sections in the order they are
+/// consulted
+pub fn list_rules() -> anyhow::Result<Value> {
+    let config = load_config()?;
+    let hits = rules::load_hits()?;
+    let rules: Vec<Value> = rules::list(&config, &hits)
+        .into_iter()
+        .map(|rule| {
+            json!({
+                "host": rule.pattern,
+                "group": rule.group,
+                "kind": rule.kind,
+                "priority": rule.priority,
+                "hits": rule.hits,
+            })
+        })
+        .collect();
+    Ok(json!({"rules": rules}))
+}
+
+/// Group the rules pick for a repository's remotes, with the rule and
+/// remote deciding it, and the branch rule matching `branch`
+pub fn test_rules(remotes: &[(String, String)], branch: Option<&str>) -> anyhow::Result<Value> {
+    let config = load_config()?;
+    let matches = resolve::remote_matches(&config, remotes);
+    let chosen = matches
+        .iter()
+        .find(|m| m.chosen)
+        .and_then(|m| m.resolution.clone());
+    let remotes: Vec<Value> = matches
+        .iter()
+        .map(|m| {
+            json!({
+                "remote": m.remote,
+                "url": m.url,
+                "group": m.resolution.as_ref().map(|r| &r.group),
+                "rule": m.resolution.as_ref().and_then(|r| r.rule.as_ref()),
+                "chosen": m.chosen,
+            })
+        })
+        .collect();
+    let branch_rule = branch
+        .and_then(|branch| rules::branch_rule(&config, branch))
+        .map(|rule| json!({"pattern": rule.pattern, "group": rule.group}));
+    Ok(json!({
+        "group": chosen.as_ref().map(|r| &r.group),
+        "reason": chosen.as_ref().map(|r| &r.reason),
+        "rule": chosen.as_ref().and_then(|r| r.rule.as_ref()),
+        "remotes": remotes,
+        "branch_rule": branch_rule,
+    }))
+}
+
+/// Remove a host or branch rule, given by pattern or priority
+pub fn remove_rule(host: &str) -> anyhow::Result<Value> {
+    let mut config = load_config()?;
+    let rule = rules::find(&config, host).ok_or_else(|| anyhow::anyhow!("No rule {}", host))?;
+    let branch = rule.kind == rules::RuleKind::Branch;
+    if branch {
+        config.branches.remove(&rule.pattern);
+    } else {
+        config.hosts.remove(&rule.pattern);
+    }
+    config.save().map_err(|e| anyhow::anyhow!("{}", e))?;
+    if !branch {
+        rules::forget_hits(&rule.pattern);
+    }
+    log::info!("Removed rule {} -> {}", rule.pattern, rule.group);
+    Ok(json!({"host": rule.pattern, "kind": rule.kind, "group": rule.group}))
+}
+
+/// Load the configuration, failing if the file could not be parsed
+fn load_config() -> anyhow::Result<Config> {
+    let config = Config::load().map_err(|e| anyhow::anyhow!("{}", e))?;
+    if let Some(e) = config.file_error {
+        return Err(anyhow::anyhow!("Failed to load config file: {}", e));
+    }
+    Ok(config)
+}
+
+/// Get a required string argument
+pub fn string_arg<'a>(args: &'a Value, key: &str) -> anyhow::Result<&'a str> {
+    args.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("Missing string argument: {}", key))
+}
+
+fn is_git_repository_at(dir: &Path) -> bool {
+    repo::detect(Some(dir)).is_some()
+}
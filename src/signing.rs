@@ -0,0 +1,114 @@
+//! # Signing Module
+//!
+//! Checks that a group's commit signing setup works by signing a throwaway
+//! payload the way git does (`gpg -bsau <key>` for OpenPGP,
+//! `ssh-keygen -Y sign -n git` for SSH), and explains common failures.
+
+use crate::utils;
+use std::path::Path;
+use std::process::Command;
+
+/// Payload signed by [`verify`]
+const PAYLOAD: &[u8] = b"gum signing check\n";
+
+/// Sign a throwaway payload with `key`
+///
+/// `format` is the `gpg.format` value (`None` means openpgp). Errors carry
+/// a diagnosis such as a missing, expired or revoked key or an agent that
+/// cannot ask for the passphrase.
+pub fn verify(key: &str, format: Option<&str>) -> anyhow::Result<()> {
+    match format.unwrap_or("openpgp") {
+        "openpgp" => sign("gpg", &["--batch", "--status-fd=2", "-bsau", key], key),
+        "ssh" => {
+            let path = utils::expand_home(key);
+            if !key.starts_with("key::") && !Path::new(&path).exists() {
+                anyhow::bail!("key file {} does not exist", path);
+            }
+            sign("ssh-keygen", &["-Y", "sign", "-n", "git", "-f", &path], key)
+        }
+        other => anyhow::bail!("verifying {} signatures is not supported", other),
+    }
+}
+
+/// Run a signing program on the payload, diagnosing failures from stderr
+fn sign(program: &str, args: &[&str], key: &str) -> anyhow::Result<()> {
+    let output = utils::output_with_input(Command::new(program).args(args), PAYLOAD)
+        .map_err(|e| anyhow::anyhow!("Failed to run {} (is it installed?): {}", program, e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    log::debug!("{} stderr: {}", program, stderr.trim());
+    if !output.status.success() || output.stdout.is_empty() {
+        anyhow::bail!("{}", diagnose(&stderr, key));
+    }
+    Ok(())
+}
+
+/// Explain a signing failure from the signing program's stderr
+fn diagnose(stderr: &str, key: &str) -> String {
+    let lower = stderr.to_lowercase();
+    let reason = if lower.contains("keyexpired") || lower.contains("key expired") {
+        format!("key {} has expired", key)
+    } else if lower.contains("keyrevoked") || lower.contains("key revoked") {
+        format!("key {} has been revoked", key)
+    } else if lower.contains("no secret key") || lower.contains("no_seckey") {
+        format!("no secret key {} in the gpg keyring", key)
+    } else if lower.contains("inappropriate ioctl") || lower.contains("pinentry") {
+        "gpg-agent cannot ask for the passphrase (set GPG_TTY=$(tty) or configure a pinentry)"
+            .to_string()
+    } else if lower.contains("agent") && lower.contains("refused") {
+        "ssh-agent refused to sign with the key".to_string()
+    } else if lower.contains("load key") || lower.contains("no such file") {
+        format!("cannot load key {}", key)
+    } else if lower.contains("incorrect passphrase") || lower.contains("bad passphrase") {
+        "incorrect passphrase".to_string()
+    } else {
+        // Status lines are noise for users, keep the human readable part
+        let message: Vec<&str> = stderr
+            .lines()
+            .filter(|line| !line.starts_with("[GNUPG:]") && !line.trim().is_empty())
+            .collect();
+        message.join("; ")
+    };
+    if reason.is_empty() {
+        "signing failed".to_string()
+    } else {
+        reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        assert_eq!(
+            diagnose(
+                "[GNUPG:] KEYEXPIRED 1600000000\n[GNUPG:] INV_SGNR 9 ABCD\n",
+                "ABCD"
+            ),
+            "key ABCD has expired"
+        );
+        assert_eq!(
+            diagnose("gpg: skipped \"ABCD\": No secret key\n", "ABCD"),
+            "no secret key ABCD in the gpg keyring"
+        );
+        assert!(
+            diagnose(
+                "gpg: signing failed: Inappropriate ioctl for device\n",
+                "ABCD"
+            )
+            .starts_with("gpg-agent cannot ask")
+        );
+        assert_eq!(
+            diagnose("[GNUPG:] FAILURE sign 1\ngpg: weird\n", "ABCD"),
+            "gpg: weird"
+        );
+    }
+
+    #[test]
+    fn test_verify_missing_ssh_key() {
+        let err = verify("/nonexistent/id_gum", Some("ssh")).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+        assert!(verify("cert", Some("x509")).is_err());
+    }
+}
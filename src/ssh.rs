@@ -0,0 +1,320 @@
+//! # SSH Module
+//!
+//! Manages `Host` aliases in `~/.ssh/config` so each group can reach a forge
+//! with its own key, e.g. `git@github-work:acme/app.git` for the work
+//! account and `git@github.com:...` for the personal one.
+//!
+//! gum only touches the section between its marker lines; everything else in
+//! the file is preserved byte for byte.
+//!
+//! Keys are generated with the system `ssh-keygen`.
+
+use crate::journal;
+use crate::utils;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// First line of the section managed by gum
+const SECTION_BEGIN: &str = "# >>> managed by gum >>>";
+/// Last line of the section managed by gum
+const SECTION_END: &str = "# <<< managed by gum <<<";
+
+/// Path of the user's SSH client configuration
+pub fn ssh_config_path() -> anyhow::Result<PathBuf> {
+    let home =
+        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot determine the home directory"))?;
+    Ok(home.join(".ssh").join("config"))
+}
+
+/// Default host alias for a group, e.g. `github-work` for github.com
+pub fn default_alias(hostname: &str, group_name: &str) -> String {
+    let label = hostname.split('.').next().unwrap_or(hostname);
+    format!("{}-{}", label, group_name)
+}
+
+/// `Host` block routing `alias` to `hostname` with `key`
+pub fn host_block(alias: &str, hostname: &str, key: &str) -> String {
+    let key = if key.contains(' ') {
+        format!("\"{}\"", key)
+    } else {
+        key.to_string()
+    };
+    format!(
+        "Host {}\n  HostName {}\n  User git\n  IdentityFile {}\n  IdentitiesOnly yes\n",
+        alias, hostname, key
+    )
+}
+
+/// Default private key path for a group, e.g. `~/.ssh/id_ed25519_work`
+pub fn default_key_path(group_name: &str) -> String {
+    format!("~/.ssh/id_ed25519_{}", group_name)
+}
+
+/// Generate an ed25519 keypair at `key` (`~/` is expanded)
+///
+/// `ssh-keygen` runs attached to the terminal so it can ask for a
+/// passphrase. The key gets no passphrase only with `no_passphrase`; in
+/// non-interactive mode it is required, since nobody can type one. An
+/// existing key is never overwritten. Returns the public key.
+pub fn keygen(key: &str, comment: &str, no_passphrase: bool) -> anyhow::Result<String> {
+    let path = PathBuf::from(utils::expand_home(key));
+    if path.exists() {
+        anyhow::bail!("{} already exists", path.display());
+    }
+    if !no_passphrase && utils::is_non_interactive() {
+        return Err(utils::input_required("ssh keygen"));
+    }
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+        && !dir.exists()
+        && !utils::is_dry_run()
+    {
+        fs::create_dir_all(dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+        }
+    }
+
+    log::info!("Generating ed25519 key {}", path.display());
+    let mut command = Command::new("ssh-keygen");
+    command.args(["-t", "ed25519", "-C", comment]);
+    if no_passphrase {
+        command.args(["-N", ""]);
+    }
+    let status = utils::run_change(command.arg("-f").arg(&path))
+        .map_err(|e| anyhow::anyhow!("Failed to run ssh-keygen (is it installed?): {}", e))?;
+    let Some(status) = status else {
+        return Ok(String::new());
+    };
+    if !status.success() {
+        anyhow::bail!("ssh-keygen failed");
+    }
+    Ok(fs::read_to_string(public_key_path(&path))?
+        .trim()
+        .to_string())
+}
+
+/// Public key file of a private key
+pub fn public_key_path(key: &Path) -> PathBuf {
+    let mut path = key.as_os_str().to_owned();
+    path.push(".pub");
+    PathBuf::from(path)
+}
+
+/// Add or replace the block for `alias` in the gum section of `content`
+///
+/// The section is created at the top of the file when missing, because ssh
+/// uses the first value it finds for each option.
+pub fn upsert_host(content: &str, alias: &str, block: &str) -> String {
+    let (before, section, after) = split_section(content);
+    let mut blocks = parse_blocks(section);
+    match blocks.iter_mut().find(|(name, _)| name == alias) {
+        Some(existing) => existing.1 = block.to_string(),
+        None => blocks.push((alias.to_string(), block.to_string())),
+    }
+
+    let mut rendered = String::from(SECTION_BEGIN);
+    rendered.push('\n');
+    for (_, block) in &blocks {
+        rendered.push_str(block);
+    }
+    rendered.push_str(SECTION_END);
+    rendered.push('\n');
+
+    match section {
+        Some(_) => format!("{}{}{}", before, rendered, after),
+        None if content.is_empty() => rendered,
+        None => format!("{}\n{}", rendered, content),
+    }
+}
+
+/// Write the `Host` block for `alias` into `~/.ssh/config`
+///
+/// Creates `~/.ssh` and the file with owner-only permissions when missing.
+/// Returns the path of the file.
+pub fn setup_host(alias: &str, hostname: &str, key: &str) -> anyhow::Result<PathBuf> {
+    let path = ssh_config_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let updated = upsert_host(&content, alias, &host_block(alias, hostname, key));
+    if updated == content {
+        log::debug!("{} already up to date", path.display());
+        return Ok(path);
+    }
+    if utils::preview_write(&path, &content, &updated) {
+        return Ok(path);
+    }
+
+    if let Some(dir) = path.parent()
+        && !dir.exists()
+    {
+        fs::create_dir_all(dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    write_atomically(&path, &updated)?;
+    journal::record_write(&path, &content, &updated);
+    log::info!("Wrote Host {} to {}", alias, path.display());
+    Ok(path)
+}
+
+/// Replace the file at `path` through a temporary file renamed over it, so
+/// an interrupted write never leaves ssh a truncated config
+///
+/// A symlinked file (e.g. from a dotfiles repository) is written through
+/// the link, and the permissions of the existing file are kept; a new file
+/// is readable by its owner only.
+fn write_atomically(path: &Path, content: &str) -> anyhow::Result<()> {
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let tmp_path = target.with_extension("tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    // Restricted before the content goes in
+    match fs::metadata(&target) {
+        Ok(metadata) => file.set_permissions(metadata.permissions())?,
+        #[cfg(unix)]
+        Err(_) => {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        #[cfg(not(unix))]
+        Err(_) => {}
+    }
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, &target)?;
+    Ok(())
+}
+
+/// Split `content` around the gum section: text before, the section body
+/// (between the markers) and text after the end marker
+fn split_section(content: &str) -> (&str, Option<&str>, &str) {
+    let Some(start) = find_line(content, SECTION_BEGIN, 0) else {
+        return (content, None, "");
+    };
+    let body_start = start + SECTION_BEGIN.len() + 1;
+    let Some(end) = find_line(content, SECTION_END, body_start.min(content.len())) else {
+        // An unterminated section runs to the end of the file
+        return (
+            &content[..start],
+            Some(&content[body_start.min(content.len())..]),
+            "",
+        );
+    };
+    let after = (end + SECTION_END.len() + 1).min(content.len());
+    (
+        &content[..start],
+        Some(&content[body_start..end]),
+        &content[after..],
+    )
+}
+
+/// Byte offset of the first line equal to `line` at or after `from`
+fn find_line(content: &str, line: &str, from: usize) -> Option<usize> {
+    let mut offset = from;
+    for current in content[from..].split_inclusive('\n') {
+        if current.trim_end() == line {
+            return Some(offset);
+        }
+        offset += current.len();
+    }
+    None
+}
+
+/// `Host` blocks of the gum section as `(alias, text)` pairs
+fn parse_blocks(section: Option<&str>) -> Vec<(String, String)> {
+    let mut blocks: Vec<(String, String)> = Vec::new();
+    for line in section.unwrap_or_default().split_inclusive('\n') {
+        let line = if line.ends_with('\n') {
+            line.to_string()
+        } else {
+            format!("{}\n", line)
+        };
+        // Lines before the first block are dropped
+        if let Some(alias) = line.trim().strip_prefix("Host ") {
+            blocks.push((alias.trim().to_string(), line));
+        } else if let Some((_, text)) = blocks.last_mut() {
+            text.push_str(&line);
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_host_new_file() {
+        let block = host_block("github-work", "github.com", "~/.ssh/id_work");
+        let content = upsert_host("", "github-work", &block);
+        assert_eq!(
+            content,
+            "# >>> managed by gum >>>\nHost github-work\n  HostName github.com\n  User git\n  IdentityFile ~/.ssh/id_work\n  IdentitiesOnly yes\n# <<< managed by gum <<<\n"
+        );
+        // Running again changes nothing
+        assert_eq!(upsert_host(&content, "github-work", &block), content);
+    }
+
+    #[test]
+    fn test_upsert_host_keeps_user_entries() {
+        let existing = "Host *\n  AddKeysToAgent yes\n";
+        let work = host_block("github-work", "github.com", "~/.ssh/id_work");
+        let content = upsert_host(existing, "github-work", &work);
+        assert!(content.starts_with(SECTION_BEGIN));
+        assert!(content.ends_with(existing));
+
+        let oss = host_block("github-oss", "github.com", "~/.ssh/id_oss");
+        let content = upsert_host(&content, "github-oss", &oss);
+        let replaced = host_block("github-work", "github.com", "~/.ssh/id_new");
+        let content = upsert_host(&content, "github-work", &replaced);
+        assert_eq!(
+            content,
+            format!(
+                "{}\n{}{}{}\n\n{}",
+                SECTION_BEGIN, replaced, oss, SECTION_END, existing
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config");
+        write_atomically(&config, "Host a\n").unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&config), 0o600);
+
+        // Existing permissions are kept and links written through
+        fs::set_permissions(&config, fs::Permissions::from_mode(0o640)).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&config, &link).unwrap();
+        write_atomically(&link, "Host b\n").unwrap();
+        assert!(
+            fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink()
+        );
+        assert_eq!(fs::read_to_string(&config).unwrap(), "Host b\n");
+        assert_eq!(mode(&config), 0o640);
+        assert!(!dir.path().join("config.tmp").exists());
+    }
+
+    #[test]
+    fn test_default_alias() {
+        assert_eq!(default_alias("github.com", "work"), "github-work");
+        assert_eq!(default_alias("gitlab.acme.com", "oss"), "gitlab-oss");
+    }
+}
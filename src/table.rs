@@ -0,0 +1,360 @@
+//! # Table Module
+//!
+//! Renders rows of text as a box-drawn table whose columns grow to fit
+//! their longest cell, so names and emails are never cut off. When the table
+//! is wider than the terminal, each row is printed as a record of
+//! `header: value` lines instead. Under `--plain` the columns are aligned
+//! with spaces only. Section titles split the rows into groups under a
+//! single header, so every section shares the same column widths.
+
+use crate::{term, timings, utils};
+
+/// A table with a header row
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    /// Section titles and the index of the row each one precedes
+    sections: Vec<(usize, String)>,
+}
+
+/// What the last rendered line of a box-drawn table was, which decides the
+/// rule drawn below it
+#[derive(PartialEq)]
+enum Line {
+    Header,
+    Title,
+    Row,
+}
+
+impl Table {
+    /// Empty table with the given column headers
+    pub fn new(headers: &[&str]) -> Self {
+        Self {
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+            rows: Vec::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Start a section: `title` is printed across the table before the rows
+    /// pushed after it
+    pub fn push_section(&mut self, title: &str) {
+        self.sections.push((self.rows.len(), title.to_string()));
+    }
+
+    /// Titles of the sections that start before row `index`
+    fn titles_at(&self, index: usize) -> impl Iterator<Item = &str> {
+        self.sections
+            .iter()
+            .filter(move |(at, _)| *at == index)
+            .map(|(_, title)| title.as_str())
+    }
+
+    /// Append a row; missing cells are left empty and extra cells ignored
+    ///
+    /// Emails in the cells are masked under `--redact`.
+    pub fn push(&mut self, row: Vec<String>) {
+        let mut row: Vec<String> = row.iter().map(|cell| utils::redact(cell)).collect();
+        row.resize(self.headers.len(), String::new());
+        self.rows.push(row);
+    }
+
+    /// Width of each column, the longest of its header and cells; the last
+    /// column grows when a section title is wider than the table
+    fn column_widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| {
+                self.rows
+                    .iter()
+                    .map(|row| text_width(&row[i]))
+                    .chain([text_width(header)])
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let inner = inner_width(&widths);
+        let title = self
+            .sections
+            .iter()
+            .map(|(_, title)| text_width(title))
+            .max()
+            .unwrap_or_default();
+        if let Some(last) = widths.last_mut() {
+            *last += title.saturating_sub(inner);
+        }
+        widths
+    }
+
+    /// Width of the rendered table in columns
+    pub fn width(&self) -> usize {
+        let widths = self.column_widths();
+        if term::is_plain() {
+            return widths.iter().sum::<usize>() + COLUMN_GAP * widths.len().saturating_sub(1);
+        }
+        widths.iter().map(|width| width + 3).sum::<usize>() + 1
+    }
+
+    /// Render as a table, or as records when wider than `max_width`
+    pub fn render_to_fit(&self, max_width: Option<usize>) -> String {
+        match max_width {
+            Some(max_width) if self.width() > max_width => self.render_records(),
+            _ => self.render(),
+        }
+    }
+
+    /// Render as a box-drawn table, or as plain columns under `--plain`
+    pub fn render(&self) -> String {
+        if term::is_plain() {
+            return timings::time(|| "render columns".to_string(), || self.render_columns());
+        }
+        timings::time(|| "render table".to_string(), || self.render_table())
+    }
+
+    /// Header and rows in columns separated by spaces, without trailing
+    /// spaces
+    fn render_columns(&self) -> String {
+        let widths = self.column_widths();
+        let gap = " ".repeat(COLUMN_GAP);
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| pad(cell, *width))
+                .collect();
+            format!("{}\n", cells.join(&gap).trim_end())
+        };
+        let mut out = line(&self.headers);
+        for i in 0..=self.rows.len() {
+            for title in self.titles_at(i) {
+                out.push_str(&format!("{}\n", title));
+            }
+            if let Some(row) = self.rows.get(i) {
+                out.push_str(&line(row));
+            }
+        }
+        out
+    }
+
+    fn render_table(&self) -> String {
+        let widths = self.column_widths();
+        let rule = |left: &str, middle: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+            format!("{}{}{}\n", left, segments.join(middle), right)
+        };
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| pad(cell, *width))
+                .collect();
+            format!("│ {} │\n", cells.join(" │ "))
+        };
+
+        let inner = inner_width(&widths);
+
+        let mut out = rule("┌", "┬", "┐");
+        out.push_str(&line(&self.headers));
+        let mut last = Line::Header;
+        for i in 0..=self.rows.len() {
+            for title in self.titles_at(i) {
+                out.push_str(&match last {
+                    Line::Title => rule("├", "─", "┤"),
+                    _ => rule("├", "┴", "┤"),
+                });
+                out.push_str(&format!("│ {} │\n", pad(title, inner)));
+                last = Line::Title;
+            }
+            if let Some(row) = self.rows.get(i) {
+                match last {
+                    Line::Header => out.push_str(&rule("├", "┼", "┤")),
+                    Line::Title => out.push_str(&rule("├", "┬", "┤")),
+                    Line::Row => {}
+                }
+                out.push_str(&line(row));
+                last = Line::Row;
+            }
+        }
+        out.push_str(&match last {
+            Line::Header => rule("├", "┼", "┤") + &rule("└", "┴", "┘"),
+            Line::Title => rule("└", "─", "┘"),
+            Line::Row => rule("└", "┴", "┘"),
+        });
+        out
+    }
+
+    /// Render each row as `header: value` lines, rows separated by a blank
+    /// line; empty cells are left out and section titles head the first
+    /// record of their section
+    pub fn render_records(&self) -> String {
+        timings::time(|| "render records".to_string(), || self.render_lines())
+    }
+
+    fn render_lines(&self) -> String {
+        let label_width = self
+            .headers
+            .iter()
+            .map(|h| text_width(h))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let records: Vec<String> = (0..=self.rows.len())
+            .filter_map(|i| {
+                let titles: String = self
+                    .titles_at(i)
+                    .map(|title| format!("{}\n", title))
+                    .collect();
+                let fields: String = self
+                    .headers
+                    .iter()
+                    .zip(self.rows.get(i)?)
+                    .filter(|(_, cell)| !cell.is_empty())
+                    .map(|(header, cell)| {
+                        format!("{} {}\n", pad(&format!("{}:", header), label_width), cell)
+                    })
+                    .collect();
+                Some(titles + &fields)
+            })
+            .collect();
+        records.join("\n")
+    }
+}
+
+/// Columns taken by `text` in a terminal
+///
+/// East Asian wide characters (CJK, Hangul, fullwidth forms, emoji) take two
+/// columns, ANSI color sequences none, everything else one.
+fn text_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| *c == 'm');
+            continue;
+        }
+        width += if is_wide(c) { 2 } else { 1 };
+    }
+    width
+}
+
+/// Whether a character is displayed two columns wide
+fn is_wide(c: char) -> bool {
+    matches!(
+        u32::from(c),
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD
+    )
+}
+
+/// Width between the outer borders of a box-drawn table with these columns
+fn inner_width(widths: &[usize]) -> usize {
+    widths.iter().sum::<usize>() + 3 * widths.len().saturating_sub(1)
+}
+
+/// Spaces between the columns of a plain table
+const COLUMN_GAP: usize = 2;
+
+/// `text` padded with spaces to `width` columns
+fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(text_width(text)))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Table {
+        let mut table = Table::new(&["group", "email"]);
+        table.push(vec!["work".to_string(), "li@acme.com".to_string()]);
+        table.push(vec!["oss".to_string()]);
+        table
+    }
+
+    #[test]
+    fn test_render() {
+        let table = sample();
+        assert_eq!(
+            table.render(),
+            "┌───────┬─────────────┐\n\
+             │ group │ email       │\n\
+             ├───────┼─────────────┤\n\
+             │ work  │ li@acme.com │\n\
+             │ oss   │             │\n\
+             └───────┴─────────────┘\n"
+        );
+        assert_eq!(table.width(), 23);
+        assert_eq!(table.render_to_fit(Some(23)), table.render());
+    }
+
+    #[test]
+    fn test_render_sections() {
+        let mut table = Table::new(&["group", "email"]);
+        table.push_section("company (1)");
+        table.push(vec!["work".to_string(), "li@acme.com".to_string()]);
+        table.push_section("untagged groups (10)");
+        table.push(vec!["oss".to_string()]);
+        assert_eq!(
+            table.render(),
+            "┌───────┬──────────────┐\n\
+             │ group │ email        │\n\
+             ├───────┴──────────────┤\n\
+             │ company (1)          │\n\
+             ├───────┬──────────────┤\n\
+             │ work  │ li@acme.com  │\n\
+             ├───────┴──────────────┤\n\
+             │ untagged groups (10) │\n\
+             ├───────┬──────────────┤\n\
+             │ oss   │              │\n\
+             └───────┴──────────────┘\n"
+        );
+        assert_eq!(
+            table.render_columns(),
+            "group  email\ncompany (1)\nwork   li@acme.com\nuntagged groups (10)\noss\n"
+        );
+        assert_eq!(
+            table.render_records(),
+            "company (1)\ngroup: work\nemail: li@acme.com\n\nuntagged groups (10)\ngroup: oss\n"
+        );
+    }
+
+    #[test]
+    fn test_render_columns() {
+        assert_eq!(
+            sample().render_columns(),
+            "group  email\nwork   li@acme.com\noss\n"
+        );
+    }
+
+    #[test]
+    fn test_text_width() {
+        assert_eq!(text_width("li@acme.com"), 11);
+        assert_eq!(text_width("李四"), 4);
+        assert_eq!(text_width("\x1b[32m● work\x1b[0m"), 6);
+        assert_eq!(pad("李四", 6), "李四  ");
+    }
+
+    #[test]
+    fn test_render_records() {
+        let table = sample();
+        assert_eq!(
+            table.render_to_fit(Some(22)),
+            "group: work\nemail: li@acme.com\n\ngroup: oss\n"
+        );
+    }
+}
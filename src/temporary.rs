@@ -0,0 +1,272 @@
+//! # Temporary Module
+//!
+//! Identities applied with `use --for <duration>` or `--until-shell-exit`.
+//! The values the `use` replaced are kept in `temporary.json` inside the
+//! state directory, and put back by the first gum invocation after the time
+//! is up or the shell has exited, which includes the prompt segment of the
+//! shell integration. A scope whose identity was changed again in between is
+//! left alone.
+
+use crate::config::UserConfig;
+use crate::history::{self, Action};
+use crate::undo::{self, Snapshot};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// File holding the pending reverts, inside the state directory
+const TEMPORARY_FILE: &str = "temporary.json";
+
+/// When a temporary identity ends
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Until {
+    /// Seconds since the Unix epoch
+    Time(i64),
+    /// Process id of the shell gum was started from
+    ShellExit(u32),
+}
+
+/// An identity to revert
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Pending {
+    pub until: Until,
+    /// Identity applied temporarily, `Name <email>`
+    pub applied: String,
+    /// Group applied, if the identity belongs to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Values the temporary `use` replaced
+    pub before: Snapshot,
+}
+
+impl Pending {
+    /// Whether the temporary identity has ended at `now`
+    pub fn is_due(&self, now: i64, shell_alive: impl Fn(u32) -> bool) -> bool {
+        match self.until {
+            Until::Time(time) => now >= time,
+            Until::ShellExit(pid) => !shell_alive(pid),
+        }
+    }
+
+    /// Whether both change the same scope
+    fn same_scope(&self, before: &Snapshot) -> bool {
+        self.before.global == before.global && self.before.repo == before.repo
+    }
+}
+
+/// A revert performed by [`revert_due`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reverted {
+    pub pending: Pending,
+    /// Whether the values were put back; `false` when the scope's identity
+    /// had been changed since
+    pub restored: bool,
+}
+
+/// `Name <email>` of an identity, as stored in [`Pending::applied`]
+pub fn describe(user: &UserConfig) -> String {
+    format!("{} <{}>", user.name, user.email)
+}
+
+/// Parse a duration such as `90s`, `30m`, `2h`, `1d` or `1h30m` into seconds
+pub fn parse_duration(text: &str) -> Result<i64, String> {
+    let invalid = || format!("invalid duration \"{}\" (use e.g. 30m, 2h, 1d)", text);
+    let mut total: i64 = 0;
+    let mut number = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            _ => return Err(invalid()),
+        };
+        let value: i64 = number.parse().map_err(|_| invalid())?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || total <= 0 {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Pending reverts
+pub fn load() -> anyhow::Result<Vec<Pending>> {
+    match fs::read_to_string(temporary_path()?) {
+        Ok(content) => Ok(serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable {}: {}", TEMPORARY_FILE, e);
+            Vec::new()
+        })),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Remember to revert a temporary `use`
+///
+/// An identity already pending in the scope keeps its original values, so
+/// the revert goes back to the identity from before the first temporary
+/// `use`. Nothing is written under `--dry-run`.
+pub fn schedule(mut pending: Pending) -> anyhow::Result<()> {
+    if utils::is_dry_run() {
+        return Ok(());
+    }
+    let mut all = load()?;
+    if let Some(index) = all.iter().position(|p| p.same_scope(&pending.before)) {
+        pending.before = all.remove(index).before;
+    }
+    all.push(pending);
+    save(&all)
+}
+
+/// Forget the pending revert of a scope, made permanent by a regular `use`
+pub fn cancel(before: &Snapshot) -> anyhow::Result<()> {
+    if utils::is_dry_run() {
+        return Ok(());
+    }
+    let mut all = load()?;
+    let count = all.len();
+    all.retain(|pending| !pending.same_scope(before));
+    if all.len() != count {
+        save(&all)?;
+    }
+    Ok(())
+}
+
+/// Revert the temporary identities that have ended
+///
+/// Called at the start of every invocation; cheap when nothing is pending.
+/// Reverts that fail are logged and dropped. Nothing happens under
+/// `--dry-run`.
+pub fn revert_due() -> anyhow::Result<Vec<Reverted>> {
+    let path = temporary_path()?;
+    if utils::is_dry_run() || !path.exists() {
+        return Ok(Vec::new());
+    }
+    let now = utils::now();
+    let (due, kept): (Vec<Pending>, Vec<Pending>) = load()?
+        .into_iter()
+        .partition(|pending| pending.is_due(now, shell_alive));
+    if due.is_empty() {
+        return Ok(Vec::new());
+    }
+    save(&kept)?;
+
+    let mut reverted = Vec::new();
+    for pending in due {
+        match revert(&pending) {
+            Ok(restored) => reverted.push(Reverted { pending, restored }),
+            Err(e) => log::warn!("Cannot revert {}: {}", pending.applied, e),
+        }
+    }
+    Ok(reverted)
+}
+
+/// Put the values back unless the identity was changed since
+fn revert(pending: &Pending) -> anyhow::Result<bool> {
+    let before = &pending.before;
+    let keys: Vec<String> = before.values.iter().map(|(key, _)| key.clone()).collect();
+    let current = undo::capture(before.global, before.repo.as_deref(), &keys)?;
+    if current.identity().map(|user| describe(&user)).as_ref() != Some(&pending.applied) {
+        log::info!(
+            "Not reverting {}: the identity was changed since",
+            pending.applied
+        );
+        return Ok(false);
+    }
+    undo::restore(before)?;
+    history::record(Action::Undo, current, before.identity().as_ref(), None);
+    Ok(true)
+}
+
+/// Whether the process `pid` is still running
+#[cfg(unix)]
+pub fn shell_alive(pid: u32) -> bool {
+    utils::output(Command::new("kill").args(["-0", &pid.to_string()]))
+        .map(|output| output.status.success())
+        .unwrap_or(true)
+}
+
+/// Whether the process `pid` is still running
+#[cfg(not(unix))]
+pub fn shell_alive(pid: u32) -> bool {
+    utils::output(Command::new("tasklist").args(["/FI", &format!("PID eq {}", pid), "/NH"]))
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(true)
+}
+
+/// Process id of the shell gum was started from
+pub fn shell_pid() -> Option<u32> {
+    #[cfg(unix)]
+    {
+        Some(std::os::unix::process::parent_id())
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+fn save(all: &[Pending]) -> anyhow::Result<()> {
+    let path = temporary_path()?;
+    if all.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        };
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(all)?)?;
+    Ok(())
+}
+
+fn temporary_path() -> anyhow::Result<PathBuf> {
+    Ok(utils::get_state_dir()?.join(TEMPORARY_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert_eq!(parse_duration("30m"), Ok(1_800));
+        assert_eq!(parse_duration("1h30m"), Ok(5_400));
+        assert_eq!(parse_duration("2d"), Ok(172_800));
+        for invalid in ["", "2", "h", "0m", "2 hours", "-1h"] {
+            assert!(parse_duration(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_is_due() {
+        let pending = |until| Pending {
+            until,
+            applied: "Li <li@acme.com>".to_string(),
+            group: None,
+            before: Snapshot {
+                global: true,
+                repo: None,
+                values: Vec::new(),
+            },
+        };
+        assert!(!pending(Until::Time(100)).is_due(99, |_| true));
+        assert!(pending(Until::Time(100)).is_due(100, |_| true));
+        assert!(!pending(Until::ShellExit(42)).is_due(0, |pid| pid == 42));
+        assert!(pending(Until::ShellExit(42)).is_due(0, |_| false));
+    }
+}
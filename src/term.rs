@@ -0,0 +1,178 @@
+//! # Terminal Module
+//!
+//! Decides whether console output may carry ANSI colors. Colors are used
+//! only when stdout is a terminal that understands them: `NO_COLOR` and
+//! `TERM=dumb` turn them off, and on Windows the console's virtual terminal
+//! processing is switched on first (older consoles that lack it get plain
+//! text instead of escape garbage). It also reports the terminal width, so
+//! tables can switch to a narrower layout, and whether plain output
+//! (`--plain`) was asked for.
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether output avoids colors and box drawing (`--plain`)
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Print without colors and box-drawing characters for the rest of the
+/// process, for screen readers, CI logs and dumb terminals
+pub fn set_plain(enabled: bool) {
+    PLAIN.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether plain output is in effect
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Whether stdout may use ANSI colors, detected once per process
+pub fn colors_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    if is_plain() {
+        return false;
+    }
+    *ENABLED.get_or_init(|| {
+        let enabled = supports_color(
+            env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+            env::var("TERM").ok().as_deref(),
+            io::stdout().is_terminal(),
+        ) && enable_virtual_terminal();
+        log::debug!("ANSI colors enabled: {}", enabled);
+        enabled
+    })
+}
+
+/// Width of the terminal in columns, detected once per process
+///
+/// `COLUMNS` wins when set; otherwise the size of the terminal on stdout is
+/// asked for. `None` when stdout is not a terminal and `COLUMNS` is unset.
+pub fn width() -> Option<usize> {
+    static WIDTH: OnceLock<Option<usize>> = OnceLock::new();
+    *WIDTH.get_or_init(|| {
+        let width = env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.trim().parse().ok())
+            .filter(|&columns| columns > 0)
+            .or_else(terminal_width);
+        log::debug!("Terminal width: {:?}", width);
+        width
+    })
+}
+
+/// Wrap `text` in the ANSI color named `color` when colors are enabled
+///
+/// Supported colors are red, yellow, green, cyan, white and blue; unknown
+/// names fall back to white.
+pub fn paint(text: &str, color: &str) -> String {
+    if !colors_enabled() {
+        return text.to_string();
+    }
+    let color_code = match color {
+        "red" => "\x1b[31m",
+        "yellow" => "\x1b[33m",
+        "green" => "\x1b[32m",
+        "cyan" => "\x1b[36m",
+        "white" => "\x1b[37m",
+        "blue" => "\x1b[34m",
+        _ => "\x1b[37m",
+    };
+    format!("{}{}\x1b[0m", color_code, text)
+}
+
+/// Color decision from the environment and the kind of stdout
+fn supports_color(no_color: bool, term: Option<&str>, is_terminal: bool) -> bool {
+    !no_color && is_terminal && term != Some("dumb")
+}
+
+/// Turn on ANSI escape processing for the Windows console
+///
+/// Returns `false` when stdout is not a console or the console is too old to
+/// support it.
+#[cfg(windows)]
+fn enable_virtual_terminal() -> bool {
+    use std::ffi::c_void;
+
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleMode(console: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: *mut c_void, mode: u32) -> i32;
+    }
+
+    // SAFETY: the calls only query and update the mode of this process's own
+    // stdout handle, which stays valid for the lifetime of the process.
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+        if handle.is_null() || handle as isize == -1 {
+            return false;
+        }
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return false;
+        }
+        mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+            || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+/// Terminals on other platforms handle ANSI escapes natively
+#[cfg(not(windows))]
+fn enable_virtual_terminal() -> bool {
+    true
+}
+
+/// Columns of the terminal on stdout
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn terminal_width() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    const TIOCGWINSZ: std::ffi::c_ulong = 0x5413;
+    #[cfg(target_os = "macos")]
+    const TIOCGWINSZ: std::ffi::c_ulong = 0x4008_7468;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct WinSize {
+        rows: u16,
+        columns: u16,
+        x_pixels: u16,
+        y_pixels: u16,
+    }
+
+    unsafe extern "C" {
+        fn ioctl(fd: std::ffi::c_int, request: std::ffi::c_ulong, ...) -> std::ffi::c_int;
+    }
+
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    let mut size = WinSize::default();
+    // SAFETY: TIOCGWINSZ only writes a `struct winsize` to the pointer, which
+    // points to a live value of the same layout.
+    let result = unsafe { ioctl(1, TIOCGWINSZ, &mut size as *mut WinSize) };
+    (result == 0 && size.columns > 0).then_some(usize::from(size.columns))
+}
+
+/// Other platforms rely on `COLUMNS`
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn terminal_width() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supports_color() {
+        assert!(supports_color(false, Some("xterm-256color"), true));
+        assert!(supports_color(false, None, true));
+        assert!(!supports_color(true, Some("xterm-256color"), true));
+        assert!(!supports_color(false, Some("dumb"), true));
+        assert!(!supports_color(false, Some("xterm"), false));
+    }
+}
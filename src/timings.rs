@@ -0,0 +1,135 @@
+//! # Timings Module
+//!
+//! Collects how long the phases of a run took when `--timings` is given:
+//! reading and parsing each configuration file, every program gum starts
+//! (through [`crate::utils::output`] and friends) and rendering the output.
+//! The report is printed on stderr when gum exits, so a slow file system,
+//! a slow git and slow rendering can be told apart.
+//!
+//! Recording is a no-op unless enabled, so the hooks cost nothing otherwise.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SPANS: Mutex<Vec<Span>> = Mutex::new(Vec::new());
+
+/// One timed phase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// What was timed, e.g. `read <path>` or `$ git config ...`
+    pub label: String,
+    pub elapsed: Duration,
+    /// Whether the span is a program gum started
+    pub command: bool,
+}
+
+/// Start collecting timings for the rest of the process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether `--timings` is in effect
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Record a phase that took `elapsed`
+pub fn record(label: impl Into<String>, elapsed: Duration) {
+    push(label.into(), elapsed, false);
+}
+
+/// Record a program gum started
+pub fn record_command(label: impl Into<String>, elapsed: Duration) {
+    push(label.into(), elapsed, true);
+}
+
+/// Run `f` and record how long it took under `label`
+pub fn time<T>(label: impl FnOnce() -> String, f: impl FnOnce() -> T) -> T {
+    if !is_enabled() {
+        return f();
+    }
+    let started = Instant::now();
+    let value = f();
+    record(label(), started.elapsed());
+    value
+}
+
+fn push(label: String, elapsed: Duration, command: bool) {
+    if !is_enabled() {
+        return;
+    }
+    // Spans come from the loading threads too; a poisoned lock still holds them
+    let mut spans = SPANS.lock().unwrap_or_else(|e| e.into_inner());
+    spans.push(Span {
+        label,
+        elapsed,
+        command,
+    });
+}
+
+/// Report of the spans recorded so far, for a run that took `total`
+pub fn report(total: Duration) -> String {
+    let spans = SPANS.lock().unwrap_or_else(|e| e.into_inner());
+    format_report(&spans, total)
+}
+
+/// One line per span in the order recorded, then the time spent in
+/// programs and the total
+fn format_report(spans: &[Span], total: Duration) -> String {
+    let mut out = format!("{}\n", crate::t!("timings_header"));
+    for span in spans {
+        out.push_str(&format!("{:>10}  {}\n", millis(span.elapsed), span.label));
+    }
+    let commands: Vec<&Span> = spans.iter().filter(|span| span.command).collect();
+    let in_commands: Duration = commands.iter().map(|span| span.elapsed).sum();
+    out.push_str(&format!(
+        "{:>10}  {}\n",
+        millis(in_commands),
+        crate::t!("timings_commands", commands.len())
+    ));
+    out.push_str(&format!(
+        "{:>10}  {}\n",
+        millis(total),
+        crate::t!("timings_total")
+    ));
+    out
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let spans = [
+            Span {
+                label: "parse config.jsonc".to_string(),
+                elapsed: Duration::from_micros(1_240),
+                command: false,
+            },
+            Span {
+                label: "$ git config --get user.name".to_string(),
+                elapsed: Duration::from_millis(3),
+                command: true,
+            },
+            Span {
+                label: "$ git config --get user.email".to_string(),
+                elapsed: Duration::from_millis(4),
+                command: true,
+            },
+        ];
+        let report = format_report(&spans, Duration::from_millis(12));
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[1], "    1.2 ms  parse config.jsonc");
+        assert_eq!(lines[2], "    3.0 ms  $ git config --get user.name");
+        assert!(lines[4].starts_with("    7.0 ms  "));
+        assert!(lines[5].starts_with("   12.0 ms  "));
+    }
+}
@@ -0,0 +1,210 @@
+//! # Undo Module
+//!
+//! Before `use` changes a scope, the values it is about to overwrite are
+//! captured and kept in the history (see the `history` module), so
+//! `gum undo` can put them back, including removing settings that were not
+//! set before.
+
+use crate::config::{self, UserConfig};
+use crate::repo;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Values of one scope before a `use`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Whether the global scope was changed
+    pub global: bool,
+    /// Repository whose local scope was changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<PathBuf>,
+    /// Setting values, `None` when the setting was not set
+    ///
+    /// A multi-valued setting appears once per value.
+    pub values: Vec<(String, Option<String>)>,
+}
+
+impl Snapshot {
+    /// Identity held by the snapshot, unless neither field was set
+    pub fn identity(&self) -> Option<UserConfig> {
+        let get = |key: &str| {
+            self.values
+                .iter()
+                .find(|(k, _)| k == key)
+                .and_then(|(_, v)| v.clone())
+        };
+        let (name, email) = (get("user.name"), get("user.email"));
+        if name.is_none() && email.is_none() {
+            return None;
+        }
+        Some(UserConfig {
+            name: name.unwrap_or_default(),
+            email: email.unwrap_or_default(),
+            ..UserConfig::default()
+        })
+    }
+}
+
+/// Settings `use` may change
+pub fn managed_keys() -> Vec<&'static str> {
+    let settings = UserConfig::default().git_settings();
+    ["user.name", "user.email"]
+        .into_iter()
+        .chain(settings.iter().map(|(key, _)| *key))
+        .collect()
+}
+
+/// Capture the current values of the scope `use` is about to change
+///
+/// `extra_keys` are captured besides [`managed_keys`], e.g. the workstyle
+/// keys of `use --with-workstyle`.
+pub fn capture(
+    global: bool,
+    dir: Option<&Path>,
+    extra_keys: &[String],
+) -> anyhow::Result<Snapshot> {
+    let scope = if global { "--global" } else { "--local" };
+    let repo = if global {
+        None
+    } else {
+        let repository =
+            repo::detect(dir).ok_or_else(|| anyhow::anyhow!("Not inside a git repository"))?;
+        Some(repository.root().to_path_buf())
+    };
+    let mut keys: Vec<String> = managed_keys().into_iter().map(str::to_string).collect();
+    for key in extra_keys {
+        if !keys.contains(key) {
+            keys.push(key.clone());
+        }
+    }
+    let values = keys
+        .into_iter()
+        .flat_map(|key| {
+            let values = config::get_git_config_all(Some(scope), &key, dir);
+            if values.is_empty() {
+                return vec![(key, None)];
+            }
+            values
+                .into_iter()
+                .map(|value| (key.clone(), Some(value)))
+                .collect()
+        })
+        .collect();
+    Ok(Snapshot {
+        global,
+        repo,
+        values,
+    })
+}
+
+/// Put the values of a snapshot back
+pub fn restore(snapshot: &Snapshot) -> anyhow::Result<()> {
+    let scope = if snapshot.global {
+        "--global"
+    } else {
+        "--local"
+    };
+    if let Some(ref repo) = snapshot.repo
+        && !repo.exists()
+    {
+        anyhow::bail!("{} no longer exists", repo.display());
+    }
+    let mut keys: Vec<&str> = Vec::new();
+    for (key, _) in &snapshot.values {
+        if !keys.contains(&key.as_str()) {
+            keys.push(key);
+        }
+    }
+    let config = |args: &[&str]| -> std::io::Result<Option<std::process::ExitStatus>> {
+        let mut command = utils::git();
+        if let Some(ref repo) = snapshot.repo {
+            command.arg("-C").arg(repo);
+        }
+        command.args(["config", scope]).args(args);
+        utils::run_change(&mut command)
+    };
+    for key in keys {
+        let values: Vec<&str> = snapshot
+            .values
+            .iter()
+            .filter(|(k, _)| k == key)
+            .filter_map(|(_, value)| value.as_deref())
+            .collect();
+        let current = config::get_git_config_all(Some(scope), key, snapshot.repo.as_deref());
+        if current == values {
+            continue;
+        }
+        // Exit code 5 means there was nothing to unset
+        if let Some(status) = config(&["--unset-all", key])?
+            && !status.success()
+            && status.code() != Some(5)
+        {
+            anyhow::bail!("Failed to restore git {}", key);
+        }
+        for value in values {
+            if let Some(status) = config(&["--add", key, value])?
+                && !status.success()
+            {
+                anyhow::bail!("Failed to restore git {}", key);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_capture_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        git(&["init"]);
+        git(&["config", "--local", "user.name", "Li Si"]);
+        git(&["config", "--local", "user.email", "li@example.com"]);
+
+        let snapshot = capture(false, Some(dir.path()), &["pull.rebase".to_string()]).unwrap();
+        assert_eq!(snapshot.identity().unwrap().email, "li@example.com");
+
+        git(&["config", "--local", "user.email", "li@acme.com"]);
+        git(&["config", "--local", "commit.template", "work.txt"]);
+        git(&["config", "--local", "pull.rebase", "true"]);
+        restore(&snapshot).unwrap();
+
+        // Every value of a multi-valued setting comes back
+        let key = "url.git@github-work:acme/.insteadOf";
+        git(&["config", "--local", "--add", key, "git@github.com:acme/"]);
+        git(&[
+            "config",
+            "--local",
+            "--add",
+            key,
+            "https://github.com/acme/",
+        ]);
+        let urls = capture(false, Some(dir.path()), &[key.to_string()]).unwrap();
+        git(&["config", "--local", "--unset-all", key]);
+        git(&["config", "--local", key, "https://example.com/"]);
+        restore(&urls).unwrap();
+        assert_eq!(
+            config::get_git_config_all(Some("--local"), key, Some(dir.path())),
+            ["git@github.com:acme/", "https://github.com/acme/"]
+        );
+        git(&["config", "--local", "--unset-all", key]);
+
+        let value = |key: &str| config::get_git_config(Some("--local"), key, Some(dir.path()));
+        assert_eq!(value("user.email").as_deref(), Some("li@example.com"));
+        // Settings that were not set before are removed again
+        assert_eq!(value("commit.template"), None);
+        assert_eq!(value("pull.rebase"), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! # Self Update Module
+//!
+//! Implements `gum self-update`: looks up the latest GitHub release, downloads
+//! the archive built for this platform, verifies its minisign signature
+//! against the public key built into gum, and swaps the running executable.
+//!
+//! The key comes from `GUM_UPDATE_PUBLIC_KEY` at build time, so a download
+//! is trusted only when it was signed with the release key, not merely
+//! because it came from the same place as its checksum. Builds without a
+//! key cannot update themselves.
+//!
+//! Downloads and extraction go through the system `curl` and `tar` (both
+//! ship with Linux, macOS and Windows 10+), the same way gum already relies
+//! on the `git` executable instead of bundling a client library.
+
+use crate::utils;
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// GitHub repository releases are published to
+pub const REPOSITORY: &str = "slow-groovin/gum-rs";
+
+/// Version of the running binary
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Minisign public key release archives are signed with, base64 encoded
+const PUBLIC_KEY: Option<&str> = option_env!("GUM_UPDATE_PUBLIC_KEY");
+
+/// Latest published release
+#[derive(Debug, Clone)]
+pub struct Release {
+    /// Version without the leading `v`
+    pub version: String,
+    /// Git tag of the release
+    pub tag: String,
+}
+
+/// Query the latest release from the GitHub API
+pub fn latest_release() -> anyhow::Result<Release> {
+    let url = format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        REPOSITORY
+    );
+    let body = utils::curl(&["-fsSL", "-H", "Accept: application/vnd.github+json", &url])?;
+    let value: serde_json::Value = serde_json::from_slice(&body)?;
+    let tag = value
+        .get("tag_name")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Release feed has no tag_name"))?;
+    Ok(Release {
+        version: tag.trim_start_matches('v').to_string(),
+        tag: tag.to_string(),
+    })
+}
+
+/// Whether `latest` is a newer version than `current`
+///
+/// Compares dot-separated numeric components; pre-release suffixes
+/// (`1.2.0-beta`) sort before the plain release.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    fn parse(version: &str) -> (Vec<u64>, bool) {
+        let (core, pre) = match version.split_once('-') {
+            Some((core, _)) => (core, true),
+            None => (version, false),
+        };
+        let parts = core.split('.').map(|p| p.parse().unwrap_or(0)).collect();
+        (parts, pre)
+    }
+    let (latest, latest_pre) = parse(latest);
+    let (current, current_pre) = parse(current);
+    match latest.cmp(&current) {
+        std::cmp::Ordering::Equal => current_pre && !latest_pre,
+        ordering => ordering.is_gt(),
+    }
+}
+
+/// Package manager that owns the running executable, if any
+///
+/// Those installs must be updated through the package manager, which
+/// otherwise would not know about the replaced file.
+pub fn package_manager(exe: &Path) -> Option<(&'static str, &'static str)> {
+    let path = exe.to_string_lossy();
+    if path.contains("/Cellar/") || path.contains("/homebrew/") {
+        Some(("Homebrew", "brew upgrade gum-rs"))
+    } else if path.contains("/nix/store/") {
+        Some(("Nix", "your Nix configuration"))
+    } else {
+        None
+    }
+}
+
+/// Release archive name for the platform gum was built for
+pub fn artifact_name() -> anyhow::Result<String> {
+    let target = match (std::env::consts::ARCH, std::env::consts::OS) {
+        ("x86_64", "linux") => "x86_64-unknown-linux-gnu",
+        ("aarch64", "linux") => "aarch64-unknown-linux-gnu",
+        ("x86_64", "macos") => "x86_64-apple-darwin",
+        ("aarch64", "macos") => "aarch64-apple-darwin",
+        ("x86_64", "windows") => "x86_64-pc-windows-msvc",
+        (arch, os) => anyhow::bail!("No prebuilt release for {}-{}", arch, os),
+    };
+    let extension = if cfg!(windows) { "zip" } else { "tar.xz" };
+    Ok(format!("gum-rs-{}.{}", target, extension))
+}
+
+/// Download, verify and install `release` over the running executable
+pub fn install(release: &Release) -> anyhow::Result<PathBuf> {
+    let public_key = PUBLIC_KEY
+        .ok_or_else(|| anyhow::anyhow!("This build of gum has no key to verify releases with"))?;
+    let exe = std::env::current_exe()?;
+    let artifact = artifact_name()?;
+    let base = format!(
+        "https://github.com/{}/releases/download/{}",
+        REPOSITORY, release.tag
+    );
+
+    let work_dir = std::env::temp_dir().join(format!("gum-update-{}", std::process::id()));
+    fs::create_dir_all(&work_dir)?;
+    let result = download_and_replace(&base, &artifact, &work_dir, &exe, public_key);
+    let _ = fs::remove_dir_all(&work_dir);
+    result.map(|_| exe)
+}
+
+fn download_and_replace(
+    base: &str,
+    artifact: &str,
+    work_dir: &Path,
+    exe: &Path,
+    public_key: &str,
+) -> anyhow::Result<()> {
+    let archive = work_dir.join(artifact);
+    log::info!("Downloading {}/{}", base, artifact);
+    utils::curl(&[
+        "-fsSL",
+        "-o",
+        &archive.to_string_lossy(),
+        &format!("{}/{}", base, artifact),
+    ])?;
+    let signature = utils::curl(&["-fsSL", &format!("{}/{}.minisig", base, artifact)])?;
+    verify_signature(
+        &fs::read(&archive)?,
+        &String::from_utf8_lossy(&signature),
+        public_key,
+    )?;
+    log::info!("Signature of {} verified", artifact);
+
+    let status = utils::status(
+        Command::new("tar")
+            .arg("-xf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(work_dir),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to run tar: {}", e))?;
+    if !status.success() {
+        anyhow::bail!("Failed to extract {}", artifact);
+    }
+
+    let binary_name = exe
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine executable name"))?;
+    let binary = find_file(work_dir, binary_name)
+        .or_else(|| {
+            find_file(
+                work_dir,
+                std::ffi::OsStr::new(if cfg!(windows) { "gum.exe" } else { "gum" }),
+            )
+        })
+        .ok_or_else(|| anyhow::anyhow!("{} does not contain a gum binary", artifact))?;
+    replace_exe(&binary, exe)
+}
+
+/// Check `data` against the content of a `.minisig` file made with the
+/// secret key of `public_key`
+pub fn verify_signature(data: &[u8], minisig_file: &str, public_key: &str) -> anyhow::Result<()> {
+    let public_key = PublicKey::from_base64(public_key)
+        .map_err(|e| anyhow::anyhow!("Invalid release public key: {}", e))?;
+    let signature = Signature::decode(minisig_file)
+        .map_err(|e| anyhow::anyhow!("Invalid release signature: {}", e))?;
+    public_key
+        .verify(data, &signature, false)
+        .map_err(|e| anyhow::anyhow!("Release signature does not verify: {}", e))
+}
+
+/// Replace `exe` with `new_binary`
+///
+/// The new file is staged next to the executable and renamed over it, which
+/// is atomic on Unix. Windows refuses to overwrite a running executable but
+/// allows renaming it, so the old one is moved aside to `<exe>.old` and
+/// removed by `cleanup_old_exe` on a later run.
+fn replace_exe(new_binary: &Path, exe: &Path) -> anyhow::Result<()> {
+    let mut staged = exe.as_os_str().to_owned();
+    staged.push(".new");
+    let staged = PathBuf::from(staged);
+    fs::copy(new_binary, &staged)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+
+    #[cfg(windows)]
+    {
+        let old = old_exe_path(exe);
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+    }
+
+    if let Err(e) = fs::rename(&staged, exe) {
+        let _ = fs::remove_file(&staged);
+        #[cfg(windows)]
+        let _ = fs::rename(old_exe_path(exe), exe);
+        anyhow::bail!("Failed to replace {}: {}", exe.display(), e);
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn old_exe_path(exe: &Path) -> PathBuf {
+    let mut old = exe.as_os_str().to_owned();
+    old.push(".old");
+    PathBuf::from(old)
+}
+
+/// Remove the executable left behind by a previous update on Windows
+pub fn cleanup_old_exe() {
+    #[cfg(windows)]
+    if let Ok(exe) = std::env::current_exe() {
+        let _ = fs::remove_file(old_exe_path(&exe));
+    }
+}
+
+fn find_file(dir: &Path, name: &std::ffi::OsStr) -> Option<PathBuf> {
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name) {
+                return Some(found);
+            }
+        } else if path.file_name() == Some(name) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Hex encoded SHA-256 digest of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let long = vec![b'a'; 1000];
+        assert_eq!(
+            sha256_hex(&long),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let public_key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+        let signature = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+        assert!(verify_signature(b"test", signature, public_key).is_ok());
+        assert!(verify_signature(b"Test", signature, public_key).is_err());
+        assert!(verify_signature(b"test", "", public_key).is_err());
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.1.0", "0.0.6"));
+        assert!(is_newer("0.0.10", "0.0.9"));
+        assert!(is_newer("1.0.0", "1.0.0-beta"));
+        assert!(!is_newer("0.0.6", "0.0.6"));
+        assert!(!is_newer("0.0.5", "0.0.6"));
+        assert!(!is_newer("1.0.0-beta", "1.0.0"));
+    }
+
+    #[test]
+    fn test_package_manager() {
+        assert!(package_manager(Path::new("/opt/homebrew/Cellar/gum-rs/0.0.6/bin/gum")).is_some());
+        assert!(package_manager(Path::new("/home/me/.cargo/bin/gum")).is_none());
+    }
+}
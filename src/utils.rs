@@ -4,103 +4,1208 @@
 //! including path handling, git repository detection, and colored output.
 //!
 //! ## Main Features
-//! - Get configuration file path
+//! - Get configuration file path (flag, environment or platform default)
 //! - Check if current directory is a git repository
 //! - Colored console output
 
-use std::io;
-use std::path::PathBuf;
-use std::process::Command;
+use crate::journal;
+use crate::term;
+use crate::timings;
+use crate::workspace;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+/// Environment variable overriding the configuration file path
+pub const CONFIG_ENV: &str = "GUM_CONFIG";
+
+/// Environment variable overriding the system-wide configuration file path
+pub const SYSTEM_CONFIG_ENV: &str = "GUM_SYSTEM_CONFIG";
+
+/// Environment variable selecting the group `use` applies without a name
+pub const GROUP_ENV: &str = "GUM_GROUP";
+
+/// Environment variable overriding the name `use` applies
+pub const NAME_ENV: &str = "GUM_NAME";
+
+/// Environment variable overriding the email `use` applies
+pub const EMAIL_ENV: &str = "GUM_EMAIL";
+
+/// Environment variable overriding the git program
+pub const GIT_ENV: &str = "GUM_GIT";
+
+/// Configuration file path given on the command line (`--config`)
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Git program from `settings.git.path`
+static GIT_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Time git may take before it is killed, from `settings.git.timeout_secs`
+static GIT_TIMEOUT: OnceLock<Option<Duration>> = OnceLock::new();
+
+/// Time git may take when `settings.git.timeout_secs` is not set
+pub const DEFAULT_GIT_TIMEOUT_SECS: u64 = 10;
+
+/// Whether changes are only printed (`--dry-run`)
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Exit status of a command that needed an answer in non-interactive mode
+pub const EXIT_INPUT_REQUIRED: i32 = 3;
+
+/// Whether prompts are refused (`--non-interactive`, or stdin is not a terminal)
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether emails are masked in output and logs (`--redact`)
+static REDACT: AtomicBool = AtomicBool::new(false);
+
+/// Whether a prompt was refused, deciding the exit status
+static INPUT_REQUIRED: AtomicBool = AtomicBool::new(false);
+
+/// Error of the first git invocation that could not start or finish
+static GIT_FAILURE: OnceLock<String> = OnceLock::new();
+
+/// Set the configuration file path given on the command line
+///
+/// Takes precedence over `GUM_CONFIG` and the platform default for the rest
+/// of the process. Only the first call has an effect.
+pub fn set_config_path_override(path: PathBuf) {
+    log::debug!("Config path override: {}", path.display());
+    let _ = CONFIG_PATH_OVERRIDE.set(path);
+}
+
+/// Set the git program configured in `settings.git.path`
+///
+/// `GUM_GIT` still takes precedence. Only the first call has an effect.
+pub fn set_git_path(path: PathBuf) {
+    log::debug!("Git path from settings: {}", path.display());
+    let _ = GIT_PATH.set(path);
+}
+
+/// Git program gum runs: `GUM_GIT`, then `settings.git.path`, then `git`
+/// from `PATH`
+pub fn git_program() -> PathBuf {
+    resolve_git_program(env_value(GIT_ENV), GIT_PATH.get().cloned())
+}
+
+fn resolve_git_program(env: Option<String>, setting: Option<PathBuf>) -> PathBuf {
+    env.map(PathBuf::from)
+        .or(setting.filter(|p| !p.as_os_str().is_empty()))
+        .unwrap_or_else(|| PathBuf::from("git"))
+}
+
+/// Set the time git may take from `settings.git.timeout_secs`, `None`
+/// for no limit
+///
+/// Only the first call has an effect.
+pub fn set_git_timeout(timeout: Option<Duration>) {
+    log::debug!("Git timeout from settings: {:?}", timeout);
+    let _ = GIT_TIMEOUT.set(timeout);
+}
+
+/// Time `command` may take before it is killed; only git is limited
+fn timeout_of(command: &Command) -> Option<Duration> {
+    if !is_git(command) {
+        return None;
+    }
+    GIT_TIMEOUT
+        .get()
+        .copied()
+        .unwrap_or(Some(Duration::from_secs(DEFAULT_GIT_TIMEOUT_SECS)))
+}
+
+/// Create a command running git
+///
+/// Every git invocation goes through this function so that the configured
+/// git program is honored.
+pub fn git() -> Command {
+    Command::new(git_program())
+}
+
+/// Create a git command, running in `dir` when given
+pub fn git_command(dir: Option<&Path>) -> Command {
+    let mut command = git();
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    command
+}
+
+/// Whether `command` runs git
+pub fn is_git(command: &Command) -> bool {
+    command.get_program() == git_program().as_os_str()
+}
+
+/// Print changes instead of making them for the rest of the process
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--dry-run` is in effect
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Mask emails in output and logs for the rest of the process
+pub fn set_redact(enabled: bool) {
+    REDACT.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--redact` is in effect
+pub fn is_redacted() -> bool {
+    REDACT.load(Ordering::Relaxed)
+}
+
+/// `text` with its emails masked when `--redact` is in effect
+pub fn redact(text: &str) -> String {
+    if is_redacted() {
+        mask_emails(text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// `text` with every email masked, `li@acme.com` becoming `l***@acme.com`
+///
+/// The domain is kept so the identity in use can still be told apart.
+pub fn mask_emails(text: &str) -> String {
+    let is_local = |c: char| c.is_alphanumeric() || "._%+-".contains(c);
+    let is_domain = |c: char| c.is_alphanumeric() || c == '.' || c == '-';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        let (before, after) = rest.split_at(at);
+        let domain_len: usize = after[1..]
+            .chars()
+            .take_while(|c| is_domain(*c))
+            .map(char::len_utf8)
+            .sum();
+        let domain = after[1..1 + domain_len].trim_end_matches('.');
+        let local_start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_local(*c))
+            .last()
+            .map_or(before.len(), |(i, _)| i);
+        let local = &before[local_start..];
+        // `git@github.com:owner/repo` is a remote, not an email
+        let remote = after[1 + domain.len()..]
+            .strip_prefix(':')
+            .is_some_and(|path| path.starts_with(|c: char| !c.is_whitespace()));
+        if local.is_empty() || !domain.contains('.') || remote {
+            out.push_str(before);
+            out.push('@');
+            rest = &after[1..];
+            continue;
+        }
+        out.push_str(&before[..local_start]);
+        out.extend(local.chars().next());
+        out.push_str("***@");
+        out.push_str(domain);
+        rest = &after[1 + domain.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Refuse prompts for the rest of the process
+pub fn set_non_interactive(enabled: bool) {
+    NON_INTERACTIVE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether prompts are refused
+pub fn is_non_interactive() -> bool {
+    NON_INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Error for a question that cannot be asked in non-interactive mode
+///
+/// Makes the process exit with [`EXIT_INPUT_REQUIRED`].
+pub fn input_required(what: &str) -> anyhow::Error {
+    log::warn!("Input required in non-interactive mode: {}", what);
+    INPUT_REQUIRED.store(true, Ordering::Relaxed);
+    anyhow::anyhow!(crate::t!("input_required", what))
+}
+
+/// Whether a command failed because it needed an answer
+pub fn was_input_required() -> bool {
+    INPUT_REQUIRED.load(Ordering::Relaxed)
+}
+
 /// Get configuration file path
 ///
-/// Returns configuration file path based on operating system:
-/// - Linux/macOS: $XDG_CONFIG_HOME/gum/config.jsonc (default: ~/.config/gum/config.jsonc)
-/// - Windows: %APPDATA%\gum\config.jsonc
+/// Resolved in order of precedence:
+/// 1. `--config <path>` command line flag
+/// 2. `GUM_CONFIG` environment variable
+/// 3. Platform default:
+///    - Linux/macOS: $XDG_CONFIG_HOME/gum/config.jsonc (default: ~/.config/gum/config.jsonc)
+///    - Windows: %APPDATA%\gum\config.jsonc
+///
+///    In a workspace, `workspaces/<name>/config.jsonc` in that directory.
+///
+/// On macOS a config file left in `~/Library/Application Support/gum` by
+/// earlier versions is migrated to the default location on first use.
 ///
 /// # Returns
 /// - `Ok(PathBuf)`: Full path to configuration file
 /// - `Err`: Error when unable to get configuration directory
 pub fn get_config_path() -> anyhow::Result<PathBuf> {
     log::debug!("Getting config path");
-    let config_dir = dirs::config_dir()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Cannot obtain config directory"))?;
+    resolve_config_path(
+        CONFIG_PATH_OVERRIDE.get().cloned(),
+        env::var_os(CONFIG_ENV).map(PathBuf::from),
+    )
+}
+
+/// Pick the configuration file path from the flag, environment and default
+///
+/// Empty values are ignored so that `GUM_CONFIG=` falls back to the default.
+fn resolve_config_path(flag: Option<PathBuf>, env: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    if let Some(path) = flag.filter(|p| !p.as_os_str().is_empty()) {
+        return Ok(path);
+    }
+    if let Some(path) = env.filter(|p| !p.as_os_str().is_empty()) {
+        log::debug!("Using config path from {}", CONFIG_ENV);
+        return Ok(path);
+    }
+    default_config_path()
+}
+
+/// Value of an environment variable, `None` when unset or blank
+pub fn env_value(var: &str) -> Option<String> {
+    env::var(var)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Get the system-wide configuration file path
+///
+/// This optional, read-only layer lets administrators pre-provision groups:
+/// - `GUM_SYSTEM_CONFIG` environment variable, if set
+/// - Linux/macOS: /etc/gum/config.json
+/// - Windows: %ProgramData%\gum\config.json
+///
+/// Returns `None` when no location applies on this platform.
+pub fn get_system_config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(SYSTEM_CONFIG_ENV).filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(windows) {
+        env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("gum").join("config.json"))
+    } else {
+        Some(PathBuf::from("/etc/gum/config.json"))
+    }
+}
 
-    let config_dir = config_dir.join("gum").join("config.jsonc");
-    Ok(config_dir)
+/// Get the cache directory for gum
+///
+/// - Linux: $XDG_CACHE_HOME/gum (default: ~/.cache/gum)
+/// - macOS: ~/Library/Caches/gum
+/// - Windows: %LOCALAPPDATA%\gum
+pub fn get_cache_dir() -> anyhow::Result<PathBuf> {
+    dirs::cache_dir()
+        .map(|dir| dir.join("gum"))
+        .ok_or_else(|| anyhow::anyhow!("Cannot obtain cache directory"))
 }
 
+/// Get the directory for state gum keeps between runs
+///
+/// - Linux: $XDG_STATE_HOME/gum (default: ~/.local/state/gum)
+/// - macOS: ~/Library/Application Support/gum
+/// - Windows: %LOCALAPPDATA%\gum
+///
+/// In a workspace, `workspaces/<name>` inside that directory.
+pub fn get_state_dir() -> anyhow::Result<PathBuf> {
+    let dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("gum"))
+        .ok_or_else(|| anyhow::anyhow!("Cannot obtain state directory"))?;
+    Ok(match workspace::active() {
+        Some(name) => workspace::dir(&dir, &name),
+        None => dir,
+    })
+}
+
+/// Get gum's directory in the platform configuration directory
+///
+/// Holds the default configuration file and the workspaces, whatever
+/// `--config` or `GUM_CONFIG` say.
+pub fn get_config_base_dir() -> anyhow::Result<PathBuf> {
+    config_dir_candidates()?
+        .into_iter()
+        .next()
+        .map(|dir| dir.join("gum"))
+        .ok_or_else(|| anyhow::anyhow!("Cannot obtain config directory"))
+}
+
+/// Configuration file name inside the configuration directory
+pub const CONFIG_FILE_NAME: &str = "config.jsonc";
+
+/// Platform default configuration file path
+///
+/// The first candidate directory is the preferred location. When it has no
+/// configuration file yet but one of the legacy locations does, the legacy
+/// file is copied over so existing users keep their groups.
+fn default_config_path() -> anyhow::Result<PathBuf> {
+    if let Some(name) = workspace::active() {
+        let dir = workspace::dir(&get_config_base_dir()?, &name);
+        return Ok(dir.join(CONFIG_FILE_NAME));
+    }
+    let candidates: Vec<PathBuf> = config_dir_candidates()?
+        .into_iter()
+        .map(|dir| dir.join("gum").join(CONFIG_FILE_NAME))
+        .collect();
+    let (preferred, legacy) = candidates
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("Cannot obtain config directory"))?;
+    Ok(migrate_legacy_config(preferred, legacy))
+}
+
+/// Copy the first existing legacy configuration file to the preferred path
+///
+/// Returns the path that should be used: the preferred one, or the legacy
+/// one if copying failed.
+fn migrate_legacy_config(preferred: &Path, legacy: &[PathBuf]) -> PathBuf {
+    if preferred.exists() {
+        return preferred.to_path_buf();
+    }
+    let Some(old) = legacy.iter().find(|p| p.exists()) else {
+        return preferred.to_path_buf();
+    };
+
+    log::info!(
+        "Migrating config file from {} to {}",
+        old.display(),
+        preferred.display()
+    );
+    let result = preferred
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::copy(old, preferred));
+    match result {
+        Ok(_) => preferred.to_path_buf(),
+        Err(e) => {
+            log::warn!(
+                "Failed to migrate config file, using {}: {}",
+                old.display(),
+                e
+            );
+            old.clone()
+        }
+    }
+}
+
+/// Candidate base configuration directories, preferred first
+///
+/// - Linux and other Unix: `$XDG_CONFIG_HOME`, or `~/.config`
+/// - macOS: `$XDG_CONFIG_HOME` or `~/.config`, then `~/Library/Application Support`
+///   (used by earlier versions)
+/// - Windows: the RoamingAppData known folder (`%APPDATA%`)
+#[cfg(all(unix, not(target_os = "macos")))]
+fn config_dir_candidates() -> anyhow::Result<Vec<PathBuf>> {
+    Ok(vec![xdg_config_home(
+        env::var_os("XDG_CONFIG_HOME"),
+        dirs::home_dir(),
+    )?])
+}
+
+#[cfg(target_os = "macos")]
+fn config_dir_candidates() -> anyhow::Result<Vec<PathBuf>> {
+    let home = dirs::home_dir();
+    let mut candidates = vec![xdg_config_home(
+        env::var_os("XDG_CONFIG_HOME"),
+        home.clone(),
+    )?];
+    if let Some(home) = home {
+        candidates.push(home.join("Library").join("Application Support"));
+    }
+    Ok(candidates)
+}
+
+#[cfg(not(unix))]
+fn config_dir_candidates() -> anyhow::Result<Vec<PathBuf>> {
+    // `dirs` resolves this through SHGetKnownFolderPath(FOLDERID_RoamingAppData)
+    let dir = dirs::config_dir().ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot obtain the AppData directory, use --config or {} to set the config path",
+            CONFIG_ENV
+        )
+    })?;
+    Ok(vec![dir])
+}
+
+/// XDG base directory for configuration files
+///
+/// `XDG_CONFIG_HOME` is only honored when it is an absolute path, as the
+/// specification requires; otherwise `~/.config` is used.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn xdg_config_home(xdg: Option<OsString>, home: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    if let Some(dir) = xdg.map(PathBuf::from).filter(|p| p.is_absolute()) {
+        return Ok(dir);
+    }
+    let home = home.filter(|p| !p.as_os_str().is_empty()).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot determine the home directory, use --config or {} to set the config path",
+            CONFIG_ENV
+        )
+    })?;
+    Ok(home.join(".config"))
+}
+
+/// Whether the current directory belongs to a git repository
+///
+/// See [`crate::repo::detect`] for the details of the repository.
 pub fn is_git_repository() -> bool {
     log::debug!("Checking if current directory is a git repository");
-    let result = Command::new("git")
-        .args(["rev-parse", "--git-dir"])
-        .output()
-        .map(|output| !output.stdout.is_empty())
-        .unwrap_or(false);
+    let result = crate::repo::detect(None).is_some();
     log::debug!("Is git repository: {}", result);
     result
 }
 
+/// Run a command that changes state, such as `git config <key> <value>`
+///
+/// Under `--dry-run` the command line is printed instead and `None` is
+/// returned. Successful `git config` changes are recorded in the journal.
+pub fn run_change(command: &mut Command) -> io::Result<Option<ExitStatus>> {
+    if is_dry_run() {
+        write_stdout(&format!("{}\n", command_line(command)));
+        return Ok(None);
+    }
+    let change = journal::git_change(command);
+    let status = status(command)?;
+    if status.success()
+        && let Some(change) = change
+    {
+        journal::record(&change);
+    }
+    Ok(Some(status))
+}
+
+/// Run a command and capture its output
+///
+/// Every program gum starts goes through this function, [`status`],
+/// [`interactive`] or [`output_with_input`], so that `-v` traces each
+/// invocation with its exit status and duration. Except under
+/// [`interactive`], git is killed once `settings.git.timeout_secs` have
+/// passed.
+pub fn output(command: &mut Command) -> io::Result<Output> {
+    let started = Instant::now();
+    let result = match timeout_of(command) {
+        Some(timeout) => command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|child| wait_with_timeout(command, child, timeout)),
+        None => command.output(),
+    }
+    .map_err(|e| spawn_error(command, e));
+    trace(
+        command,
+        result.as_ref().map(|output| output.status),
+        started,
+    );
+    result
+}
+
+/// Run a command attached to the terminal
+pub fn status(command: &mut Command) -> io::Result<ExitStatus> {
+    status_within(command, timeout_of(command))
+}
+
+/// Run a program the user works with, such as the command of `gum exec`,
+/// attached to the terminal and without a time limit even when it is git
+pub fn interactive(command: &mut Command) -> io::Result<ExitStatus> {
+    status_within(command, None)
+}
+
+fn status_within(command: &mut Command, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+    let started = Instant::now();
+    let result = match timeout {
+        Some(timeout) => command
+            .spawn()
+            .and_then(|child| wait_with_timeout(command, child, timeout))
+            .map(|output| output.status),
+        None => command.status(),
+    }
+    .map_err(|e| spawn_error(command, e));
+    trace(command, result.as_ref().copied(), started);
+    result
+}
+
+/// Run a command with `input` on its stdin and capture its output
+pub fn output_with_input(command: &mut Command, input: &[u8]) -> io::Result<Output> {
+    let started = Instant::now();
+    let result = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                // The program may exit before reading; its status tells why
+                let _ = stdin.write_all(input);
+            }
+            match timeout_of(command) {
+                Some(timeout) => wait_with_timeout(command, child, timeout),
+                None => child.wait_with_output(),
+            }
+        })
+        .map_err(|e| spawn_error(command, e));
+    trace(
+        command,
+        result.as_ref().map(|output| output.status),
+        started,
+    );
+    result
+}
+
+/// Wait for `child` and collect its output, killing it once `timeout` has
+/// passed
+///
+/// A hanging credential helper or fsmonitor would otherwise hang gum with
+/// it, including the prompt segment of the shell integration. Output the
+/// killed process leaves behind is not waited for, since its own children
+/// may keep the pipes open.
+fn wait_with_timeout(command: &Command, mut child: Child, timeout: Duration) -> io::Result<Output> {
+    fn read_all(pipe: Option<impl Read + Send + 'static>) -> Task<Vec<u8>> {
+        spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buffer);
+            }
+            buffer
+        })
+    }
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let mut pause = Duration::from_millis(1);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            let message = crate::t!("git_timed_out", timeout.as_secs(), command_line(command));
+            log::warn!("{}", message);
+            let _ = GIT_FAILURE.set(message.clone());
+            return Err(io::Error::new(io::ErrorKind::TimedOut, message));
+        }
+        thread::sleep(pause.min(deadline - now));
+        pause = (pause * 2).min(Duration::from_millis(50));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Replace the error of a git program that does not exist with one saying
+/// how to fix it
+fn spawn_error(command: &Command, e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::NotFound && is_git(command) {
+        let program = command.get_program().to_string_lossy();
+        let message = GIT_FAILURE.get_or_init(|| crate::t!("git_not_found", program));
+        log::warn!("{}", message);
+        return io::Error::new(e.kind(), message.clone());
+    }
+    e
+}
+
+/// Why git could not be run, if it was missing or killed during this
+/// process
+///
+/// Many callers treat a failed git invocation as "no repository" or "no
+/// identity"; a failing command reports this instead so the cause is clear.
+pub fn git_failure() -> Option<String> {
+    GIT_FAILURE.get().cloned()
+}
+
+/// Log a finished command at info level and record it for `--timings`
+fn trace(command: &Command, result: Result<ExitStatus, &io::Error>, started: Instant) {
+    timings::record_command(format!("$ {}", command_line(command)), started.elapsed());
+    let elapsed = started.elapsed().as_millis();
+    match result {
+        Ok(status) => log::info!("$ {} ({}, {} ms)", command_line(command), status, elapsed),
+        Err(e) => log::info!(
+            "$ {} (failed to start: {}, {} ms)",
+            command_line(command),
+            e,
+            elapsed
+        ),
+    }
+}
+
+/// Under `--dry-run`, print the lines writing `new` over `old` at `path`
+/// would change and return `true` so the caller skips the write
+pub fn preview_write(path: &Path, old: &str, new: &str) -> bool {
+    if !is_dry_run() {
+        return false;
+    }
+    let (removed, added) = changed_lines(old, new);
+    let mut preview = format!("# write {}\n", path.display());
+    for line in removed {
+        preview.push_str(&format!("-{}\n", line));
+    }
+    for line in added {
+        preview.push_str(&format!("+{}\n", line));
+    }
+    write_stdout(&preview);
+    true
+}
+
+/// Lines of `old` replaced and lines of `new` added, once the lines both
+/// texts start and end with are left out
+pub fn changed_lines<'a>(old: &'a str, new: &'a str) -> (Vec<&'a str>, Vec<&'a str>) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    (
+        old[prefix..old.len() - suffix].to_vec(),
+        new[prefix..new.len() - suffix].to_vec(),
+    )
+}
+
+/// Command line of `command`, quoted so it can be pasted into a shell
+pub fn command_line(command: &Command) -> String {
+    let mut parts = vec![shell_quote(&command.get_program().to_string_lossy())];
+    parts.extend(
+        command
+            .get_args()
+            .map(|arg| shell_quote(&arg.to_string_lossy())),
+    );
+    parts.join(" ")
+}
+
+/// Quote `arg` for POSIX shells when it contains special characters
+pub fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@+,%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Expand a leading `~/` the way git and ssh do for path-valued settings
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path.to_string(),
+    }
+}
+
+/// Format seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS` (UTC)
+pub fn format_timestamp(seconds: i64) -> String {
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let days = seconds.div_euclid(86_400);
+    let secs = seconds.rem_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+/// Parse a `YYYY-MM-DD` date as seconds since the Unix epoch at midnight
+/// UTC, `None` for malformed or impossible dates
+pub fn parse_date(text: &str) -> Option<i64> {
+    let mut parts = text.trim().splitn(3, '-');
+    let mut number = |len: usize| {
+        parts
+            .next()
+            .filter(|part| part.len() == len && part.bytes().all(|b| b.is_ascii_digit()))?
+            .parse::<i64>()
+            .ok()
+    };
+    let (year, month, day) = (number(4)?, number(2)?, number(2)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days since 1970-01-01 from a civil date, the inverse of format_timestamp
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let seconds = (era * 146_097 + doe - 719_468) * 86_400;
+    // Days past the end of the month roll over; reject them
+    format_timestamp(seconds)
+        .starts_with(text.trim())
+        .then_some(seconds)
+}
+
+/// Edit distance between two strings, counted in characters
+///
+/// Levenshtein distance where swapping two adjacent characters counts as a
+/// single edit, the most common typo in a name.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Run `curl` and return its standard output
+///
+/// Fails with curl's error message when the transfer fails; pass `-f` to
+/// treat HTTP errors as failures.
+pub fn curl(args: &[&str]) -> anyhow::Result<Vec<u8>> {
+    run_curl(args, None)
+}
+
+/// Run `curl` with an extra request header kept off the command line
+///
+/// Used for credentials, which would otherwise be visible to other users in
+/// the process list. The header is passed as curl config on stdin.
+pub fn curl_with_header(args: &[&str], header: &str) -> anyhow::Result<Vec<u8>> {
+    let escaped = header.replace('\\', "\\\\").replace('"', "\\\"");
+    run_curl(args, Some(format!("header = \"{}\"\n", escaped)))
+}
+
+fn run_curl(args: &[&str], config: Option<String>) -> anyhow::Result<Vec<u8>> {
+    let mut command = Command::new("curl");
+    command.args(args);
+    if config.is_some() {
+        command.args(["--config", "-"]);
+    }
+    let output = output_with_input(&mut command, config.unwrap_or_default().as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to run curl (is it installed?): {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
 /// Colored print function
 ///
-/// Uses ANSI escape sequences to output colored text to console. Supported colors
-/// include red, yellow, green, cyan, white. If unsupported color is specified,
-/// defaults to white.
+/// Prints a blank line followed by the text in the given color. Colors are
+/// only emitted when the terminal supports them (see [`crate::term`]);
+/// otherwise the text is printed plainly.
 ///
 /// # Parameters
 /// - `val`: Text content to print
 /// - `color`: Color name
 pub fn printer(val: &str, color: &str) {
-    let color_code = match color {
-        "red" => "\x1b[31m",
-        "yellow" => "\x1b[33m",
-        "green" => "\x1b[32m",
-        "cyan" => "\x1b[36m",
-        "white" => "\x1b[37m",
-        "blue" => "\x1b[34m",
-        _ => "\x1b[37m",
-    };
-
-    println!();
-    println!("{}{}\x1b[0m", color_code, val);
+    write_stdout(&format!("\n{}\n", term::paint(val, color)));
 }
 
 /// Colored print function (no newline)
 ///
-/// Uses ANSI escape sequences to output colored text to console. Supported colors
-/// include red, yellow, green, cyan, white. If unsupported color is specified,
-/// defaults to white.
+/// Like [`printer`], without the blank line before and the newline after.
 ///
 /// # Parameters
 /// - `val`: Text content to print
 /// - `color`: Color name
 pub fn printer_no_newline(val: &str, color: &str) {
-    let color_code = match color {
-        "red" => "\x1b[31m",
-        "yellow" => "\x1b[33m",
-        "green" => "\x1b[32m",
-        "cyan" => "\x1b[36m",
-        "white" => "\x1b[37m",
-        "blue" => "\x1b[34m",
-        _ => "\x1b[37m",
-    };
+    write_stdout(&term::paint(val, color));
+}
+
+/// Write to stdout, ignoring errors
+///
+/// `print!` panics when stdout is closed, e.g. piped into `head`; output
+/// nobody reads is dropped instead.
+pub fn write_stdout(text: &str) {
+    let _ = io::stdout().write_all(redact(text).as_bytes());
+}
+
+/// A task started by [`spawn`]
+pub struct Task<T>(TaskState<T>);
+
+enum TaskState<T> {
+    Thread(thread::JoinHandle<Option<T>>),
+    Done(Option<T>),
+}
+
+impl<T> Task<T> {
+    /// Wait for the result; `Err` when the task panicked
+    pub fn join(self) -> thread::Result<T> {
+        let result = match self.0 {
+            TaskState::Thread(handle) => handle.join()?,
+            TaskState::Done(result) => result,
+        };
+        result.ok_or_else(|| Box::new("task did not run") as Box<dyn std::any::Any + Send>)
+    }
+}
+
+/// Run `f` on a new thread, or on this one when no thread can be started
+///
+/// `std::thread::spawn` panics when the system refuses a thread (e.g. under
+/// a process limit); here the work only loses its parallelism.
+pub fn spawn<T, F>(f: F) -> Task<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    // A failed spawn drops its closure, so `f` waits in a slot both can reach
+    let slot = Arc::new(Mutex::new(Some(f)));
+    let theirs = Arc::clone(&slot);
+    let take = |slot: &Mutex<Option<F>>| slot.lock().ok().and_then(|mut f| f.take());
+    match thread::Builder::new().spawn(move || take(&theirs).map(|f| f())) {
+        Ok(handle) => Task(TaskState::Thread(handle)),
+        Err(e) => {
+            log::debug!("Cannot start a thread, running the task inline: {}", e);
+            Task(TaskState::Done(take(&slot).map(|f| f())))
+        }
+    }
+}
+
+/// Ask a yes/no question on the console
+///
+/// Returns `true` only when the user answers `y` or `yes` (case insensitive).
+/// Any read error or empty answer counts as "no", and so does non-interactive
+/// mode, where nothing is read.
+///
+/// # Parameters
+/// - `question`: Question text, printed without a trailing newline
+pub fn confirm(question: &str) -> bool {
+    if is_non_interactive() {
+        return false;
+    }
+    printer_no_newline(&format!("{} [y/N] ", question), "cyan");
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Open a file in the user's editor and wait for it to exit
+///
+/// The editor is taken from `VISUAL`, then `EDITOR`, falling back to
+/// `notepad` on Windows and `vi` elsewhere. The variable may contain
+/// arguments, e.g. `code --wait`.
+pub fn open_editor(path: &Path) -> anyhow::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    log::debug!("Opening {} with {}", path.display(), editor);
+
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Editor command is empty"))?;
+    let status = status(Command::new(program).args(parts).arg(path))
+        .map_err(|e| anyhow::anyhow!("Failed to start editor '{}': {}", editor, e))?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "Editor '{}' exited with {:?}",
+            editor,
+            status.code()
+        ));
+    }
+    Ok(())
+}
 
-    print!("{}{}\x1b[0m", color_code, val);
+/// Ask for a line of input on the console
+///
+/// Returns the trimmed answer, or `None` if stdin could not be read, the
+/// answer was empty or prompts are refused.
+///
+/// # Parameters
+/// - `question`: Question text, printed without a trailing newline
+pub fn prompt(question: &str) -> Option<String> {
+    if is_non_interactive() {
+        return None;
+    }
+    printer_no_newline(&format!("{} ", question), "cyan");
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok()?;
+    let answer = answer.trim();
+    (!answer.is_empty()).then(|| answer.to_string())
+}
+
+/// Parse a selection of numbered items, e.g. `1 3 5-7` or `all`, into
+/// sorted 0-based indices
+///
+/// Numbers are 1-based and may be separated by spaces or commas; each must
+/// be at most `count`.
+pub fn parse_selection(text: &str, count: usize) -> Result<Vec<usize>, String> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("all") || text == "*" {
+        return Ok((0..count).collect());
+    }
+    let number = |part: &str| {
+        part.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=count).contains(n))
+            .ok_or_else(|| format!("\"{}\" is not a number from 1 to {}", part.trim(), count))
+    };
+    let mut selected = std::collections::BTreeSet::new();
+    for part in text.split([' ', ',']).filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (number(first)?, number(last)?);
+                if first > last {
+                    return Err(format!("\"{}\" is not an ascending range", part));
+                }
+                selected.extend(first - 1..last);
+            }
+            None => {
+                selected.insert(number(part)? - 1);
+            }
+        }
+    }
+    Ok(selected.into_iter().collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("1 3 5-7", 8), Ok(vec![0, 2, 4, 5, 6]));
+        assert_eq!(parse_selection("2,1, 2", 3), Ok(vec![0, 1]));
+        assert_eq!(parse_selection("all", 3), Ok(vec![0, 1, 2]));
+        assert_eq!(parse_selection("", 3), Ok(vec![]));
+        for invalid in ["0", "4", "x", "3-1", "1-"] {
+            assert!(parse_selection(invalid, 3).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_mask_emails() {
+        assert_eq!(mask_emails("li@acme.com"), "l***@acme.com");
+        assert_eq!(
+            mask_emails("Using Li <li.si+work@mail.acme.com>."),
+            "Using Li <l***@mail.acme.com>."
+        );
+        assert_eq!(mask_emails("a@x.io -> b@y.dev"), "a***@x.io -> b***@y.dev");
+        assert_eq!(
+            mask_emails("git@github.com:acme/app"),
+            "git@github.com:acme/app"
+        );
+        assert_eq!(mask_emails("li@acme.com: denied"), "l***@acme.com: denied");
+        assert_eq!(
+            mask_emails("@ li@localhost 张@例子.中国"),
+            "@ li@localhost 张***@例子.中国"
+        );
+    }
+
+    #[test]
+    fn test_resolve_git_program() {
+        let setting = Some(PathBuf::from("C:/PortableGit/bin/git.exe"));
+        assert_eq!(resolve_git_program(None, None), PathBuf::from("git"));
+        assert_eq!(
+            resolve_git_program(None, setting.clone()),
+            setting.clone().unwrap()
+        );
+        assert_eq!(
+            resolve_git_program(Some("/opt/git/bin/git".to_string()), setting),
+            PathBuf::from("/opt/git/bin/git")
+        );
+        assert_eq!(
+            resolve_git_program(None, Some(PathBuf::new())),
+            PathBuf::from("git")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_with_timeout() {
+        let run = |script: &str, timeout: u64| {
+            let mut command = Command::new("sh");
+            command.args(["-c", script]).stdout(Stdio::piped());
+            let child = command.spawn().unwrap();
+            wait_with_timeout(&command, child, Duration::from_millis(timeout))
+        };
+        let output = run("echo done", 5_000).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"done\n");
+
+        let started = Instant::now();
+        let error = run("sleep 5", 100).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_command_line() {
+        let mut command = Command::new("git");
+        command.args(["config", "--local", "user.name", "Li Si"]);
+        assert_eq!(
+            command_line(&command),
+            "git config --local user.name 'Li Si'"
+        );
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("plain@x.org"), "plain@x.org");
+        assert_eq!(shell_quote("a b"), "'a b'");
+    }
+
     #[test]
     fn test_get_config_path() {
         let path = get_config_path().unwrap();
         assert!(path.ends_with("config.jsonc"));
     }
 
+    #[test]
+    fn test_resolve_config_path_precedence() {
+        let flag = PathBuf::from("/flag/config.jsonc");
+        let env = PathBuf::from("/env/config.jsonc");
+
+        let path = resolve_config_path(Some(flag.clone()), Some(env.clone())).unwrap();
+        assert_eq!(path, flag);
+
+        let path = resolve_config_path(None, Some(env.clone())).unwrap();
+        assert_eq!(path, env);
+
+        let path = resolve_config_path(None, Some(PathBuf::new())).unwrap();
+        assert!(path.ends_with("gum/config.jsonc"));
+    }
+
+    #[test]
+    fn test_spawn() {
+        assert_eq!(spawn(|| 6 * 7).join().unwrap(), 42);
+        assert!(spawn(|| -> u8 { panic!("task failed") }).join().is_err());
+    }
+
+    #[test]
+    fn test_xdg_config_home() {
+        let home = Some(PathBuf::from("/home/user"));
+
+        let dir = xdg_config_home(Some(OsString::from("/xdg")), home.clone()).unwrap();
+        assert_eq!(dir, PathBuf::from("/xdg"));
+
+        let dir = xdg_config_home(Some(OsString::from("relative")), home.clone()).unwrap();
+        assert_eq!(dir, PathBuf::from("/home/user/.config"));
+
+        assert!(xdg_config_home(None, None).is_err());
+    }
+
+    #[test]
+    fn test_migrate_legacy_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let preferred = dir.path().join("new").join("gum").join("config.jsonc");
+        let legacy = dir.path().join("old").join("config.jsonc");
+
+        assert_eq!(
+            migrate_legacy_config(&preferred, std::slice::from_ref(&legacy)),
+            preferred
+        );
+        assert!(!preferred.exists());
+
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, "{\"groups\": {}}").unwrap();
+        assert_eq!(
+            migrate_legacy_config(&preferred, std::slice::from_ref(&legacy)),
+            preferred
+        );
+        assert_eq!(fs::read_to_string(&preferred).unwrap(), "{\"groups\": {}}");
+        assert!(legacy.exists());
+    }
+
     #[test]
     fn test_printer() {
         // Just test that it doesn't panic
         printer("test", "red");
         printer("test", "invalid");
     }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("work", "work"), 0);
+        assert_eq!(edit_distance("wrok", "work"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("personal", "persnal"), 1);
+        assert_eq!(edit_distance("", "oss"), 3);
+        assert_eq!(edit_distance("工作", "工作2"), 1);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let old = "{\n  \"a\": 1,\n  \"b\": 2\n}\n";
+        let new = "{\n  \"a\": 1,\n  \"b\": 3,\n  \"c\": 4\n}\n";
+        assert_eq!(
+            changed_lines(old, new),
+            (vec!["  \"b\": 2"], vec!["  \"b\": 3,", "  \"c\": 4"])
+        );
+        assert_eq!(changed_lines("a\n", "a\n"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-02-29"), Some(951_782_400));
+        assert_eq!(parse_date("2023-11-14"), Some(1_699_920_000));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("2023-1-01"), None);
+        assert_eq!(parse_date("next week"), None);
+    }
 }
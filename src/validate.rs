@@ -0,0 +1,1301 @@
+//! # Configuration Validation Module
+//!
+//! Checks the configuration file beyond what deserialization enforces:
+//! structure, duplicate groups, empty fields, malformed emails and unknown keys.
+//! Every finding carries the line and column it refers to, so it can be
+//! reported the same way compilers report source errors.
+//!
+//! ## Main Features
+//! - Lint configuration file content (`lint`)
+//! - Email syntax check shared with the `set` command (`is_valid_email`)
+//! - Group name rules and the reserved name list (`check_group_name`)
+
+use crate::config::is_workstyle_key;
+use crate::utils;
+use std::fmt;
+
+/// Keys accepted at the top level of the configuration file
+const TOP_LEVEL_KEYS: &[&str] = &[
+    "groups", "aliases", "hosts", "branches", "policies", "includes", "settings",
+];
+
+/// Keys accepted inside the `settings` object
+const SETTINGS_KEYS: &[&str] = &[
+    "allowed_email_domains",
+    "max_group_name_length",
+    "lowercase_group_names",
+    "use_config_only",
+    "locale",
+    "suggest_rules",
+    "redact",
+    "plain",
+    "retention",
+    "git",
+];
+
+/// Keys accepted inside `settings.retention`
+const RETENTION_KEYS: &[&str] = &["versions", "history_days", "journal_kib", "cache_days"];
+
+/// Keys accepted inside `settings.git`
+const GIT_KEYS: &[&str] = &["path", "timeout_secs"];
+
+/// Group names that cannot be used for configuration groups
+///
+/// `global` is the pseudo group shown for the global git identity; the
+/// others are kept free for scope names.
+pub const RESERVED_GROUP_NAMES: &[&str] = &["global", "local", "system"];
+
+/// Default maximum length of a group name, in characters
+pub const DEFAULT_MAX_GROUP_NAME_LENGTH: usize = 64;
+
+/// Keys accepted inside a group entry
+pub const GROUP_KEYS: &[&str] = &[
+    "name",
+    "email",
+    "committer_name",
+    "committer_email",
+    "commit_template",
+    "inherit",
+    "email_variants",
+    "ssh_alias",
+    "ssh_key",
+    "signing_key",
+    "signing_format",
+    "http_proxy",
+    "ssl_ca_info",
+    "credential_helper",
+    "sendemail_from",
+    "smtp_server",
+    "smtp_server_port",
+    "smtp_user",
+    "smtp_encryption",
+    "workstyle",
+    "url_rewrites",
+    "trailers",
+    "signoff",
+    "locked",
+    "expires",
+    "tags",
+    "description",
+];
+
+/// Severity of a validation finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The file cannot be used as is
+    Error,
+    /// The file is usable but probably not what the user meant
+    Warning,
+}
+
+/// A single validation finding
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, level, self.message
+        )
+    }
+}
+
+/// Validate configuration file content
+///
+/// Returns all findings in document order. Syntax errors stop the analysis
+/// and are reported as a single error.
+pub fn lint(content: &str) -> Vec<Issue> {
+    if let Err(e) = serde_json::from_str::<serde_json::Value>(content) {
+        return vec![Issue {
+            severity: Severity::Error,
+            line: e.line(),
+            column: e.column(),
+            message: format!("syntax error: {}", strip_location(&e.to_string())),
+        }];
+    }
+
+    let mut parser = Parser::new(content);
+    let root = match parser.parse_value() {
+        Some(node) => node,
+        None => return Vec::new(),
+    };
+
+    let mut issues = Vec::new();
+    let entries = match &root.value {
+        Value::Object(entries) => entries,
+        _ => {
+            issues.push(root.issue(Severity::Error, "top level must be an object"));
+            return issues;
+        }
+    };
+
+    let mut groups = None;
+    let mut group_maps = Vec::new();
+    for entry in entries {
+        if entry.key == "groups" {
+            groups = Some(&entry.node);
+        } else if matches!(entry.key.as_str(), "aliases" | "hosts" | "branches") {
+            group_maps.push(entry);
+        } else if entry.key == "settings" {
+            lint_settings(&entry.node, &mut issues);
+        } else if entry.key == "policies" {
+            lint_policies(&entry.node, &mut issues);
+        } else if entry.key == "includes" {
+            lint_includes(&entry.node, &mut issues);
+        } else if !TOP_LEVEL_KEYS.contains(&entry.key.as_str()) {
+            issues.push(entry.issue(Severity::Warning, &format!("unknown key \"{}\"", entry.key)));
+        }
+    }
+
+    match groups {
+        Some(node) => lint_groups(node, &mut issues),
+        None => issues.push(root.issue(Severity::Error, "missing \"groups\" object")),
+    }
+    let group_names: Vec<&str> = match groups.map(|g| &g.value) {
+        Some(Value::Object(entries)) => entries.iter().map(|e| e.key.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    for entry in group_maps {
+        match entry.key.as_str() {
+            "aliases" => lint_aliases(&entry.node, &group_names, &mut issues),
+            "hosts" => lint_rules(entry, "host", &group_names, &mut issues),
+            _ => lint_rules(entry, "branch", &group_names, &mut issues),
+        }
+    }
+
+    issues
+}
+
+/// Validate the `settings` object
+fn lint_settings(node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Object(entries) = &node.value else {
+        issues.push(node.issue(Severity::Error, "\"settings\" must be an object"));
+        return;
+    };
+    for entry in entries {
+        if !SETTINGS_KEYS.contains(&entry.key.as_str()) {
+            issues.push(entry.issue(
+                Severity::Warning,
+                &format!("unknown setting \"{}\"", entry.key),
+            ));
+        } else if entry.key == "retention" {
+            lint_retention(&entry.node, issues);
+        } else if entry.key == "git" {
+            lint_git(&entry.node, issues);
+        }
+    }
+}
+
+/// Validate the `settings.git` object
+fn lint_git(node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Object(entries) = &node.value else {
+        issues.push(node.issue(Severity::Error, "\"git\" must be an object"));
+        return;
+    };
+    for entry in entries {
+        if !GIT_KEYS.contains(&entry.key.as_str()) {
+            issues.push(entry.issue(
+                Severity::Warning,
+                &format!("unknown git setting \"{}\"", entry.key),
+            ));
+        } else if entry.key == "path" && !matches!(entry.node.value, Value::String(_)) {
+            issues.push(
+                entry
+                    .node
+                    .issue(Severity::Error, "git setting \"path\" must be a string"),
+            );
+        } else if entry.key == "timeout_secs" && !matches!(entry.node.value, Value::Other) {
+            issues.push(entry.node.issue(
+                Severity::Error,
+                "git setting \"timeout_secs\" must be a number",
+            ));
+        }
+    }
+}
+
+/// Validate the `settings.retention` object
+fn lint_retention(node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Object(entries) = &node.value else {
+        issues.push(node.issue(Severity::Error, "\"retention\" must be an object"));
+        return;
+    };
+    for entry in entries {
+        if !RETENTION_KEYS.contains(&entry.key.as_str()) {
+            issues.push(entry.issue(
+                Severity::Warning,
+                &format!("unknown retention setting \"{}\"", entry.key),
+            ));
+        } else if !matches!(entry.node.value, Value::Other) {
+            issues.push(entry.node.issue(
+                Severity::Error,
+                &format!("retention setting \"{}\" must be a number", entry.key),
+            ));
+        }
+    }
+}
+
+/// Validate the `aliases` object
+///
+/// Aliases pointing to a missing group and aliases shadowed by a group with
+/// the same name are reported as warnings; neither stops gum from working.
+fn lint_aliases(node: &Node, group_names: &[&str], issues: &mut Vec<Issue>) {
+    let Value::Object(entries) = &node.value else {
+        issues.push(node.issue(Severity::Error, "\"aliases\" must be an object"));
+        return;
+    };
+    for entry in entries {
+        let Value::String(target) = &entry.node.value else {
+            issues.push(entry.node.issue(
+                Severity::Error,
+                &format!("alias \"{}\" must be a group name", entry.key),
+            ));
+            continue;
+        };
+        if group_names.contains(&entry.key.as_str()) {
+            issues.push(entry.issue(
+                Severity::Warning,
+                &format!("alias \"{}\" is shadowed by a group", entry.key),
+            ));
+        } else if !group_names.contains(&target.as_str()) {
+            issues.push(entry.node.issue(
+                Severity::Warning,
+                &format!(
+                    "alias \"{}\" points to unknown group \"{}\"",
+                    entry.key, target
+                ),
+            ));
+        }
+    }
+}
+
+/// Validate the `includes` list
+fn lint_includes(node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Array(items) = &node.value else {
+        issues.push(node.issue(Severity::Error, "\"includes\" must be a list"));
+        return;
+    };
+    for item in items {
+        match &item.value {
+            Value::String(path) if path.trim().is_empty() => {
+                issues.push(item.issue(Severity::Warning, "empty include path"))
+            }
+            Value::String(_) => {}
+            _ => issues.push(item.issue(Severity::Error, "includes must be file paths")),
+        }
+    }
+}
+
+/// Validate the `policies` list
+fn lint_policies(node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Array(items) = &node.value else {
+        issues.push(node.issue(Severity::Error, "\"policies\" must be a list"));
+        return;
+    };
+    for (i, item) in items.iter().enumerate() {
+        let Value::Object(entries) = &item.value else {
+            issues.push(item.issue(
+                Severity::Error,
+                &format!("policy {} must be an object", i + 1),
+            ));
+            continue;
+        };
+        for key in ["email", "remote"] {
+            match entries.iter().find(|entry| entry.key == key) {
+                Some(entry) if matches!(entry.node.value, Value::String(_)) => {}
+                Some(entry) => issues.push(entry.node.issue(
+                    Severity::Error,
+                    &format!("\"{}\" of policy {} must be a string", key, i + 1),
+                )),
+                None => issues.push(item.issue(
+                    Severity::Error,
+                    &format!("policy {} is missing \"{}\"", i + 1, key),
+                )),
+            }
+        }
+        for entry in entries {
+            if entry.key != "email" && entry.key != "remote" {
+                issues.push(entry.issue(
+                    Severity::Warning,
+                    &format!("unknown key \"{}\" in policy {}", entry.key, i + 1),
+                ));
+            }
+        }
+    }
+}
+
+/// Validate the `hosts` or `branches` object, whose entries are `kind`
+/// patterns mapped to groups
+fn lint_rules(entry: &Entry, kind: &str, group_names: &[&str], issues: &mut Vec<Issue>) {
+    let node = &entry.node;
+    let Value::Object(entries) = &node.value else {
+        issues.push(node.issue(
+            Severity::Error,
+            &format!("\"{}\" must be an object", entry.key),
+        ));
+        return;
+    };
+    for entry in entries {
+        match &entry.node.value {
+            Value::String(target) if !group_names.contains(&target.as_str()) => {
+                issues.push(entry.node.issue(
+                    Severity::Warning,
+                    &format!(
+                        "{} \"{}\" points to unknown group \"{}\"",
+                        kind, entry.key, target
+                    ),
+                ))
+            }
+            Value::String(_) => {}
+            _ => issues.push(entry.node.issue(
+                Severity::Error,
+                &format!("{} \"{}\" must be a group name", kind, entry.key),
+            )),
+        }
+    }
+}
+
+/// Validate the `groups` object
+fn lint_groups(node: &Node, issues: &mut Vec<Issue>) {
+    let entries = match &node.value {
+        Value::Object(entries) => entries,
+        _ => {
+            issues.push(node.issue(Severity::Error, "\"groups\" must be an object"));
+            return;
+        }
+    };
+
+    for (i, entry) in entries.iter().enumerate() {
+        if entries[..i].iter().any(|prev| prev.key == entry.key) {
+            issues.push(entry.issue(
+                Severity::Error,
+                &format!(
+                    "duplicate group \"{}\", only the last one is used",
+                    entry.key
+                ),
+            ));
+        }
+        if entry.key.trim().is_empty() {
+            issues.push(entry.issue(Severity::Error, "group name is empty"));
+        } else if let Err(reason) = check_group_name(&entry.key, usize::MAX) {
+            issues.push(entry.issue(Severity::Warning, &reason));
+        }
+        let group_names: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+        lint_group(&entry.key, &entry.node, &group_names, issues);
+    }
+}
+
+/// Validate a single group entry
+///
+/// Groups inheriting from another group may leave their name and email
+/// empty.
+fn lint_group(group: &str, node: &Node, group_names: &[&str], issues: &mut Vec<Issue>) {
+    let entries = match &node.value {
+        Value::Object(entries) => entries,
+        _ => {
+            issues.push(node.issue(
+                Severity::Error,
+                &format!("group \"{}\" must be an object", group),
+            ));
+            return;
+        }
+    };
+
+    for field in ["name", "email"] {
+        if !entries.iter().any(|e| e.key == field) {
+            issues.push(node.issue(
+                Severity::Error,
+                &format!("group \"{}\" is missing \"{}\"", group, field),
+            ));
+        }
+    }
+
+    for entry in entries {
+        if !GROUP_KEYS.contains(&entry.key.as_str()) {
+            issues.push(entry.issue(
+                Severity::Warning,
+                &format!("unknown key \"{}\" in group \"{}\"", entry.key, group),
+            ));
+            continue;
+        }
+        if entry.key == "email_variants" {
+            lint_email_variants(group, &entry.node, issues);
+            continue;
+        }
+        if entry.key == "workstyle" {
+            lint_workstyle(group, &entry.node, issues);
+            continue;
+        }
+        if entry.key == "url_rewrites" {
+            lint_url_rewrites(group, &entry.node, issues);
+            continue;
+        }
+        if entry.key == "trailers" {
+            lint_trailers(group, &entry.node, issues);
+            continue;
+        }
+        if entry.key == "tags" {
+            lint_tags(group, &entry.node, issues);
+            continue;
+        }
+        if entry.key == "signoff" || entry.key == "locked" {
+            if !matches!(entry.node.value, Value::Bool) {
+                issues.push(entry.node.issue(
+                    Severity::Error,
+                    &format!(
+                        "\"{}\" of group \"{}\" must be true or false",
+                        entry.key, group
+                    ),
+                ));
+            }
+            continue;
+        }
+
+        let value = match &entry.node.value {
+            Value::String(value) => value,
+            _ => {
+                issues.push(entry.node.issue(
+                    Severity::Error,
+                    &format!("\"{}\" of group \"{}\" must be a string", entry.key, group),
+                ));
+                continue;
+            }
+        };
+
+        let inherits = entries.iter().any(|e| e.key == "inherit");
+        match entry.key.as_str() {
+            "name" | "email" if inherits && value.is_empty() => {}
+            "inherit" if !group_names.contains(&value.as_str()) => issues.push(entry.node.issue(
+                Severity::Warning,
+                &format!(
+                    "group \"{}\" inherits from unknown group \"{}\"",
+                    group, value
+                ),
+            )),
+            "name" if value.trim().is_empty() => issues.push(entry.node.issue(
+                Severity::Warning,
+                &format!("group \"{}\" has an empty name", group),
+            )),
+            "email" if value.is_empty() => issues.push(entry.node.issue(
+                Severity::Warning,
+                &format!("group \"{}\" has an empty email", group),
+            )),
+            "email" if !is_valid_email(value) => issues.push(entry.node.issue(
+                Severity::Warning,
+                &format!("group \"{}\" has a malformed email \"{}\"", group, value),
+            )),
+            "committer_email" if !is_valid_email(value) => issues.push(entry.node.issue(
+                Severity::Warning,
+                &format!(
+                    "group \"{}\" has a malformed committer email \"{}\"",
+                    group, value
+                ),
+            )),
+            "expires" if utils::parse_date(value).is_none() => issues.push(entry.node.issue(
+                Severity::Error,
+                &format!(
+                    "\"expires\" of group \"{}\" must be a date like 2025-12-31",
+                    group
+                ),
+            )),
+            "smtp_encryption" if !matches!(value.as_str(), "ssl" | "tls") => {
+                issues.push(entry.node.issue(
+                    Severity::Warning,
+                    &format!(
+                        "\"smtp_encryption\" of group \"{}\" should be \"ssl\" or \"tls\"",
+                        group
+                    ),
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Validate the alternate emails of a group
+fn lint_email_variants(group: &str, node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Object(entries) = &node.value else {
+        issues.push(node.issue(
+            Severity::Error,
+            &format!(
+                "\"email_variants\" of group \"{}\" must be an object",
+                group
+            ),
+        ));
+        return;
+    };
+    for entry in entries {
+        match &entry.node.value {
+            Value::String(email) if !is_valid_email(email) => issues.push(entry.node.issue(
+                Severity::Warning,
+                &format!(
+                    "email variant \"{}\" of group \"{}\" is malformed",
+                    entry.key, group
+                ),
+            )),
+            Value::String(_) => {}
+            _ => issues.push(entry.node.issue(
+                Severity::Error,
+                &format!(
+                    "email variant \"{}\" of group \"{}\" must be a string",
+                    entry.key, group
+                ),
+            )),
+        }
+    }
+}
+
+/// Validate the workstyle settings of a group
+fn lint_workstyle(group: &str, node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Object(entries) = &node.value else {
+        issues.push(node.issue(
+            Severity::Error,
+            &format!("\"workstyle\" of group \"{}\" must be an object", group),
+        ));
+        return;
+    };
+    for entry in entries {
+        if !is_workstyle_key(&entry.key) {
+            issues.push(entry.issue(
+                Severity::Warning,
+                &format!(
+                    "\"{}\" in the workstyle of group \"{}\" is not a workstyle setting",
+                    entry.key, group
+                ),
+            ));
+        }
+        if !matches!(entry.node.value, Value::String(_)) {
+            issues.push(entry.node.issue(
+                Severity::Error,
+                &format!(
+                    "workstyle setting \"{}\" of group \"{}\" must be a string",
+                    entry.key, group
+                ),
+            ));
+        }
+    }
+}
+
+/// Validate the URL rewrites of a group
+fn lint_url_rewrites(group: &str, node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Object(entries) = &node.value else {
+        issues.push(node.issue(
+            Severity::Error,
+            &format!("\"url_rewrites\" of group \"{}\" must be an object", group),
+        ));
+        return;
+    };
+    let mut replacements: Vec<&str> = Vec::new();
+    for entry in entries {
+        match &entry.node.value {
+            Value::String(replacement) if replacements.contains(&replacement.as_str()) => {
+                issues.push(entry.node.issue(
+                    Severity::Warning,
+                    &format!(
+                        "URL rewrite \"{}\" of group \"{}\" has the replacement of another rewrite; only one is applied",
+                        entry.key, group
+                    ),
+                ))
+            }
+            Value::String(replacement) if !replacement.is_empty() => {
+                replacements.push(replacement)
+            }
+            _ => issues.push(entry.node.issue(
+                Severity::Error,
+                &format!(
+                    "URL rewrite \"{}\" of group \"{}\" must be a non-empty string",
+                    entry.key, group
+                ),
+            )),
+        }
+    }
+}
+
+/// Validate the commit trailer templates of a group
+fn lint_trailers(group: &str, node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Array(items) = &node.value else {
+        issues.push(node.issue(
+            Severity::Error,
+            &format!("\"trailers\" of group \"{}\" must be a list", group),
+        ));
+        return;
+    };
+    for item in items {
+        match &item.value {
+            Value::String(template) => {
+                if let Err(reason) = check_trailer(template) {
+                    issues.push(item.issue(
+                        Severity::Warning,
+                        &format!("trailer of group \"{}\": {}", group, reason),
+                    ));
+                }
+            }
+            _ => issues.push(item.issue(
+                Severity::Error,
+                &format!("trailers of group \"{}\" must be strings", group),
+            )),
+        }
+    }
+}
+
+/// Validate the tags of a group
+fn lint_tags(group: &str, node: &Node, issues: &mut Vec<Issue>) {
+    let Value::Array(items) = &node.value else {
+        issues.push(node.issue(
+            Severity::Error,
+            &format!("\"tags\" of group \"{}\" must be a list", group),
+        ));
+        return;
+    };
+    for item in items {
+        if !matches!(item.value, Value::String(_)) {
+            issues.push(item.issue(
+                Severity::Error,
+                &format!("tags of group \"{}\" must be strings", group),
+            ));
+        }
+    }
+}
+
+/// Check a commit trailer template, e.g. `Signed-off-by: {name} <{email}>`
+///
+/// The token before the colon may hold letters, digits and dashes, and a
+/// value must follow it on the same line.
+pub fn check_trailer(template: &str) -> Result<(), String> {
+    let Some((token, value)) = template.split_once(':') else {
+        return Err(format!("\"{}\" is not of the form Token: value", template));
+    };
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("\"{}\" is not a valid trailer token", token));
+    }
+    if value.trim().is_empty() || template.contains('\n') {
+        return Err(format!(
+            "trailer \"{}\" needs a value on a single line",
+            token
+        ));
+    }
+    Ok(())
+}
+
+/// Check that an email address is syntactically plausible
+///
+/// Requires exactly one `@`, a non-empty local part, a domain containing a
+/// dot that is neither leading nor trailing, and no whitespace anywhere.
+pub fn is_valid_email(email: &str) -> bool {
+    if email.chars().any(char::is_whitespace) {
+        return false;
+    }
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !domain.contains("..")
+}
+
+/// Whether a group name is reserved (case insensitive)
+pub fn is_reserved_group_name(name: &str) -> bool {
+    RESERVED_GROUP_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+}
+
+/// Check that a group name is safe to use
+///
+/// Allowed characters are letters and digits (including non-ASCII ones)
+/// and `-`, `_`, `.`, `@`, `+`. Names may not start with `-` (they would look
+/// like flags), may not be `.` or `..`, and may not be reserved.
+///
+/// # Returns
+/// - `Err(reason)`: Human readable reason the name was rejected
+pub fn check_group_name(name: &str, max_len: usize) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("group name is empty".to_string());
+    }
+    if is_reserved_group_name(name) {
+        return Err(format!("group name \"{}\" is reserved", name));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_alphanumeric() && !matches!(c, '-' | '_' | '.' | '@' | '+'))
+    {
+        return Err(format!(
+            "group name \"{}\" contains invalid character {:?}",
+            name, c
+        ));
+    }
+    if name.starts_with('-') || name == "." || name == ".." {
+        return Err(format!("group name \"{}\" is not allowed", name));
+    }
+    if name.chars().count() > max_len {
+        return Err(format!(
+            "group name \"{}\" is longer than {} characters",
+            name, max_len
+        ));
+    }
+    Ok(())
+}
+
+/// Check an email address given on the command line
+///
+/// Besides the syntax check of `is_valid_email`, rejects surrounding
+/// whitespace and, when `allowed_domains` is not empty, domains outside the
+/// list (subdomains of an allowed domain are accepted).
+///
+/// # Returns
+/// - `Err(reason)`: Human readable reason the email was rejected
+pub fn check_email(email: &str, allowed_domains: &[String]) -> Result<(), String> {
+    if email.trim() != email {
+        return Err(format!("email \"{}\" has surrounding whitespace", email));
+    }
+    if !is_valid_email(email) {
+        return Err(format!("\"{}\" is not a valid email address", email));
+    }
+    if allowed_domains.is_empty() {
+        return Ok(());
+    }
+
+    let domain = email
+        .rsplit_once('@')
+        .map(|(_, d)| d.to_lowercase())
+        .unwrap_or_default();
+    let allowed = allowed_domains.iter().any(|allowed| {
+        let allowed = allowed.trim().trim_start_matches('@').to_lowercase();
+        domain == allowed || domain.ends_with(&format!(".{}", allowed))
+    });
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!(
+            "domain \"{}\" is not in the allowed list ({})",
+            domain,
+            allowed_domains.join(", ")
+        ))
+    }
+}
+
+/// Check that an identity has both a name and an email
+///
+/// # Returns
+/// - `Err(reason)`: Which of the two is empty
+pub fn check_identity_complete(name: &str, email: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("name is empty".to_string());
+    }
+    if email.is_empty() {
+        return Err("email is empty".to_string());
+    }
+    Ok(())
+}
+
+/// Check an identity before it becomes a group
+///
+/// Rejects an empty name or email, then checks the email like
+/// [`check_email`].
+///
+/// # Returns
+/// - `Err(reason)`: Human readable reason the identity was rejected
+pub fn check_identity(name: &str, email: &str, allowed_domains: &[String]) -> Result<(), String> {
+    check_identity_complete(name, email)?;
+    check_email(email, allowed_domains)
+}
+
+/// Remove the " at line X column Y" suffix serde_json appends to messages
+fn strip_location(message: &str) -> &str {
+    match message.rfind(" at line ") {
+        Some(idx) => &message[..idx],
+        None => message,
+    }
+}
+
+/// Parsed JSON value with its source position
+struct Node {
+    line: usize,
+    column: usize,
+    value: Value,
+}
+
+impl Node {
+    fn issue(&self, severity: Severity, message: &str) -> Issue {
+        Issue {
+            severity,
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Object member, positioned at its key
+struct Entry {
+    key: String,
+    line: usize,
+    column: usize,
+    node: Node,
+}
+
+impl Entry {
+    fn issue(&self, severity: Severity, message: &str) -> Issue {
+        Issue {
+            severity,
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// JSON value kinds the linter distinguishes
+///
+/// Objects keep every member in order, including duplicate keys.
+enum Value {
+    Object(Vec<Entry>),
+    Array(Vec<Node>),
+    String(String),
+    Bool,
+    Other,
+}
+
+/// Minimal position-tracking JSON reader
+///
+/// Only run on input that `serde_json` already accepted, so it gives up
+/// (returns `None`) instead of reporting syntax errors itself.
+struct Parser {
+    chars: Vec<char>,
+    idx: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn new(content: &str) -> Self {
+        Self {
+            chars: content.chars().collect(),
+            idx: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.idx).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.idx += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn parse_value(&mut self) -> Option<Node> {
+        self.skip_whitespace();
+        let (line, column) = (self.line, self.column);
+        let value = match self.peek()? {
+            '{' => self.parse_object()?,
+            '[' => self.parse_array()?,
+            '"' => Value::String(self.parse_string()?),
+            _ => {
+                let start = self.idx;
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, ',' | '}' | ']') && !c.is_whitespace())
+                {
+                    self.bump();
+                }
+                let literal: String = self.chars[start..self.idx].iter().collect();
+                if matches!(literal.as_str(), "true" | "false") {
+                    Value::Bool
+                } else {
+                    Value::Other
+                }
+            }
+        };
+        Some(Node {
+            line,
+            column,
+            value,
+        })
+    }
+
+    fn parse_object(&mut self) -> Option<Value> {
+        self.bump();
+        let mut entries = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek()? {
+                '}' => {
+                    self.bump();
+                    return Some(Value::Object(entries));
+                }
+                ',' => {
+                    self.bump();
+                }
+                '"' => {
+                    let (line, column) = (self.line, self.column);
+                    let key = self.parse_string()?;
+                    self.skip_whitespace();
+                    if self.bump()? != ':' {
+                        return None;
+                    }
+                    let node = self.parse_value()?;
+                    entries.push(Entry {
+                        key,
+                        line,
+                        column,
+                        node,
+                    });
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Option<Value> {
+        self.bump();
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek()? {
+                ']' => {
+                    self.bump();
+                    return Some(Value::Array(items));
+                }
+                ',' => {
+                    self.bump();
+                }
+                _ => items.push(self.parse_value()?),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        let start = self.idx;
+        self.bump();
+        loop {
+            match self.bump()? {
+                '\\' => {
+                    self.bump()?;
+                }
+                '"' => break,
+                _ => {}
+            }
+        }
+        let raw: String = self.chars[start..self.idx].iter().collect();
+        serde_json::from_str(&raw).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_email() {
+        assert!(is_valid_email("lisi@gmail.com"));
+        assert!(is_valid_email("a.b+tag@mail.example.org"));
+        assert!(!is_valid_email("lisi"));
+        assert!(!is_valid_email("lisi@gmail"));
+        assert!(!is_valid_email(" lisi@gmail.com"));
+        assert!(!is_valid_email("a@b@c.com"));
+        assert!(!is_valid_email("@gmail.com"));
+        assert!(!is_valid_email("lisi@gmail..com"));
+    }
+
+    #[test]
+    fn test_check_group_name() {
+        assert!(check_group_name("work", 64).is_ok());
+        assert!(check_group_name("acme-oss_2.0", 64).is_ok());
+        assert!(check_group_name("工作", 64).is_ok());
+        assert!(check_group_name("  ", 64).is_err());
+        assert!(check_group_name("a/b", 64).is_err());
+        assert!(check_group_name("a\\b", 64).is_err());
+        assert!(check_group_name("li si", 64).is_err());
+        assert!(check_group_name("-x", 64).is_err());
+        assert!(check_group_name("..", 64).is_err());
+        assert!(check_group_name("Global", 64).is_err());
+        assert!(check_group_name("abcdef", 5).is_err());
+    }
+
+    #[test]
+    fn test_check_email() {
+        assert!(check_email("lisi@gmail.com", &[]).is_ok());
+        assert!(check_email("lisi@gmail.com ", &[]).is_err());
+        assert!(check_email("lisi@gmail", &[]).is_err());
+
+        let allowed = vec!["acme.com".to_string()];
+        assert!(check_email("li@acme.com", &allowed).is_ok());
+        assert!(check_email("li@eu.ACME.com", &allowed).is_ok());
+        assert!(check_email("li@notacme.com", &allowed).is_err());
+        assert!(check_email("li@gmail.com", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_check_identity() {
+        let allowed = vec!["acme.com".to_string()];
+        assert!(check_identity("Li", "li@acme.com", &allowed).is_ok());
+        assert_eq!(
+            check_identity(" ", "li@acme.com", &allowed),
+            Err("name is empty".to_string())
+        );
+        assert_eq!(
+            check_identity("Li", "", &allowed),
+            Err("email is empty".to_string())
+        );
+        assert!(check_identity("Li", "li", &[]).is_err());
+        assert!(check_identity_complete("Li", "li").is_ok());
+        assert!(check_identity("Li", "li@gmail.com", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_lint_reports_positions() {
+        let content = r#"{
+  "groups": {
+    "work": { "name": "li si", "email": "lisi" },
+    "work": { "name": "", "email": "lisi@gmail.com", "extra": 1 }
+  },
+  "other": true
+}"#;
+        let issues = lint(content);
+        let rendered: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+
+        assert!(
+            rendered.contains(
+                &"3:41: warning: group \"work\" has a malformed email \"lisi\"".to_string()
+            )
+        );
+        assert!(rendered.contains(
+            &"4:5: error: duplicate group \"work\", only the last one is used".to_string()
+        ));
+        assert!(rendered.contains(&"4:23: warning: group \"work\" has an empty name".to_string()));
+        assert!(
+            rendered
+                .iter()
+                .any(|r| r.starts_with("4:54: warning: unknown key \"extra\""))
+        );
+        assert!(rendered.contains(&"6:3: warning: unknown key \"other\"".to_string()));
+    }
+
+    #[test]
+    fn test_lint_syntax_error() {
+        let issues = lint("{\n  \"groups\": {,\n}");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].line, 2);
+    }
+
+    #[test]
+    fn test_lint_clean_file() {
+        let content = r#"{"groups": {"a": {"name": "A", "email": "a@example.com"}}}"#;
+        assert!(lint(content).is_empty());
+    }
+
+    #[test]
+    fn test_lint_inherit() {
+        let content = r#"{"groups": {
+  "a": { "name": "A", "email": "a@example.com" },
+  "b": { "name": "", "email": "b@example.com", "inherit": "a" },
+  "c": { "name": "C", "email": "", "inherit": "x" }
+}}"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            ["4:47: warning: group \"c\" inherits from unknown group \"x\""]
+        );
+    }
+
+    #[test]
+    fn test_lint_email_variants() {
+        let content = r#"{"groups": {
+  "a": { "name": "A", "email": "a@example.com",
+         "email_variants": { "noreply": "a@users.noreply.github.com", "bad": "a", "num": 1 } }
+}}"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "3:78: warning: email variant \"bad\" of group \"a\" is malformed",
+                "3:90: error: email variant \"num\" of group \"a\" must be a string",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_workstyle() {
+        let content = r#"{"groups": {
+  "a": { "name": "A", "email": "a@example.com",
+         "workstyle": { "alias.st": "status", "user.name": "B", "pull.rebase": true } }
+}}"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "3:47: warning: \"user.name\" in the workstyle of group \"a\" is not a workstyle setting",
+                "3:80: error: workstyle setting \"pull.rebase\" of group \"a\" must be a string",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_trailers() {
+        let content = r#"{"groups": {
+  "a": { "name": "A", "email": "a@example.com",
+         "trailers": [ "Signed-off-by: {name} <{email}>", "Bad token: x", "Empty:", 1 ] },
+  "b": { "name": "B", "email": "b@example.com", "trailers": "On-behalf-of: @acme",
+         "signoff": "yes" },
+  "c": { "name": "C", "email": "c@example.com", "signoff": true,
+         "expires": "2025-12-31" },
+  "d": { "name": "D", "email": "d@example.com", "expires": "31/12/2025" },
+  "e": { "name": "E", "email": "e@example.com", "tags": ["work", 2] }
+}}"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "3:59: warning: trailer of group \"a\": \"Bad token\" is not a valid trailer token",
+                "3:75: warning: trailer of group \"a\": trailer \"Empty\" needs a value on a single line",
+                "3:85: error: trailers of group \"a\" must be strings",
+                "4:61: error: \"trailers\" of group \"b\" must be a list",
+                "5:21: error: \"signoff\" of group \"b\" must be true or false",
+                "8:60: error: \"expires\" of group \"d\" must be a date like 2025-12-31",
+                "9:66: error: tags of group \"e\" must be strings",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_sendemail() {
+        let content = r#"{"groups": {
+  "a": { "name": "A", "email": "a@example.com", "smtp_server": "smtp.example.com",
+         "smtp_encryption": "starttls", "smtp_server_port": 587 }
+}}"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "3:29: warning: \"smtp_encryption\" of group \"a\" should be \"ssl\" or \"tls\"",
+                "3:61: error: \"smtp_server_port\" of group \"a\" must be a string",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_aliases() {
+        let content = r#"{
+  "groups": { "a": { "name": "A", "email": "a@example.com" } },
+  "aliases": { "x": "a", "a": "a", "y": "missing", "z": 1 }
+}"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "3:26: warning: alias \"a\" is shadowed by a group",
+                "3:41: warning: alias \"y\" points to unknown group \"missing\"",
+                "3:57: error: alias \"z\" must be a group name",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_includes() {
+        let content = r#"{ "groups": {}, "includes": ["~/work/gum.json", "", 3] }"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "1:49: warning: empty include path",
+                "1:53: error: includes must be file paths",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_retention() {
+        let content = r#"{ "groups": {},
+  "settings": { "retention": { "versions": 10, "history_days": "30", "logs": 1 } } }"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "2:64: error: retention setting \"history_days\" must be a number",
+                "2:70: warning: unknown retention setting \"logs\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_git() {
+        let content = r#"{ "groups": {},
+  "settings": { "git": { "path": 1, "bin": "git", "timeout_secs": "5" } } }"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "2:34: error: git setting \"path\" must be a string",
+                "2:37: warning: unknown git setting \"bin\"",
+                "2:67: error: git setting \"timeout_secs\" must be a number",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_policies() {
+        let content = r#"{
+  "groups": {},
+  "policies": [{ "email": "*@gmail.com", "remote": "*acme*", "deny": true }, { "email": 1 }]
+}"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "3:62: warning: unknown key \"deny\" in policy 1",
+                "3:89: error: \"email\" of policy 2 must be a string",
+                "3:78: error: policy 2 is missing \"remote\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let content = r#"{
+  "groups": { "a": { "name": "A", "email": "a@example.com" } },
+  "hosts": { "github.com": "a", "*.acme.com": "b", "bitbucket.org": [] },
+  "branches": { "release/*": "c" }
+}"#;
+        let rendered: Vec<String> = lint(content).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            rendered,
+            [
+                "3:47: warning: host \"*.acme.com\" points to unknown group \"b\"",
+                "3:69: error: host \"bitbucket.org\" must be a group name",
+                "4:30: warning: branch \"release/*\" points to unknown group \"c\"",
+            ]
+        );
+    }
+}
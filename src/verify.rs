@@ -0,0 +1,366 @@
+//! # Verify Module
+//!
+//! Checks commit signatures against the signing keys of the groups. A commit
+//! authored by one of the groups that has a `signing_key` must be signed, the
+//! signature must be valid, and it must have been made with that key. Commits
+//! by people without a group, or by groups without a signing key, are not
+//! checked.
+
+use crate::config::{Config, UserConfig};
+use crate::utils;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Separator between the fields of a `git log` line
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// Why a commit is flagged
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Problem {
+    /// The commit has no signature
+    Unsigned,
+    /// The signature is bad or its key was revoked
+    Invalid,
+    /// The signature cannot be checked, usually because the public key is
+    /// missing from the keyring or no allowed signers file is configured
+    Unverifiable,
+    /// The commit was signed with a key the author's groups do not register
+    WrongKey,
+    /// The signature or its key has expired
+    Expired,
+    /// The signature is good but the key's validity is unknown, i.e. the key
+    /// is not trusted in the keyring
+    Untrusted,
+}
+
+/// Signature of a commit as reported by `git log`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Signature {
+    /// `%G?`: `G` good, `B` bad, `U` unknown validity, `X` expired
+    /// signature, `Y` expired key, `R` revoked key, `E` cannot be checked,
+    /// `N` no signature
+    pub status: char,
+    /// `%GK`: key used to sign
+    pub key: String,
+    /// `%GF`: fingerprint of that key
+    pub fingerprint: String,
+    /// `%GS`: signer, `Name <email>` for OpenPGP
+    pub signer: String,
+}
+
+/// A commit whose signature does not fit its author's groups
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FlaggedCommit {
+    pub commit: String,
+    pub name: String,
+    pub email: String,
+    pub subject: String,
+    /// Groups holding the identity the commit was authored with
+    pub groups: Vec<String>,
+    pub problem: Problem,
+    /// Key the commit was signed with, if any
+    pub key: Option<String>,
+}
+
+/// Verification of the commits of one repository
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Number of commits authored by a group with a signing key
+    pub checked: usize,
+    pub flagged: Vec<FlaggedCommit>,
+}
+
+/// Verify the commits of the repository at `dir`
+///
+/// With `since`, the commits in `since..HEAD` are checked, otherwise the
+/// latest ones; at most `max_count` either way.
+pub fn verify_repository(
+    config: &Config,
+    dir: &Path,
+    since: Option<&str>,
+    max_count: usize,
+) -> anyhow::Result<Report> {
+    let commits = parse_log(&git_log(dir, since, max_count)?);
+    let groups = config.get_all_config_info();
+    let mut keys: HashMap<String, Option<String>> = HashMap::new();
+    Ok(check_commits(config, commits, |group_name| {
+        keys.entry(group_name.to_string())
+            .or_insert_with(|| groups.get(group_name).and_then(registered_key))
+            .clone()
+    }))
+}
+
+/// Flag the commits whose signature does not fit the author's groups
+///
+/// `key_of` returns the key registered on a group, as it is compared with
+/// the signatures (see [`key_matches`]).
+pub fn check_commits(
+    config: &Config,
+    commits: Vec<(String, UserConfig, Signature, String)>,
+    mut key_of: impl FnMut(&str) -> Option<String>,
+) -> Report {
+    let mut report = Report::default();
+    for (commit, author, signature, subject) in commits {
+        let groups = config.match_groups(&author);
+        let keys: Vec<String> = groups.iter().filter_map(|group| key_of(group)).collect();
+        if keys.is_empty() {
+            continue;
+        }
+        report.checked += 1;
+
+        let matches = keys.iter().any(|key| key_matches(key, &signature));
+        let problem = match signature.status {
+            'N' => Some(Problem::Unsigned),
+            'B' | 'R' => Some(Problem::Invalid),
+            _ if !matches => Some(Problem::WrongKey),
+            'E' => Some(Problem::Unverifiable),
+            'X' | 'Y' => Some(Problem::Expired),
+            'U' => Some(Problem::Untrusted),
+            _ => None,
+        };
+        let Some(problem) = problem else {
+            continue;
+        };
+        let key = [&signature.fingerprint, &signature.key]
+            .into_iter()
+            .find(|key| !key.is_empty())
+            .cloned();
+        report.flagged.push(FlaggedCommit {
+            commit,
+            name: author.name,
+            email: author.email,
+            subject,
+            groups,
+            problem,
+            key,
+        });
+    }
+    report
+}
+
+/// Whether `registered`, a group's key, made `signature`
+///
+/// OpenPGP long key ids (16 hex digits) and fingerprints must equal those
+/// of the signing key, or of its primary key for a long id (spaces, `0x`
+/// and a trailing `!` are ignored); short key ids are too easy to forge and
+/// never match. SSH `SHA256:` fingerprints must be equal; anything else,
+/// such as an email, must equal the email of the signer.
+pub fn key_matches(registered: &str, signature: &Signature) -> bool {
+    let registered = registered.trim();
+    if registered.starts_with("SHA256:") {
+        return [&signature.key, &signature.fingerprint]
+            .iter()
+            .any(|key| key.as_str() == registered);
+    }
+    let hex: String = registered
+        .trim_start_matches("0x")
+        .trim_end_matches('!')
+        .split_whitespace()
+        .collect::<String>()
+        .to_uppercase();
+    if hex.len() >= 8 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        let key = signature.key.to_uppercase();
+        let fingerprint = signature.fingerprint.to_uppercase();
+        return match hex.len() {
+            16 => key == hex || (!fingerprint.is_empty() && fingerprint.ends_with(&hex)),
+            40 | 64 => fingerprint == hex,
+            _ => false,
+        };
+    }
+    !registered.is_empty() && signer_email(&signature.signer).eq_ignore_ascii_case(registered)
+}
+
+/// Email of a `%GS` signer: the part in angle brackets of `Name <email>`,
+/// the whole signer otherwise (SSH principals)
+fn signer_email(signer: &str) -> &str {
+    signer
+        .rsplit_once('<')
+        .and_then(|(_, rest)| rest.strip_suffix('>'))
+        .unwrap_or(signer)
+        .trim()
+}
+
+/// Key of a group as compared with signatures: the `signing_key` for
+/// OpenPGP, the fingerprint of the key file for SSH
+fn registered_key(group: &UserConfig) -> Option<String> {
+    let key = group.signing_key.clone()?;
+    if group.signing_format.as_deref() != Some("ssh") {
+        return Some(key);
+    }
+    match ssh_fingerprint(&key) {
+        Ok(fingerprint) => Some(fingerprint),
+        Err(e) => {
+            log::warn!("Cannot read the fingerprint of {}: {}", key, e);
+            Some(key)
+        }
+    }
+}
+
+/// `SHA256:` fingerprint of an SSH key file or `key::` literal
+fn ssh_fingerprint(key: &str) -> anyhow::Result<String> {
+    let output = match key.strip_prefix("key::") {
+        Some(literal) => utils::output_with_input(
+            Command::new("ssh-keygen").args(["-l", "-f", "-"]),
+            literal.as_bytes(),
+        )?,
+        None => utils::output(
+            Command::new("ssh-keygen")
+                .args(["-l", "-f"])
+                .arg(utils::expand_home(key)),
+        )?,
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .split_whitespace()
+        .find(|field| field.starts_with("SHA256:"))
+        .map(str::to_string)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "ssh-keygen printed no fingerprint: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        })
+}
+
+/// `git log` output with signature details
+fn git_log(dir: &Path, since: Option<&str>, max_count: usize) -> anyhow::Result<String> {
+    let format = format!(
+        "--format=%H{0}%an{0}%ae{0}%G?{0}%GK{0}%GF{0}%GS{0}%s",
+        FIELD_SEPARATOR
+    );
+    let mut command = utils::git();
+    command
+        .arg("-C")
+        .arg(dir)
+        .args(["log", "--no-color", &format])
+        .arg(format!("--max-count={}", max_count));
+    if let Some(since) = since {
+        command.arg(format!("{}..HEAD", since));
+    }
+    let output = utils::output(&mut command)?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git log failed in {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `git log` lines into commit id, author, signature and subject
+fn parse_log(log: &str) -> Vec<(String, UserConfig, Signature, String)> {
+    log.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(8, FIELD_SEPARATOR);
+            let commit = fields.next()?.to_string();
+            let author = UserConfig {
+                name: fields.next()?.to_string(),
+                email: fields.next()?.to_string(),
+                ..UserConfig::default()
+            };
+            let signature = Signature {
+                status: fields.next()?.chars().next().unwrap_or('N'),
+                key: fields.next()?.to_string(),
+                fingerprint: fields.next()?.to_string(),
+                signer: fields.next()?.to_string(),
+            };
+            Some((
+                commit,
+                author,
+                signature,
+                fields.next().unwrap_or_default().to_string(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_commits() {
+        let mut config = Config::new();
+        for (group, email) in [("work", "li@acme.com"), ("oss", "li@example.org")] {
+            config.groups.insert(
+                group.to_string(),
+                UserConfig {
+                    name: "Li".to_string(),
+                    email: email.to_string(),
+                    ..UserConfig::default()
+                },
+            );
+        }
+        let log = "a1\x1fLi\x1fli@acme.com\x1fG\x1f89ABCDEF01234567\x1f0123456789ABCDEF0123456789ABCDEF01234567\x1fLi <li@acme.com>\x1fSigned\n\
+                   b2\x1fLi\x1fli@acme.com\x1fN\x1f\x1f\x1f\x1fUnsigned\n\
+                   c3\x1fLi\x1fli@acme.com\x1fG\x1fFFFFFFFFFFFFFFFF\x1fAAAAFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF\x1fLi <li@acme.com>\x1fOther key\n\
+                   d4\x1fLi\x1fli@example.org\x1fN\x1f\x1f\x1f\x1fNo key on oss\n\
+                   e5\x1fSomeone\x1fsomeone@acme.com\x1fN\x1f\x1f\x1f\x1fNot a group\n\
+                   f6\x1fLi\x1fli@acme.com\x1fY\x1f89ABCDEF01234567\x1f0123456789ABCDEF0123456789ABCDEF01234567\x1fLi <li@acme.com>\x1fExpired key\n\
+                   g7\x1fLi\x1fli@acme.com\x1fU\x1f89ABCDEF01234567\x1f0123456789ABCDEF0123456789ABCDEF01234567\x1fLi <li@acme.com>\x1fUntrusted key\n";
+        let report = check_commits(&config, parse_log(log), |group| {
+            (group == "work").then(|| "0x89ABCDEF01234567".to_string())
+        });
+        assert_eq!(report.checked, 5);
+        let flagged: Vec<(&str, Problem)> = report
+            .flagged
+            .iter()
+            .map(|commit| (commit.commit.as_str(), commit.problem))
+            .collect();
+        assert_eq!(
+            flagged,
+            [
+                ("b2", Problem::Unsigned),
+                ("c3", Problem::WrongKey),
+                ("f6", Problem::Expired),
+                ("g7", Problem::Untrusted)
+            ]
+        );
+        assert_eq!(report.flagged[0].groups, ["work"]);
+    }
+
+    #[test]
+    fn test_key_matches() {
+        let gpg = Signature {
+            status: 'G',
+            key: "89ABCDEF01234567".to_string(),
+            fingerprint: "0123456789ABCDEF0123456789ABCDEF01234567".to_string(),
+            signer: "Li <li@acme.com>".to_string(),
+        };
+        assert!(key_matches("89abcdef01234567!", &gpg));
+        assert!(key_matches(
+            "0123 4567 89AB CDEF 0123  4567 89AB CDEF 0123 4567",
+            &gpg
+        ));
+        assert!(key_matches("li@acme.com", &gpg));
+        assert!(!key_matches("FFFFFFFF", &gpg));
+        // Short key ids are not accepted, even when they match
+        assert!(!key_matches("01234567", &gpg));
+        assert!(!key_matches("li@example.org", &gpg));
+        assert!(!key_matches("i@acme.com", &gpg));
+        let lookalike = Signature {
+            signer: "Li <li@acme.com.evil>".to_string(),
+            ..gpg.clone()
+        };
+        assert!(!key_matches("li@acme.com", &lookalike));
+        let other = Signature {
+            signer: "Bli <bli@acme.com>".to_string(),
+            ..gpg.clone()
+        };
+        assert!(!key_matches("li@acme.com", &other));
+
+        let ssh = Signature {
+            status: 'G',
+            key: "SHA256:abc".to_string(),
+            fingerprint: "SHA256:abc".to_string(),
+            signer: "li@acme.com".to_string(),
+        };
+        assert!(key_matches("SHA256:abc", &ssh));
+        assert!(!key_matches("SHA256:abd", &ssh));
+        assert!(key_matches("li@acme.com", &ssh));
+    }
+}
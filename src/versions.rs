@@ -0,0 +1,226 @@
+//! # Versions Module
+//!
+//! Small content-addressed history of the configuration file, kept beside
+//! it in `<config file>.versions/`. Every state gum writes is stored once
+//! under the SHA-256 hash of its content, and `index.jsonl` lists the states
+//! in the order they were written, so `gum config rollback` can go back
+//! precisely several saves, past the single `.bak` backup.
+//!
+//! A file edited outside gum is recorded as its own state before it is
+//! replaced. Only the last `settings.retention.versions` states are kept
+//! ([`MAX_VERSIONS`] by default).
+
+use crate::{update, utils};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Index of the states, inside the versions directory
+const INDEX_FILE: &str = "index.jsonl";
+
+/// Number of states kept by default
+pub const MAX_VERSIONS: usize = 50;
+
+/// One state of the configuration file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// Seconds since the Unix epoch
+    pub timestamp: i64,
+    /// Hash of the content, also the name of the stored file
+    pub hash: String,
+}
+
+/// Directory holding the states of the configuration file at `config_path`
+pub fn versions_dir(config_path: &Path) -> PathBuf {
+    let mut name = config_path.as_os_str().to_owned();
+    name.push(".versions");
+    PathBuf::from(name)
+}
+
+/// SHA-256 digest of `content`, as 64 hex digits
+pub fn hash(content: &str) -> String {
+    update::sha256_hex(content.as_bytes())
+}
+
+/// States of the configuration file, oldest first
+///
+/// Lines that do not parse are skipped.
+pub fn list(config_path: &Path) -> anyhow::Result<Vec<Version>> {
+    let path = versions_dir(config_path).join(INDEX_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Content of a stored state
+pub fn read(config_path: &Path, version: &Version) -> anyhow::Result<String> {
+    let path = object_path(config_path, &version.hash);
+    fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
+
+/// Record that `new` replaced `old` in the configuration file
+///
+/// `old` is recorded first when it is not the last state, e.g. after a hand
+/// edit; an empty `old` (no file yet) is not. Only the last `keep` states
+/// are kept.
+pub fn record(config_path: &Path, old: &str, new: &str, keep: usize) -> anyhow::Result<()> {
+    let mut versions = list(config_path)?;
+    let dir = versions_dir(config_path);
+    fs::create_dir_all(&dir)?;
+    let mut index = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(INDEX_FILE))?;
+    for content in [old, new] {
+        let hash = hash(content);
+        if content.is_empty() || versions.last().is_some_and(|last| last.hash == hash) {
+            continue;
+        }
+        let object = object_path(config_path, &hash);
+        if !object.exists() {
+            fs::write(&object, content)?;
+        }
+        let version = Version {
+            timestamp: utils::now(),
+            hash,
+        };
+        writeln!(index, "{}", serde_json::to_string(&version)?)?;
+        versions.push(version);
+    }
+    if versions.len() > keep {
+        let kept = versions.split_off(versions.len() - keep);
+        trim(config_path, &versions, &kept)?;
+    }
+    Ok(())
+}
+
+/// State `steps` saves before `current`, the content of the file now
+///
+/// When `current` was not written by gum, it counts as the latest state.
+pub fn before(config_path: &Path, current: &str, steps: usize) -> anyhow::Result<Version> {
+    let versions = list(config_path)?;
+    let latest = match versions.last() {
+        Some(last) if last.hash == hash(current) => versions.len() - 1,
+        _ => versions.len(),
+    };
+    latest
+        .checked_sub(steps)
+        .and_then(|i| versions.get(i))
+        .cloned()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "only {} earlier version(s) of the config file are kept",
+                latest
+            )
+        })
+}
+
+/// Keep only the last `keep` states, returning how many were dropped
+///
+/// Under `--dry-run` nothing is removed.
+pub fn trim_to(config_path: &Path, keep: usize) -> anyhow::Result<usize> {
+    let mut versions = list(config_path)?;
+    if versions.len() <= keep {
+        return Ok(0);
+    }
+    let kept = versions.split_off(versions.len() - keep);
+    if !utils::is_dry_run() {
+        trim(config_path, &versions, &kept)?;
+    }
+    Ok(versions.len())
+}
+
+/// Rewrite the index with the states kept and delete the stored contents
+/// only the dropped states used
+fn trim(config_path: &Path, dropped: &[Version], keep: &[Version]) -> anyhow::Result<()> {
+    let index: String = keep
+        .iter()
+        .map(|version| serde_json::to_string(version).map(|line| line + "\n"))
+        .collect::<Result<_, _>>()?;
+    fs::write(versions_dir(config_path).join(INDEX_FILE), index)?;
+    for version in dropped {
+        if !keep.iter().any(|kept| kept.hash == version.hash) {
+            let _ = fs::remove_file(object_path(config_path, &version.hash));
+        }
+    }
+    Ok(())
+}
+
+fn object_path(config_path: &Path, hash: &str) -> PathBuf {
+    versions_dir(config_path).join(format!("{}.jsonc", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(
+            hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(hash("{}"), hash("{ }"));
+    }
+
+    #[test]
+    fn test_record_and_before() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.jsonc");
+        record(&path, "", "one", MAX_VERSIONS).unwrap();
+        record(&path, "one", "two", MAX_VERSIONS).unwrap();
+        // Edited by hand, then saved by gum
+        record(&path, "two (edited)", "three", MAX_VERSIONS).unwrap();
+        record(&path, "three", "three", MAX_VERSIONS).unwrap();
+
+        let versions = list(&path).unwrap();
+        let contents: Vec<String> = versions.iter().map(|v| read(&path, v).unwrap()).collect();
+        assert_eq!(contents, ["one", "two", "two (edited)", "three"]);
+
+        let previous = before(&path, "three", 1).unwrap();
+        assert_eq!(read(&path, &previous).unwrap(), "two (edited)");
+        assert_eq!(before(&path, "three", 3).unwrap().hash, hash("one"));
+        assert!(before(&path, "three", 4).is_err());
+        // An unrecorded current file counts as the latest state
+        assert_eq!(before(&path, "four", 1).unwrap().hash, hash("three"));
+    }
+
+    #[test]
+    fn test_trim() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.jsonc");
+        let states = (0..MAX_VERSIONS).chain([0, MAX_VERSIONS]);
+        for i in states {
+            record(&path, "", &format!("state {}", i), MAX_VERSIONS).unwrap();
+        }
+        let versions = list(&path).unwrap();
+        assert_eq!(versions.len(), MAX_VERSIONS);
+        // "state 0" leaves the start but is kept, since it was written again
+        assert!(object_path(&path, &hash("state 0")).exists());
+        assert!(!object_path(&path, &hash("state 1")).exists());
+
+        assert_eq!(trim_to(&path, 2).unwrap(), MAX_VERSIONS - 2);
+        let contents: Vec<String> = list(&path)
+            .unwrap()
+            .iter()
+            .map(|v| read(&path, v).unwrap())
+            .collect();
+        assert_eq!(
+            contents,
+            ["state 0".to_string(), format!("state {}", MAX_VERSIONS)]
+        );
+        assert_eq!(trim_to(&path, 2).unwrap(), 0);
+
+        // A lower retention applies on the next write
+        record(&path, "", "state 1", 1).unwrap();
+        assert_eq!(list(&path).unwrap().len(), 1);
+        assert!(!object_path(&path, &hash("state 0")).exists());
+    }
+}
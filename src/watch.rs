@@ -0,0 +1,127 @@
+//! # Watch Module
+//!
+//! Finds repositories created under workspace directories, so `gum watch`
+//! can apply the rule-resolved identity before the first commit. The
+//! directories are scanned periodically, which works the same on every
+//! platform without a file notification library.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long a new repository may lack a resolvable remote before giving up
+///
+/// `git clone` creates `.git` before it writes the remote, and a repository
+/// made with `git init` usually gets its remote shortly after.
+pub const RESOLVE_GRACE: Duration = Duration::from_secs(60);
+
+/// Repositories under watched directories
+pub struct Watcher {
+    roots: Vec<PathBuf>,
+    max_depth: usize,
+    known: HashSet<PathBuf>,
+}
+
+impl Watcher {
+    /// Watch `roots` down to `max_depth` levels; existing repositories are
+    /// not reported
+    pub fn new(roots: Vec<PathBuf>, max_depth: usize) -> Self {
+        let mut watcher = Self {
+            roots,
+            max_depth,
+            known: HashSet::new(),
+        };
+        watcher.known = watcher.scan();
+        log::debug!("Watching {} existing repositories", watcher.known.len());
+        watcher
+    }
+
+    /// Repositories that appeared since the last poll, sorted
+    ///
+    /// A repository removed and created again is reported again.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let current = self.scan();
+        let mut created: Vec<PathBuf> = current.difference(&self.known).cloned().collect();
+        created.sort();
+        self.known = current;
+        created
+    }
+
+    fn scan(&self) -> HashSet<PathBuf> {
+        self.roots
+            .iter()
+            .flat_map(|root| find_repositories(root, self.max_depth))
+            .collect()
+    }
+}
+
+/// Work trees (directories holding a `.git` directory or file) under `root`
+///
+/// Looks `max_depth` levels down and does not descend into repositories or
+/// follow symbolic links. Unreadable directories are skipped.
+pub fn find_repositories(root: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut stack = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = stack.pop() {
+        if dir.join(".git").exists() {
+            found.push(dir);
+            continue;
+        }
+        if depth == max_depth {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            log::debug!("Cannot read {}", dir.display());
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                stack.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_repositories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for repo in ["a", "org/b", "org/b/vendor/c", "x/y/z/deep"] {
+            fs::create_dir_all(root.join(repo).join(".git")).unwrap();
+        }
+        fs::create_dir_all(root.join("worktree")).unwrap();
+        fs::write(root.join("worktree/.git"), "gitdir: /elsewhere").unwrap();
+
+        let mut found = find_repositories(root, 3);
+        found.sort();
+        let expected: Vec<PathBuf> = ["a", "org/b", "worktree"]
+            .iter()
+            .map(|repo| root.join(repo))
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_poll() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("old/.git")).unwrap();
+        let mut watcher = Watcher::new(vec![root.to_path_buf()], 2);
+        assert!(watcher.poll().is_empty());
+
+        fs::create_dir_all(root.join("new/.git")).unwrap();
+        assert_eq!(watcher.poll(), vec![root.join("new")]);
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_dir_all(root.join("old")).unwrap();
+        assert!(watcher.poll().is_empty());
+        fs::create_dir_all(root.join("old/.git")).unwrap();
+        assert_eq!(watcher.poll(), vec![root.join("old")]);
+    }
+}
@@ -0,0 +1,177 @@
+//! # Workspace Module
+//!
+//! Named sets of groups, rules and history kept apart from each other, e.g.
+//! one per client. A workspace has its own configuration file in
+//! `workspaces/<name>/` next to the default one, and its own state directory
+//! (history, rule hits) in `workspaces/<name>/` inside gum's state directory.
+//! Without a workspace, gum uses the default files as before.
+//!
+//! The workspace comes from `--workspace`, then `GUM_WORKSPACE`, then the
+//! one chosen with `gum workspace use`, which is remembered in the
+//! `workspace` file of the configuration directory.
+
+use crate::utils;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable selecting the workspace
+pub const WORKSPACE_ENV: &str = "GUM_WORKSPACE";
+
+/// Name standing for the default files, outside any workspace
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// Directory holding the workspaces, inside the configuration and state
+/// directories
+const WORKSPACES_DIR: &str = "workspaces";
+
+/// File remembering the workspace chosen with `gum workspace use`
+const ACTIVE_FILE: &str = "workspace";
+
+/// Workspace given on the command line (`--workspace`)
+static WORKSPACE_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Workspace in effect, resolved once per process
+static ACTIVE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Set the workspace given on the command line
+///
+/// Takes precedence over `GUM_WORKSPACE` and the remembered workspace for
+/// the rest of the process. Only the first call has an effect.
+pub fn set_override(name: String) {
+    log::debug!("Workspace override: {}", name);
+    let _ = WORKSPACE_OVERRIDE.set(name);
+}
+
+/// Workspace in effect, `None` for the default files
+pub fn active() -> Option<String> {
+    ACTIVE
+        .get_or_init(|| {
+            let name = WORKSPACE_OVERRIDE
+                .get()
+                .cloned()
+                .or_else(|| utils::env_value(WORKSPACE_ENV))
+                .or_else(remembered);
+            name.filter(|name| name != DEFAULT_WORKSPACE)
+        })
+        .clone()
+}
+
+/// Workspace chosen with `gum workspace use`
+fn remembered() -> Option<String> {
+    let path = utils::get_config_base_dir().ok()?.join(ACTIVE_FILE);
+    let name = fs::read_to_string(path).ok()?.trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Remember `name` as the workspace of later invocations
+///
+/// [`DEFAULT_WORKSPACE`] goes back to the default files. Nothing is written
+/// under `--dry-run`.
+pub fn remember(name: &str) -> anyhow::Result<()> {
+    if utils::is_dry_run() {
+        return Ok(());
+    }
+    let path = utils::get_config_base_dir()?.join(ACTIVE_FILE);
+    if name == DEFAULT_WORKSPACE {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        };
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, format!("{}\n", name))?;
+    Ok(())
+}
+
+/// Check a workspace name, which becomes a directory name
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.starts_with('.') {
+        return Err(format!("invalid workspace name \"{}\"", name));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        return Err(format!(
+            "invalid workspace name \"{}\": '{}' is not allowed (use letters, digits, '-', '_' and '.')",
+            name, c
+        ));
+    }
+    Ok(())
+}
+
+/// Directory of a workspace below `base`, a configuration or state directory
+pub fn dir(base: &Path, name: &str) -> PathBuf {
+    base.join(WORKSPACES_DIR).join(name)
+}
+
+/// Whether the workspace exists; the default one always does
+pub fn exists(name: &str) -> anyhow::Result<bool> {
+    if name == DEFAULT_WORKSPACE {
+        return Ok(true);
+    }
+    Ok(dir(&utils::get_config_base_dir()?, name).is_dir())
+}
+
+/// Create a workspace, returning its configuration directory
+pub fn create(name: &str) -> anyhow::Result<PathBuf> {
+    let path = dir(&utils::get_config_base_dir()?, name);
+    if !utils::is_dry_run() {
+        fs::create_dir_all(&path)?;
+    }
+    Ok(path)
+}
+
+/// Names of the workspaces, sorted, without the default one
+pub fn list() -> anyhow::Result<Vec<String>> {
+    names(&utils::get_config_base_dir()?.join(WORKSPACES_DIR))
+}
+
+/// Names of the directories in `dir`, sorted
+fn names(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| validate_name(name).is_ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        for valid in ["client-a", "acme_2024", "v1.2"] {
+            assert!(validate_name(valid).is_ok(), "{}", valid);
+        }
+        for invalid in ["", ".hidden", "a/b", "..", "client a"] {
+            assert!(validate_name(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        let base = tempfile::tempdir().unwrap();
+        assert!(names(&base.path().join(WORKSPACES_DIR)).unwrap().is_empty());
+        for name in ["client-b", "client-a", ".trash"] {
+            fs::create_dir_all(dir(base.path(), name)).unwrap();
+        }
+        fs::write(base.path().join(WORKSPACES_DIR).join("notes.txt"), "").unwrap();
+        assert_eq!(
+            names(&base.path().join(WORKSPACES_DIR)).unwrap(),
+            ["client-a", "client-b"]
+        );
+    }
+}
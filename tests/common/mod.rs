@@ -0,0 +1,30 @@
+//! Running the gum binary in a temporary home
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Run gum with its configuration, state and git settings inside `home`,
+/// failing the test when gum fails
+pub fn gum(home: &Path, dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_gum"))
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_DATA_HOME", home.join(".local/share"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env("GUM_CONFIG", home.join("gum/config.json"))
+        .env("GUM_SYSTEM_CONFIG", home.join("gum/system.json"))
+        .env("GIT_CONFIG_GLOBAL", home.join(".gitconfig"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "gum {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
@@ -0,0 +1,40 @@
+//! Group listings
+
+mod common;
+
+use common::gum;
+
+#[test]
+fn test_list_long_shows_aliases() {
+    let tmp = tempfile::tempdir().unwrap();
+    let home = tmp.path();
+    gum(
+        home,
+        home,
+        &["set", "work", "--name", "Li", "--email", "li@acme.com"],
+    );
+    gum(
+        home,
+        home,
+        &["set", "oss", "--name", "Li", "--email", "li@ex.org"],
+    );
+    gum(home, home, &["alias", "add", "w", "work"]);
+    gum(home, home, &["alias", "add", "job", "work"]);
+
+    let list = gum(home, home, &["--plain", "list", "--long", "--wide"]);
+    let header = list
+        .lines()
+        .find(|line| line.contains("group-name"))
+        .unwrap();
+    assert!(header.contains("aliases"), "{}", list);
+    let work = list
+        .lines()
+        .find(|line| line.contains("li@acme.com"))
+        .unwrap();
+    assert!(work.contains("job, w"), "{}", list);
+    let oss = list
+        .lines()
+        .find(|line| line.contains("li@ex.org"))
+        .unwrap();
+    assert!(!oss.contains("job"), "{}", list);
+}
@@ -0,0 +1,42 @@
+//! `--redact` masks the emails printed by commands whose output is not
+//! meant for scripts
+
+mod common;
+
+use common::gum;
+use std::process::Command;
+
+#[test]
+fn test_redact_diff_and_log() {
+    let tmp = tempfile::tempdir().unwrap();
+    let home = tmp.path();
+    let repo = home.join("repo");
+    std::fs::create_dir(&repo).unwrap();
+    let status = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(&repo)
+        .env("GIT_CONFIG_GLOBAL", home.join(".gitconfig"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    gum(
+        home,
+        &repo,
+        &["set", "work", "--name", "Li", "--email", "li@acme.com"],
+    );
+    gum(home, &repo, &["use", "work"]);
+
+    let diff = gum(home, &repo, &["--redact", "diff", "work"]);
+    assert!(diff.contains("l***@acme.com"), "{}", diff);
+    assert!(!diff.contains("li@acme.com"), "{}", diff);
+
+    let log = gum(home, &repo, &["--redact", "log"]);
+    assert!(log.contains("l***@acme.com"), "{}", log);
+    assert!(!log.contains("li@acme.com"), "{}", log);
+
+    // Without --redact the emails are printed as they are
+    let log = gum(home, &repo, &["log"]);
+    assert!(log.contains("li@acme.com"), "{}", log);
+}